@@ -1,9 +1,9 @@
 use nix::sys::stat::SFlag;
 use std::collections::HashMap;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -35,7 +35,7 @@ pub struct User {
     pub username: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Process {
     #[serde(default)]
@@ -58,6 +58,8 @@ pub struct Process {
     pub capabilities: Option<LinuxCapabilities>,
     #[serde(default)]
     pub rlimits: Vec<LinuxRlimit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oom_score_adj: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -69,7 +71,7 @@ pub struct LinuxRlimit {
     #[serde(default)]
     pub soft: u64,
 }
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum LinuxRlimitType {
     RlimitCpu,
@@ -90,12 +92,64 @@ pub enum LinuxRlimitType {
     RlimitRttime,
 }
 
+impl LinuxRlimitType {
+    /// Every rlimit with its name, which is the name of the resource in the kernel headers
+    pub const ALL: [(&'static str, LinuxRlimitType); 16] = [
+        ("RLIMIT_CPU", LinuxRlimitType::RlimitCpu),
+        ("RLIMIT_FSIZE", LinuxRlimitType::RlimitFsize),
+        ("RLIMIT_DATA", LinuxRlimitType::RlimitData),
+        ("RLIMIT_STACK", LinuxRlimitType::RlimitStack),
+        ("RLIMIT_CORE", LinuxRlimitType::RlimitCore),
+        ("RLIMIT_RSS", LinuxRlimitType::RlimitRss),
+        ("RLIMIT_NPROC", LinuxRlimitType::RlimitNproc),
+        ("RLIMIT_NOFILE", LinuxRlimitType::RlimitNofile),
+        ("RLIMIT_MEMLOCK", LinuxRlimitType::RlimitMemlock),
+        ("RLIMIT_AS", LinuxRlimitType::RlimitAs),
+        ("RLIMIT_LOCKS", LinuxRlimitType::RlimitLocks),
+        ("RLIMIT_SIGPENDING", LinuxRlimitType::RlimitSigpending),
+        ("RLIMIT_MSGQUEUE", LinuxRlimitType::RlimitMsgqueue),
+        ("RLIMIT_NICE", LinuxRlimitType::RlimitNice),
+        ("RLIMIT_RTPRIO", LinuxRlimitType::RlimitRtprio),
+        ("RLIMIT_RTTIME", LinuxRlimitType::RlimitRttime),
+    ];
+}
+
+impl std::str::FromStr for LinuxRlimitType {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match LinuxRlimitType::ALL.iter().find(|(n, _)| *n == name) {
+            Some((_, typ)) => Ok(*typ),
+            None => bail!("unknown rlimit {:?}", name),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LinuxRlimitType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 use caps::Capability;
 #[derive(Debug, Clone)]
 pub struct LinuxCapabilityType {
     pub cap: Capability,
 }
 
+impl Serialize for LinuxCapabilityType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(&self.cap.to_string())
+    }
+}
+
 impl<'de> Deserialize<'de> for LinuxCapabilityType {
     fn deserialize<D>(desirializer: D) -> Result<Self, D::Error>
     where
@@ -131,7 +185,7 @@ impl<'de> Deserialize<'de> for LinuxCapabilityType {
                     "CAP_SYS_NICE" => Capability::CAP_SYS_NICE,
                     "CAP_SYS_RESOURCE" => Capability::CAP_SYS_RESOURCE,
                     "CAP_SYS_TIME" => Capability::CAP_SYS_TIME,
-                    "CAP_SYS_TTYCONFIG" => Capability::CAP_SYS_TTY_CONFIG,
+                    "CAP_SYS_TTY_CONFIG" => Capability::CAP_SYS_TTY_CONFIG,
                     "CAP_SYSLOG" => Capability::CAP_SYSLOG,
                     "CAP_MKNOD" => Capability::CAP_MKNOD,
                     "CAP_LEASE" => Capability::CAP_LEASE,
@@ -143,6 +197,9 @@ impl<'de> Deserialize<'de> for LinuxCapabilityType {
                     "CAP_MAC_ADMIN" => Capability::CAP_MAC_ADMIN,
                     "CAP_WAKE_ALARM" => Capability::CAP_WAKE_ALARM,
                     "CAP_BLOCK_SUSPEND" => Capability::CAP_BLOCK_SUSPEND,
+                    "CAP_PERFMON" => Capability::CAP_PERFMON,
+                    "CAP_BPF" => Capability::CAP_BPF,
+                    "CAP_CHECKPOINT_RESTORE" => Capability::CAP_CHECKPOINT_RESTORE,
                     unknown_cap => {
                         return Err(serde::de::Error::custom(format!(
                             "{:?} is unexpected type in capabilites",
@@ -157,7 +214,7 @@ impl<'de> Deserialize<'de> for LinuxCapabilityType {
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LinuxCapabilities {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub bounding: Vec<LinuxCapabilityType>,
@@ -343,7 +400,7 @@ impl ToString for LinuxDeviceCgroup {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct LinuxMemory {
     pub limit: Option<i64>,
     pub reservation: Option<i64>,
@@ -354,18 +411,24 @@ pub struct LinuxMemory {
     pub swappiness: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct LinuxCpu {
     pub shares: Option<u64>,
     pub quota: Option<i64>,
     pub period: Option<u64>,
+    pub burst: Option<u64>,
     pub realtime_runtime: Option<i64>,
     pub realtime_period: Option<u64>,
     #[serde(default)]
     pub cpus: Option<String>,
     #[serde(default)]
     pub mems: Option<String>,
+    // not part of the runtime spec, only supported by the cpuset controller of cgroup v1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_migrate: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_exclusive: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -395,7 +458,7 @@ pub struct LinuxThrottleDevice {
     pub rate: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct LinuxBlockIo {
     pub blkio_weight: Option<u16>,
@@ -462,7 +525,7 @@ pub struct LinuxResources {
     pub network: Option<LinuxNetwork>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum LinuxNamespaceType {
     Mount = 0x00020000,
@@ -518,6 +581,7 @@ pub enum LinuxSeccompAction {
     ScmpActErrno = 0x00050001,
     ScmpActTrace = 0x7ff00001,
     ScmpActAllow = 0x7fff0000,
+    ScmpActNotify = 0x7fc00000,
 }
 
 #[allow(clippy::enum_clike_unportable_variant)]
@@ -556,12 +620,42 @@ pub enum LinuxSeccompOperator {
     ScmpCmpMaskedEq = 7,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LinuxSeccompArg {
+    pub index: usize,
+    pub value: u64,
+    #[serde(default)]
+    pub value_two: u64,
+    pub op: LinuxSeccompOperator,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LinuxSyscall {
+    pub names: Vec<String>,
+    pub action: LinuxSeccompAction,
+    pub errno_ret: Option<u32>,
+    #[serde(default)]
+    pub args: Vec<LinuxSeccompArg>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LinuxSeccomp {
+    pub default_action: LinuxSeccompAction,
+    #[serde(default)]
+    pub architectures: Vec<Arch>,
+    #[serde(default)]
+    pub syscalls: Vec<LinuxSyscall>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Linux {
-    #[serde(default, rename = "LinuxIDMapping")]
+    #[serde(default)]
     pub uid_mappings: Vec<LinuxIdMapping>,
-    #[serde(default, rename = "LinuxIDMapping")]
+    #[serde(default)]
     pub gid_mappings: Vec<LinuxIdMapping>,
     #[serde(default)]
     pub sysctl: HashMap<String, String>,
@@ -580,14 +674,54 @@ pub struct Linux {
     pub readonly_paths: Vec<String>,
     #[serde(default)]
     pub mount_label: String,
+    pub seccomp: Option<LinuxSeccomp>,
+    pub intel_rdt: Option<LinuxIntelRdt>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LinuxIntelRdt {
+    #[serde(rename = "closID")]
+    pub clos_id: Option<String>,
+    pub l3_cache_schema: Option<String>,
+    pub mem_bw_schema: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Hook {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: Vec<String>,
+    pub timeout: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Hooks {
+    #[serde(default)]
+    pub prestart: Vec<Hook>,
+    #[serde(default)]
+    pub create_runtime: Vec<Hook>,
+    #[serde(default)]
+    pub create_container: Vec<Hook>,
+    #[serde(default)]
+    pub start_container: Vec<Hook>,
+    #[serde(default)]
+    pub poststart: Vec<Hook>,
+    #[serde(default)]
+    pub poststop: Vec<Hook>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Spec {
     #[serde(default, rename = "ociVersion")]
     pub version: String,
     pub platform: Option<Platform>,
-    pub process: Process,
+    // a bundle may leave out the process, if its processes are only executed in the container later
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<Process>,
     pub root: Root,
     #[serde(default)]
     pub hostname: String,
@@ -596,18 +730,108 @@ pub struct Spec {
     #[serde(default)]
     pub annotations: HashMap<String, String>,
     pub linux: Option<Linux>,
+    pub hooks: Option<Hooks>,
+    /// Problems with names of namespaces, rlimits and capabilities, which are not known.
+    /// The entries with them are left out when the spec is loaded, so that they are reported
+    /// along with the other problems, when the spec is validated
+    #[serde(skip)]
+    pub unknown_names: Vec<String>,
 }
 
 impl Spec {
     pub fn load(path: &str) -> Result<Self> {
-        let file = File::open(path)?;
-        let mut spec: Spec = serde_json::from_reader(&file)?;
-        // FIME: It is fail if the caller isn't in the correct directory.
-        spec.root.path = std::fs::canonicalize(spec.root.path)?;
+        let file = File::open(path).with_context(|| format!("failed to open {}", path))?;
+        let mut value: serde_json::Value =
+            serde_json::from_reader(&file).with_context(|| format!("failed to parse {}", path))?;
+        let unknown_names = take_unknown_names(&mut value);
+        let mut spec: Spec =
+            serde_json::from_value(value).with_context(|| format!("failed to parse {}", path))?;
+        spec.unknown_names = unknown_names;
+        // an empty root filesystem is left as it is, for the validation of the spec to report it.
+        // Otherwise a relative root filesystem is resolved relative to the bundle, which contains the spec
+        if !spec.root.path.as_os_str().is_empty() {
+            let bundle = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+            spec.root.path =
+                std::fs::canonicalize(bundle.join(&spec.root.path)).with_context(|| {
+                    format!("failed to find the root filesystem {:?}", spec.root.path)
+                })?;
+        }
         Ok(spec)
     }
 }
 
+/// Removes the namespaces, rlimits and capabilities with unknown names from a spec,
+/// returning a problem for each of them
+fn take_unknown_names(spec: &mut serde_json::Value) -> Vec<String> {
+    let mut unknown = Vec::new();
+    if let Some(namespaces) = spec
+        .pointer_mut("/linux/namespaces")
+        .and_then(serde_json::Value::as_array_mut)
+    {
+        retain_known::<LinuxNamespaceType>(
+            namespaces,
+            Some("type"),
+            "linux.namespaces: unknown namespace type",
+            &mut unknown,
+        );
+    }
+    if let Some(rlimits) = spec
+        .pointer_mut("/process/rlimits")
+        .and_then(serde_json::Value::as_array_mut)
+    {
+        retain_known::<LinuxRlimitType>(
+            rlimits,
+            Some("type"),
+            "process.rlimits: unknown rlimit",
+            &mut unknown,
+        );
+    }
+    match spec.pointer_mut("/process/capabilities") {
+        Some(serde_json::Value::Array(caps)) => retain_known::<LinuxCapabilityType>(
+            caps,
+            None,
+            "process.capabilities: unknown capability",
+            &mut unknown,
+        ),
+        Some(serde_json::Value::Object(sets)) => {
+            for (set, caps) in sets.iter_mut() {
+                if let serde_json::Value::Array(caps) = caps {
+                    retain_known::<LinuxCapabilityType>(
+                        caps,
+                        None,
+                        &format!("process.capabilities.{}: unknown capability", set),
+                        &mut unknown,
+                    );
+                }
+            }
+        }
+        _ => {}
+    }
+    unknown
+}
+
+/// Removes the entries, whose name is a string which can not be parsed as a T. The name is
+/// the field key of an entry, or the entry itself without a key. Names of any other type are
+/// kept, for parsing the spec to fail on them
+fn retain_known<T: serde::de::DeserializeOwned>(
+    entries: &mut Vec<serde_json::Value>,
+    key: Option<&str>,
+    problem: &str,
+    unknown: &mut Vec<String>,
+) {
+    entries.retain(
+        |entry| match key.map_or(Some(entry), |key| entry.get(key)) {
+            Some(name @ serde_json::Value::String(_))
+                if serde_json::from_value::<T>(name.clone()).is_err() =>
+            {
+                unknown.push(format!("{} {}", problem, name));
+                false
+            }
+            _ => true,
+        },
+    );
+}
+
 #[cfg(feature = "proptests")]
 use quickcheck::{Arbitrary, Gen};
 
@@ -698,4 +922,73 @@ mod tests {
         };
         assert_eq!(ldc.to_string(), "a 1:9 rwm");
     }
+
+    #[test]
+    fn test_capability_names() {
+        // every capability known to the caps crate can be used in the spec by its name
+        for cap in caps::all() {
+            let name = serde_json::Value::String(cap.to_string());
+            let capability = LinuxCapabilityType::deserialize(&name)
+                .unwrap_or_else(|_| panic!("deserialize {}", cap));
+            assert_eq!(capability.cap, cap);
+        }
+    }
+
+    #[test]
+    fn test_unknown_capability_name() {
+        let name = serde_json::Value::String("CAP_UNKNOWN".to_string());
+        assert!(LinuxCapabilityType::deserialize(&name).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_capability_sets() {
+        let process: Process = serde_json::from_str(
+            r#"{
+                "user": {"uid": 0, "gid": 0},
+                "args": ["sh"],
+                "capabilities": {
+                    "bounding": ["CAP_NET_RAW", "CAP_SYS_TTY_CONFIG"],
+                    "permitted": ["CAP_NET_RAW"],
+                    "ambient": null
+                }
+            }"#,
+        )
+        .expect("deserialize process");
+
+        let caps = process.capabilities.expect("capabilities are set");
+        let names = |caps: &[LinuxCapabilityType]| -> Vec<Capability> {
+            caps.iter().map(|c| c.cap).collect()
+        };
+        assert_eq!(
+            names(&caps.bounding),
+            vec![Capability::CAP_NET_RAW, Capability::CAP_SYS_TTY_CONFIG]
+        );
+        assert_eq!(names(&caps.permitted), vec![Capability::CAP_NET_RAW]);
+        assert!(caps.effective.is_empty());
+        assert!(caps.inheritable.is_empty());
+        assert!(caps.ambient.is_empty());
+    }
+
+    #[test]
+    fn test_rlimit_names() {
+        for (name, typ) in LinuxRlimitType::ALL.iter() {
+            let value = serde_json::Value::String(name.to_string());
+            assert_eq!(
+                LinuxRlimitType::deserialize(&value).expect("deserialize rlimit"),
+                *typ
+            );
+            // the names are the same in both directions
+            assert_eq!(serde_json::to_value(typ).expect("serialize rlimit"), value);
+        }
+    }
+
+    #[test]
+    fn test_unknown_rlimit_name() {
+        let err = serde_json::from_str::<LinuxRlimit>(r#"{"type": "RLIMIT_UNKNOWN", "hard": 1}"#)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("unknown rlimit \"RLIMIT_UNKNOWN\""));
+        assert!("nofile".parse::<LinuxRlimitType>().is_err());
+    }
 }
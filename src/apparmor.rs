@@ -0,0 +1,75 @@
+//! Confinement of the container process with an AppArmor profile, which is changed on exec
+//! check https://gitlab.com/apparmor/apparmor/-/wikis/AppArmorInterfaces for more information
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+
+const ENABLED_PARAMETER: &str = "/sys/module/apparmor/parameters/enabled";
+
+/// Returns true if AppArmor is enabled on the host
+pub fn is_enabled() -> bool {
+    fs::read_to_string(ENABLED_PARAMETER)
+        .map(|enabled| enabled.starts_with('Y'))
+        .unwrap_or(false)
+}
+
+/// Returns the file, through which the profile is changed on the next exec. Newer kernels
+/// have files for every security module, the legacy file is shared by all of them
+fn exec_attr_path(proc_self: &Path) -> PathBuf {
+    let path = proc_self.join("attr/apparmor/exec");
+    if path.exists() {
+        path
+    } else {
+        proc_self.join("attr/exec")
+    }
+}
+
+/// Changes the profile of the current process to the given one, once it executes its program
+pub fn apply_profile(profile: &str) -> Result<()> {
+    if profile.is_empty() {
+        return Ok(());
+    }
+    if !is_enabled() {
+        bail!(
+            "apparmor profile {} is requested, but AppArmor is not enabled on this host",
+            profile
+        )
+    }
+
+    let path = exec_attr_path(Path::new("/proc/self"));
+    fs::write(&path, format!("exec {}", profile)).with_context(|| {
+        format!(
+            "failed to apply apparmor profile {} through {:?}",
+            profile, path
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_temp_dir;
+
+    #[test]
+    fn test_exec_attr_path() {
+        let tmp = create_temp_dir("test_exec_attr_path").expect("create temp directory");
+        fs::create_dir_all(tmp.join("attr")).expect("create attr directory");
+        fs::write(tmp.join("attr/exec"), "").expect("create legacy attr file");
+
+        // only the legacy file exists
+        assert_eq!(exec_attr_path(&tmp), tmp.join("attr/exec"));
+
+        fs::create_dir_all(tmp.join("attr/apparmor")).expect("create apparmor attr directory");
+        fs::write(tmp.join("attr/apparmor/exec"), "").expect("create apparmor attr file");
+        assert_eq!(exec_attr_path(&tmp), tmp.join("attr/apparmor/exec"));
+    }
+
+    #[test]
+    fn test_apply_empty_profile() {
+        // nothing is written, even if AppArmor is not enabled
+        assert!(apply_profile("").is_ok());
+    }
+}
@@ -1,7 +1,7 @@
 use crate::command::Command;
 use caps::*;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use oci_spec::{LinuxCapabilities, LinuxCapabilityType};
 
 fn to_set(caps: &[LinuxCapabilityType]) -> CapsHashSet {
@@ -18,26 +18,49 @@ pub fn reset_effective(command: &impl Command) -> Result<()> {
     Ok(())
 }
 
+/// Returns the ambient capabilities, which are not both permitted and inheritable,
+/// as the kernel refuses to raise them
+// see https://man7.org/linux/man-pages/man7/capabilities.7.html for more information
+pub fn invalid_ambient_capabilities(cs: &LinuxCapabilities) -> Vec<Capability> {
+    let permitted = to_set(&cs.permitted);
+    let inheritable = to_set(&cs.inheritable);
+    let mut invalid: Vec<Capability> = to_set(&cs.ambient)
+        .into_iter()
+        .filter(|c| !permitted.contains(c) || !inheritable.contains(c))
+        .collect();
+    invalid.sort_by_key(|c| c.index());
+    invalid
+}
+
+/// Drops all capabilities of the process which are not part of the capability sets in the spec
 pub fn drop_privileges(cs: &LinuxCapabilities, command: &impl Command) -> Result<()> {
-    let all = caps::all();
-    log::debug!("dropping bounding capabilities to {:?}", cs.bounding);
-    for c in all.difference(&to_set(&cs.bounding)) {
-        match c {
-            Capability::CAP_PERFMON | Capability::CAP_CHECKPOINT_RESTORE | Capability::CAP_BPF => {
-                log::warn!("{:?} doesn't support.", c);
-                continue;
-            }
-            _ => caps::drop(None, CapSet::Bounding, *c)?,
-        }
+    let bounding = to_set(&cs.bounding);
+    let effective = to_set(&cs.effective);
+    let permitted = to_set(&cs.permitted);
+    let inheritable = to_set(&cs.inheritable);
+    let ambient = to_set(&cs.ambient);
+
+    let invalid_ambient = invalid_ambient_capabilities(cs);
+    if !invalid_ambient.is_empty() {
+        bail!(
+            "ambient capabilities {:?} must be in the permitted and the inheritable set",
+            invalid_ambient
+        )
     }
 
-    command.set_capability(CapSet::Effective, &to_set(&cs.effective))?;
-    command.set_capability(CapSet::Permitted, &to_set(&cs.permitted))?;
-    command.set_capability(CapSet::Inheritable, &to_set(&cs.inheritable))?;
+    log::debug!("dropping bounding capabilities to {:?}", cs.bounding);
+    command
+        .set_capability(CapSet::Bounding, &bounding)
+        .context("failed to drop bounding capabilities")?;
 
-    if let Err(e) = command.set_capability(CapSet::Ambient, &to_set(&cs.ambient)) {
-        log::error!("failed to set ambient capabilities: {}", e);
-    }
+    command.set_capability(CapSet::Effective, &effective)?;
+    command.set_capability(CapSet::Permitted, &permitted)?;
+    command.set_capability(CapSet::Inheritable, &inheritable)?;
+
+    // ambient capabilities can only be raised once the permitted and inheritable sets are in place
+    command
+        .set_capability(CapSet::Ambient, &ambient)
+        .context("failed to set ambient capabilities")?;
     Ok(())
 }
 
@@ -46,6 +69,12 @@ mod tests {
     use super::*;
     use crate::command::test::TestHelperCommand;
 
+    fn to_capability_types(caps: &[Capability]) -> Vec<LinuxCapabilityType> {
+        caps.iter()
+            .map(|cap| LinuxCapabilityType { cap: *cap })
+            .collect()
+    }
+
     #[test]
     fn test_reset_effective() {
         let test_command = TestHelperCommand::default();
@@ -57,4 +86,68 @@ mod tests {
             .collect();
         assert_eq!(set_capability_args, vec![caps::all()]);
     }
+
+    #[test]
+    fn test_drop_privileges() {
+        // arrange
+        let test_command = TestHelperCommand::default();
+        let cs = LinuxCapabilities {
+            bounding: to_capability_types(&[
+                Capability::CAP_NET_RAW,
+                Capability::CAP_NET_BIND_SERVICE,
+                Capability::CAP_CHOWN,
+            ]),
+            effective: to_capability_types(&[Capability::CAP_CHOWN]),
+            inheritable: to_capability_types(&[
+                Capability::CAP_NET_RAW,
+                Capability::CAP_NET_BIND_SERVICE,
+            ]),
+            permitted: to_capability_types(&[
+                Capability::CAP_NET_RAW,
+                Capability::CAP_NET_BIND_SERVICE,
+                Capability::CAP_CHOWN,
+            ]),
+            ambient: to_capability_types(&[Capability::CAP_NET_BIND_SERVICE]),
+        };
+
+        // act
+        drop_privileges(&cs, &test_command).expect("drop privileges");
+
+        // assert
+        // CapSet does not implement PartialEq, so the sets are compared by their names
+        let set_capability_args: Vec<_> = test_command
+            .get_set_capability_args()
+            .into_iter()
+            .map(|(capset, caps)| (format!("{:?}", capset), caps))
+            .collect();
+        let expected = vec![
+            ("Bounding".to_string(), to_set(&cs.bounding)),
+            ("Effective".to_string(), to_set(&cs.effective)),
+            ("Permitted".to_string(), to_set(&cs.permitted)),
+            ("Inheritable".to_string(), to_set(&cs.inheritable)),
+            ("Ambient".to_string(), to_set(&cs.ambient)),
+        ];
+        assert_eq!(set_capability_args, expected);
+    }
+
+    #[test]
+    fn test_drop_privileges_invalid_ambient() {
+        let test_command = TestHelperCommand::default();
+        let cs = LinuxCapabilities {
+            bounding: to_capability_types(&[Capability::CAP_NET_RAW]),
+            effective: vec![],
+            inheritable: vec![],
+            permitted: to_capability_types(&[Capability::CAP_NET_RAW]),
+            ambient: to_capability_types(&[Capability::CAP_NET_RAW]),
+        };
+
+        let result = drop_privileges(&cs, &test_command);
+
+        let err = result.expect_err("ambient capability is not inheritable");
+        assert!(err.to_string().contains("CAP_NET_RAW"));
+        assert!(
+            test_command.get_set_capability_args().is_empty(),
+            "no capabilities should be changed if the spec is invalid"
+        );
+    }
 }
@@ -1,25 +1,93 @@
 use std::{
+    collections::BTreeSet,
     env,
     fmt::{Debug, Display},
     fs,
-    io::Write,
+    io::{self, Write},
     path::{Path, PathBuf},
+    thread,
+    time::Duration,
 };
 
-use anyhow::{anyhow, bail, Result};
-use nix::unistd::Pid;
+use anyhow::{anyhow, bail, Context, Result};
+use nix::{
+    errno::Errno,
+    unistd::{self, Pid},
+};
 use oci_spec::LinuxResources;
 use procfs::process::Process;
+use regex::Regex;
 
+use crate::cgroups::stats::Stats;
+use crate::cgroups::systemd;
 use crate::cgroups::v1;
 use crate::cgroups::v2;
+use crate::error::YoukiError;
+use crate::rootless;
 
 pub const CGROUP_PROCS: &str = "cgroup.procs";
 pub const DEFAULT_CGROUP_ROOT: &str = "/sys/fs/cgroup";
+/// List of the cpus of the host which are online, in the format of cpuset.cpus
+pub const ONLINE_CPUS: &str = "/sys/devices/system/cpu/online";
+/// Directory with a directory for every size of hugepages the host supports, e.g. hugepages-2048kB
+pub const HUGEPAGES: &str = "/sys/kernel/mm/hugepages";
+/// Parent of the cgroups of containers, whose cgroups path is relative or not set
+pub const DEFAULT_CGROUP_PARENT: &str = "/youki";
+const CGROUP_CONTROLLERS: &str = "cgroup.controllers";
+// mount point of the cgroup v2 hierarchy below the cgroup root in a hybrid setup
+const CGROUP_UNIFIED: &str = "unified";
+// runtime directory of a user, which systemd creates for its session
+const XDG_RUNTIME_DIR: &str = "XDG_RUNTIME_DIR";
+const USER_RUNTIME_DIR: &str = "/run/user";
+
+/// Number of attempts to remove a cgroup which still has tasks attached
+const REMOVE_CGROUP_RETRIES: u32 = 5;
+
+/// Options of a cgroup manager, which do not come from the resources of the spec
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ManagerOptions {
+    /// Makes every missing controller an error as soon as the spec restricts its resources,
+    /// instead of only the controllers of the cpu and memory
+    pub strict: bool,
+    /// Owner of the new cgroup directories of a rootless container, which the root user of the
+    /// container is mapped to, so that the container can manage the cgroups below its own
+    pub owner: Option<(u32, u32)>,
+}
 
 pub trait CgroupManager {
-    fn apply(&self, linux_resources: &LinuxResources, pid: Pid) -> Result<()>;
+    /// Adds a task specified by its pid to the cgroup
+    fn add_task(&self, pid: Pid) -> Result<()>;
+    /// Applies resource restrictions to the cgroup
+    fn apply(&self, linux_resources: &LinuxResources) -> Result<()>;
+    /// Applies the resource restrictions of a running container again. Only the
+    /// restrictions which are set in the given resources are changed
+    fn update(&self, linux_resources: &LinuxResources) -> Result<()> {
+        self.apply(linux_resources)
+    }
     fn remove(&self) -> Result<()>;
+    fn freeze(&self, state: FreezerState) -> Result<()>;
+    /// Reports the resource usage of the cgroup
+    fn stats(&self) -> Result<Stats>;
+    /// Returns the pids of all tasks in the cgroup
+    fn get_all_pids(&self) -> Result<Vec<Pid>>;
+}
+
+/// Desired state of the tasks in a cgroup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FreezerState {
+    Frozen,
+    Thawed,
+}
+
+/// Describes which cgroup hierarchies are mounted on the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgroupVersion {
+    // only cgroup v1 hierarchies are mounted
+    Legacy,
+    // cgroup v1 hierarchies are mounted along with a cgroup v2 hierarchy
+    Hybrid,
+    // only the cgroup v2 hierarchy is mounted
+    Unified,
 }
 
 #[derive(Debug)]
@@ -41,28 +109,154 @@ impl Display for Cgroup {
 
 #[inline]
 pub fn write_cgroup_file_str<P: AsRef<Path>>(path: P, data: &str) -> Result<()> {
+    let path = path.as_ref();
     fs::OpenOptions::new()
         .create(false)
         .write(true)
         .truncate(false)
-        .open(path)?
-        .write_all(data.as_bytes())?;
+        .open(path)
+        .and_then(|mut f| f.write_all(data.as_bytes()))
+        .with_context(|| YoukiError::CgroupWrite {
+            path: path.to_path_buf(),
+            data: data.to_string(),
+        })?;
 
     Ok(())
 }
 
 #[inline]
 pub fn write_cgroup_file<P: AsRef<Path>, T: ToString>(path: P, data: T) -> Result<()> {
+    write_cgroup_file_str(path, &data.to_string())
+}
+
+/// Appends a single entry to a cgroup file. Some interface files, e.g. the blkio
+/// device files, only accept one entry per write so every entry is written separately
+#[inline]
+pub fn append_cgroup_file_str<P: AsRef<Path>>(path: P, data: &str) -> Result<()> {
+    let path = path.as_ref();
     fs::OpenOptions::new()
         .create(false)
-        .write(true)
-        .truncate(false)
-        .open(path)?
-        .write_all(data.to_string().as_bytes())?;
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(format!("{}\n", data).as_bytes()))
+        .with_context(|| YoukiError::CgroupWrite {
+            path: path.to_path_buf(),
+            data: data.to_string(),
+        })?;
+
+    Ok(())
+}
+
+/// Reads the pids of the tasks which are attached to a cgroup. A cgroup which
+/// does not exist has no tasks
+pub fn get_procs(cgroup_path: &Path) -> Result<Vec<Pid>> {
+    let procs_path = cgroup_path.join(CGROUP_PROCS);
+    if !procs_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let procs = fs::read_to_string(&procs_path)?;
+    parse_procs(&procs).map_err(|e| anyhow!("failed to parse {:?}: {}", procs_path, e))
+}
+
+fn parse_procs(procs: &str) -> Result<Vec<Pid>> {
+    procs
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.parse()
+                .map(Pid::from_raw)
+                .map_err(|_| anyhow!("invalid pid {:?}", line))
+        })
+        .collect()
+}
+
+/// Removes a cgroup directory. A cgroup cannot be removed as long as tasks are attached
+/// to it, so the remaining tasks are moved to the root cgroup of the hierarchy first
+pub fn remove_cgroup(root_path: &Path, cgroup_path: &Path) -> Result<()> {
+    if !cgroup_path.exists() {
+        return Ok(());
+    }
+
+    let mut delay = Duration::from_millis(10);
+    for _ in 0..REMOVE_CGROUP_RETRIES {
+        move_tasks(cgroup_path, root_path)?;
+
+        log::debug!("remove cgroup {:?}", cgroup_path);
+        match fs::remove_dir(cgroup_path) {
+            Ok(()) => return Ok(()),
+            // tasks which have been forked while the tasks were moved are still attached
+            // to the cgroup, so they have to be moved as well before trying again
+            Err(e) if e.raw_os_error() == Some(Errno::EBUSY as i32) => {
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            Err(e) => bail!("failed to remove cgroup {:?}: {}", cgroup_path, e),
+        }
+    }
+
+    bail!(
+        "failed to remove cgroup {:?}: tasks are still attached after {} attempts",
+        cgroup_path,
+        REMOVE_CGROUP_RETRIES
+    )
+}
+
+fn move_tasks(from: &Path, to: &Path) -> Result<()> {
+    let procs = match fs::read_to_string(from.join(CGROUP_PROCS)) {
+        Ok(procs) => procs,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => bail!("failed to read tasks of cgroup {:?}: {}", from, e),
+    };
+
+    for pid in procs.lines() {
+        let result = fs::OpenOptions::new()
+            .create(false)
+            .append(true)
+            .open(to.join(CGROUP_PROCS))
+            .and_then(|mut f| f.write_all(format!("{}\n", pid).as_bytes()));
+
+        match result {
+            Ok(()) => {}
+            // the task has exited in the meantime
+            Err(e) if e.raw_os_error() == Some(Errno::ESRCH as i32) => {}
+            Err(e) => bail!("failed to move task {} to cgroup {:?}: {}", pid, to, e),
+        }
+    }
 
     Ok(())
 }
 
+/// Resolves the cgroups path of the spec to the path of the cgroup below the mount point of
+/// every hierarchy. A relative path is placed below the parent and without a path the cgroup is
+/// named after the container. An absolute path is used as is, but if a parent is given explicitly,
+/// it has to be below the parent. Without an explicit parent, DEFAULT_CGROUP_PARENT is used
+pub fn resolve_cgroup_path(
+    cgroups_path: Option<&Path>,
+    container_id: &str,
+    parent: Option<&Path>,
+) -> Result<PathBuf> {
+    // a relative parent is placed below the root of the hierarchy as well
+    let explicit_parent = parent.is_some();
+    let parent = Path::new("/").join(parent.unwrap_or_else(|| Path::new(DEFAULT_CGROUP_PARENT)));
+    let path = match cgroups_path {
+        Some(path) if path.is_absolute() => {
+            if explicit_parent && !path.starts_with(&parent) {
+                bail!(
+                    "cgroups path {:?} is not below the cgroup parent {:?}",
+                    path,
+                    parent
+                )
+            }
+            path.to_path_buf()
+        }
+        Some(path) if !path.as_os_str().is_empty() => parent.join(path),
+        _ => parent.join(container_id),
+    };
+    Ok(path)
+}
+
 pub fn get_cgroupv1_mount_path(subsystem: &str) -> Result<PathBuf> {
     Process::myself()?
         .mountinfo()?
@@ -72,45 +266,530 @@ pub fn get_cgroupv1_mount_path(subsystem: &str) -> Result<PathBuf> {
         .ok_or_else(|| anyhow!("could not find mountpoint for {}", subsystem))
 }
 
-pub fn create_cgroup_manager<P: Into<PathBuf>>(cgroup_path: P) -> Result<Box<dyn CgroupManager>> {
-    let cgroup_mount = Process::myself()?
-        .mountinfo()?
-        .into_iter()
-        .find(|m| m.fs_type == "cgroup");
+/// Creates the manager of the cgroup of a container. With the systemd cgroup driver, the cgroup
+/// belongs to a unit of systemd and the cgroups path has the form slice:prefix:name
+pub fn create_cgroup_manager<P: Into<PathBuf>>(
+    cgroup_path: P,
+    systemd_cgroup: bool,
+    options: ManagerOptions,
+) -> Result<Box<dyn CgroupManager>> {
+    if systemd_cgroup {
+        log::info!("systemd cgroup manager will be used");
+        return Ok(Box::new(systemd::manager::Manager::new(
+            cgroup_path.into(),
+            options,
+        )?));
+    }
 
-    let cgroup2_mount = Process::myself()?
-        .mountinfo()?
-        .into_iter()
-        .find(|m| m.fs_type == "cgroup2");
+    create_fs_cgroup_manager(cgroup_path, options)
+}
 
-    match (cgroup_mount, cgroup2_mount) {
-        (Some(_), None) => {
+/// Creates the manager of a cgroup, which is changed through its interface files
+pub fn create_fs_cgroup_manager<P: Into<PathBuf>>(
+    cgroup_path: P,
+    options: ManagerOptions,
+) -> Result<Box<dyn CgroupManager>> {
+    let cgroup_root = Path::new(DEFAULT_CGROUP_ROOT);
+    match detect_cgroup_version(cgroup_root)? {
+        CgroupVersion::Legacy => {
             log::info!("cgroup manager V1 will be used");
-            Ok(Box::new(v1::manager::Manager::new(cgroup_path.into())?))
-        }
-        (None, Some(cgroup2)) => {
-            log::info!("cgroup manager V2 will be used");
-            Ok(Box::new(v2::manager::Manager::new(
-                cgroup2.mount_point,
+            Ok(Box::new(v1::manager::Manager::new(
                 cgroup_path.into(),
+                options.strict,
             )?))
         }
-        (Some(_), Some(cgroup2)) => {
+        CgroupVersion::Unified => {
+            create_v2_cgroup_manager(cgroup_root.to_path_buf(), cgroup_path.into(), options)
+        }
+        CgroupVersion::Hybrid => {
             let cgroup_override = env::var("YOUKI_PREFER_CGROUPV2");
             match cgroup_override {
-                Ok(v) if v == "true" => {
-                    log::info!("cgroup manager V2 will be used");
-                    Ok(Box::new(v2::manager::Manager::new(
-                        cgroup2.mount_point,
+                Ok(v) if v == "true" => create_v2_cgroup_manager(
+                    cgroup_root.join(CGROUP_UNIFIED),
+                    cgroup_path.into(),
+                    options,
+                ),
+                _ => {
+                    log::info!("cgroup manager V1 will be used");
+                    Ok(Box::new(v1::manager::Manager::new(
                         cgroup_path.into(),
+                        options.strict,
                     )?))
                 }
-                _ => {
-                    log::info!("cgroup manager V1 will be used");
-                    Ok(Box::new(v1::manager::Manager::new(cgroup_path.into())?))
+            }
+        }
+    }
+}
+
+/// Creates the manager of a cgroup v2 hierarchy. An unprivileged user can not write to the
+/// hierarchy, except for the subtree which systemd delegates to the service manager of the user,
+/// so the cgroup of a rootless container is placed below it
+fn create_v2_cgroup_manager(
+    cgroup_root: PathBuf,
+    cgroup_path: PathBuf,
+    options: ManagerOptions,
+) -> Result<Box<dyn CgroupManager>> {
+    log::info!("cgroup manager V2 will be used");
+    if rootless::is_rootless() {
+        let uid = rootless_uid(
+            env::var(XDG_RUNTIME_DIR).ok().as_deref(),
+            unistd::geteuid().as_raw(),
+        );
+        let base = cgroup_root.join(rootless_cgroup_base(uid));
+        if base.exists() {
+            log::info!("delegated cgroup {:?} will be used", base);
+            return Ok(Box::new(v2::manager::Manager::new_rootless(
+                base,
+                cgroup_path,
+                options.owner,
+            )?));
+        }
+        log::warn!(
+            "cgroup {:?}, which is delegated to user {}, does not exist",
+            base,
+            uid
+        );
+    }
+
+    Ok(Box::new(v2::manager::Manager::new(
+        cgroup_root,
+        cgroup_path,
+    )?))
+}
+
+/// Returns the user, which the session belongs to. The runtime directory of the session is
+/// named after the user, which differs from the effective user in a user namespace
+pub fn rootless_uid(xdg_runtime_dir: Option<&str>, euid: u32) -> u32 {
+    xdg_runtime_dir
+        .and_then(|dir| Path::new(dir).strip_prefix(USER_RUNTIME_DIR).ok())
+        .and_then(|uid| uid.to_str())
+        .and_then(|uid| uid.parse().ok())
+        .unwrap_or(euid)
+}
+
+/// Path of the cgroup of the service manager of a user below the root of the hierarchy,
+/// which systemd delegates to the user
+pub fn rootless_cgroup_base(uid: u32) -> PathBuf {
+    PathBuf::from(format!(
+        "user.slice/user-{uid}.slice/user@{uid}.service",
+        uid = uid
+    ))
+}
+
+/// Detects which cgroup hierarchies are mounted below the cgroup root. The
+/// cgroup.controllers interface file only exists in a cgroup v2 hierarchy
+pub fn detect_cgroup_version(cgroup_root: &Path) -> Result<CgroupVersion> {
+    if cgroup_root.join(CGROUP_CONTROLLERS).exists() {
+        return Ok(CgroupVersion::Unified);
+    }
+
+    if cgroup_root
+        .join(CGROUP_UNIFIED)
+        .join(CGROUP_CONTROLLERS)
+        .exists()
+    {
+        return Ok(CgroupVersion::Hybrid);
+    }
+
+    // every cgroup v1 hierarchy is mounted in its own directory below the cgroup root
+    if cgroup_root.is_dir() {
+        for entry in fs::read_dir(cgroup_root)? {
+            if entry?.path().join(CGROUP_PROCS).exists() {
+                return Ok(CgroupVersion::Legacy);
+            }
+        }
+    }
+
+    bail!("could not find cgroup filesystem at {:?}", cgroup_root)
+}
+
+/// Parses a list of cpus in the format of cpuset.cpus, e.g. 0-3,7
+pub fn parse_cpu_list(list: &str) -> Result<BTreeSet<u32>> {
+    let mut cpus = BTreeSet::new();
+    for part in list.trim().split(',').filter(|part| !part.is_empty()) {
+        let parse = |cpu: &str| {
+            cpu.trim()
+                .parse::<u32>()
+                .map_err(|_| anyhow!("invalid cpu {:?} in cpu list {:?}", cpu, list))
+        };
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse(first)?, parse(last)?);
+                if first > last {
+                    bail!("invalid cpu range {:?} in cpu list {:?}", part, list)
                 }
+                cpus.extend(first..=last);
+            }
+            None => {
+                cpus.insert(parse(part)?);
             }
         }
-        _ => bail!("could not find cgroup filesystem"),
+    }
+    Ok(cpus)
+}
+
+/// Checks that all requested cpus are online, as the kernel would otherwise
+/// restrict the cpuset to an empty set of effective cpus without an error
+pub fn validate_cpus(cpus: &str, online_cpus: &Path) -> Result<()> {
+    let requested = parse_cpu_list(cpus)?;
+    let online = fs::read_to_string(online_cpus)
+        .with_context(|| format!("failed to read the online cpus from {:?}", online_cpus))?;
+    let offline: Vec<u32> = requested
+        .difference(&parse_cpu_list(&online)?)
+        .copied()
+        .collect();
+    if !offline.is_empty() {
+        bail!(
+            "cpus {:?} of cpuset {:?} are not online, the online cpus are {}",
+            offline,
+            cpus,
+            online.trim()
+        )
+    }
+    Ok(())
+}
+
+/// Checks that a hugepage size in the format of the spec, e.g. 2MB, is supported. That is the case
+/// if the host has hugepages of the size, or if the cgroup has the interface file for them
+pub fn validate_hugepage_size(page_size: &str, hugepages: &Path, limit_file: &Path) -> Result<()> {
+    let re = Regex::new(r"^(?P<size>[0-9]+)(?P<unit>[KMG])B$")?;
+    let caps = re.captures(page_size).ok_or_else(|| {
+        anyhow!(
+            "unsupported page size {:?}: page size must be in the format [0-9]+[KMG]B",
+            page_size
+        )
+    })?;
+    let shift = match &caps["unit"] {
+        "K" => 0,
+        "M" => 10,
+        _ => 20,
+    };
+    let supported_by_host = caps["size"]
+        .parse::<u64>()
+        .ok()
+        .and_then(|size| size.checked_mul(1 << shift))
+        .is_some_and(|kb| hugepages.join(format!("hugepages-{}kB", kb)).is_dir());
+    if !supported_by_host && !limit_file.exists() {
+        bail!(
+            "unsupported page size {:?}: the host has no hugepages of this size",
+            page_size
+        )
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgroups::test::{create_temp_dir, set_fixture};
+    use crate::utils::PathBufExt;
+
+    #[test]
+    fn test_rootless_uid() {
+        assert_eq!(rootless_uid(Some("/run/user/1000"), 0), 1000);
+        assert_eq!(rootless_uid(Some("/run/user/1000/"), 0), 1000);
+        assert_eq!(rootless_uid(Some("/tmp/runtime"), 1001), 1001);
+        assert_eq!(rootless_uid(Some("/run/user/youki"), 1001), 1001);
+        assert_eq!(rootless_uid(None, 1001), 1001);
+    }
+
+    #[test]
+    fn test_rootless_cgroup_base() {
+        assert_eq!(
+            rootless_cgroup_base(1000),
+            PathBuf::from("user.slice/user-1000.slice/user@1000.service")
+        );
+    }
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(
+            parse_cpu_list("0-3,7")
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 7]
+        );
+        assert_eq!(
+            parse_cpu_list("5,1-2,2\n")
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![1, 2, 5]
+        );
+        assert!(parse_cpu_list("").unwrap().is_empty());
+        for invalid in &["a", "3-1", "1-", "-1", "0,,x"] {
+            assert!(
+                parse_cpu_list(invalid).is_err(),
+                "{:?} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_cpus() {
+        let tmp = create_temp_dir("test_validate_cpus").expect("create temp directory for test");
+        set_fixture(&tmp, "online", "0-3,6\n").expect("set fixture for online cpus");
+        let online = tmp.join("online");
+
+        assert!(validate_cpus("0-3", &online).is_ok());
+        assert!(validate_cpus("1,6", &online).is_ok());
+        let err = validate_cpus("2-5", &online).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cpus [4, 5] of cpuset \"2-5\" are not online, the online cpus are 0-3,6"
+        );
+        assert!(validate_cpus("7", &online).is_err());
+    }
+
+    #[test]
+    fn test_validate_hugepage_size() {
+        let tmp =
+            create_temp_dir("test_validate_hugepage_size").expect("create temp directory for test");
+        let hugepages = tmp.join("hugepages");
+        fs::create_dir_all(hugepages.join("hugepages-2048kB")).expect("create 2 MB hugepages");
+        fs::create_dir_all(hugepages.join("hugepages-1048576kB")).expect("create 1 GB hugepages");
+        set_fixture(&tmp, "hugetlb.64KB.limit_in_bytes", "0").expect("set fixture for 64 KB");
+        let limit_file =
+            |page_size: &str| tmp.join(format!("hugetlb.{}.limit_in_bytes", page_size));
+
+        // supported by the host
+        for page_size in &["2MB", "2048KB", "1GB"] {
+            assert!(validate_hugepage_size(page_size, &hugepages, &limit_file(page_size)).is_ok());
+        }
+        // supported by the cgroup
+        assert!(validate_hugepage_size("64KB", &hugepages, &limit_file("64KB")).is_ok());
+
+        let err = validate_hugepage_size("4MB", &hugepages, &limit_file("4MB")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported page size \"4MB\": the host has no hugepages of this size"
+        );
+        assert!(validate_hugepage_size("3MB", &hugepages, &limit_file("3MB")).is_err());
+        assert!(validate_hugepage_size("2TB", &hugepages, &limit_file("2TB")).is_err());
+    }
+
+    #[test]
+    fn test_write_cgroup_file_error_contains_path() {
+        let tmp = create_temp_dir("test_write_cgroup_file_error_contains_path")
+            .expect("create temp directory for test");
+        // the interface file does not exist, so the write has to fail
+        let cpu_shares = tmp.join("cpu.shares");
+
+        let err = write_cgroup_file(&cpu_shares, 2048).expect_err("write to missing file");
+
+        let message = err.to_string();
+        assert!(message.contains("2048"), "unexpected message: {}", message);
+        assert!(
+            message.contains(&*cpu_shares.to_string_lossy()),
+            "unexpected message: {}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_resolve_absolute_cgroup_path() {
+        let path = resolve_cgroup_path(Some(Path::new("/foo/bar")), "cid", None).unwrap();
+
+        assert_eq!(path, PathBuf::from("/foo/bar"));
+        assert_eq!(
+            Path::new("/sys/fs/cgroup/cpu")
+                .to_path_buf()
+                .join_absolute_path(&path)
+                .unwrap(),
+            PathBuf::from("/sys/fs/cgroup/cpu/foo/bar")
+        );
+    }
+
+    #[test]
+    fn test_resolve_relative_cgroup_path() {
+        let path = resolve_cgroup_path(Some(Path::new("foo/bar")), "cid", None).unwrap();
+
+        assert_eq!(path, PathBuf::from("/youki/foo/bar"));
+        assert_eq!(
+            Path::new("/sys/fs/cgroup/memory")
+                .to_path_buf()
+                .join_absolute_path(&path)
+                .unwrap(),
+            PathBuf::from("/sys/fs/cgroup/memory/youki/foo/bar")
+        );
+    }
+
+    #[test]
+    fn test_resolve_empty_cgroup_path() {
+        for cgroups_path in &[None, Some(Path::new(""))] {
+            let path = resolve_cgroup_path(*cgroups_path, "cid", None).unwrap();
+
+            assert_eq!(path, PathBuf::from("/youki/cid"));
+            assert_eq!(
+                Path::new("/sys/fs/cgroup")
+                    .to_path_buf()
+                    .join_absolute_path(&path)
+                    .unwrap(),
+                PathBuf::from("/sys/fs/cgroup/youki/cid")
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_cgroup_path_with_parent() {
+        let resolve = |cgroups_path: Option<&str>, parent: &str| {
+            resolve_cgroup_path(cgroups_path.map(Path::new), "cid", Some(Path::new(parent)))
+        };
+
+        for parent in &["/machine", "machine"] {
+            assert_eq!(
+                resolve(Some("foo/bar"), parent).unwrap(),
+                PathBuf::from("/machine/foo/bar")
+            );
+            assert_eq!(
+                resolve(None, parent).unwrap(),
+                PathBuf::from("/machine/cid")
+            );
+            // an absolute path, which is already below the parent, is not nested again
+            assert_eq!(
+                resolve(Some("/machine/foo"), parent).unwrap(),
+                PathBuf::from("/machine/foo")
+            );
+            let err = resolve(Some("/foo/bar"), parent).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "cgroups path \"/foo/bar\" is not below the cgroup parent \"/machine\""
+            );
+        }
+        // only whole components of the parent match
+        assert!(resolve(Some("/machine-2/foo"), "/machine").is_err());
+    }
+
+    #[test]
+    fn test_parse_procs() {
+        let pids = parse_procs("1000\n1001\n\n1002\n").expect("parse procs");
+        assert_eq!(
+            pids,
+            vec![
+                Pid::from_raw(1000),
+                Pid::from_raw(1001),
+                Pid::from_raw(1002)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_procs() {
+        assert!(parse_procs("1000\nabc\n").is_err());
+    }
+
+    #[test]
+    fn test_get_procs_of_missing_cgroup() {
+        let tmp = create_temp_dir("test_get_procs_of_missing_cgroup")
+            .expect("create temp directory for test");
+
+        let pids = get_procs(&tmp.join("youki")).expect("get procs");
+        assert!(pids.is_empty());
+    }
+
+    #[test]
+    fn test_get_procs() {
+        let tmp = create_temp_dir("test_get_procs").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_PROCS, "1000\n1001\n").expect("set fixture for cgroup procs");
+
+        let pids = get_procs(&tmp).expect("get procs");
+        assert_eq!(pids, vec![Pid::from_raw(1000), Pid::from_raw(1001)]);
+    }
+
+    #[test]
+    fn test_remove_cgroup_without_tasks() {
+        let tmp = create_temp_dir("test_remove_cgroup_without_tasks")
+            .expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_PROCS, "").expect("set fixture for root cgroup procs");
+        let cgroup_path = tmp.join("youki");
+        fs::create_dir(&cgroup_path).expect("create cgroup directory");
+
+        remove_cgroup(&tmp, &cgroup_path).expect("remove cgroup");
+
+        assert!(!cgroup_path.exists());
+        let root_procs = fs::read_to_string(tmp.join(CGROUP_PROCS))
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_PROCS));
+        assert_eq!(root_procs, "");
+    }
+
+    #[test]
+    fn test_remove_cgroup_moves_tasks_to_root() {
+        let tmp = create_temp_dir("test_remove_cgroup_moves_tasks_to_root")
+            .expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_PROCS, "").expect("set fixture for root cgroup procs");
+        let cgroup_path = tmp.join("youki");
+        fs::create_dir(&cgroup_path).expect("create cgroup directory");
+        set_fixture(&cgroup_path, CGROUP_PROCS, "1000\n1001\n")
+            .expect("set fixture for cgroup procs");
+
+        // a regular directory which still contains files cannot be removed like a
+        // cgroup, so the removal itself has to fail after the tasks have been moved
+        let result = remove_cgroup(&tmp, &cgroup_path);
+
+        let root_procs = fs::read_to_string(tmp.join(CGROUP_PROCS))
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_PROCS));
+        assert_eq!(root_procs, "1000\n1001\n");
+        let err = result.expect_err("removing a non empty directory should fail");
+        assert!(err.to_string().contains("failed to remove cgroup"));
+    }
+
+    #[test]
+    fn test_detect_unified_cgroup() {
+        let tmp =
+            create_temp_dir("test_detect_unified_cgroup").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_CONTROLLERS, "cpu memory pids")
+            .expect("set fixture for cgroup controllers");
+        set_fixture(&tmp, CGROUP_PROCS, "").expect("set fixture for cgroup procs");
+
+        let version = detect_cgroup_version(&tmp).expect("detect cgroup version");
+        assert_eq!(version, CgroupVersion::Unified);
+    }
+
+    #[test]
+    fn test_detect_legacy_cgroup() {
+        let tmp =
+            create_temp_dir("test_detect_legacy_cgroup").expect("create temp directory for test");
+        for subsystem in &["cpu,cpuacct", "memory", "pids"] {
+            let hierarchy = tmp.join(subsystem);
+            fs::create_dir(&hierarchy).expect("create cgroup hierarchy");
+            set_fixture(&hierarchy, CGROUP_PROCS, "").expect("set fixture for cgroup procs");
+        }
+
+        let version = detect_cgroup_version(&tmp).expect("detect cgroup version");
+        assert_eq!(version, CgroupVersion::Legacy);
+    }
+
+    #[test]
+    fn test_detect_hybrid_cgroup() {
+        let tmp =
+            create_temp_dir("test_detect_hybrid_cgroup").expect("create temp directory for test");
+        for subsystem in &["memory", CGROUP_UNIFIED] {
+            let hierarchy = tmp.join(subsystem);
+            fs::create_dir(&hierarchy).expect("create cgroup hierarchy");
+            set_fixture(&hierarchy, CGROUP_PROCS, "").expect("set fixture for cgroup procs");
+        }
+        set_fixture(&tmp.join(CGROUP_UNIFIED), CGROUP_CONTROLLERS, "")
+            .expect("set fixture for cgroup controllers");
+
+        let version = detect_cgroup_version(&tmp).expect("detect cgroup version");
+        assert_eq!(version, CgroupVersion::Hybrid);
+    }
+
+    #[test]
+    fn test_detect_missing_cgroup() {
+        let tmp =
+            create_temp_dir("test_detect_missing_cgroup").expect("create temp directory for test");
+
+        assert!(detect_cgroup_version(&tmp).is_err());
+    }
+
+    #[test]
+    fn test_remove_missing_cgroup() {
+        let tmp =
+            create_temp_dir("test_remove_missing_cgroup").expect("create temp directory for test");
+
+        remove_cgroup(&tmp, &tmp.join("youki")).expect("remove missing cgroup");
     }
 }
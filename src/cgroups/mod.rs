@@ -3,6 +3,8 @@
 //! freezing, checkpointing and restarting groups of processes.
 
 pub mod common;
+pub mod stats;
+pub mod systemd;
 mod test;
 pub mod v1;
 pub mod v2;
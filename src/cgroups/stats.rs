@@ -0,0 +1,389 @@
+//! Resource usage statistics which can be read back from the cgroup interface files
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+/// Reports the resource usage of a cgroup for a specific controller
+pub trait StatsProvider {
+    type Stats;
+
+    fn stats(cgroup_path: &Path) -> Result<Self::Stats>;
+}
+
+/// Resource usage of a container as reported by the cgroup controllers
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    pub cpu: CpuStats,
+    pub memory: MemoryStats,
+    pub pids: PidsStats,
+    pub blkio: BlkioStats,
+}
+
+/// Reports the cpu usage and throttling of a cgroup
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CpuStats {
+    pub usage: CpuUsage,
+    pub throttling: CpuThrottling,
+}
+
+/// Cpu time consumed by the tasks of a cgroup in nanoseconds
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CpuUsage {
+    // total cpu time consumed
+    pub usage_total: u64,
+    // cpu time consumed in user mode
+    pub usage_user: u64,
+    // cpu time consumed in kernel mode
+    pub usage_kernel: u64,
+}
+
+/// Reports how often the tasks of a cgroup have been throttled because they exceeded the quota
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CpuThrottling {
+    // number of enforcement periods that have elapsed
+    pub periods: u64,
+    // number of periods in which the cgroup was throttled
+    pub throttled_periods: u64,
+    // total time the tasks were throttled in nanoseconds
+    pub throttled_time: u64,
+}
+
+/// Reports the memory usage of a cgroup in bytes
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryStats {
+    // current memory usage
+    pub usage: u64,
+    // highest memory usage which has been recorded
+    pub max_usage: u64,
+    // memory limit, u64::MAX if the memory is not limited
+    pub limit: u64,
+    // number of times the memory usage hit the limit
+    pub fail_count: u64,
+    // number of times the cgroup ran out of memory, only reported by cgroup v2
+    pub oom: u64,
+    // number of processes of the cgroup which were killed by the oom killer
+    pub oom_kill: u64,
+    // breakdown of the memory usage
+    pub stat: MemoryStat,
+}
+
+/// Breakdown of the memory usage of a cgroup as reported by memory.stat. Sizes are in bytes,
+/// the fields named after events count the events. Fields the kernel does not report are 0
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryStat {
+    // page cache, including tmpfs
+    pub cache: u64,
+    // anonymous memory, excluding tmpfs
+    pub rss: u64,
+    // anonymous transparent huge pages
+    pub rss_huge: u64,
+    // shared memory, including tmpfs
+    pub shmem: u64,
+    // page cache which is mapped into the address space of processes
+    pub mapped_file: u64,
+    // page cache which waits to be written to disk
+    pub dirty: u64,
+    // page cache which is being written to disk
+    pub writeback: u64,
+    // swap usage, only reported by cgroup v1
+    pub swap: u64,
+    // pages charged to and uncharged from the cgroup, only reported by cgroup v1
+    pub pgpgin: u64,
+    pub pgpgout: u64,
+    // page faults, of which major page faults required to read from disk
+    pub pgfault: u64,
+    pub pgmajfault: u64,
+    // memory on the lru lists the kernel uses to decide which memory to reclaim
+    pub active_anon: u64,
+    pub inactive_anon: u64,
+    pub active_file: u64,
+    pub inactive_file: u64,
+    pub unevictable: u64,
+    // memory limits including the limits of the parent cgroups, only reported by cgroup v1
+    pub hierarchical_memory_limit: u64,
+    pub hierarchical_memsw_limit: u64,
+}
+
+impl MemoryStat {
+    /// Takes the standard fields from memory.stat of cgroup v1. Other fields are ignored,
+    /// so that fields added by newer kernels do not cause an error
+    pub fn from_v1(stat: &HashMap<String, u64>) -> Self {
+        let field = |key: &str| stat.get(key).copied().unwrap_or(0);
+        Self {
+            cache: field("cache"),
+            rss: field("rss"),
+            rss_huge: field("rss_huge"),
+            shmem: field("shmem"),
+            mapped_file: field("mapped_file"),
+            dirty: field("dirty"),
+            writeback: field("writeback"),
+            swap: field("swap"),
+            pgpgin: field("pgpgin"),
+            pgpgout: field("pgpgout"),
+            pgfault: field("pgfault"),
+            pgmajfault: field("pgmajfault"),
+            active_anon: field("active_anon"),
+            inactive_anon: field("inactive_anon"),
+            active_file: field("active_file"),
+            inactive_file: field("inactive_file"),
+            unevictable: field("unevictable"),
+            hierarchical_memory_limit: field("hierarchical_memory_limit"),
+            hierarchical_memsw_limit: field("hierarchical_memsw_limit"),
+        }
+    }
+
+    /// Takes the standard fields from memory.stat of cgroup v2, which names some of them differently
+    pub fn from_v2(stat: &HashMap<String, u64>) -> Self {
+        let field = |key: &str| stat.get(key).copied().unwrap_or(0);
+        Self {
+            cache: field("file"),
+            rss: field("anon"),
+            rss_huge: field("anon_thp"),
+            shmem: field("shmem"),
+            mapped_file: field("file_mapped"),
+            dirty: field("file_dirty"),
+            writeback: field("file_writeback"),
+            pgfault: field("pgfault"),
+            pgmajfault: field("pgmajfault"),
+            active_anon: field("active_anon"),
+            inactive_anon: field("inactive_anon"),
+            active_file: field("active_file"),
+            inactive_file: field("inactive_file"),
+            unevictable: field("unevictable"),
+            ..Default::default()
+        }
+    }
+}
+
+/// Reports the number of tasks in a cgroup
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PidsStats {
+    // number of tasks which are currently in the cgroup
+    pub current: u64,
+    // maximum number of tasks, None if the number of tasks is not limited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+}
+
+/// Reports the I/O of a cgroup for every block device it accessed
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BlkioStats {
+    // counters of the devices keyed by major:minor, e.g. 8:0
+    pub devices: BTreeMap<String, BlkioDeviceStats>,
+}
+
+/// I/O of a cgroup on a single block device
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BlkioDeviceStats {
+    // number of bytes transferred from and to the device
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    // number of I/O operations issued to the device
+    pub read_ops: u64,
+    pub write_ops: u64,
+}
+
+/// Parses a file which contains a single numeric value, e.g. cpuacct.usage
+pub fn parse_single_value(file_path: &Path) -> Result<u64> {
+    let value = fs::read_to_string(file_path)?;
+    let value = value.trim();
+    value
+        .parse()
+        .map_err(|_| anyhow!("failed to parse {:?} from {:?}", value, file_path))
+}
+
+/// Parses a file which contains either a single numeric value or "max" if there is no
+/// limit, e.g. pids.max. Returns None if there is no limit
+pub fn parse_max_value(file_path: &Path) -> Result<Option<u64>> {
+    let value = fs::read_to_string(file_path)?;
+    match value.trim() {
+        "max" => Ok(None),
+        value => value
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow!("failed to parse {:?} from {:?}", value, file_path)),
+    }
+}
+
+/// Parses a file in the flat keyed format where every line consists of a key and a value
+/// separated by a space, e.g. cpu.stat
+pub fn parse_flat_keyed_data(file_path: &Path) -> Result<HashMap<String, u64>> {
+    let content = fs::read_to_string(file_path)?;
+    let mut stats = HashMap::new();
+
+    for line in content.lines() {
+        let mut entry = line.split_whitespace();
+        if let (Some(key), Some(value)) = (entry.next(), entry.next()) {
+            let value = value.parse().map_err(|_| {
+                anyhow!("failed to parse {:?} of {} in {:?}", value, key, file_path)
+            })?;
+            stats.insert(key.to_owned(), value);
+        }
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgroups::test::{create_temp_dir, set_fixture};
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_single_value() {
+        let tmp =
+            create_temp_dir("test_parse_single_value").expect("create temp directory for test");
+        let file = set_fixture(&tmp, "cpuacct.usage", "1234567\n").expect("set fixture");
+
+        let value = parse_single_value(&file).expect("parse single value");
+        assert_eq!(value, 1234567);
+    }
+
+    #[test]
+    fn test_parse_max_value() {
+        let tmp = create_temp_dir("test_parse_max_value").expect("create temp directory for test");
+        let limited = set_fixture(&tmp, "pids.max", "1024\n").expect("set fixture");
+        assert_eq!(parse_max_value(&limited).expect("parse value"), Some(1024));
+
+        let unlimited = set_fixture(&tmp, "pids.max", "max\n").expect("set fixture");
+        assert_eq!(parse_max_value(&unlimited).expect("parse value"), None);
+    }
+
+    #[test]
+    fn test_parse_flat_keyed_data() {
+        let tmp =
+            create_temp_dir("test_parse_flat_keyed_data").expect("create temp directory for test");
+        let file =
+            set_fixture(&tmp, "cpu.stat", "nr_periods 10\nnr_throttled 5\n").expect("set fixture");
+
+        let stats = parse_flat_keyed_data(&file).expect("parse flat keyed data");
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["nr_periods"], 10);
+        assert_eq!(stats["nr_throttled"], 5);
+    }
+
+    #[test]
+    fn test_parse_flat_keyed_data_invalid_value() {
+        let tmp = create_temp_dir("test_parse_flat_keyed_data_invalid_value")
+            .expect("create temp directory for test");
+        let file = set_fixture(&tmp, "cpu.stat", "nr_periods ten\n").expect("set fixture");
+
+        assert!(parse_flat_keyed_data(&file).is_err());
+    }
+
+    #[test]
+    fn test_stats_serialization() {
+        let stats = Stats {
+            cpu: CpuStats {
+                usage: CpuUsage {
+                    usage_total: 3000,
+                    usage_user: 2000,
+                    usage_kernel: 1000,
+                },
+                throttling: CpuThrottling {
+                    periods: 10,
+                    throttled_periods: 5,
+                    throttled_time: 500,
+                },
+            },
+            memory: MemoryStats {
+                usage: 1024,
+                max_usage: 2048,
+                limit: 4096,
+                fail_count: 2,
+                oom: 1,
+                oom_kill: 1,
+                stat: MemoryStat {
+                    cache: 512,
+                    rss: 256,
+                    ..Default::default()
+                },
+            },
+            pids: PidsStats {
+                current: 3,
+                limit: None,
+            },
+            blkio: BlkioStats {
+                devices: vec![(
+                    "8:0".to_string(),
+                    BlkioDeviceStats {
+                        read_bytes: 4096,
+                        write_bytes: 8192,
+                        read_ops: 1,
+                        write_ops: 2,
+                    },
+                )]
+                .into_iter()
+                .collect(),
+            },
+        };
+
+        let value = serde_json::to_value(&stats).expect("serialize stats");
+        assert_eq!(
+            value,
+            json!({
+                "cpu": {
+                    "usage": {
+                        "usage_total": 3000,
+                        "usage_user": 2000,
+                        "usage_kernel": 1000,
+                    },
+                    "throttling": {
+                        "periods": 10,
+                        "throttled_periods": 5,
+                        "throttled_time": 500,
+                    },
+                },
+                "memory": {
+                    "usage": 1024,
+                    "max_usage": 2048,
+                    "limit": 4096,
+                    "fail_count": 2,
+                    "oom": 1,
+                    "oom_kill": 1,
+                    "stat": {
+                        "cache": 512,
+                        "rss": 256,
+                        "rss_huge": 0,
+                        "shmem": 0,
+                        "mapped_file": 0,
+                        "dirty": 0,
+                        "writeback": 0,
+                        "swap": 0,
+                        "pgpgin": 0,
+                        "pgpgout": 0,
+                        "pgfault": 0,
+                        "pgmajfault": 0,
+                        "active_anon": 0,
+                        "inactive_anon": 0,
+                        "active_file": 0,
+                        "inactive_file": 0,
+                        "unevictable": 0,
+                        "hierarchical_memory_limit": 0,
+                        "hierarchical_memsw_limit": 0,
+                    },
+                },
+                "pids": {
+                    "current": 3,
+                },
+                "blkio": {
+                    "devices": {
+                        "8:0": {
+                            "read_bytes": 4096,
+                            "write_bytes": 8192,
+                            "read_ops": 1,
+                            "write_ops": 2,
+                        },
+                    },
+                },
+            })
+        );
+    }
+}
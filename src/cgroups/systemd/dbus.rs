@@ -0,0 +1,165 @@
+//! Client of the manager object of systemd on D-Bus. The methods are called
+//! with busctl, which takes care of the authentication and the wire format
+//! check https://www.freedesktop.org/wiki/Software/systemd/dbus/ for more information
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use nix::unistd;
+
+const SYSTEMD_DESTINATION: &str = "org.freedesktop.systemd1";
+const SYSTEMD_OBJECT: &str = "/org/freedesktop/systemd1";
+const SYSTEMD_MANAGER: &str = "org.freedesktop.systemd1.Manager";
+
+/// Value of a unit property together with its D-Bus type
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Str(String),
+    U64(u64),
+    U32Array(Vec<u32>),
+}
+
+impl Value {
+    fn signature(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "b",
+            Value::Str(_) => "s",
+            Value::U64(_) => "t",
+            Value::U32Array(_) => "au",
+        }
+    }
+
+    // busctl expects the number of elements in front of the elements of an array
+    fn args(&self) -> Vec<String> {
+        match self {
+            Value::Bool(b) => vec![b.to_string()],
+            Value::Str(s) => vec![s.clone()],
+            Value::U64(n) => vec![n.to_string()],
+            Value::U32Array(items) => {
+                let mut args = vec![items.len().to_string()];
+                args.extend(items.iter().map(|i| i.to_string()));
+                args
+            }
+        }
+    }
+}
+
+/// Property of a unit, e.g. ("MemoryMax", Value::U64(1024))
+pub type Property = (&'static str, Value);
+
+/// Returns the arguments of busctl for an array of properties with the signature a(sv)
+fn properties_args(properties: &[Property]) -> Vec<String> {
+    let mut args = vec![properties.len().to_string()];
+    for (name, value) in properties {
+        args.push(name.to_string());
+        args.push(value.signature().to_string());
+        args.extend(value.args());
+    }
+    args
+}
+
+pub struct Client {
+    // the units of an unprivileged user are managed by the systemd instance of the user
+    user: bool,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self {
+            user: !unistd::geteuid().is_root(),
+        }
+    }
+
+    /// Creates a transient unit, which only exists until it is stopped
+    pub fn start_transient_unit(&self, unit_name: &str, properties: &[Property]) -> Result<()> {
+        let mut args = vec![unit_name.to_string(), "replace".to_string()];
+        args.extend(properties_args(properties));
+        // no auxiliary units are created along with the unit
+        args.push("0".to_string());
+        self.call("StartTransientUnit", "ssa(sv)a(sa(sv))", &args)
+    }
+
+    /// Changes the properties of a unit until it is stopped
+    pub fn set_unit_properties(&self, unit_name: &str, properties: &[Property]) -> Result<()> {
+        let mut args = vec![unit_name.to_string(), "true".to_string()];
+        args.extend(properties_args(properties));
+        self.call("SetUnitProperties", "sba(sv)", &args)
+    }
+
+    /// Moves processes into the cgroup of a unit, which has already been started
+    pub fn attach_processes_to_unit(&self, unit_name: &str, pids: &[u32]) -> Result<()> {
+        let mut args = vec![unit_name.to_string(), String::new()];
+        args.extend(Value::U32Array(pids.to_vec()).args());
+        self.call("AttachProcessesToUnit", "ssau", &args)
+    }
+
+    pub fn stop_unit(&self, unit_name: &str) -> Result<()> {
+        self.call(
+            "StopUnit",
+            "ss",
+            &[unit_name.to_string(), "replace".to_string()],
+        )
+    }
+
+    fn call(&self, method: &str, signature: &str, args: &[String]) -> Result<()> {
+        log::debug!("call {} of systemd with {:?}", method, args);
+        let output = Command::new("busctl")
+            .arg(if self.user { "--user" } else { "--system" })
+            .args(["call", SYSTEMD_DESTINATION, SYSTEMD_OBJECT, SYSTEMD_MANAGER])
+            .arg(method)
+            .arg(signature)
+            .args(args)
+            .output()
+            .context("failed to run busctl")?;
+        if !output.status.success() {
+            bail!(
+                "failed to call {} of systemd: {}",
+                method,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_properties_args() {
+        let properties = vec![
+            ("Slice", Value::Str("system.slice".to_string())),
+            ("Delegate", Value::Bool(true)),
+            ("MemoryMax", Value::U64(1048576)),
+            ("PIDs", Value::U32Array(vec![1000, 1001])),
+        ];
+
+        assert_eq!(
+            properties_args(&properties),
+            vec![
+                "4",
+                "Slice",
+                "s",
+                "system.slice",
+                "Delegate",
+                "b",
+                "true",
+                "MemoryMax",
+                "t",
+                "1048576",
+                "PIDs",
+                "au",
+                "2",
+                "1000",
+                "1001"
+            ]
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use std::{
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use nix::unistd::Pid;
+use oci_spec::{LinuxCpu, LinuxMemory, LinuxResources};
+
+use super::dbus::{Client, Property, Value};
+use crate::cgroups::{
+    common::{
+        self, CgroupManager, CgroupVersion, FreezerState, ManagerOptions, DEFAULT_CGROUP_ROOT,
+    },
+    stats::Stats,
+    v2::cpu::Cpu,
+};
+
+/// Slice of the units of containers, if the cgroups path does not name one
+const DEFAULT_SLICE: &str = "system.slice";
+/// Number of checks whether systemd has moved the process into the cgroup of the unit
+const ADD_TASK_RETRIES: u32 = 100;
+const ADD_TASK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Parts of a cgroups path in the form slice:prefix:name, e.g. system.slice:youki:1234
+#[derive(Debug, PartialEq)]
+struct CgroupsPath {
+    parent: String,
+    prefix: String,
+    name: String,
+}
+
+pub struct Manager {
+    // name of the scope unit, which owns the cgroup of the container
+    unit_name: String,
+    // slice, which the scope unit is placed in
+    slice: String,
+    // the resource restrictions are translated to the properties of cgroup v2, if it is the only hierarchy
+    unified: bool,
+    // the cgroup is created by systemd, but it is still read and frozen through its interface files
+    fs_manager: Box<dyn CgroupManager>,
+    client: Client,
+}
+
+impl Manager {
+    pub fn new(cgroups_path: PathBuf, options: ManagerOptions) -> Result<Self> {
+        let cgroups_path = parse_cgroups_path(&cgroups_path)?;
+        let unit_name = unit_name(&cgroups_path);
+        let cgroup_path = expand_slice(&cgroups_path.parent)?.join(&unit_name);
+        log::debug!(
+            "systemd unit {} has the cgroup {:?}",
+            unit_name,
+            cgroup_path
+        );
+
+        let unified = common::detect_cgroup_version(Path::new(DEFAULT_CGROUP_ROOT))?
+            == CgroupVersion::Unified;
+        Ok(Self {
+            unit_name,
+            slice: cgroups_path.parent,
+            unified,
+            fs_manager: common::create_fs_cgroup_manager(cgroup_path, options)?,
+            client: Client::new(),
+        })
+    }
+
+    /// Returns the properties of the unit, which restrict the cpu and memory usage
+    fn resource_properties(&self, resources: &LinuxResources) -> Result<Vec<Property>> {
+        let mut properties = Vec::new();
+        if let Some(cpu) = &resources.cpu {
+            self.cpu_properties(cpu, &mut properties)?;
+        }
+        if let Some(memory) = &resources.memory {
+            self.memory_properties(memory, &mut properties)?;
+        }
+
+        Ok(properties)
+    }
+
+    fn cpu_properties(&self, cpu: &LinuxCpu, properties: &mut Vec<Property>) -> Result<()> {
+        if cpu.cpus.is_some()
+            || cpu.mems.is_some()
+            || cpu.realtime_period.is_some()
+            || cpu.realtime_runtime.is_some()
+        {
+            bail!("only the shares, quota and period of cpu are supported by the systemd cgroup driver yet")
+        }
+
+        if let Some(shares) = cpu.shares {
+            if shares != 0 {
+                properties.push(if self.unified {
+                    (
+                        "CPUWeight",
+                        Value::U64(Cpu::convert_shares_to_cgroup2(shares)),
+                    )
+                } else {
+                    ("CPUShares", Value::U64(shares))
+                });
+            }
+        }
+
+        // systemd takes the quota as the cpu time per second
+        let period = cpu.period.filter(|p| *p > 0);
+        if let Some(quota) = cpu.quota.filter(|q| *q > 0) {
+            let period = period.unwrap_or(100_000);
+            properties.push((
+                "CPUQuotaPerSecUSec",
+                Value::U64(quota as u64 * 1_000_000 / period),
+            ));
+        }
+        if let Some(period) = period {
+            properties.push(("CPUQuotaPeriodUSec", Value::U64(period)));
+        }
+
+        Ok(())
+    }
+
+    fn memory_properties(
+        &self,
+        memory: &LinuxMemory,
+        properties: &mut Vec<Property>,
+    ) -> Result<()> {
+        if memory.kernel.is_some()
+            || memory.kernel_tcp.is_some()
+            || memory.swappiness.is_some()
+            || (!self.unified && memory.swap.is_some())
+            || (!self.unified && memory.reservation.is_some())
+        {
+            bail!("only the limit, reservation and swap of memory are supported by the systemd cgroup driver yet")
+        }
+
+        // a negative value means that the usage is not limited
+        let bytes = |value: i64| Value::U64(if value < 0 { u64::MAX } else { value as u64 });
+        if let Some(limit) = memory.limit.filter(|l| *l != 0) {
+            let name = if self.unified {
+                "MemoryMax"
+            } else {
+                "MemoryLimit"
+            };
+            properties.push((name, bytes(limit)));
+        }
+        if let Some(reservation) = memory.reservation.filter(|r| *r != 0) {
+            properties.push(("MemoryLow", bytes(reservation)));
+        }
+        // the swap limit of the spec includes the memory limit, unlike the one of systemd
+        match (memory.limit, memory.swap) {
+            (_, None) | (_, Some(0)) => {}
+            (_, Some(swap)) if swap < 0 => properties.push(("MemorySwapMax", bytes(swap))),
+            (Some(limit), Some(swap)) if limit > 0 && swap >= limit => {
+                properties.push(("MemorySwapMax", bytes(swap - limit)))
+            }
+            (limit, Some(swap)) => bail!(
+                "memory swap {} has to be greater than the memory limit {:?}",
+                swap,
+                limit
+            ),
+        }
+
+        Ok(())
+    }
+}
+
+impl CgroupManager for Manager {
+    fn add_task(&self, pid: Pid) -> Result<()> {
+        let pids = [pid.as_raw() as u32];
+        // the unit exists already if a process is executed in a running container. A scope
+        // without processes is removed by systemd, so the unit is only started once
+        if !self.fs_manager.get_all_pids()?.is_empty() {
+            return self.client.attach_processes_to_unit(&self.unit_name, &pids);
+        }
+
+        let properties = vec![
+            (
+                "Description",
+                Value::Str(format!("youki container {}", self.unit_name)),
+            ),
+            ("Slice", Value::Str(self.slice.clone())),
+            // the other controllers are still restricted through the interface files of the cgroup
+            ("Delegate", Value::Bool(true)),
+            ("CPUAccounting", Value::Bool(true)),
+            ("MemoryAccounting", Value::Bool(true)),
+            ("DefaultDependencies", Value::Bool(false)),
+            ("PIDs", Value::U32Array(pids.to_vec())),
+        ];
+        self.client
+            .start_transient_unit(&self.unit_name, &properties)?;
+
+        // the unit is started by a job of systemd, which moves the process asynchronously
+        for _ in 0..ADD_TASK_RETRIES {
+            if self.fs_manager.get_all_pids()?.contains(&pid) {
+                return Ok(());
+            }
+            thread::sleep(ADD_TASK_RETRY_INTERVAL);
+        }
+        bail!(
+            "systemd did not move {} into the cgroup of {}",
+            pid,
+            self.unit_name
+        )
+    }
+
+    fn apply(&self, linux_resources: &LinuxResources) -> Result<()> {
+        let properties = self.resource_properties(linux_resources)?;
+        if !properties.is_empty() {
+            self.client
+                .set_unit_properties(&self.unit_name, &properties)?;
+        }
+
+        // the other controllers are restricted through the interface files, as the cgroup is delegated
+        let remaining = LinuxResources {
+            cpu: None,
+            memory: None,
+            ..linux_resources.clone()
+        };
+        self.fs_manager.apply(&remaining)
+    }
+
+    fn remove(&self) -> Result<()> {
+        // systemd removes the cgroup along with the unit
+        match self.client.stop_unit(&self.unit_name) {
+            Err(e) if e.to_string().contains("not loaded") => Ok(()),
+            result => result,
+        }
+    }
+
+    fn freeze(&self, state: FreezerState) -> Result<()> {
+        self.fs_manager.freeze(state)
+    }
+
+    fn stats(&self) -> Result<Stats> {
+        self.fs_manager.stats()
+    }
+
+    fn get_all_pids(&self) -> Result<Vec<Pid>> {
+        self.fs_manager.get_all_pids()
+    }
+}
+
+fn parse_cgroups_path(cgroups_path: &Path) -> Result<CgroupsPath> {
+    let path = cgroups_path
+        .to_str()
+        .ok_or_else(|| anyhow!("cgroups path {:?} is not valid utf-8", cgroups_path))?;
+    let parts: Vec<&str> = path.split(':').collect();
+    if parts.len() != 3 || parts[2].is_empty() {
+        bail!(
+            "cgroups path {:?} has to be in the form slice:prefix:name for the systemd cgroup driver",
+            path
+        )
+    }
+
+    let parent = match parts[0] {
+        "" => DEFAULT_SLICE,
+        slice if slice.ends_with(".slice") => slice,
+        slice => bail!("{:?} of cgroups path {:?} is not a slice", slice, path),
+    };
+    Ok(CgroupsPath {
+        parent: parent.to_string(),
+        prefix: parts[1].to_string(),
+        name: parts[2].to_string(),
+    })
+}
+
+fn unit_name(cgroups_path: &CgroupsPath) -> String {
+    if cgroups_path.prefix.is_empty() {
+        format!("{}.scope", cgroups_path.name)
+    } else {
+        format!("{}-{}.scope", cgroups_path.prefix, cgroups_path.name)
+    }
+}
+
+/// Returns the path of the cgroup of a slice below the cgroup root. Every dash in the
+/// name of a slice starts a nested slice, e.g. a-b.slice is the cgroup /a.slice/a-b.slice
+fn expand_slice(slice: &str) -> Result<PathBuf> {
+    // the root slice is the only slice, whose name starts with a dash
+    if slice == "-.slice" {
+        return Ok(PathBuf::from("/"));
+    }
+
+    let name = slice
+        .strip_suffix(".slice")
+        .filter(|name| !name.contains('/'))
+        .ok_or_else(|| anyhow!("{:?} is not a valid name of a slice", slice))?;
+    let mut path = PathBuf::from("/");
+    let mut prefix = String::new();
+    for component in name.split('-') {
+        if component.is_empty() {
+            bail!("{:?} is not a valid name of a slice", slice)
+        }
+        prefix.push_str(component);
+        path.push(format!("{}.slice", prefix));
+        prefix.push('-');
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cgroups_path() {
+        let cgroups_path =
+            parse_cgroups_path(Path::new("machine.slice:youki:1234")).expect("parse cgroups path");
+
+        assert_eq!(
+            cgroups_path,
+            CgroupsPath {
+                parent: "machine.slice".to_string(),
+                prefix: "youki".to_string(),
+                name: "1234".to_string(),
+            }
+        );
+        assert_eq!(unit_name(&cgroups_path), "youki-1234.scope");
+    }
+
+    #[test]
+    fn test_parse_cgroups_path_defaults() {
+        let cgroups_path = parse_cgroups_path(Path::new("::1234")).expect("parse cgroups path");
+
+        assert_eq!(cgroups_path.parent, DEFAULT_SLICE);
+        assert_eq!(unit_name(&cgroups_path), "1234.scope");
+    }
+
+    #[test]
+    fn test_parse_invalid_cgroups_path() {
+        for path in &[
+            "/youki/1234",
+            "system.slice:youki",
+            "system.slice:youki:",
+            "system.scope:youki:1234",
+            "a:b:c:d",
+        ] {
+            assert!(
+                parse_cgroups_path(Path::new(path)).is_err(),
+                "{} should be rejected",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand_slice() {
+        assert_eq!(expand_slice("-.slice").unwrap(), PathBuf::from("/"));
+        assert_eq!(
+            expand_slice("system.slice").unwrap(),
+            PathBuf::from("/system.slice")
+        );
+        assert_eq!(
+            expand_slice("user-1000.slice").unwrap(),
+            PathBuf::from("/user.slice/user-1000.slice")
+        );
+        assert_eq!(
+            expand_slice("a-b-c.slice").unwrap(),
+            PathBuf::from("/a.slice/a-b.slice/a-b-c.slice")
+        );
+        assert!(expand_slice("a--b.slice").is_err());
+        assert!(expand_slice("-a.slice").is_err());
+        assert!(expand_slice("a/b.slice").is_err());
+        assert!(expand_slice("system").is_err());
+    }
+}
@@ -0,0 +1,5 @@
+//! Cgroups which are managed by systemd. The cgroup of a container belongs to a transient
+//! scope unit, which is created through the D-Bus API of systemd
+
+pub mod dbus;
+pub mod manager;
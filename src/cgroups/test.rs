@@ -2,58 +2,13 @@
 
 use anyhow::Result;
 use std::{
-    fs,
     io::Write,
-    ops::Deref,
     path::{Path, PathBuf},
 };
 
 use oci_spec::LinuxCpu;
 
-pub struct TempDir {
-    path: Option<PathBuf>,
-}
-
-impl TempDir {
-    pub fn new<P: Into<PathBuf>>(path: P) -> Result<Self> {
-        let p = path.into();
-        std::fs::create_dir_all(&p)?;
-        Ok(Self { path: Some(p) })
-    }
-
-    pub fn path(&self) -> &Path {
-        self.path
-            .as_ref()
-            .expect("temp dir has already been removed")
-    }
-
-    pub fn remove(&mut self) {
-        if let Some(p) = &self.path {
-            let _ = fs::remove_dir_all(p);
-            self.path = None;
-        }
-    }
-}
-
-impl Drop for TempDir {
-    fn drop(&mut self) {
-        self.remove();
-    }
-}
-
-impl AsRef<Path> for TempDir {
-    fn as_ref(&self) -> &Path {
-        self.path()
-    }
-}
-
-impl Deref for TempDir {
-    type Target = Path;
-
-    fn deref(&self) -> &Self::Target {
-        self.path()
-    }
-}
+pub use crate::utils::{create_temp_dir, TempDir};
 
 pub fn setup(testname: &str, cgroup_file: &str) -> (TempDir, PathBuf) {
     let tmp = create_temp_dir(testname).expect("create temp directory for test");
@@ -76,11 +31,6 @@ pub fn set_fixture(temp_dir: &Path, filename: &str, val: &str) -> Result<PathBuf
     Ok(full_path)
 }
 
-pub fn create_temp_dir(test_name: &str) -> Result<TempDir> {
-    let dir = TempDir::new(std::env::temp_dir().join(test_name))?;
-    Ok(dir)
-}
-
 pub struct LinuxCpuBuilder {
     resource: LinuxCpu,
 }
@@ -92,10 +42,13 @@ impl LinuxCpuBuilder {
                 shares: None,
                 quota: None,
                 period: None,
+                burst: None,
                 realtime_runtime: None,
                 realtime_period: None,
                 cpus: None,
                 mems: None,
+                memory_migrate: None,
+                cpu_exclusive: None,
             },
         }
     }
@@ -115,6 +68,11 @@ impl LinuxCpuBuilder {
         self
     }
 
+    pub fn with_burst(mut self, burst: u64) -> Self {
+        self.resource.burst = Some(burst);
+        self
+    }
+
     pub fn with_realtime_runtime(mut self, runtime: i64) -> Self {
         self.resource.realtime_runtime = Some(runtime);
         self
@@ -135,6 +93,16 @@ impl LinuxCpuBuilder {
         self
     }
 
+    pub fn with_memory_migrate(mut self, memory_migrate: bool) -> Self {
+        self.resource.memory_migrate = Some(memory_migrate);
+        self
+    }
+
+    pub fn with_cpu_exclusive(mut self, cpu_exclusive: bool) -> Self {
+        self.resource.cpu_exclusive = Some(cpu_exclusive);
+        self
+    }
+
     pub fn build(self) -> LinuxCpu {
         self.resource
     }
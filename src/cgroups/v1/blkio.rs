@@ -1,66 +1,156 @@
-use std::{
-    fs::{self},
-    path::Path,
-};
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{anyhow, Result};
 
 use crate::cgroups::{
-    common::{self, CGROUP_PROCS},
+    common,
+    stats::{BlkioDeviceStats, BlkioStats, StatsProvider},
     v1::Controller,
 };
-use oci_spec::{LinuxBlockIo, LinuxResources};
+use oci_spec::{LinuxBlockIo, LinuxResources, LinuxThrottleDevice};
 
+const CGROUP_BLKIO_WEIGHT: &str = "blkio.weight";
+const CGROUP_BLKIO_LEAF_WEIGHT: &str = "blkio.leaf_weight";
+const CGROUP_BLKIO_WEIGHT_DEVICE: &str = "blkio.weight_device";
+const CGROUP_BLKIO_LEAF_WEIGHT_DEVICE: &str = "blkio.leaf_weight_device";
 const CGROUP_BLKIO_THROTTLE_READ_BPS: &str = "blkio.throttle.read_bps_device";
 const CGROUP_BLKIO_THROTTLE_WRITE_BPS: &str = "blkio.throttle.write_bps_device";
 const CGROUP_BLKIO_THROTTLE_READ_IOPS: &str = "blkio.throttle.read_iops_device";
 const CGROUP_BLKIO_THROTTLE_WRITE_IOPS: &str = "blkio.throttle.write_iops_device";
+const CGROUP_BLKIO_THROTTLE_IO_SERVICE_BYTES: &str = "blkio.throttle.io_service_bytes";
+const CGROUP_BLKIO_THROTTLE_IO_SERVICED: &str = "blkio.throttle.io_serviced";
 
 pub struct Blkio {}
 
 impl Controller for Blkio {
-    fn apply(
-        linux_resources: &LinuxResources,
-        cgroup_root: &Path,
-        pid: nix::unistd::Pid,
-    ) -> anyhow::Result<()> {
+    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path) -> anyhow::Result<()> {
         log::debug!("Apply blkio cgroup config");
-        fs::create_dir_all(cgroup_root)?;
 
         if let Some(blkio) = &linux_resources.block_io {
             Self::apply(cgroup_root, blkio)?;
         }
 
-        common::write_cgroup_file(cgroup_root.join(CGROUP_PROCS), pid)?;
         Ok(())
     }
 }
 
+impl StatsProvider for Blkio {
+    type Stats = BlkioStats;
+
+    fn stats(cgroup_root: &Path) -> Result<Self::Stats> {
+        let mut devices: BTreeMap<String, BlkioDeviceStats> = BTreeMap::new();
+        for (device, (read, write)) in
+            Self::parse_io_stats(&cgroup_root.join(CGROUP_BLKIO_THROTTLE_IO_SERVICE_BYTES))?
+        {
+            let stats = devices.entry(device).or_default();
+            stats.read_bytes = read;
+            stats.write_bytes = write;
+        }
+        for (device, (read, write)) in
+            Self::parse_io_stats(&cgroup_root.join(CGROUP_BLKIO_THROTTLE_IO_SERVICED))?
+        {
+            let stats = devices.entry(device).or_default();
+            stats.read_ops = read;
+            stats.write_ops = write;
+        }
+
+        Ok(BlkioStats { devices })
+    }
+}
+
 impl Blkio {
+    /// Parses the read and write counters of every device from a file like
+    /// blkio.throttle.io_service_bytes, whose lines look like '8:0 Read 4096'. The
+    /// sums of a device and of all devices are reported as Total and are skipped
+    fn parse_io_stats(file_path: &Path) -> Result<BTreeMap<String, (u64, u64)>> {
+        let content = fs::read_to_string(file_path)?;
+        let mut devices: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+
+        for line in content.lines() {
+            let entry: Vec<&str> = line.split_whitespace().collect();
+            let (device, op, value) = match entry.as_slice() {
+                [device, op, value] => (device, op, value),
+                // the sum of all devices, e.g. 'Total 12288'
+                _ => continue,
+            };
+            if *op != "Read" && *op != "Write" {
+                continue;
+            }
+
+            let value: u64 = value.parse().map_err(|_| {
+                anyhow!(
+                    "failed to parse {:?} of {} {} in {:?}",
+                    value,
+                    device,
+                    op,
+                    file_path
+                )
+            })?;
+            let counters = devices.entry(device.to_string()).or_default();
+            if *op == "Read" {
+                counters.0 = value;
+            } else {
+                counters.1 = value;
+            }
+        }
+
+        Ok(devices)
+    }
+
     fn apply(root_path: &Path, blkio: &LinuxBlockIo) -> anyhow::Result<()> {
-        for trbd in &blkio.blkio_throttle_read_bps_device {
-            common::write_cgroup_file_str(
-                &root_path.join(CGROUP_BLKIO_THROTTLE_READ_BPS),
-                &format!("{}:{} {}", trbd.major, trbd.minor, trbd.rate),
-            )?;
+        if let Some(weight) = blkio.blkio_weight {
+            if weight != 0 {
+                common::write_cgroup_file(root_path.join(CGROUP_BLKIO_WEIGHT), weight)?;
+            }
         }
 
-        for twbd in &blkio.blkio_throttle_write_bps_device {
-            common::write_cgroup_file_str(
-                &root_path.join(CGROUP_BLKIO_THROTTLE_WRITE_BPS),
-                &format!("{}:{} {}", twbd.major, twbd.minor, twbd.rate),
-            )?;
+        if let Some(leaf_weight) = blkio.blkio_leaf_weight {
+            if leaf_weight != 0 {
+                common::write_cgroup_file(root_path.join(CGROUP_BLKIO_LEAF_WEIGHT), leaf_weight)?;
+            }
         }
 
-        for trid in &blkio.blkio_throttle_read_iops_device {
-            common::write_cgroup_file_str(
-                &root_path.join(CGROUP_BLKIO_THROTTLE_READ_IOPS),
-                &format!("{}:{} {}", trid.major, trid.minor, trid.rate),
-            )?;
+        for wd in &blkio.blkio_weight_device {
+            if let Some(weight) = wd.weight {
+                common::append_cgroup_file_str(
+                    root_path.join(CGROUP_BLKIO_WEIGHT_DEVICE),
+                    &format!("{}:{} {}", wd.major, wd.minor, weight),
+                )?;
+            }
+
+            if let Some(leaf_weight) = wd.leaf_weight {
+                common::append_cgroup_file_str(
+                    root_path.join(CGROUP_BLKIO_LEAF_WEIGHT_DEVICE),
+                    &format!("{}:{} {}", wd.major, wd.minor, leaf_weight),
+                )?;
+            }
         }
 
-        for twid in &blkio.blkio_throttle_write_iops_device {
-            common::write_cgroup_file_str(
-                &root_path.join(CGROUP_BLKIO_THROTTLE_WRITE_IOPS),
-                &format!("{}:{} {}", twid.major, twid.minor, twid.rate),
+        Self::apply_throttle(
+            &root_path.join(CGROUP_BLKIO_THROTTLE_READ_BPS),
+            &blkio.blkio_throttle_read_bps_device,
+        )?;
+        Self::apply_throttle(
+            &root_path.join(CGROUP_BLKIO_THROTTLE_WRITE_BPS),
+            &blkio.blkio_throttle_write_bps_device,
+        )?;
+        Self::apply_throttle(
+            &root_path.join(CGROUP_BLKIO_THROTTLE_READ_IOPS),
+            &blkio.blkio_throttle_read_iops_device,
+        )?;
+        Self::apply_throttle(
+            &root_path.join(CGROUP_BLKIO_THROTTLE_WRITE_IOPS),
+            &blkio.blkio_throttle_write_iops_device,
+        )?;
+
+        Ok(())
+    }
+
+    fn apply_throttle(path: &Path, devices: &[LinuxThrottleDevice]) -> anyhow::Result<()> {
+        for td in devices {
+            common::append_cgroup_file_str(
+                path,
+                &format!("{}:{} {}", td.major, td.minor, td.rate),
             )?;
         }
 
@@ -71,8 +161,9 @@ impl Blkio {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cgroups::test::setup;
-    use oci_spec::{LinuxBlockIo, LinuxThrottleDevice};
+    use crate::cgroups::test::{create_temp_dir, set_fixture, setup};
+    use oci_spec::{LinuxBlockIo, LinuxThrottleDevice, LinuxWeightDevice};
+    use std::fs;
 
     struct BlockIoBuilder {
         block_io: LinuxBlockIo,
@@ -93,6 +184,16 @@ mod tests {
             Self { block_io }
         }
 
+        fn with_weight(mut self, weight: u16) -> Self {
+            self.block_io.blkio_weight = Some(weight);
+            self
+        }
+
+        fn with_weight_device(mut self, weight_device: Vec<LinuxWeightDevice>) -> Self {
+            self.block_io.blkio_weight_device = weight_device;
+            self
+        }
+
         fn with_read_bps(mut self, throttle: Vec<LinuxThrottleDevice>) -> Self {
             self.block_io.blkio_throttle_read_bps_device = throttle;
             self
@@ -134,7 +235,7 @@ mod tests {
         let content = fs::read_to_string(throttle)
             .unwrap_or_else(|_| panic!("read {} content", CGROUP_BLKIO_THROTTLE_READ_BPS));
 
-        assert_eq!("8:0 102400", content);
+        assert_eq!("8:0 102400\n", content);
     }
 
     #[test]
@@ -153,7 +254,7 @@ mod tests {
         let content = fs::read_to_string(throttle)
             .unwrap_or_else(|_| panic!("read {} content", CGROUP_BLKIO_THROTTLE_WRITE_BPS));
 
-        assert_eq!("8:0 102400", content);
+        assert_eq!("8:0 102400\n", content);
     }
 
     #[test]
@@ -172,7 +273,7 @@ mod tests {
         let content = fs::read_to_string(throttle)
             .unwrap_or_else(|_| panic!("read {} content", CGROUP_BLKIO_THROTTLE_READ_IOPS));
 
-        assert_eq!("8:0 102400", content);
+        assert_eq!("8:0 102400\n", content);
     }
 
     #[test]
@@ -194,6 +295,130 @@ mod tests {
         let content = fs::read_to_string(throttle)
             .unwrap_or_else(|_| panic!("read {} content", CGROUP_BLKIO_THROTTLE_WRITE_IOPS));
 
-        assert_eq!("8:0 102400", content);
+        assert_eq!("8:0 102400\n", content);
+    }
+
+    #[test]
+    fn test_set_blkio_weight() {
+        let (tmp, weight) = setup("test_set_blkio_weight", CGROUP_BLKIO_WEIGHT);
+
+        let blkio = BlockIoBuilder::new().with_weight(500).build();
+
+        Blkio::apply(&tmp, &blkio).expect("apply blkio");
+        let content = fs::read_to_string(weight)
+            .unwrap_or_else(|_| panic!("read {} content", CGROUP_BLKIO_WEIGHT));
+
+        assert_eq!("500", content);
+    }
+
+    #[test]
+    fn test_set_blkio_weight_device() {
+        let (tmp, weight_device) =
+            setup("test_set_blkio_weight_device", CGROUP_BLKIO_WEIGHT_DEVICE);
+        let leaf_weight_device = set_fixture(&tmp, CGROUP_BLKIO_LEAF_WEIGHT_DEVICE, "")
+            .unwrap_or_else(|_| panic!("set fixture for {}", CGROUP_BLKIO_LEAF_WEIGHT_DEVICE));
+
+        let blkio = BlockIoBuilder::new()
+            .with_weight_device(vec![
+                LinuxWeightDevice {
+                    major: 8,
+                    minor: 0,
+                    weight: Some(100),
+                    leaf_weight: Some(50),
+                },
+                LinuxWeightDevice {
+                    major: 8,
+                    minor: 16,
+                    weight: Some(200),
+                    leaf_weight: None,
+                },
+            ])
+            .build();
+
+        Blkio::apply(&tmp, &blkio).expect("apply blkio");
+        let content = fs::read_to_string(weight_device)
+            .unwrap_or_else(|_| panic!("read {} content", CGROUP_BLKIO_WEIGHT_DEVICE));
+        assert_eq!("8:0 100\n8:16 200\n", content);
+
+        let content = fs::read_to_string(leaf_weight_device)
+            .unwrap_or_else(|_| panic!("read {} content", CGROUP_BLKIO_LEAF_WEIGHT_DEVICE));
+        assert_eq!("8:0 50\n", content);
+    }
+
+    #[test]
+    fn test_set_blkio_multiple_throttle_devices() {
+        let (tmp, throttle) = setup(
+            "test_set_blkio_multiple_throttle_devices",
+            CGROUP_BLKIO_THROTTLE_READ_BPS,
+        );
+
+        let blkio = BlockIoBuilder::new()
+            .with_read_bps(vec![
+                LinuxThrottleDevice {
+                    major: 8,
+                    minor: 0,
+                    rate: 102400,
+                },
+                LinuxThrottleDevice {
+                    major: 8,
+                    minor: 16,
+                    rate: 204800,
+                },
+                LinuxThrottleDevice {
+                    major: 253,
+                    minor: 1,
+                    rate: 4096,
+                },
+            ])
+            .build();
+
+        Blkio::apply(&tmp, &blkio).expect("apply blkio");
+        let content = fs::read_to_string(throttle)
+            .unwrap_or_else(|_| panic!("read {} content", CGROUP_BLKIO_THROTTLE_READ_BPS));
+
+        assert_eq!("8:0 102400\n8:16 204800\n253:1 4096\n", content);
+    }
+
+    #[test]
+    fn test_stat_blkio() {
+        let tmp = create_temp_dir("test_stat_blkio").expect("create temp directory for test");
+        set_fixture(
+            &tmp,
+            CGROUP_BLKIO_THROTTLE_IO_SERVICE_BYTES,
+            "8:0 Read 4096\n8:0 Write 8192\n8:0 Sync 12288\n8:0 Async 0\n8:0 Discard 0\n8:0 Total 12288\n\
+             8:16 Read 1024\n8:16 Write 0\n8:16 Sync 1024\n8:16 Async 0\n8:16 Discard 0\n8:16 Total 1024\n\
+             Total 13312\n",
+        )
+        .expect("set fixture for io service bytes");
+        set_fixture(
+            &tmp,
+            CGROUP_BLKIO_THROTTLE_IO_SERVICED,
+            "8:0 Read 1\n8:0 Write 2\n8:0 Sync 3\n8:0 Async 0\n8:0 Discard 0\n8:0 Total 3\n\
+             8:16 Read 4\n8:16 Write 0\n8:16 Sync 4\n8:16 Async 0\n8:16 Discard 0\n8:16 Total 4\n\
+             Total 7\n",
+        )
+        .expect("set fixture for io serviced");
+
+        let stats = Blkio::stats(&tmp).expect("get blkio stats");
+
+        assert_eq!(stats.devices.len(), 2);
+        assert_eq!(
+            stats.devices["8:0"],
+            BlkioDeviceStats {
+                read_bytes: 4096,
+                write_bytes: 8192,
+                read_ops: 1,
+                write_ops: 2,
+            }
+        );
+        assert_eq!(
+            stats.devices["8:16"],
+            BlkioDeviceStats {
+                read_bytes: 1024,
+                write_bytes: 0,
+                read_ops: 4,
+                write_ops: 0,
+            }
+        );
     }
 }
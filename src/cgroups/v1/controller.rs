@@ -1,10 +1,19 @@
-use std::path::Path;
+use std::{fs, path::Path};
 
 use anyhow::Result;
 use nix::unistd::Pid;
 
+use crate::cgroups::common::{self, CGROUP_PROCS};
 use oci_spec::LinuxResources;
 
 pub trait Controller {
-    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path, pid: Pid) -> Result<()>;
+    /// Adds a new task specified by its pid to the cgroup
+    fn add_task(pid: Pid, cgroup_path: &Path) -> Result<()> {
+        fs::create_dir_all(cgroup_path)?;
+        common::write_cgroup_file(cgroup_path.join(CGROUP_PROCS), pid)?;
+        Ok(())
+    }
+
+    /// Applies resource restrictions to the cgroup
+    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path) -> Result<()>;
 }
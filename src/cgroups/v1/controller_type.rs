@@ -1,9 +1,11 @@
 use std::string::ToString;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ControllerType {
     Cpu,
     CpuSet,
     Devices,
+    Freezer,
     HugeTlb,
     Pids,
     Memory,
@@ -18,6 +20,7 @@ impl ToString for ControllerType {
             Self::Cpu => "cpu".into(),
             Self::CpuSet => "cpuset".into(),
             Self::Devices => "devices".into(),
+            Self::Freezer => "freezer".into(),
             Self::HugeTlb => "hugetlb".into(),
             Self::Pids => "pids".into(),
             Self::Memory => "memory".into(),
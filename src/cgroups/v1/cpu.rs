@@ -1,34 +1,52 @@
-use std::{fs, path::Path};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
-use nix::unistd::Pid;
+use anyhow::{Context, Result};
 use oci_spec::{LinuxCpu, LinuxResources};
 
-use crate::cgroups::common::{self, CGROUP_PROCS};
+use crate::cgroups::{
+    common,
+    stats::{self, CpuStats, CpuThrottling, CpuUsage, StatsProvider},
+};
 
 use super::Controller;
 
 const CGROUP_CPU_SHARES: &str = "cpu.shares";
 const CGROUP_CPU_QUOTA: &str = "cpu.cfs_quota_us";
 const CGROUP_CPU_PERIOD: &str = "cpu.cfs_period_us";
+const CGROUP_CPU_BURST: &str = "cpu.cfs_burst_us";
 const CGROUP_CPU_RT_RUNTIME: &str = "cpu.rt_runtime_us";
 const CGROUP_CPU_RT_PERIOD: &str = "cpu.rt_period_us";
+const CGROUP_CPU_STAT: &str = "cpu.stat";
+const CGROUP_CPUACCT_USAGE: &str = "cpuacct.usage";
+const CGROUP_CPUACCT_STAT: &str = "cpuacct.stat";
 
 pub struct Cpu {}
 
 impl Controller for Cpu {
-    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path, pid: Pid) -> Result<()> {
+    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path) -> Result<()> {
         log::debug!("Apply Cpu cgroup config");
-        fs::create_dir_all(cgroup_root)?;
         if let Some(cpu) = &linux_resources.cpu {
             Self::apply(cgroup_root, cpu)?;
         }
 
-        common::write_cgroup_file(cgroup_root.join(CGROUP_PROCS), pid)?;
         Ok(())
     }
 }
 
+impl StatsProvider for Cpu {
+    type Stats = CpuStats;
+
+    fn stats(cgroup_root: &Path) -> Result<Self::Stats> {
+        Ok(CpuStats {
+            usage: Self::get_usage(cgroup_root)?,
+            throttling: Self::get_throttling(cgroup_root)?,
+        })
+    }
+}
+
 impl Cpu {
     fn apply(root_path: &Path, cpu: &LinuxCpu) -> Result<()> {
         if let Some(cpu_shares) = cpu.shares {
@@ -44,31 +62,140 @@ impl Cpu {
         }
 
         if let Some(cpu_quota) = cpu.quota {
+            // -1 has to be passed through as it removes a previously set quota
             if cpu_quota != 0 {
                 common::write_cgroup_file(root_path.join(CGROUP_CPU_QUOTA), cpu_quota)?;
             }
         }
 
-        if let Some(rt_runtime) = cpu.realtime_runtime {
-            if rt_runtime != 0 {
-                common::write_cgroup_file(root_path.join(CGROUP_CPU_RT_RUNTIME), rt_runtime)?;
+        if let Some(cpu_burst) = cpu.burst {
+            if cpu_burst != 0 {
+                // burst is only supported by newer kernels
+                let burst_path = root_path.join(CGROUP_CPU_BURST);
+                if burst_path.exists() {
+                    common::write_cgroup_file(burst_path, cpu_burst)?;
+                } else {
+                    log::warn!("cpu burst is not supported by the kernel and will be ignored");
+                }
             }
         }
 
+        // the runtime can not exceed the period, so the period is set first
         if let Some(rt_period) = cpu.realtime_period {
             if rt_period != 0 {
                 common::write_cgroup_file(root_path.join(CGROUP_CPU_RT_PERIOD), rt_period)?;
             }
         }
 
+        if let Some(rt_runtime) = cpu.realtime_runtime {
+            if rt_runtime != 0 {
+                if rt_runtime > 0 {
+                    Self::ensure_rt_runtime_of_ancestors(root_path, rt_runtime)?;
+                }
+                common::write_cgroup_file(root_path.join(CGROUP_CPU_RT_RUNTIME), rt_runtime)?;
+            }
+        }
+
         Ok(())
     }
+
+    // the realtime runtime of a cgroup can not exceed the runtime of its parent, so the
+    // ancestors with less runtime are raised first, starting with the topmost one
+    fn ensure_rt_runtime_of_ancestors(cgroup_path: &Path, rt_runtime: i64) -> Result<()> {
+        for ancestor in Self::rt_ancestors(cgroup_path) {
+            let runtime_path = ancestor.join(CGROUP_CPU_RT_RUNTIME);
+            let content = fs::read_to_string(&runtime_path)
+                .with_context(|| format!("failed to read {:?}", runtime_path))?;
+            let current: i64 = content
+                .trim()
+                .parse()
+                .with_context(|| format!("failed to parse {:?} of {:?}", content, runtime_path))?;
+            // a larger allocation of an ancestor may be shared with other cgroups, -1 is unlimited
+            if current == -1 || current >= rt_runtime {
+                continue;
+            }
+
+            log::debug!(
+                "raise {:?} from {} to {}",
+                runtime_path,
+                current,
+                rt_runtime
+            );
+            common::write_cgroup_file(runtime_path, rt_runtime)?;
+        }
+
+        Ok(())
+    }
+
+    // returns the ancestors of a cgroup below the mount point of the hierarchy, the topmost first
+    fn rt_ancestors(cgroup_path: &Path) -> Vec<PathBuf> {
+        let mut ancestors: Vec<PathBuf> = cgroup_path
+            .ancestors()
+            .skip(1)
+            .take_while(|ancestor| ancestor.join(CGROUP_CPU_RT_RUNTIME).exists())
+            .map(Path::to_path_buf)
+            .collect();
+        ancestors.reverse();
+        ancestors
+    }
+
+    fn get_throttling(cgroup_root: &Path) -> Result<CpuThrottling> {
+        let mut throttling = CpuThrottling::default();
+        let stat = stats::parse_flat_keyed_data(&cgroup_root.join(CGROUP_CPU_STAT))?;
+        if let Some(periods) = stat.get("nr_periods") {
+            throttling.periods = *periods;
+        }
+        if let Some(throttled_periods) = stat.get("nr_throttled") {
+            throttling.throttled_periods = *throttled_periods;
+        }
+        if let Some(throttled_time) = stat.get("throttled_time") {
+            throttling.throttled_time = *throttled_time;
+        }
+
+        Ok(throttling)
+    }
+
+    // cpuacct is usually co-mounted with cpu, but if it is not the usage
+    // cannot be determined and is reported as zero
+    fn get_usage(cgroup_root: &Path) -> Result<CpuUsage> {
+        let mut usage = CpuUsage::default();
+
+        let usage_path = cgroup_root.join(CGROUP_CPUACCT_USAGE);
+        if usage_path.exists() {
+            usage.usage_total = stats::parse_single_value(&usage_path)?;
+        }
+
+        let stat_path = cgroup_root.join(CGROUP_CPUACCT_STAT);
+        if stat_path.exists() {
+            // user and system time are reported in USER_HZ units
+            let stat = stats::parse_flat_keyed_data(&stat_path)?;
+            let ns_per_tick = Self::ns_per_tick();
+            if let Some(user) = stat.get("user") {
+                usage.usage_user = user * ns_per_tick;
+            }
+            if let Some(system) = stat.get("system") {
+                usage.usage_kernel = system * ns_per_tick;
+            }
+        }
+
+        Ok(usage)
+    }
+
+    fn ns_per_tick() -> u64 {
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks > 0 {
+            1_000_000_000 / ticks as u64
+        } else {
+            // USER_HZ is 100 on almost all platforms
+            10_000_000
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cgroups::test::{set_fixture, setup, LinuxCpuBuilder};
+    use crate::cgroups::test::{create_temp_dir, set_fixture, setup, LinuxCpuBuilder};
     use std::fs;
 
     #[test]
@@ -104,6 +231,53 @@ mod tests {
         assert_eq!(content, QUOTA.to_string());
     }
 
+    #[test]
+    fn test_set_burst() {
+        // arrange
+        const BURST: u64 = 100000;
+        let (tmp, burst) = setup("test_set_burst", CGROUP_CPU_BURST);
+        let cpu = LinuxCpuBuilder::new().with_burst(BURST).build();
+
+        // act
+        Cpu::apply(&tmp, &cpu).expect("apply cpu");
+
+        // assert
+        let content = fs::read_to_string(burst)
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPU_BURST));
+        assert_eq!(content, BURST.to_string());
+    }
+
+    #[test]
+    fn test_set_burst_not_supported() {
+        // arrange
+        let tmp = create_temp_dir("test_set_burst_not_supported")
+            .expect("create temp directory for test");
+        let cpu = LinuxCpuBuilder::new().with_burst(100000).build();
+
+        // act
+        let result = Cpu::apply(&tmp, &cpu);
+
+        // assert
+        assert!(result.is_ok(), "a missing burst file should be tolerated");
+        assert!(!tmp.join(CGROUP_CPU_BURST).exists());
+    }
+
+    #[test]
+    fn test_set_quota_unlimited() {
+        // arrange
+        const QUOTA: i64 = -1;
+        let (tmp, max) = setup("test_set_quota_unlimited", CGROUP_CPU_QUOTA);
+        let cpu = LinuxCpuBuilder::new().with_quota(QUOTA).build();
+
+        // act
+        Cpu::apply(&tmp, &cpu).expect("apply cpu");
+
+        // assert
+        let content = fs::read_to_string(max)
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPU_QUOTA));
+        assert_eq!(content, QUOTA.to_string());
+    }
+
     #[test]
     fn test_set_period() {
         // arrange
@@ -153,4 +327,118 @@ mod tests {
             .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPU_RT_PERIOD));
         assert_eq!(content, PERIOD.to_string());
     }
+
+    #[test]
+    fn test_set_rt_runtime_of_ancestors() {
+        // arrange
+        const RUNTIME: i64 = 100000;
+        let tmp = create_temp_dir("test_set_rt_runtime_of_ancestors")
+            .expect("create temp directory for test");
+        let parent = tmp.join("parent");
+        let child = parent.join("child");
+        fs::create_dir_all(&child).expect("create cgroup directories");
+        set_fixture(&tmp, CGROUP_CPU_RT_RUNTIME, "0")
+            .unwrap_or_else(|_| panic!("set test fixture for {}", CGROUP_CPU_RT_RUNTIME));
+        set_fixture(&parent, CGROUP_CPU_RT_RUNTIME, "0")
+            .unwrap_or_else(|_| panic!("set test fixture for {}", CGROUP_CPU_RT_RUNTIME));
+        set_fixture(&child, CGROUP_CPU_RT_RUNTIME, "")
+            .unwrap_or_else(|_| panic!("set test fixture for {}", CGROUP_CPU_RT_RUNTIME));
+        let cpu = LinuxCpuBuilder::new()
+            .with_realtime_runtime(RUNTIME)
+            .build();
+
+        // act
+        Cpu::apply(&child, &cpu).expect("apply cpu");
+
+        // assert
+        assert_eq!(
+            Cpu::rt_ancestors(&child),
+            vec![tmp.to_path_buf(), parent.clone()],
+            "the topmost ancestor is written first"
+        );
+        for cgroup in &[&*tmp, &parent, &child] {
+            let content = fs::read_to_string(cgroup.join(CGROUP_CPU_RT_RUNTIME))
+                .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPU_RT_RUNTIME));
+            assert_eq!(content, RUNTIME.to_string());
+        }
+    }
+
+    #[test]
+    fn test_keep_larger_rt_runtime_of_ancestors() {
+        // arrange
+        let tmp = create_temp_dir("test_keep_larger_rt_runtime_of_ancestors")
+            .expect("create temp directory for test");
+        let child = tmp.join("child");
+        fs::create_dir_all(&child).expect("create cgroup directory");
+        set_fixture(&tmp, CGROUP_CPU_RT_RUNTIME, "950000\n")
+            .unwrap_or_else(|_| panic!("set test fixture for {}", CGROUP_CPU_RT_RUNTIME));
+        set_fixture(&child, CGROUP_CPU_RT_RUNTIME, "")
+            .unwrap_or_else(|_| panic!("set test fixture for {}", CGROUP_CPU_RT_RUNTIME));
+        let cpu = LinuxCpuBuilder::new().with_realtime_runtime(500).build();
+
+        // act
+        Cpu::apply(&child, &cpu).expect("apply cpu");
+
+        // assert
+        let content = fs::read_to_string(tmp.join(CGROUP_CPU_RT_RUNTIME))
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPU_RT_RUNTIME));
+        assert_eq!(content, "950000\n");
+    }
+
+    #[test]
+    fn test_stat_throttling() {
+        // arrange
+        let tmp = create_temp_dir("test_stat_throttling").expect("create temp directory for test");
+        set_fixture(
+            &tmp,
+            CGROUP_CPU_STAT,
+            "nr_periods 165000\nnr_throttled 27\nthrottled_time 1080\n",
+        )
+        .unwrap_or_else(|_| panic!("set test fixture for {}", CGROUP_CPU_STAT));
+        set_fixture(&tmp, CGROUP_CPUACCT_USAGE, "12262454190222160\n")
+            .unwrap_or_else(|_| panic!("set test fixture for {}", CGROUP_CPUACCT_USAGE));
+        set_fixture(&tmp, CGROUP_CPUACCT_STAT, "user 1300888\nsystem 364592\n")
+            .unwrap_or_else(|_| panic!("set test fixture for {}", CGROUP_CPUACCT_STAT));
+
+        // act
+        let stats = Cpu::stats(&tmp).expect("get cpu stats");
+
+        // assert
+        let ns_per_tick = Cpu::ns_per_tick();
+        let expected = CpuStats {
+            usage: CpuUsage {
+                usage_total: 12262454190222160,
+                usage_user: 1300888 * ns_per_tick,
+                usage_kernel: 364592 * ns_per_tick,
+            },
+            throttling: CpuThrottling {
+                periods: 165000,
+                throttled_periods: 27,
+                throttled_time: 1080,
+            },
+        };
+        assert_eq!(stats, expected);
+    }
+
+    #[test]
+    fn test_stat_without_cpuacct() {
+        // arrange
+        let tmp =
+            create_temp_dir("test_stat_without_cpuacct").expect("create temp directory for test");
+        set_fixture(
+            &tmp,
+            CGROUP_CPU_STAT,
+            "nr_periods 10\nnr_throttled 2\nthrottled_time 300\n",
+        )
+        .unwrap_or_else(|_| panic!("set test fixture for {}", CGROUP_CPU_STAT));
+
+        // act
+        let stats = Cpu::stats(&tmp).expect("get cpu stats");
+
+        // assert
+        assert_eq!(stats.usage, CpuUsage::default());
+        assert_eq!(stats.throttling.periods, 10);
+        assert_eq!(stats.throttling.throttled_periods, 2);
+        assert_eq!(stats.throttling.throttled_time, 300);
+    }
 }
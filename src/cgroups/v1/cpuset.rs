@@ -1,6 +1,6 @@
 use std::{fs, path::Path};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use nix::unistd::Pid;
 use oci_spec::{LinuxCpu, LinuxResources};
 
@@ -10,43 +10,100 @@ use super::{Controller, ControllerType};
 
 const CGROUP_CPUSET_CPUS: &str = "cpuset.cpus";
 const CGROUP_CPUSET_MEMS: &str = "cpuset.mems";
+const CGROUP_CPUSET_MEMORY_MIGRATE: &str = "cpuset.memory_migrate";
+const CGROUP_CPUSET_CPU_EXCLUSIVE: &str = "cpuset.cpu_exclusive";
+const CGROUP_CLONE_CHILDREN: &str = "cgroup.clone_children";
 
 pub struct CpuSet {}
 
 impl Controller for CpuSet {
-    fn apply(linux_resources: &LinuxResources, cgroup_path: &Path, pid: Pid) -> Result<()> {
-        log::debug!("Apply CpuSet cgroup config");
-        fs::create_dir_all(cgroup_path)?;
+    fn add_task(pid: Pid, cgroup_path: &Path) -> Result<()> {
+        let root = common::get_cgroupv1_mount_path(&ControllerType::CpuSet.to_string())?;
+        Self::create_cgroup(&root, cgroup_path)?;
+
+        // cgroups, which existed before, may still lack cpus and mems
+        Self::inherit_from_ancestors(&root, cgroup_path, CGROUP_CPUSET_CPUS)?;
+        Self::inherit_from_ancestors(&root, cgroup_path, CGROUP_CPUSET_MEMS)?;
+
+        common::write_cgroup_file(cgroup_path.join(CGROUP_PROCS), pid)?;
+        Ok(())
+    }
 
-        Self::ensure_not_empty(cgroup_path, CGROUP_CPUSET_CPUS)?;
-        Self::ensure_not_empty(cgroup_path, CGROUP_CPUSET_MEMS)?;
+    fn apply(linux_resources: &LinuxResources, cgroup_path: &Path) -> Result<()> {
+        log::debug!("Apply CpuSet cgroup config");
 
         if let Some(cpuset) = &linux_resources.cpu {
-            Self::apply(cgroup_path, cpuset)?;
+            Self::apply(cgroup_path, cpuset, Path::new(common::ONLINE_CPUS))?;
         }
 
-        common::write_cgroup_file(cgroup_path.join(CGROUP_PROCS), pid)?;
         Ok(())
     }
 }
 
 impl CpuSet {
-    fn apply(cgroup_path: &Path, cpuset: &LinuxCpu) -> Result<()> {
+    fn apply(cgroup_path: &Path, cpuset: &LinuxCpu, online_cpus: &Path) -> Result<()> {
         if let Some(cpus) = &cpuset.cpus {
+            common::validate_cpus(cpus, online_cpus)?;
             common::write_cgroup_file_str(cgroup_path.join(CGROUP_CPUSET_CPUS), cpus)?;
-        } 
+        }
 
         if let Some(mems) = &cpuset.mems {
             common::write_cgroup_file_str(cgroup_path.join(CGROUP_CPUSET_MEMS), mems)?;
-        } 
+        }
 
+        // the pages of the tasks are moved to the new memory nodes, if mems changes
+        if let Some(memory_migrate) = cpuset.memory_migrate {
+            common::write_cgroup_file(
+                cgroup_path.join(CGROUP_CPUSET_MEMORY_MIGRATE),
+                memory_migrate as u8,
+            )?;
+        }
+
+        // the cpus are not shared with the siblings of the cgroup. The kernel rejects the flag
+        // if the cpus of a sibling overlap or if the parent is not exclusive itself
+        if let Some(cpu_exclusive) = cpuset.cpu_exclusive {
+            common::write_cgroup_file(
+                cgroup_path.join(CGROUP_CPUSET_CPU_EXCLUSIVE),
+                cpu_exclusive as u8,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // creates the missing cgroups from the root of the cpuset hierarchy down to the cgroup. A new
+    // cgroup copies cpus and mems of its parent, if clone_children is set in the parent before
+    fn create_cgroup(root: &Path, cgroup_path: &Path) -> Result<()> {
+        let mut current = root.to_path_buf();
+        for component in cgroup_path.strip_prefix(root)?.components() {
+            let child = current.join(component);
+            if !child.exists() {
+                Self::set_clone_children(&current)?;
+                fs::create_dir(&child)?;
+            }
+            current = child;
+        }
+        Ok(())
+    }
+
+    fn set_clone_children(cgroup_path: &Path) -> Result<()> {
+        let path = cgroup_path.join(CGROUP_CLONE_CHILDREN);
+        if fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {:?}", path))?
+            .trim()
+            != "1"
+        {
+            common::write_cgroup_file_str(&path, "1")?;
+        }
         Ok(())
     }
 
     // if a task is moved into the cgroup and a value has not been set for cpus and mems
-    // Errno 28 (no space left on device) will be returned. Therefore we set the value from the parent if required.
-    fn ensure_not_empty(cgroup_path: &Path, interface_file: &str) -> Result<()> {
-        let mut current = common::get_cgroupv1_mount_path(&ControllerType::CpuSet.to_string())?;
+    // Errno 28 (no space left on device) will be returned. Therefore the value of the parent is set if required.
+    // Walks down from the root of the cpuset hierarchy to the cgroup and copies
+    // the value of the parent into every cgroup on the way which has no value yet
+    fn inherit_from_ancestors(root: &Path, cgroup_path: &Path, interface_file: &str) -> Result<()> {
+        let mut current = root.to_path_buf();
         let relative_cgroup_path = cgroup_path.strip_prefix(&current)?;
 
         for component in relative_cgroup_path.components() {
@@ -74,7 +131,13 @@ mod tests {
     use std::fs;
 
     use super::*;
-    use crate::cgroups::test::{setup, LinuxCpuBuilder};
+    use crate::cgroups::test::{create_temp_dir, set_fixture, setup, LinuxCpuBuilder};
+    use std::path::PathBuf;
+
+    fn online_cpus(tmp: &Path) -> PathBuf {
+        set_fixture(tmp, "online", "0-7\n").expect("set fixture for online cpus");
+        tmp.join("online")
+    }
 
     #[test]
     fn test_set_cpus() {
@@ -83,7 +146,7 @@ mod tests {
         let cpuset = LinuxCpuBuilder::new().with_cpus("1-3".to_owned()).build();
 
         // act
-        CpuSet::apply(&tmp, &cpuset).expect("apply cpuset");
+        CpuSet::apply(&tmp, &cpuset, &online_cpus(&tmp)).expect("apply cpuset");
 
         // assert
         let content = fs::read_to_string(&cpus)
@@ -91,6 +154,17 @@ mod tests {
         assert_eq!(content, "1-3");
     }
 
+    #[test]
+    fn test_set_offline_cpus() {
+        let (tmp, cpus) = setup("test_set_offline_cpus", CGROUP_CPUSET_CPUS);
+        let cpuset = LinuxCpuBuilder::new().with_cpus("6-9".to_owned()).build();
+
+        assert!(CpuSet::apply(&tmp, &cpuset, &online_cpus(&tmp)).is_err());
+        let content = fs::read_to_string(&cpus)
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPUSET_CPUS));
+        assert_eq!(content, "", "offline cpus must not be written");
+    }
+
     #[test]
     fn test_set_mems() {
         // arrange
@@ -98,11 +172,141 @@ mod tests {
         let cpuset = LinuxCpuBuilder::new().with_mems("1-3".to_owned()).build();
 
         // act
-        CpuSet::apply(&tmp, &cpuset).expect("apply cpuset");
+        CpuSet::apply(&tmp, &cpuset, &online_cpus(&tmp)).expect("apply cpuset");
 
         // assert
         let content = fs::read_to_string(&mems)
             .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPUSET_MEMS));
         assert_eq!(content, "1-3");
     }
+
+    #[test]
+    fn test_set_cpuset_flags() {
+        let (tmp, memory_migrate) = setup("test_set_cpuset_flags", CGROUP_CPUSET_MEMORY_MIGRATE);
+        set_fixture(&tmp, CGROUP_CPUSET_CPU_EXCLUSIVE, "1").expect("set fixture for cpu exclusive");
+        let cpuset = LinuxCpuBuilder::new()
+            .with_memory_migrate(true)
+            .with_cpu_exclusive(false)
+            .build();
+
+        CpuSet::apply(&tmp, &cpuset, &online_cpus(&tmp)).expect("apply cpuset");
+
+        let content = fs::read_to_string(&memory_migrate)
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPUSET_MEMORY_MIGRATE));
+        assert_eq!(content, "1");
+        let content = fs::read_to_string(tmp.join(CGROUP_CPUSET_CPU_EXCLUSIVE))
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPUSET_CPU_EXCLUSIVE));
+        assert_eq!(content, "0");
+    }
+
+    #[test]
+    fn test_cpuset_flags_are_kept_if_unset() {
+        let (tmp, _) = setup("test_cpuset_flags_are_kept_if_unset", CGROUP_CPUSET_CPUS);
+        let memory_migrate = set_fixture(&tmp, CGROUP_CPUSET_MEMORY_MIGRATE, "1")
+            .expect("set fixture for memory migrate");
+        let cpuset = LinuxCpuBuilder::new().with_cpus("1-3".to_owned()).build();
+
+        CpuSet::apply(&tmp, &cpuset, &online_cpus(&tmp)).expect("apply cpuset");
+
+        let content = fs::read_to_string(&memory_migrate)
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPUSET_MEMORY_MIGRATE));
+        assert_eq!(content, "1");
+        assert!(!tmp.join(CGROUP_CPUSET_CPU_EXCLUSIVE).exists());
+    }
+
+    #[test]
+    fn test_create_cgroup_with_clone_children() {
+        let tmp = create_temp_dir("test_create_cgroup_with_clone_children")
+            .expect("create temp directory for test");
+        let parent = tmp.join("youki");
+        fs::create_dir(&parent).expect("create parent cgroup");
+        set_fixture(&tmp, CGROUP_CLONE_CHILDREN, "0\n").expect("set fixture for root");
+        set_fixture(&parent, CGROUP_CLONE_CHILDREN, "0\n").expect("set fixture for parent");
+
+        CpuSet::create_cgroup(&tmp, &parent.join("container")).expect("create cgroup");
+
+        assert!(parent.join("container").is_dir());
+        let content = fs::read_to_string(parent.join(CGROUP_CLONE_CHILDREN))
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CLONE_CHILDREN));
+        assert_eq!(content.trim(), "1");
+        // the parent existed already, so the root is not changed
+        let content = fs::read_to_string(tmp.join(CGROUP_CLONE_CHILDREN))
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CLONE_CHILDREN));
+        assert_eq!(content, "0\n");
+    }
+
+    #[test]
+    fn test_create_cgroup_without_clone_children() {
+        let tmp = create_temp_dir("test_create_cgroup_without_clone_children")
+            .expect("create temp directory for test");
+
+        // the leaf is only created once clone_children has been set in its parent
+        assert!(CpuSet::create_cgroup(&tmp, &tmp.join("container")).is_err());
+        assert!(!tmp.join("container").exists());
+    }
+
+    #[test]
+    fn test_inherit_from_ancestors() {
+        // arrange
+        let tmp =
+            create_temp_dir("test_inherit_from_ancestors").expect("create temp directory for test");
+        let leaf = tmp.join("youki").join("container");
+        fs::create_dir_all(&leaf).expect("create cgroup directories");
+        set_fixture(&tmp, CGROUP_CPUSET_CPUS, "0-3\n").expect("set fixture for root cpus");
+        set_fixture(&tmp.join("youki"), CGROUP_CPUSET_CPUS, "\n")
+            .expect("set fixture for parent cpus");
+        set_fixture(&leaf, CGROUP_CPUSET_CPUS, "").expect("set fixture for leaf cpus");
+
+        // act
+        CpuSet::inherit_from_ancestors(&tmp, &leaf, CGROUP_CPUSET_CPUS)
+            .expect("inherit cpus from ancestors");
+
+        // assert
+        let parent_content = fs::read_to_string(tmp.join("youki").join(CGROUP_CPUSET_CPUS))
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPUSET_CPUS));
+        assert_eq!(parent_content, "0-3\n");
+        let leaf_content = fs::read_to_string(leaf.join(CGROUP_CPUSET_CPUS))
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPUSET_CPUS));
+        assert_eq!(leaf_content, "0-3\n");
+    }
+
+    #[test]
+    fn test_inherit_from_ancestors_keeps_existing_value() {
+        // arrange
+        let tmp = create_temp_dir("test_inherit_from_ancestors_keeps_existing_value")
+            .expect("create temp directory for test");
+        let leaf = tmp.join("container");
+        fs::create_dir_all(&leaf).expect("create cgroup directory");
+        set_fixture(&tmp, CGROUP_CPUSET_MEMS, "0-1").expect("set fixture for root mems");
+        set_fixture(&leaf, CGROUP_CPUSET_MEMS, "1").expect("set fixture for leaf mems");
+
+        // act
+        CpuSet::inherit_from_ancestors(&tmp, &leaf, CGROUP_CPUSET_MEMS)
+            .expect("inherit mems from ancestors");
+
+        // assert
+        let content = fs::read_to_string(leaf.join(CGROUP_CPUSET_MEMS))
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_CPUSET_MEMS));
+        assert_eq!(content, "1");
+    }
+
+    #[test]
+    fn test_inherit_from_empty_root() {
+        // arrange
+        let tmp = create_temp_dir("test_inherit_from_empty_root")
+            .expect("create temp directory for test");
+        let leaf = tmp.join("container");
+        fs::create_dir_all(&leaf).expect("create cgroup directory");
+        set_fixture(&tmp, CGROUP_CPUSET_CPUS, "").expect("set fixture for root cpus");
+        set_fixture(&leaf, CGROUP_CPUSET_CPUS, "").expect("set fixture for leaf cpus");
+
+        // act
+        let result = CpuSet::inherit_from_ancestors(&tmp, &leaf, CGROUP_CPUSET_CPUS);
+
+        // assert
+        assert!(
+            result.is_err(),
+            "an empty parent value cannot be inherited and should return an error"
+        );
+    }
 }
@@ -1,18 +1,23 @@
-use std::{fs::create_dir_all, path::Path};
+use std::path::Path;
 
 use anyhow::Result;
-use nix::unistd::Pid;
 
-use crate::cgroups::common::{self, CGROUP_PROCS};
+use crate::cgroups::common;
 use crate::{cgroups::v1::Controller, rootfs::default_devices};
 use oci_spec::{LinuxDeviceCgroup, LinuxDeviceType, LinuxResources};
 
+const CGROUP_DEVICES_ALLOW: &str = "devices.allow";
+const CGROUP_DEVICES_DENY: &str = "devices.deny";
+
 pub struct Devices {}
 
 impl Controller for Devices {
-    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path, pid: Pid) -> Result<()> {
+    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path) -> Result<()> {
         log::debug!("Apply Devices cgroup config");
-        create_dir_all(&cgroup_root)?;
+
+        // deny access to all devices first, so that only the devices which are
+        // explicitly allowed afterwards can be accessed
+        common::write_cgroup_file_str(cgroup_root.join(CGROUP_DEVICES_DENY), "a")?;
 
         for d in &linux_resources.devices {
             Self::apply_device(d, cgroup_root)?;
@@ -27,7 +32,6 @@ impl Controller for Devices {
             Self::apply_device(&d, &cgroup_root)?;
         }
 
-        common::write_cgroup_file(cgroup_root.join(CGROUP_PROCS), pid)?;
         Ok(())
     }
 }
@@ -35,16 +39,16 @@ impl Controller for Devices {
 impl Devices {
     fn apply_device(device: &LinuxDeviceCgroup, cgroup_root: &Path) -> Result<()> {
         let path = if device.allow {
-            cgroup_root.join("devices.allow")
+            cgroup_root.join(CGROUP_DEVICES_ALLOW)
         } else {
-            cgroup_root.join("devices.deny")
+            cgroup_root.join(CGROUP_DEVICES_DENY)
         };
 
         common::write_cgroup_file_str(path, &device.to_string())?;
         Ok(())
     }
 
-    fn default_allow_devices() -> Vec<LinuxDeviceCgroup> {
+    pub fn default_allow_devices() -> Vec<LinuxDeviceCgroup> {
         vec![
             LinuxDeviceCgroup {
                 allow: true,
@@ -181,6 +185,85 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_deny_all_devices_first() {
+        let tmp =
+            create_temp_dir("test_deny_all_devices_first").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_DEVICES_ALLOW, "").expect("create allowed devices list");
+        set_fixture(&tmp, CGROUP_DEVICES_DENY, "").expect("create denied devices list");
+
+        let linux_resources = LinuxResources::default();
+        <Devices as Controller>::apply(&linux_resources, &tmp).expect("apply devices");
+
+        // the default devices are all allowed so the deny list only contains the deny-all rule
+        let denied_content = read_to_string(tmp.join(CGROUP_DEVICES_DENY)).expect("read to string");
+        assert_eq!(denied_content, "a");
+    }
+
+    #[test]
+    fn test_set_block_and_character_devices() {
+        let tmp = create_temp_dir("test_set_block_and_character_devices")
+            .expect("create temp directory for test");
+        [
+            (
+                LinuxDeviceCgroup {
+                    allow: true,
+                    typ: LinuxDeviceType::B,
+                    major: Some(8),
+                    minor: Some(0),
+                    access: "rw".to_string(),
+                },
+                "b 8:0 rw",
+            ),
+            (
+                LinuxDeviceCgroup {
+                    allow: true,
+                    typ: LinuxDeviceType::C,
+                    major: Some(1),
+                    minor: Some(3),
+                    access: "rwm".to_string(),
+                },
+                "c 1:3 rwm",
+            ),
+            (
+                LinuxDeviceCgroup {
+                    allow: false,
+                    typ: LinuxDeviceType::B,
+                    major: None,
+                    minor: None,
+                    access: "m".to_string(),
+                },
+                "b *:* m",
+            ),
+            (
+                LinuxDeviceCgroup {
+                    allow: false,
+                    typ: LinuxDeviceType::C,
+                    major: Some(136),
+                    minor: None,
+                    access: "rwm".to_string(),
+                },
+                "c 136:* rwm",
+            ),
+        ]
+        .iter()
+        .for_each(|(d, expected)| {
+            set_fixture(&tmp, CGROUP_DEVICES_ALLOW, "").expect("create allowed devices list");
+            set_fixture(&tmp, CGROUP_DEVICES_DENY, "").expect("create denied devices list");
+
+            Devices::apply_device(&d, &tmp).expect("Apply device");
+            let (written, untouched) = if d.allow {
+                (CGROUP_DEVICES_ALLOW, CGROUP_DEVICES_DENY)
+            } else {
+                (CGROUP_DEVICES_DENY, CGROUP_DEVICES_ALLOW)
+            };
+            let written_content = read_to_string(tmp.join(written)).expect("read to string");
+            assert_eq!(&written_content, expected);
+            let untouched_content = read_to_string(tmp.join(untouched)).expect("read to string");
+            assert_eq!(untouched_content, "");
+        });
+    }
+
     quickcheck! {
         fn property_test_apply_device(device: LinuxDeviceCgroup) -> bool {
             let tmp = create_temp_dir("property_test_apply_device").expect("create temp directory for test");
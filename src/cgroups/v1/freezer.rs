@@ -0,0 +1,149 @@
+use std::{fs, path::Path, thread, time::Duration};
+
+use anyhow::{bail, Result};
+
+use crate::cgroups::{
+    common::{self, FreezerState},
+    v1::Controller,
+};
+use oci_spec::LinuxResources;
+
+const CGROUP_FREEZER_STATE: &str = "freezer.state";
+const FREEZER_STATE_THAWED: &str = "THAWED";
+const FREEZER_STATE_FROZEN: &str = "FROZEN";
+const FREEZER_STATE_FREEZING: &str = "FREEZING";
+
+/// Number of times the freezer state is read back before giving up
+const FREEZER_RETRIES: u32 = 1000;
+/// Time to wait between two reads of the freezer state
+const FREEZER_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct Freezer {}
+
+impl Controller for Freezer {
+    fn apply(_: &LinuxResources, _: &Path) -> Result<()> {
+        log::debug!("Apply Freezer cgroup config");
+        Ok(())
+    }
+}
+
+impl Freezer {
+    pub fn apply(freezer_state: &FreezerState, cgroup_root: &Path) -> Result<()> {
+        match freezer_state {
+            FreezerState::Thawed => {
+                common::write_cgroup_file_str(
+                    cgroup_root.join(CGROUP_FREEZER_STATE),
+                    FREEZER_STATE_THAWED,
+                )?;
+                Self::wait_for_state(cgroup_root, FREEZER_STATE_THAWED)
+            }
+            FreezerState::Frozen => {
+                common::write_cgroup_file_str(
+                    cgroup_root.join(CGROUP_FREEZER_STATE),
+                    FREEZER_STATE_FROZEN,
+                )?;
+                // the kernel freezes the tasks asynchronously, so the state reads
+                // back as FREEZING until all tasks of the cgroup have been frozen
+                let result = Self::wait_for_state(cgroup_root, FREEZER_STATE_FROZEN);
+                if result.is_err() {
+                    // do not leave the cgroup half frozen
+                    let _ = common::write_cgroup_file_str(
+                        cgroup_root.join(CGROUP_FREEZER_STATE),
+                        FREEZER_STATE_THAWED,
+                    );
+                }
+                result
+            }
+        }
+    }
+
+    fn wait_for_state(cgroup_root: &Path, desired_state: &str) -> Result<()> {
+        for _ in 0..FREEZER_RETRIES {
+            let state = fs::read_to_string(cgroup_root.join(CGROUP_FREEZER_STATE))?;
+            match state.trim() {
+                state if state == desired_state => return Ok(()),
+                FREEZER_STATE_FREEZING => thread::sleep(FREEZER_RETRY_INTERVAL),
+                state => bail!(
+                    "unexpected freezer state {} while waiting for {}",
+                    state,
+                    desired_state
+                ),
+            }
+        }
+
+        bail!(
+            "freezer state did not become {} within {} ms",
+            desired_state,
+            FREEZER_RETRIES * FREEZER_RETRY_INTERVAL.as_millis() as u32
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgroups::test::{create_temp_dir, set_fixture};
+
+    #[test]
+    fn test_set_freezer_state() {
+        let tmp =
+            create_temp_dir("test_set_freezer_state").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_FREEZER_STATE, "").expect("set fixture for freezer state");
+
+        // set Frozen state
+        {
+            Freezer::apply(&FreezerState::Frozen, &tmp).expect("freeze cgroup");
+            let state_content =
+                fs::read_to_string(tmp.join(CGROUP_FREEZER_STATE)).expect("read to string");
+            assert_eq!(FREEZER_STATE_FROZEN, state_content);
+        }
+
+        // set Thawed state
+        {
+            Freezer::apply(&FreezerState::Thawed, &tmp).expect("thaw cgroup");
+            let state_content =
+                fs::read_to_string(tmp.join(CGROUP_FREEZER_STATE)).expect("read to string");
+            assert_eq!(FREEZER_STATE_THAWED, state_content);
+        }
+    }
+
+    #[test]
+    fn test_wait_while_freezing() {
+        let tmp =
+            create_temp_dir("test_wait_while_freezing").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_FREEZER_STATE, FREEZER_STATE_FREEZING)
+            .expect("set fixture for freezer state");
+
+        // the kernel finishes freezing the cgroup after a while. The state is
+        // replaced atomically so that the reader never sees a partially written file
+        let cgroup_root = tmp.to_path_buf();
+        let kernel = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            let frozen = set_fixture(&cgroup_root, "freezer.state.new", FREEZER_STATE_FROZEN)
+                .expect("set fixture for freezer state");
+            fs::rename(frozen, cgroup_root.join(CGROUP_FREEZER_STATE))
+                .expect("replace freezer state");
+        });
+
+        Freezer::wait_for_state(&tmp, FREEZER_STATE_FROZEN).expect("wait for frozen state");
+        kernel.join().expect("join fixture thread");
+
+        let state_content =
+            fs::read_to_string(tmp.join(CGROUP_FREEZER_STATE)).expect("read to string");
+        assert_eq!(FREEZER_STATE_FROZEN, state_content);
+    }
+
+    #[test]
+    fn test_wait_for_unexpected_state() {
+        let tmp = create_temp_dir("test_wait_for_unexpected_state")
+            .expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_FREEZER_STATE, FREEZER_STATE_THAWED)
+            .expect("set fixture for freezer state");
+
+        let result = Freezer::wait_for_state(&tmp, FREEZER_STATE_FROZEN);
+        assert!(
+            result.is_err(),
+            "a state other than FREEZING while waiting should be an error"
+        );
+    }
+}
@@ -1,12 +1,6 @@
-use std::{fs, path::Path};
+use std::path::Path;
 
-use anyhow::anyhow;
-use regex::Regex;
-
-use crate::cgroups::{
-    common::{self, CGROUP_PROCS},
-    v1::Controller,
-};
+use crate::cgroups::{common, v1::Controller};
 use oci_spec::{LinuxHugepageLimit, LinuxResources};
 
 pub struct Hugetlb {}
@@ -15,44 +9,28 @@ impl Controller for Hugetlb {
     fn apply(
         linux_resources: &LinuxResources,
         cgroup_root: &std::path::Path,
-        pid: nix::unistd::Pid,
     ) -> anyhow::Result<()> {
         log::debug!("Apply Hugetlb cgroup config");
-        fs::create_dir_all(cgroup_root)?;
 
         for hugetlb in &linux_resources.hugepage_limits {
             Self::apply(cgroup_root, hugetlb)?
         }
 
-        common::write_cgroup_file(cgroup_root.join(CGROUP_PROCS), pid)?;
         Ok(())
     }
 }
 
 impl Hugetlb {
     fn apply(root_path: &Path, hugetlb: &LinuxHugepageLimit) -> anyhow::Result<()> {
-        let re = Regex::new(r"(?P<pagesize>[0-9]+)[KMG]B")?;
-        let caps = re.captures(&hugetlb.page_size);
-        match caps {
-            None => return Err(anyhow!("page size must be in the format [0-9]+[KMG]B")),
-            Some(caps) => {
-                let page_size: u64 = caps["pagesize"].parse()?;
-                if !Self::is_power_of_two(page_size) {
-                    return Err(anyhow!("page size must be in the format of 2^(integer)"));
-                }
-            }
-        }
-
-        common::write_cgroup_file(
-            &root_path.join(format!("hugetlb.{}.limit_in_bytes", hugetlb.page_size)),
-            &hugetlb.limit,
+        let limit_file = root_path.join(format!("hugetlb.{}.limit_in_bytes", hugetlb.page_size));
+        common::validate_hugepage_size(
+            &hugetlb.page_size,
+            Path::new(common::HUGEPAGES),
+            &limit_file,
         )?;
+        common::write_cgroup_file(&limit_file, &hugetlb.limit)?;
         Ok(())
     }
-
-    fn is_power_of_two(number: u64) -> bool {
-        (number != 0) && (number & (number - 1)) == 0
-    }
 }
 
 #[cfg(test)]
@@ -77,6 +55,42 @@ mod tests {
         assert_eq!(hugetlb.limit.to_string(), content);
     }
 
+    #[test]
+    fn test_set_hugetlb_1gb() {
+        let page_file_name = "hugetlb.1GB.limit_in_bytes";
+        let tmp = create_temp_dir("test_set_hugetlb_1gb").expect("create temp directory for test");
+        set_fixture(&tmp, page_file_name, "0").expect("Set fixture for 1 GB page size");
+
+        let hugetlb = LinuxHugepageLimit {
+            page_size: "1GB".to_owned(),
+            limit: 1073741824,
+        };
+        Hugetlb::apply(&tmp, &hugetlb).expect("apply hugetlb");
+        let content = read_to_string(tmp.join(page_file_name)).expect("Read hugetlb file content");
+        assert_eq!(hugetlb.limit.to_string(), content);
+    }
+
+    #[test]
+    fn test_set_hugetlb_with_invalid_format() {
+        let tmp = create_temp_dir("test_set_hugetlb_with_invalid_format")
+            .expect("create temp directory for test");
+
+        for page_size in &["2TB", "2 MB", "MB", "x2MB", "2MBy"] {
+            let hugetlb = LinuxHugepageLimit {
+                page_size: page_size.to_string(),
+                limit: 16384,
+            };
+
+            let result = Hugetlb::apply(&tmp, &hugetlb);
+            assert!(
+                result.is_err(),
+                "page size {} is in an invalid format and should be an error",
+                page_size
+            );
+            assert!(result.unwrap_err().to_string().contains(page_size));
+        }
+    }
+
     #[test]
     fn test_set_hugetlb_with_invalid_page_size() {
         let tmp = create_temp_dir("test_set_hugetlb_with_invalid_page_size")
@@ -90,29 +104,22 @@ mod tests {
         let result = Hugetlb::apply(&tmp, &hugetlb);
         assert!(
             result.is_err(),
-            "page size that is not a power of two should be an error"
+            "page size that is neither supported by the host nor the cgroup should be an error"
         );
     }
 
     quickcheck! {
         fn property_test_set_hugetlb(hugetlb: LinuxHugepageLimit) -> bool {
-            let page_file_name = format!("hugetlb.{:?}.limit_in_bytes", hugetlb.page_size);
+            // the cgroup has the interface file, so that the page size is supported
+            let page_file_name = format!("hugetlb.{}.limit_in_bytes", hugetlb.page_size);
             let tmp = create_temp_dir("property_test_set_hugetlb").expect("create temp directory for test");
             set_fixture(&tmp, &page_file_name, "0").expect("Set fixture for page size");
 
-            let result = Hugetlb::apply(&tmp, &hugetlb);
+            Hugetlb::apply(&tmp, &hugetlb).expect("apply hugetlb");
 
-            let re = Regex::new(r"(?P<pagesize>[0-9]+)[KMG]B").expect("create regex for parsing pagesize");
-            let caps = re.captures(&hugetlb.page_size).expect("should capture pagesize");
-
-            let page_size: u64 = caps["pagesize"].parse().expect("should contain captured pagesize");
-            if Hugetlb::is_power_of_two(page_size) && page_size != 1 {
-                let content =
-                    read_to_string(tmp.join(page_file_name)).expect("Read hugetlb file content");
-                hugetlb.limit.to_string() == content
-            } else {
-                result.is_err()
-            }
+            let content =
+                read_to_string(tmp.join(page_file_name)).expect("Read hugetlb file content");
+            hugetlb.limit.to_string() == content
         }
     }
 }
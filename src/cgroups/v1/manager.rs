@@ -1,20 +1,22 @@
-use std::fs;
 use std::path::Path;
 use std::{collections::HashMap, path::PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use nix::unistd::Pid;
 
-use procfs::process::Process;
+use procfs::{
+    process::{MountInfo, Process},
+    ProcessCgroup,
+};
 
 use super::{
-    blkio::Blkio, cpu::Cpu, cpuset::CpuSet, devices::Devices, hugetlb::Hugetlb, memory::Memory,
-    network_classifier::NetworkClassifier, network_priority::NetworkPriority, pids::Pids,
-    Controller, ControllerType,
+    blkio::Blkio, cpu::Cpu, cpuset::CpuSet, devices::Devices, freezer::Freezer, hugetlb::Hugetlb,
+    memory::Memory, network_classifier::NetworkClassifier, network_priority::NetworkPriority,
+    pids::Pids, Controller, ControllerType,
 };
 
-use crate::cgroups::common::CGROUP_PROCS;
-use crate::utils;
+use crate::cgroups::common::{self, FreezerState};
+use crate::cgroups::stats::{Stats, StatsProvider};
 use crate::{cgroups::common::CgroupManager, utils::PathBufExt};
 use oci_spec::LinuxResources;
 
@@ -22,6 +24,7 @@ const CONTROLLERS: &[ControllerType] = &[
     ControllerType::Cpu,
     ControllerType::CpuSet,
     ControllerType::Devices,
+    ControllerType::Freezer,
     ControllerType::HugeTlb,
     ControllerType::Memory,
     ControllerType::Pids,
@@ -32,83 +35,151 @@ const CONTROLLERS: &[ControllerType] = &[
 
 pub struct Manager {
     subsystems: HashMap<String, PathBuf>,
+    // mount points of the hierarchies the subsystems are attached to
+    mount_points: HashMap<String, PathBuf>,
+    // controllers which are not mounted on this system
+    missing: Vec<ControllerType>,
+    // every missing controller, whose resources are restricted, fails the setup
+    strict: bool,
+}
+
+/// What is done with the resources of a controller, which is not mounted
+#[derive(Debug, PartialEq, Eq)]
+enum MissingController {
+    // the resources are not restricted, which is only logged
+    Skip,
+    // the setup of the cgroup fails
+    Fail,
 }
 
 impl Manager {
-    pub fn new(cgroup_path: PathBuf) -> Result<Self> {
+    pub fn new(cgroup_path: PathBuf, strict: bool) -> Result<Self> {
+        Self::with_process(cgroup_path, &Process::myself()?, strict)
+    }
+
+    // discovers the mounted subsystems from the mountinfo of the given process. Controllers
+    // which are not mounted on this system only fail the setup if their resources are restricted
+    fn with_process(cgroup_path: PathBuf, process: &Process, strict: bool) -> Result<Self> {
+        let mounts = process.mountinfo()?;
+        let process_cgroups = process.cgroups()?;
+
         let mut subsystems = HashMap::<String, PathBuf>::new();
-        for subsystem in CONTROLLERS.iter().map(|c| c.to_string()) {
-            subsystems.insert(
-                subsystem.to_owned(),
-                Self::get_subsystem_path(&cgroup_path, &subsystem)?,
-            );
+        let mut mount_points = HashMap::<String, PathBuf>::new();
+        let mut missing = Vec::new();
+        for controller in CONTROLLERS {
+            let subsystem = controller.to_string();
+            // the super options of a cgroup mount list the subsystems which are attached
+            // to the hierarchy. This also covers hierarchies which combine several
+            // subsystems, e.g. cpu,cpuacct or net_cls,net_prio
+            let mount = mounts
+                .iter()
+                .find(|m| m.fs_type == "cgroup" && m.super_options.contains_key(&subsystem));
+
+            match mount {
+                Some(mount) => {
+                    let subsystem_path = Self::get_subsystem_path(
+                        &cgroup_path,
+                        &subsystem,
+                        mount,
+                        &process_cgroups,
+                    )?;
+                    subsystems.insert(subsystem.clone(), subsystem_path);
+                    mount_points.insert(subsystem, mount.mount_point.clone());
+                }
+                None => {
+                    log::debug!("{} cgroup is not mounted", subsystem);
+                    missing.push(*controller);
+                }
+            }
         }
 
-        Ok(Manager { subsystems })
+        Ok(Manager {
+            subsystems,
+            mount_points,
+            missing,
+            strict,
+        })
     }
 
-    fn get_subsystem_path(cgroup_path: &Path, subsystem: &str) -> anyhow::Result<PathBuf> {
-        log::debug!("Get path for subsystem: {}", subsystem);
-        let mount = Process::myself()?
-            .mountinfo()?
-            .into_iter()
-            .find(|m| {
-                if m.fs_type == "cgroup" {
-                    // Some systems mount net_prio and net_cls in the same directory
-                    // other systems mount them in their own diretories. This
-                    // should handle both cases.
-                    if subsystem == "net_cls" {
-                        return m.mount_point.ends_with("net_cls,net_prio")
-                            || m.mount_point.ends_with("net_prio,net_cls")
-                            || m.mount_point.ends_with("net_cls");
-                    } else if subsystem == "net_prio" {
-                        return m.mount_point.ends_with("net_cls,net_prio")
-                            || m.mount_point.ends_with("net_prio,net_cls")
-                            || m.mount_point.ends_with("net_prio");
-                    }
-
-                    if subsystem == "cpu" {
-                        return m.mount_point.ends_with("cpu,cpuacct")
-                            || m.mount_point.ends_with("cpu");
-                    }
-                }
-                m.mount_point.ends_with(subsystem)
-            })
-            .unwrap();
-
-        let cgroup = Process::myself()?
-            .cgroups()?
-            .into_iter()
-            .find(|c| c.controllers.contains(&subsystem.to_owned()))
-            .unwrap();
+    /// Checks the controllers, which are not mounted, but whose resources are restricted by the spec
+    fn check_missing_controllers(&self, linux_resources: &LinuxResources) -> Result<()> {
+        for controller in &self.missing {
+            if !is_restricted(*controller, linux_resources) {
+                continue;
+            }
+            match missing_controller_action(*controller, self.strict) {
+                MissingController::Skip => log::warn!(
+                    "{} cgroup is not mounted, so its resources are not restricted",
+                    controller.to_string()
+                ),
+                MissingController::Fail => bail!(
+                    "{} cgroup is not mounted, but its resources are restricted by the spec",
+                    controller.to_string()
+                ),
+            }
+        }
+        Ok(())
+    }
 
+    fn get_subsystem_path(
+        cgroup_path: &Path,
+        subsystem: &str,
+        mount: &MountInfo,
+        process_cgroups: &[ProcessCgroup],
+    ) -> Result<PathBuf> {
+        log::debug!("Get path for subsystem: {}", subsystem);
         let p = if cgroup_path.to_string_lossy().into_owned().is_empty() {
+            let cgroup = process_cgroups
+                .iter()
+                .find(|c| c.controllers.iter().any(|c| c == subsystem))
+                .ok_or_else(|| anyhow!("process is not part of a {} cgroup", subsystem))?;
             mount
                 .mount_point
                 .join_absolute_path(Path::new(&cgroup.pathname))?
         } else if cgroup_path.is_absolute() {
-            mount.mount_point.join_absolute_path(&cgroup_path)?
+            mount.mount_point.join_absolute_path(cgroup_path)?
         } else {
             mount.mount_point.join(cgroup_path)
         };
 
         Ok(p)
     }
+
+    fn apply_subsystem(
+        subsystem: &str,
+        linux_resources: &LinuxResources,
+        path: &Path,
+    ) -> Result<()> {
+        match subsystem {
+            "cpu" => Cpu::apply(linux_resources, path),
+            "cpuset" => CpuSet::apply(linux_resources, path),
+            "devices" => Devices::apply(linux_resources, path),
+            "freezer" => <Freezer as Controller>::apply(linux_resources, path),
+            "hugetlb" => Hugetlb::apply(linux_resources, path),
+            "memory" => Memory::apply(linux_resources, path),
+            "pids" => Pids::apply(linux_resources, path),
+            "blkio" => Blkio::apply(linux_resources, path),
+            "net_prio" => NetworkPriority::apply(linux_resources, path),
+            "net_cls" => NetworkClassifier::apply(linux_resources, path),
+            _ => unreachable!("every subsystem should have an associated controller"),
+        }
+    }
 }
 
 impl CgroupManager for Manager {
-    fn apply(&self, linux_resources: &LinuxResources, pid: Pid) -> Result<()> {
+    fn add_task(&self, pid: Pid) -> Result<()> {
         for subsys in &self.subsystems {
             match subsys.0.as_str() {
-                "cpu" => Cpu::apply(linux_resources, &subsys.1, pid)?,
-                "cpuset" => CpuSet::apply(linux_resources, &subsys.1, pid)?,
-                "devices" => Devices::apply(linux_resources, &subsys.1, pid)?,
-                "hugetlb" => Hugetlb::apply(linux_resources, &subsys.1, pid)?,
-                "memory" => Memory::apply(linux_resources, &subsys.1, pid)?,
-                "pids" => Pids::apply(linux_resources, &subsys.1, pid)?,
-                "blkio" => Blkio::apply(linux_resources, &subsys.1, pid)?,
-                "net_prio" => NetworkPriority::apply(linux_resources, &subsys.1, pid)?,
-                "net_cls" => NetworkClassifier::apply(linux_resources, &subsys.1, pid)?,
+                "cpu" => Cpu::add_task(pid, subsys.1)?,
+                "cpuset" => CpuSet::add_task(pid, subsys.1)?,
+                "devices" => Devices::add_task(pid, subsys.1)?,
+                "freezer" => Freezer::add_task(pid, subsys.1)?,
+                "hugetlb" => Hugetlb::add_task(pid, subsys.1)?,
+                "memory" => Memory::add_task(pid, subsys.1)?,
+                "pids" => Pids::add_task(pid, subsys.1)?,
+                "blkio" => Blkio::add_task(pid, subsys.1)?,
+                "net_prio" => NetworkPriority::add_task(pid, subsys.1)?,
+                "net_cls" => NetworkClassifier::add_task(pid, subsys.1)?,
                 _ => unreachable!("every subsystem should have an associated controller"),
             }
         }
@@ -116,22 +187,329 @@ impl CgroupManager for Manager {
         Ok(())
     }
 
-    fn remove(&self) -> Result<()> {
-        for cgroup_path in &self.subsystems {
-            if cgroup_path.1.exists() {
-                log::debug!("remove cgroup {:?}", cgroup_path.1);
-                let procs_path = cgroup_path.1.join(CGROUP_PROCS);
-                let procs = fs::read_to_string(&procs_path)?;
-
-                for line in procs.lines() {
-                    let pid: i32 = line.parse()?;
-                    let _ = nix::sys::signal::kill(Pid::from_raw(pid), nix::sys::signal::SIGKILL);
-                }
+    fn apply(&self, linux_resources: &LinuxResources) -> Result<()> {
+        self.check_missing_controllers(linux_resources)?;
+        for (subsystem, path) in &self.subsystems {
+            Self::apply_subsystem(subsystem, linux_resources, path)?;
+        }
+
+        Ok(())
+    }
 
-                utils::delete_with_retry(cgroup_path.1)?;
+    fn update(&self, linux_resources: &LinuxResources) -> Result<()> {
+        self.check_missing_controllers(linux_resources)?;
+        for (subsystem, path) in &self.subsystems {
+            // the device rules are replaced as a whole, so they are
+            // only applied again if the update contains any rules
+            if subsystem == "devices" && linux_resources.devices.is_empty() {
+                continue;
             }
+            Self::apply_subsystem(subsystem, linux_resources, path)?;
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self) -> Result<()> {
+        for (subsystem, cgroup_path) in &self.subsystems {
+            common::remove_cgroup(&self.mount_points[subsystem], cgroup_path)?;
         }
 
         Ok(())
     }
+
+    fn freeze(&self, state: FreezerState) -> Result<()> {
+        let freezer_path = self
+            .subsystems
+            .get(&ControllerType::Freezer.to_string())
+            .ok_or_else(|| anyhow!("freezer cgroup is not available"))?;
+        Freezer::apply(&state, freezer_path)
+    }
+
+    fn stats(&self) -> Result<Stats> {
+        let mut stats = Stats::default();
+        for (subsystem, cgroup_path) in &self.subsystems {
+            match subsystem.as_str() {
+                "cpu" => stats.cpu = Cpu::stats(cgroup_path)?,
+                "memory" => stats.memory = Memory::stats(cgroup_path)?,
+                "pids" => stats.pids = Pids::stats(cgroup_path)?,
+                "blkio" => stats.blkio = Blkio::stats(cgroup_path)?,
+                _ => continue,
+            }
+        }
+
+        Ok(stats)
+    }
+
+    fn get_all_pids(&self) -> Result<Vec<Pid>> {
+        // every task of the container is attached to all of the subsystems
+        match self.subsystems.values().next() {
+            Some(cgroup_path) => common::get_procs(cgroup_path),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Returns true if the resources of the spec restrict the controller
+fn is_restricted(controller: ControllerType, linux_resources: &LinuxResources) -> bool {
+    let cpu = linux_resources.cpu.as_ref();
+    match controller {
+        ControllerType::Cpu => cpu.is_some_and(|cpu| {
+            cpu.shares.is_some()
+                || cpu.quota.is_some()
+                || cpu.period.is_some()
+                || cpu.realtime_runtime.is_some()
+                || cpu.realtime_period.is_some()
+        }),
+        ControllerType::CpuSet => cpu.is_some_and(|cpu| cpu.cpus.is_some() || cpu.mems.is_some()),
+        ControllerType::Devices => !linux_resources.devices.is_empty(),
+        ControllerType::Freezer => false,
+        ControllerType::HugeTlb => !linux_resources.hugepage_limits.is_empty(),
+        ControllerType::Memory => linux_resources.memory.as_ref().is_some_and(|memory| {
+            memory.limit.is_some()
+                || memory.reservation.is_some()
+                || memory.swap.is_some()
+                || memory.kernel.is_some()
+                || memory.kernel_tcp.is_some()
+                || memory.swappiness.is_some()
+        }),
+        ControllerType::Pids => linux_resources.pids.is_some(),
+        ControllerType::Blkio => linux_resources.block_io.as_ref().is_some_and(|blkio| {
+            blkio.blkio_weight.is_some()
+                || blkio.blkio_leaf_weight.is_some()
+                || !blkio.blkio_weight_device.is_empty()
+                || !blkio.blkio_throttle_read_bps_device.is_empty()
+                || !blkio.blkio_throttle_write_bps_device.is_empty()
+                || !blkio.blkio_throttle_read_iops_device.is_empty()
+                || !blkio.blkio_throttle_write_iops_device.is_empty()
+        }),
+        ControllerType::NetworkPriority => linux_resources
+            .network
+            .as_ref()
+            .is_some_and(|network| !network.priorities.is_empty()),
+        ControllerType::NetworkClassifier => linux_resources
+            .network
+            .as_ref()
+            .is_some_and(|network| network.class_id.is_some()),
+    }
+}
+
+/// Decides what is done if the resources of a missing controller are restricted. The limits of
+/// memory and cpu are essential for the host to stay responsive, so they are never skipped
+fn missing_controller_action(controller: ControllerType, strict: bool) -> MissingController {
+    match controller {
+        _ if strict => MissingController::Fail,
+        ControllerType::Memory | ControllerType::Cpu => MissingController::Fail,
+        _ => MissingController::Skip,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgroups::common::CGROUP_PROCS;
+    use crate::cgroups::test::{create_temp_dir, set_fixture};
+    use std::fs;
+
+    // creates a fake proc directory for a process with the given mountinfo and cgroup files
+    fn setup_process(cgroup_root: &Path, mountinfo: &str, cgroups: &str) -> Process {
+        let proc_root = cgroup_root.join("proc");
+        fs::create_dir_all(&proc_root).expect("create proc directory");
+        // procfs requires a valid stat file to create a process
+        fs::copy("/proc/self/stat", proc_root.join("stat")).expect("copy stat file");
+        set_fixture(&proc_root, "mountinfo", mountinfo).expect("set fixture for mountinfo");
+        set_fixture(&proc_root, "cgroup", cgroups).expect("set fixture for cgroup");
+
+        Process::new_with_root(proc_root).expect("create process from fixture")
+    }
+
+    fn cgroup_mount(mnt_id: i32, mount_point: &Path, subsystems: &str) -> String {
+        format!(
+            "{} 25 0:{} / {} rw,nosuid,nodev,noexec,relatime shared:{} - cgroup cgroup rw,{}\n",
+            mnt_id,
+            mnt_id,
+            mount_point.display(),
+            mnt_id,
+            subsystems
+        )
+    }
+
+    #[test]
+    fn test_only_mounted_subsystems_are_used() {
+        let tmp = create_temp_dir("test_only_mounted_subsystems_are_used")
+            .expect("create temp directory for test");
+        let mountinfo = [
+            "22 1 0:21 / /sys rw,nosuid,nodev,noexec,relatime shared:7 - sysfs sysfs rw\n"
+                .to_owned(),
+            cgroup_mount(30, &tmp.join("cpu,cpuacct"), "cpu,cpuacct"),
+            cgroup_mount(31, &tmp.join("memory"), "memory"),
+            cgroup_mount(32, &tmp.join("net_cls,net_prio"), "net_cls,net_prio"),
+        ]
+        .concat();
+        let process = setup_process(&tmp, &mountinfo, "");
+
+        let manager =
+            Manager::with_process(PathBuf::from("youki"), &process, false).expect("create manager");
+
+        let mut subsystems: Vec<&String> = manager.subsystems.keys().collect();
+        subsystems.sort();
+        assert_eq!(subsystems, vec!["cpu", "memory", "net_cls", "net_prio"]);
+        assert_eq!(
+            manager.subsystems["cpu"],
+            tmp.join("cpu,cpuacct").join("youki")
+        );
+        assert_eq!(
+            manager.subsystems["memory"],
+            tmp.join("memory").join("youki")
+        );
+        assert_eq!(
+            manager.subsystems["net_prio"],
+            tmp.join("net_cls,net_prio").join("youki")
+        );
+    }
+
+    #[test]
+    fn test_missing_controller_action() {
+        use ControllerType::*;
+
+        for controller in &[Memory, Cpu] {
+            assert_eq!(
+                missing_controller_action(*controller, false),
+                MissingController::Fail
+            );
+        }
+        for controller in &[CpuSet, Devices, HugeTlb, Pids, Blkio, NetworkClassifier] {
+            assert_eq!(
+                missing_controller_action(*controller, false),
+                MissingController::Skip
+            );
+            assert_eq!(
+                missing_controller_action(*controller, true),
+                MissingController::Fail
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_controllers() {
+        let tmp =
+            create_temp_dir("test_missing_controllers").expect("create temp directory for test");
+        // neither memory nor pids are mounted
+        let mountinfo = cgroup_mount(30, &tmp.join("freezer"), "freezer");
+        let process = setup_process(&tmp, &mountinfo, "");
+        let manager =
+            Manager::with_process(PathBuf::from("youki"), &process, false).expect("create manager");
+        assert!(manager.missing.contains(&ControllerType::Memory));
+        assert!(!manager.missing.contains(&ControllerType::Freezer));
+
+        let pids = LinuxResources {
+            pids: Some(oci_spec::LinuxPids { limit: 10 }),
+            ..Default::default()
+        };
+        assert!(manager.check_missing_controllers(&pids).is_ok());
+
+        let memory = LinuxResources {
+            memory: Some(oci_spec::LinuxMemory {
+                limit: Some(1024 * 1024),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(manager.check_missing_controllers(&memory).is_err());
+        // the resources of controllers which are not restricted are never checked
+        assert!(manager
+            .check_missing_controllers(&LinuxResources::default())
+            .is_ok());
+        let empty_memory = LinuxResources {
+            memory: Some(oci_spec::LinuxMemory::default()),
+            ..Default::default()
+        };
+        assert!(manager.check_missing_controllers(&empty_memory).is_ok());
+
+        let strict =
+            Manager::with_process(PathBuf::from("youki"), &process, true).expect("create manager");
+        assert!(strict.check_missing_controllers(&pids).is_err());
+        assert!(strict.check_missing_controllers(&empty_memory).is_ok());
+    }
+
+    #[test]
+    fn test_subsystem_path_defaults_to_process_cgroup() {
+        let tmp = create_temp_dir("test_subsystem_path_defaults_to_process_cgroup")
+            .expect("create temp directory for test");
+        let mountinfo = cgroup_mount(30, &tmp.join("memory"), "memory");
+        let process = setup_process(&tmp, &mountinfo, "4:memory:/user.slice/session-1.scope\n");
+
+        let manager =
+            Manager::with_process(PathBuf::new(), &process, false).expect("create manager");
+
+        assert_eq!(
+            manager.subsystems["memory"],
+            tmp.join("memory/user.slice/session-1.scope")
+        );
+    }
+
+    #[test]
+    fn test_add_task_to_every_subsystem() {
+        let tmp = create_temp_dir("test_add_task_to_every_subsystem")
+            .expect("create temp directory for test");
+        let mountinfo = [
+            cgroup_mount(30, &tmp.join("pids"), "pids"),
+            cgroup_mount(31, &tmp.join("freezer"), "freezer"),
+        ]
+        .concat();
+        let process = setup_process(&tmp, &mountinfo, "");
+        for subsystem in &["pids", "freezer"] {
+            let cgroup_path = tmp.join(subsystem).join("youki");
+            fs::create_dir_all(&cgroup_path).expect("create cgroup directory");
+            set_fixture(&cgroup_path, CGROUP_PROCS, "").expect("set fixture for proc file");
+        }
+
+        let manager =
+            Manager::with_process(PathBuf::from("youki"), &process, false).expect("create manager");
+        manager.add_task(Pid::from_raw(1000)).expect("add task");
+
+        for subsystem in &["pids", "freezer"] {
+            let procs = tmp.join(subsystem).join("youki").join(CGROUP_PROCS);
+            let content = fs::read_to_string(&procs)
+                .unwrap_or_else(|_| panic!("read {} file content", CGROUP_PROCS));
+            // the pid has to be written exactly once
+            assert_eq!(content, "1000");
+        }
+    }
+
+    #[test]
+    fn test_update_keeps_device_rules() {
+        let tmp = create_temp_dir("test_update_keeps_device_rules")
+            .expect("create temp directory for test");
+        let mountinfo = [
+            cgroup_mount(30, &tmp.join("pids"), "pids"),
+            cgroup_mount(31, &tmp.join("devices"), "devices"),
+        ]
+        .concat();
+        let process = setup_process(&tmp, &mountinfo, "");
+        let pids_path = tmp.join("pids").join("youki");
+        let devices_path = tmp.join("devices").join("youki");
+        fs::create_dir_all(&pids_path).expect("create pids cgroup directory");
+        fs::create_dir_all(&devices_path).expect("create devices cgroup directory");
+        set_fixture(&pids_path, "pids.max", "").expect("set fixture for pids.max");
+        set_fixture(&devices_path, "devices.deny", "").expect("set fixture for devices.deny");
+        set_fixture(&devices_path, "devices.allow", "").expect("set fixture for devices.allow");
+
+        let manager =
+            Manager::with_process(PathBuf::from("youki"), &process, false).expect("create manager");
+        let resources = LinuxResources {
+            pids: Some(oci_spec::LinuxPids { limit: 10 }),
+            ..Default::default()
+        };
+        manager.update(&resources).expect("update resources");
+
+        let pids_max = fs::read_to_string(pids_path.join("pids.max")).expect("read pids.max");
+        assert_eq!(pids_max, "10");
+        let devices_deny =
+            fs::read_to_string(devices_path.join("devices.deny")).expect("read devices.deny");
+        assert_eq!(
+            devices_deny, "",
+            "device rules should not be reset by an update"
+        );
+    }
 }
@@ -1,13 +1,11 @@
 use std::io::{prelude::*, Write};
-use std::{
-    fs::{create_dir_all, OpenOptions},
-    path::Path,
-};
+use std::{fs::OpenOptions, path::Path};
 
 use anyhow::{Result, *};
-use nix::{errno::Errno, unistd::Pid};
+use nix::errno::Errno;
 
-use crate::cgroups::common::{self, CGROUP_PROCS};
+use crate::cgroups::common;
+use crate::cgroups::stats::{self, MemoryStat, MemoryStats, StatsProvider};
 use crate::cgroups::v1::Controller;
 use oci_spec::{LinuxMemory, LinuxResources};
 
@@ -18,28 +16,28 @@ const CGROUP_MEMORY_MAX_USAGE: &str = "memory.max_usage_in_bytes";
 const CGROUP_MEMORY_SWAPPINESS: &str = "memory.swappiness";
 const CGROUP_MEMORY_RESERVATION: &str = "memory.soft_limit_in_bytes";
 const CGROUP_MEMORY_OOM_CONTROL: &str = "memory.oom_control";
+const CGROUP_MEMORY_FAIL_COUNT: &str = "memory.failcnt";
+const CGROUP_MEMORY_STAT: &str = "memory.stat";
 
 const CGROUP_KERNEL_MEMORY_LIMIT: &str = "memory.kmem.limit_in_bytes";
 const CGROUP_KERNEL_TCP_MEMORY_LIMIT: &str = "memory.kmem.tcp.limit_in_bytes";
 
 pub struct Memory {}
 
+/// A write of memory.limit_in_bytes or memory.memsw.limit_in_bytes. A limit of 0 is not written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LimitWrite {
+    Memory(i64),
+    Swap(i64),
+}
+
 impl Controller for Memory {
-    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path, pid: Pid) -> Result<()> {
+    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path) -> Result<()> {
         log::debug!("Apply Memory cgroup config");
-        create_dir_all(&cgroup_root)?;
 
         if let Some(memory) = &linux_resources.memory {
-            let reservation = memory.reservation.unwrap_or(0);
-
             Self::apply(&memory, cgroup_root)?;
-
-            if reservation != 0 {
-                common::write_cgroup_file(
-                    cgroup_root.join(CGROUP_MEMORY_RESERVATION),
-                    reservation,
-                )?;
-            }
+            Self::set_reservation(memory.reservation.unwrap_or(0), memory.limit, cgroup_root)?;
 
             if linux_resources.disable_oom_killer {
                 common::write_cgroup_file(cgroup_root.join(CGROUP_MEMORY_OOM_CONTROL), 0)?;
@@ -76,11 +74,33 @@ impl Controller for Memory {
             }
         }
 
-        common::write_cgroup_file(cgroup_root.join(CGROUP_PROCS), pid)?;
         Ok(())
     }
 }
 
+impl StatsProvider for Memory {
+    type Stats = MemoryStats;
+
+    fn stats(cgroup_root: &Path) -> Result<Self::Stats> {
+        // memory.oom_control reports whether the oom killer is disabled and whether the cgroup is
+        // currently out of memory, newer kernels also count the processes killed by the oom killer
+        let oom_control =
+            stats::parse_flat_keyed_data(&cgroup_root.join(CGROUP_MEMORY_OOM_CONTROL))?;
+
+        Ok(MemoryStats {
+            usage: stats::parse_single_value(&cgroup_root.join(CGROUP_MEMORY_USAGE))?,
+            max_usage: stats::parse_single_value(&cgroup_root.join(CGROUP_MEMORY_MAX_USAGE))?,
+            limit: stats::parse_single_value(&cgroup_root.join(CGROUP_MEMORY_LIMIT))?,
+            fail_count: stats::parse_single_value(&cgroup_root.join(CGROUP_MEMORY_FAIL_COUNT))?,
+            oom: 0,
+            oom_kill: oom_control.get("oom_kill").copied().unwrap_or(0),
+            stat: MemoryStat::from_v1(&stats::parse_flat_keyed_data(
+                &cgroup_root.join(CGROUP_MEMORY_STAT),
+            )?),
+        })
+    }
+}
+
 impl Memory {
     fn get_memory_usage(cgroup_root: &Path) -> Result<u64> {
         let path = cgroup_root.join(CGROUP_MEMORY_USAGE);
@@ -179,6 +199,28 @@ impl Memory {
         }
     }
 
+    // The soft limit is written after the hard limit. Unlike the swap limit, the kernel does not
+    // validate it against memory.limit_in_bytes, but a reservation above the hard limit has no
+    // effect, as the usage can never exceed the hard limit. The soft limit is only enforced
+    // when the memory of the system runs low and, as with memory.use_hierarchy the usage of a
+    // cgroup includes its descendants, it applies to the cgroup along with all of its children
+    fn set_reservation(reservation: i64, limit: Option<i64>, cgroup_root: &Path) -> Result<()> {
+        if reservation == 0 {
+            return Ok(());
+        }
+        if let Some(limit) = limit.filter(|limit| *limit > 0) {
+            if reservation > limit {
+                log::warn!(
+                    "memory reservation {} is above the memory limit {} and has no effect",
+                    reservation,
+                    limit
+                );
+            }
+        }
+
+        common::write_cgroup_file(cgroup_root.join(CGROUP_MEMORY_RESERVATION), reservation)
+    }
+
     fn set_swap(swap: i64, cgroup_root: &Path) -> Result<()> {
         if swap == 0 {
             return Ok(());
@@ -188,49 +230,53 @@ impl Memory {
         Ok(())
     }
 
-    fn set_memory_and_swap(
-        limit: i64,
-        swap: i64,
-        is_updated: bool,
-        cgroup_root: &Path,
-    ) -> Result<()> {
+    /// Returns the writes of the memory and the swap limit, in the order in which they have to be written
+    fn memory_and_swap_writes(resource: &LinuxMemory, current_limit: i64) -> [LimitWrite; 2] {
+        match resource.limit {
+            Some(limit) => match resource.swap {
+                Some(swap) => {
+                    let is_updated = swap == -1 || current_limit < swap;
+                    Self::ordered_writes(limit, swap, is_updated)
+                }
+                None => {
+                    if limit == -1 {
+                        Self::ordered_writes(limit, -1, true)
+                    } else {
+                        let is_updated = current_limit < 0;
+                        Self::ordered_writes(limit, 0, is_updated)
+                    }
+                }
+            },
+            None => Self::ordered_writes(0, resource.swap.unwrap_or(0), false),
+        }
+    }
+
+    fn ordered_writes(limit: i64, swap: i64, is_updated: bool) -> [LimitWrite; 2] {
         // According to runc we need to change the write sequence of
         // limit and swap so it won't fail, because the new and old
-        // values don't fit the kernel's validation
+        // values don't fit the kernel's validation. The kernel requires
+        // memory.memsw.limit_in_bytes to be at least memory.limit_in_bytes,
+        // so when the limits are raised swap has to be written first and
+        // when they are lowered the memory limit has to be written first.
         // see:
         // https://github.com/opencontainers/runc/blob/3f6594675675d4e88901c782462f56497260b1d2/libcontainer/cgroups/fs/memory.go#L89
         if is_updated {
-            Self::set_swap(swap, cgroup_root)?;
-            Self::set_memory(limit, cgroup_root)?;
+            [LimitWrite::Swap(swap), LimitWrite::Memory(limit)]
+        } else {
+            [LimitWrite::Memory(limit), LimitWrite::Swap(swap)]
         }
-        Self::set_memory(limit, cgroup_root)?;
-        Self::set_swap(swap, cgroup_root)?;
-        Ok(())
     }
 
     fn apply(resource: &LinuxMemory, cgroup_root: &Path) -> Result<()> {
-        match resource.limit {
-            Some(limit) => {
-                let current_limit = Self::get_memory_limit(cgroup_root)?;
-                match resource.swap {
-                    Some(swap) => {
-                        let is_updated = swap == -1 || current_limit < swap;
-                        Self::set_memory_and_swap(limit, swap, is_updated, cgroup_root)?;
-                    }
-                    None => {
-                        if limit == -1 {
-                            Self::set_memory_and_swap(limit, -1, true, cgroup_root)?;
-                        } else {
-                            let is_updated = current_limit < 0;
-                            Self::set_memory_and_swap(limit, 0, is_updated, cgroup_root)?;
-                        }
-                    }
-                }
+        let current_limit = match resource.limit {
+            Some(_) => Self::get_memory_limit(cgroup_root)?,
+            None => 0,
+        };
+        for write in &Self::memory_and_swap_writes(resource, current_limit) {
+            match *write {
+                LimitWrite::Memory(limit) => Self::set_memory(limit, cgroup_root)?,
+                LimitWrite::Swap(swap) => Self::set_swap(swap, cgroup_root)?,
             }
-            None => match resource.swap {
-                Some(swap) => Self::set_memory_and_swap(0, swap, false, cgroup_root)?,
-                None => Self::set_memory_and_swap(0, 0, false, cgroup_root)?,
-            },
         }
         Ok(())
     }
@@ -242,6 +288,27 @@ mod tests {
     use crate::cgroups::test::{create_temp_dir, set_fixture};
     use oci_spec::LinuxMemory;
 
+    /// Writes the limits like the kernel, which rejects a swap limit below the memory limit,
+    /// returning the memory and the swap limit after the writes
+    fn write_like_kernel(
+        mut limit: i64,
+        mut swap: i64,
+        writes: &[LimitWrite],
+    ) -> Result<(i64, i64)> {
+        for write in writes {
+            match *write {
+                LimitWrite::Memory(0) | LimitWrite::Swap(0) => continue,
+                LimitWrite::Memory(value) => limit = value,
+                LimitWrite::Swap(value) => swap = value,
+            }
+            // -1 means unlimited
+            if swap != -1 && (limit == -1 || swap < limit) {
+                bail!("swap limit {} is below the memory limit {}", swap, limit)
+            }
+        }
+        Ok((limit, swap))
+    }
+
     #[test]
     fn test_set_memory() {
         let limit = 1024;
@@ -335,8 +402,240 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_raise_memory_and_swap() {
+        let tmp =
+            create_temp_dir("test_raise_memory_and_swap").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_MEMORY_USAGE, "0").expect("Set fixure for memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_MAX_USAGE, "0").expect("Set fixure for max memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_LIMIT, "1024").expect("Set fixure for memory limit");
+        set_fixture(&tmp, CGROUP_MEMORY_SWAP_LIMIT, "2048").expect("Set fixure for swap limit");
+
+        let limit = 4096;
+        let swap = 8192;
+        let linux_memory = &LinuxMemory {
+            limit: Some(limit),
+            swap: Some(swap),
+            reservation: None,
+            kernel: None,
+            kernel_tcp: None,
+            swappiness: None,
+        };
+        // swap has to be raised first, the memory limit would be above the old swap limit otherwise
+        let writes = Memory::memory_and_swap_writes(linux_memory, 1024);
+        assert_eq!(writes, [LimitWrite::Swap(swap), LimitWrite::Memory(limit)]);
+        assert_eq!(
+            write_like_kernel(1024, 2048, &writes).unwrap(),
+            (limit, swap)
+        );
+        let err = write_like_kernel(1024, 2048, &[writes[1], writes[0]]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "swap limit 2048 is below the memory limit 4096"
+        );
+
+        Memory::apply(linux_memory, &tmp).expect("Raise memory and swap");
+
+        let limit_content =
+            std::fs::read_to_string(tmp.join(CGROUP_MEMORY_LIMIT)).expect("Read to string");
+        assert_eq!(limit.to_string(), limit_content);
+        let swap_content =
+            std::fs::read_to_string(tmp.join(CGROUP_MEMORY_SWAP_LIMIT)).expect("Read to string");
+        assert_eq!(swap.to_string(), swap_content);
+    }
+
+    #[test]
+    fn test_lower_memory_and_swap() {
+        let tmp =
+            create_temp_dir("test_lower_memory_and_swap").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_MEMORY_USAGE, "0").expect("Set fixure for memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_MAX_USAGE, "0").expect("Set fixure for max memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_LIMIT, "8192").expect("Set fixure for memory limit");
+        set_fixture(&tmp, CGROUP_MEMORY_SWAP_LIMIT, "9999").expect("Set fixure for swap limit");
+
+        let limit = 1024;
+        let swap = 2048;
+        let linux_memory = &LinuxMemory {
+            limit: Some(limit),
+            swap: Some(swap),
+            reservation: None,
+            kernel: None,
+            kernel_tcp: None,
+            swappiness: None,
+        };
+        // the memory limit has to be lowered first, the swap limit would be below the old memory limit otherwise
+        let writes = Memory::memory_and_swap_writes(linux_memory, 8192);
+        assert_eq!(writes, [LimitWrite::Memory(limit), LimitWrite::Swap(swap)]);
+        assert_eq!(
+            write_like_kernel(8192, 9999, &writes).unwrap(),
+            (limit, swap)
+        );
+        let err = write_like_kernel(8192, 9999, &[writes[1], writes[0]]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "swap limit 2048 is below the memory limit 8192"
+        );
+
+        Memory::apply(linux_memory, &tmp).expect("Lower memory and swap");
+
+        let limit_content =
+            std::fs::read_to_string(tmp.join(CGROUP_MEMORY_LIMIT)).expect("Read to string");
+        assert_eq!(limit.to_string(), limit_content);
+        let swap_content =
+            std::fs::read_to_string(tmp.join(CGROUP_MEMORY_SWAP_LIMIT)).expect("Read to string");
+        assert_eq!(swap.to_string(), swap_content);
+    }
+
+    #[test]
+    fn test_set_memory_reservation() {
+        let tmp =
+            create_temp_dir("test_set_memory_reservation").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_MEMORY_LIMIT, "0").expect("Set fixure for memory limit");
+        set_fixture(&tmp, CGROUP_MEMORY_SWAP_LIMIT, "0").expect("Set fixure for swap limit");
+        set_fixture(&tmp, CGROUP_MEMORY_RESERVATION, "0")
+            .expect("Set fixture for memory reservation");
+        set_fixture(&tmp, CGROUP_MEMORY_OOM_CONTROL, "0").expect("Set fixture for oom control");
+
+        let reservation = 512 * 1024 * 1024;
+        let linux_resources = LinuxResources {
+            memory: Some(LinuxMemory {
+                limit: None,
+                swap: None,
+                reservation: Some(reservation),
+                kernel: None,
+                kernel_tcp: None,
+                swappiness: None,
+            }),
+            ..Default::default()
+        };
+        <Memory as Controller>::apply(&linux_resources, &tmp).expect("apply memory");
+
+        let reservation_content =
+            std::fs::read_to_string(tmp.join(CGROUP_MEMORY_RESERVATION)).expect("Read to string");
+        assert_eq!(reservation.to_string(), reservation_content);
+        // the hard limit is left as it was
+        let limit_content =
+            std::fs::read_to_string(tmp.join(CGROUP_MEMORY_LIMIT)).expect("Read to string");
+        assert_eq!("0", limit_content);
+    }
+
+    #[test]
+    fn test_stat_memory() {
+        let tmp = create_temp_dir("test_stat_memory").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_MEMORY_USAGE, "1024\n").expect("set fixture for memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_MAX_USAGE, "2048\n")
+            .expect("set fixture for max memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_LIMIT, "4096\n").expect("set fixture for memory limit");
+        set_fixture(&tmp, CGROUP_MEMORY_FAIL_COUNT, "5\n").expect("set fixture for fail count");
+        set_fixture(
+            &tmp,
+            CGROUP_MEMORY_OOM_CONTROL,
+            "oom_kill_disable 0\nunder_oom 0\noom_kill 3\n",
+        )
+        .expect("set fixture for oom control");
+        set_fixture(&tmp, CGROUP_MEMORY_STAT, "cache 512\nrss 256\n")
+            .expect("set fixture for memory stat");
+
+        let stats = Memory::stats(&tmp).expect("get memory stats");
+
+        let expected = MemoryStats {
+            usage: 1024,
+            max_usage: 2048,
+            limit: 4096,
+            fail_count: 5,
+            oom: 0,
+            oom_kill: 3,
+            stat: MemoryStat {
+                cache: 512,
+                rss: 256,
+                ..Default::default()
+            },
+        };
+        assert_eq!(stats, expected);
+    }
+
+    #[test]
+    fn test_stat_memory_stat() {
+        let tmp = create_temp_dir("test_stat_memory_stat").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_MEMORY_USAGE, "1024\n").expect("set fixture for memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_MAX_USAGE, "2048\n")
+            .expect("set fixture for max memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_LIMIT, "4096\n").expect("set fixture for memory limit");
+        set_fixture(&tmp, CGROUP_MEMORY_FAIL_COUNT, "0\n").expect("set fixture for fail count");
+        set_fixture(
+            &tmp,
+            CGROUP_MEMORY_OOM_CONTROL,
+            "oom_kill_disable 0\nunder_oom 0\n",
+        )
+        .expect("set fixture for oom control");
+        // memory.stat of a cgroup v1 on linux 5.4
+        let content = [
+            "cache 34410496",
+            "rss 5001216",
+            "rss_huge 2097152",
+            "shmem 135168",
+            "mapped_file 8650752",
+            "dirty 270336",
+            "writeback 0",
+            "swap 0",
+            "pgpgin 38709",
+            "pgpgout 29087",
+            "pgfault 43617",
+            "pgmajfault 165",
+            "inactive_anon 2703360",
+            "active_anon 2297856",
+            "inactive_file 15994880",
+            "active_file 18296832",
+            "unevictable 0",
+            "hierarchical_memory_limit 9223372036854771712",
+            "hierarchical_memsw_limit 9223372036854771712",
+            "total_cache 34410496",
+            "total_rss 5001216",
+            "total_pgfault 43617",
+            "workingset_refault_anon 12",
+        ]
+        .join("\n");
+        set_fixture(&tmp, CGROUP_MEMORY_STAT, &content).expect("set fixture for memory stat");
+
+        let stat = Memory::stats(&tmp).expect("get memory stats").stat;
+
+        assert_eq!(stat.cache, 34410496);
+        assert_eq!(stat.rss, 5001216);
+        assert_eq!(stat.rss_huge, 2097152);
+        assert_eq!(stat.mapped_file, 8650752);
+        assert_eq!(stat.pgfault, 43617);
+        assert_eq!(stat.pgmajfault, 165);
+        assert_eq!(stat.active_anon, 2297856);
+        assert_eq!(stat.inactive_file, 15994880);
+        assert_eq!(stat.hierarchical_memory_limit, 9223372036854771712);
+    }
+
+    #[test]
+    fn test_stat_memory_without_oom_kill() {
+        let tmp = create_temp_dir("test_stat_memory_without_oom_kill")
+            .expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_MEMORY_USAGE, "1024\n").expect("set fixture for memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_MAX_USAGE, "2048\n")
+            .expect("set fixture for max memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_LIMIT, "4096\n").expect("set fixture for memory limit");
+        set_fixture(&tmp, CGROUP_MEMORY_FAIL_COUNT, "0\n").expect("set fixture for fail count");
+        // kernels before 4.13 do not count the processes killed by the oom killer
+        set_fixture(
+            &tmp,
+            CGROUP_MEMORY_OOM_CONTROL,
+            "oom_kill_disable 1\nunder_oom 1\n",
+        )
+        .expect("set fixture for oom control");
+        set_fixture(&tmp, CGROUP_MEMORY_STAT, "").expect("set fixture for memory stat");
+
+        let stats = Memory::stats(&tmp).expect("get memory stats");
+
+        assert_eq!(stats.oom_kill, 0);
+        assert_eq!(stats.fail_count, 0);
+    }
+
     quickcheck! {
-        fn property_test_set_memory(linux_memory: LinuxMemory, disable_oom_killer: bool, pid_int: i32) -> bool {
+        fn property_test_set_memory(linux_memory: LinuxMemory, disable_oom_killer: bool) -> bool {
             let tmp =
                 create_temp_dir("property_test_set_memory").expect("create temp directory for test");
             set_fixture(&tmp, CGROUP_MEMORY_USAGE, "0").expect("Set fixure for memory usage");
@@ -348,7 +647,6 @@ mod tests {
             set_fixture(&tmp, CGROUP_MEMORY_OOM_CONTROL, "0").expect("Set fixture for oom control");
             set_fixture(&tmp, CGROUP_KERNEL_MEMORY_LIMIT, "0").expect("Set fixture for kernel memory limit");
             set_fixture(&tmp, CGROUP_KERNEL_TCP_MEMORY_LIMIT, "0").expect("Set fixture for kernel tcp memory limit");
-            set_fixture(&tmp, CGROUP_PROCS, "").expect("set fixture for proc file");
 
 
             // clone to avoid use of moved value later on
@@ -366,8 +664,7 @@ mod tests {
                 network: None,
             };
 
-            let pid = Pid::from_raw(pid_int);
-            let result = <Memory as Controller>::apply(&linux_resources, &tmp, pid);
+            let result = <Memory as Controller>::apply(&linux_resources, &tmp);
 
             if result.is_err() {
                 if let Some(swappiness) = memory_limits.swappiness {
@@ -453,10 +750,6 @@ mod tests {
                 }
             };
 
-            // check procs file
-            let procs_content = std::fs::read_to_string(tmp.join(CGROUP_PROCS)).expect("read procs file");
-            let procs_check = procs_content == pid.to_string();
-
             // useful for debugging
             println!("reservation_check: {:?}", reservation_check);
             println!("kernel_check: {:?}", kernel_check);
@@ -465,7 +758,7 @@ mod tests {
             println!("limit_swap_check: {:?}", limit_swap_check);
 
             // combine all the checks
-            reservation_check && kernel_check && kernel_tcp_check && swappiness_check && limit_swap_check && procs_check
+            reservation_check && kernel_check && kernel_tcp_check && swappiness_check && limit_swap_check
         }
     }
 }
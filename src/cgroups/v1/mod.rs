@@ -3,7 +3,8 @@ mod controller;
 mod controller_type;
 mod cpu;
 mod cpuset;
-mod devices;
+pub(crate) mod devices;
+mod freezer;
 mod hugetlb;
 pub mod manager;
 mod memory;
@@ -1,25 +1,23 @@
-use std::{fs::create_dir_all, path::Path};
+use std::path::Path;
 
 use anyhow::Result;
-use nix::unistd::Pid;
 
 use crate::cgroups::common;
-use crate::cgroups::common::CGROUP_PROCS;
 use crate::cgroups::v1::Controller;
 use oci_spec::{LinuxNetwork, LinuxResources};
 
+const CGROUP_NET_CLS_CLASSID: &str = "net_cls.classid";
+
 pub struct NetworkClassifier {}
 
 impl Controller for NetworkClassifier {
-    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path, pid: Pid) -> Result<()> {
+    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path) -> Result<()> {
         log::debug!("Apply NetworkClassifier cgroup config");
-        create_dir_all(&cgroup_root)?;
 
         if let Some(network) = linux_resources.network.as_ref() {
             Self::apply(cgroup_root, network)?;
         }
 
-        common::write_cgroup_file(cgroup_root.join(CGROUP_PROCS), pid)?;
         Ok(())
     }
 }
@@ -27,7 +25,7 @@ impl Controller for NetworkClassifier {
 impl NetworkClassifier {
     fn apply(root_path: &Path, network: &LinuxNetwork) -> Result<()> {
         if let Some(class_id) = network.class_id {
-            common::write_cgroup_file(root_path.join("net_cls.classid"), class_id)?;
+            common::write_cgroup_file(root_path.join(CGROUP_NET_CLS_CLASSID), class_id)?;
         }
 
         Ok(())
@@ -44,7 +42,7 @@ mod tests {
     fn test_apply_network_classifier() {
         let tmp = create_temp_dir("test_apply_network_classifier")
             .expect("create temp directory for test");
-        set_fixture(&tmp, "net_cls.classid", "0").expect("set fixture for classID");
+        set_fixture(&tmp, CGROUP_NET_CLS_CLASSID, "0").expect("set fixture for classID");
 
         let id = 0x100001;
         let network = LinuxNetwork {
@@ -54,8 +52,26 @@ mod tests {
 
         NetworkClassifier::apply(&tmp, &network).expect("apply network classID");
 
-        let content =
-            std::fs::read_to_string(tmp.join("net_cls.classid")).expect("Read classID contents");
+        let content = std::fs::read_to_string(tmp.join(CGROUP_NET_CLS_CLASSID))
+            .expect("Read classID contents");
         assert_eq!(id.to_string(), content);
     }
+
+    #[test]
+    fn test_apply_network_classifier_without_class_id() {
+        let tmp = create_temp_dir("test_apply_network_classifier_without_class_id")
+            .expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_NET_CLS_CLASSID, "0").expect("set fixture for classID");
+
+        let network = LinuxNetwork {
+            class_id: None,
+            priorities: vec![],
+        };
+
+        NetworkClassifier::apply(&tmp, &network).expect("apply network classID");
+
+        let content = std::fs::read_to_string(tmp.join(CGROUP_NET_CLS_CLASSID))
+            .expect("Read classID contents");
+        assert_eq!("0", content);
+    }
 }
@@ -1,33 +1,36 @@
-use std::{fs::create_dir_all, path::Path};
+use std::path::Path;
 
 use anyhow::Result;
-use nix::unistd::Pid;
 
 use crate::cgroups::common;
-use crate::cgroups::common::CGROUP_PROCS;
 use crate::cgroups::v1::Controller;
 use oci_spec::{LinuxNetwork, LinuxResources};
 
+const CGROUP_NET_PRIO_IFPRIOMAP: &str = "net_prio.ifpriomap";
+
 pub struct NetworkPriority {}
 
 impl Controller for NetworkPriority {
-    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path, pid: Pid) -> Result<()> {
+    fn apply(linux_resources: &LinuxResources, cgroup_root: &Path) -> Result<()> {
         log::debug!("Apply NetworkPriority cgroup config");
-        create_dir_all(&cgroup_root)?;
 
         if let Some(network) = linux_resources.network.as_ref() {
             Self::apply(cgroup_root, network)?;
         }
 
-        common::write_cgroup_file(cgroup_root.join(CGROUP_PROCS), pid)?;
         Ok(())
     }
 }
 
 impl NetworkPriority {
     fn apply(root_path: &Path, network: &LinuxNetwork) -> Result<()> {
-        let priorities: String = network.priorities.iter().map(|p| p.to_string()).collect();
-        common::write_cgroup_file_str(root_path.join("net_prio.ifpriomap"), &priorities.trim())?;
+        // the kernel only parses a single interface priority per write
+        for priority in &network.priorities {
+            common::append_cgroup_file_str(
+                root_path.join(CGROUP_NET_PRIO_IFPRIOMAP),
+                &format!("{} {}", priority.name, priority.priority),
+            )?;
+        }
 
         Ok(())
     }
@@ -43,7 +46,7 @@ mod tests {
     fn test_apply_network_priorites() {
         let tmp = create_temp_dir("test_apply_network_priorites")
             .expect("create temp directory for test");
-        set_fixture(&tmp, "net_prio.ifpriomap", "").expect("set fixture for priority map");
+        set_fixture(&tmp, CGROUP_NET_PRIO_IFPRIOMAP, "").expect("set fixture for priority map");
         let priorities = vec![
             LinuxInterfacePriority {
                 name: "a".to_owned(),
@@ -54,7 +57,6 @@ mod tests {
                 priority: 2,
             },
         ];
-        let priorities_string = priorities.iter().map(|p| p.to_string()).collect::<String>();
         let network = LinuxNetwork {
             class_id: None,
             priorities,
@@ -62,8 +64,25 @@ mod tests {
 
         NetworkPriority::apply(&tmp, &network).expect("apply network priorities");
 
-        let content =
-            std::fs::read_to_string(tmp.join("net_prio.ifpriomap")).expect("Read classID contents");
-        assert_eq!(priorities_string.trim(), content);
+        let content = std::fs::read_to_string(tmp.join(CGROUP_NET_PRIO_IFPRIOMAP))
+            .expect("Read priority map contents");
+        assert_eq!("a 1\nb 2\n", content);
+    }
+
+    #[test]
+    fn test_apply_network_priorities_empty() {
+        let tmp = create_temp_dir("test_apply_network_priorities_empty")
+            .expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_NET_PRIO_IFPRIOMAP, "").expect("set fixture for priority map");
+        let network = LinuxNetwork {
+            class_id: None,
+            priorities: vec![],
+        };
+
+        NetworkPriority::apply(&tmp, &network).expect("apply network priorities");
+
+        let content = std::fs::read_to_string(tmp.join(CGROUP_NET_PRIO_IFPRIOMAP))
+            .expect("Read priority map contents");
+        assert_eq!("", content);
     }
 }
@@ -1,36 +1,45 @@
-use std::{
-    fs::{self},
-    path::Path,
-};
+use std::path::Path;
 
 use anyhow::Result;
 
 use crate::cgroups::{
-    common::{self, CGROUP_PROCS},
+    common,
+    stats::{self, PidsStats, StatsProvider},
     v1::Controller,
 };
 use oci_spec::{LinuxPids, LinuxResources};
 
+const CGROUP_PIDS_CURRENT: &str = "pids.current";
+const CGROUP_PIDS_MAX: &str = "pids.max";
+
 pub struct Pids {}
 
 impl Controller for Pids {
     fn apply(
         linux_resources: &LinuxResources,
         cgroup_root: &std::path::Path,
-        pid: nix::unistd::Pid,
     ) -> anyhow::Result<()> {
         log::debug!("Apply pids cgroup config");
-        fs::create_dir_all(cgroup_root)?;
 
         if let Some(pids) = &linux_resources.pids {
             Self::apply(cgroup_root, pids)?;
         }
 
-        common::write_cgroup_file(cgroup_root.join(CGROUP_PROCS), pid)?;
         Ok(())
     }
 }
 
+impl StatsProvider for Pids {
+    type Stats = PidsStats;
+
+    fn stats(cgroup_root: &Path) -> Result<Self::Stats> {
+        Ok(PidsStats {
+            current: stats::parse_single_value(&cgroup_root.join(CGROUP_PIDS_CURRENT))?,
+            limit: stats::parse_max_value(&cgroup_root.join(CGROUP_PIDS_MAX))?,
+        })
+    }
+}
+
 impl Pids {
     fn apply(root_path: &Path, pids: &LinuxPids) -> Result<()> {
         let limit = if pids.limit > 0 {
@@ -39,7 +48,7 @@ impl Pids {
             "max".to_string()
         };
 
-        common::write_cgroup_file_str(&root_path.join("pids.max"), &limit)?;
+        common::write_cgroup_file_str(root_path.join(CGROUP_PIDS_MAX), &limit)?;
         Ok(())
     }
 }
@@ -53,7 +62,7 @@ mod tests {
 
     #[test]
     fn test_set_pids() {
-        let pids_file_name = "pids.max";
+        let pids_file_name = CGROUP_PIDS_MAX;
         let tmp = create_temp_dir("test_set_pids").expect("create temp directory for test");
         set_fixture(&tmp, pids_file_name, "1000").expect("Set fixture for 1000 pids");
 
@@ -67,7 +76,7 @@ mod tests {
 
     #[test]
     fn test_set_pids_max() {
-        let pids_file_name = "pids.max";
+        let pids_file_name = CGROUP_PIDS_MAX;
         let tmp = create_temp_dir("test_set_pids_max").expect("create temp directory for test");
         set_fixture(&tmp, pids_file_name, "0").expect("set fixture for 0 pids");
 
@@ -79,4 +88,45 @@ mod tests {
             std::fs::read_to_string(tmp.join(pids_file_name)).expect("Read pids contents");
         assert_eq!("max".to_string(), content);
     }
+
+    #[test]
+    fn test_set_pids_max_with_negative_limit() {
+        let tmp = create_temp_dir("test_set_pids_max_with_negative_limit")
+            .expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_PIDS_MAX, "").expect("set fixture for negative pids");
+
+        let pids = LinuxPids { limit: -1 };
+
+        Pids::apply(&tmp, &pids).expect("apply pids");
+
+        let content =
+            std::fs::read_to_string(tmp.join(CGROUP_PIDS_MAX)).expect("Read pids contents");
+        assert_eq!("max".to_string(), content);
+    }
+
+    #[test]
+    fn test_stat_pids() {
+        let tmp = create_temp_dir("test_stat_pids").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_PIDS_CURRENT, "5\n").expect("set fixture for current pids");
+        set_fixture(&tmp, CGROUP_PIDS_MAX, "max\n").expect("set fixture for max pids");
+
+        let stats = Pids::stats(&tmp).expect("get pids stats");
+        assert_eq!(
+            stats,
+            PidsStats {
+                current: 5,
+                limit: None
+            }
+        );
+
+        set_fixture(&tmp, CGROUP_PIDS_MAX, "1024\n").expect("set fixture for max pids");
+        let stats = Pids::stats(&tmp).expect("get pids stats");
+        assert_eq!(
+            stats,
+            PidsStats {
+                current: 5,
+                limit: Some(1024)
+            }
+        );
+    }
 }
@@ -1,13 +1,17 @@
 use anyhow::{anyhow, Result};
-use std::path::Path;
+use std::{fs, path::Path};
 
-use crate::cgroups::common;
+use crate::cgroups::{
+    common,
+    stats::{self, CpuStats, StatsProvider},
+};
 use oci_spec::{LinuxCpu, LinuxResources};
 
 use super::controller::Controller;
 
 const CGROUP_CPU_WEIGHT: &str = "cpu.weight";
 const CGROUP_CPU_MAX: &str = "cpu.max";
+const CGROUP_CPU_STAT: &str = "cpu.stat";
 const DEFAULT_PERIOD: &str = "100000";
 const UNRESTRICTED_QUOTA: &str = "max";
 
@@ -23,6 +27,26 @@ impl Controller for Cpu {
     }
 }
 
+impl StatsProvider for Cpu {
+    type Stats = CpuStats;
+
+    fn stats(cgroup_path: &Path) -> Result<Self::Stats> {
+        let mut stats = CpuStats::default();
+        // the times in cpu.stat are reported in microseconds
+        let stat = stats::parse_flat_keyed_data(&cgroup_path.join(CGROUP_CPU_STAT))?;
+        let usec = |key: &str| stat.get(key).map(|v| v * 1000).unwrap_or_default();
+
+        stats.usage.usage_total = usec("usage_usec");
+        stats.usage.usage_user = usec("user_usec");
+        stats.usage.usage_kernel = usec("system_usec");
+        stats.throttling.periods = stat.get("nr_periods").copied().unwrap_or_default();
+        stats.throttling.throttled_periods = stat.get("nr_throttled").copied().unwrap_or_default();
+        stats.throttling.throttled_time = usec("throttled_usec");
+
+        Ok(stats)
+    }
+}
+
 impl Cpu {
     fn apply(path: &Path, cpu: &LinuxCpu) -> Result<()> {
         if Self::is_realtime_requested(cpu) {
@@ -37,32 +61,41 @@ impl Cpu {
             }
         }
 
-        // if quota is unrestricted set to 'max'
-        let mut quota_string = UNRESTRICTED_QUOTA.to_owned();
-        if let Some(quota) = cpu.quota {
-            if quota > 0 {
-                quota_string = quota.to_string();
-            }
+        // cpu.max holds both the quota and the period, so the current value of the one, which
+        // is not given, is kept. Otherwise an update of the shares would remove the quota
+        if cpu.quota.is_some() || cpu.period.is_some() {
+            let max = Self::cpu_max(path, cpu.quota, cpu.period);
+            common::write_cgroup_file_str(path.join(CGROUP_CPU_MAX), &max)?;
         }
 
-        let mut period_string: String = DEFAULT_PERIOD.to_owned();
-        if let Some(period) = cpu.period {
-            if period > 0 {
-                period_string = period.to_string();
-            }
-        }
+        Ok(())
+    }
 
-        // format is 'quota period'
-        // the kernel default is 'max 100000'
-        // 250000 250000 -> 1 CPU worth of runtime every 250ms
-        // 10000 50000 -> 20% of one CPU every 50ms
-        let max = quota_string + " " + &period_string;
-        common::write_cgroup_file_str(path.join(CGROUP_CPU_MAX), &max)?;
+    /// Returns the value of cpu.max for the quota and the period. The format is 'quota period',
+    /// the kernel default is 'max 100000'
+    /// 250000 250000 -> 1 CPU worth of runtime every 250ms
+    /// 10000 50000 -> 20% of one CPU every 50ms
+    fn cpu_max(path: &Path, quota: Option<i64>, period: Option<u64>) -> String {
+        let current = fs::read_to_string(path.join(CGROUP_CPU_MAX)).unwrap_or_default();
+        let mut current = current.split_whitespace();
+        let current_quota = current.next().unwrap_or(UNRESTRICTED_QUOTA);
+        let current_period = current.next().unwrap_or(DEFAULT_PERIOD);
 
-        Ok(())
+        // if quota is unrestricted set to 'max'
+        let quota = match quota {
+            Some(quota) if quota > 0 => quota.to_string(),
+            Some(_) => UNRESTRICTED_QUOTA.to_owned(),
+            None => current_quota.to_owned(),
+        };
+        let period = match period {
+            Some(period) if period > 0 => period.to_string(),
+            _ => current_period.to_owned(),
+        };
+
+        quota + " " + &period
     }
 
-    fn convert_shares_to_cgroup2(shares: u64) -> u64 {
+    pub(crate) fn convert_shares_to_cgroup2(shares: u64) -> u64 {
         if shares == 0 {
             return 0;
         }
@@ -87,7 +120,6 @@ impl Cpu {
 mod tests {
     use super::*;
     use crate::cgroups::test::{create_temp_dir, set_fixture, setup, LinuxCpuBuilder};
-    use std::fs;
 
     #[test]
     fn test_set_shares() {
@@ -106,6 +138,43 @@ mod tests {
         assert_eq!(content, 840.to_string());
     }
 
+    #[test]
+    fn test_set_shares_keeps_max() {
+        let (tmp, max) = setup("test_set_shares_keeps_max", CGROUP_CPU_MAX);
+        set_fixture(&tmp, CGROUP_CPU_MAX, "50000 100000").expect("set fixture for cpu.max");
+        set_fixture(&tmp, CGROUP_CPU_WEIGHT, "").expect("set fixture for cpu.weight");
+        let cpu = LinuxCpuBuilder::new().with_shares(1024).build();
+
+        Cpu::apply(&tmp, &cpu).expect("apply cpu");
+
+        let content = fs::read_to_string(max).expect("read cpu.max");
+        assert_eq!(content, "50000 100000");
+    }
+
+    #[test]
+    fn test_set_quota_keeps_period() {
+        let (tmp, max) = setup("test_set_quota_keeps_period", CGROUP_CPU_MAX);
+        set_fixture(&tmp, CGROUP_CPU_MAX, "max 250000\n").expect("set fixture for cpu.max");
+        let cpu = LinuxCpuBuilder::new().with_quota(200000).build();
+
+        Cpu::apply(&tmp, &cpu).expect("apply cpu");
+
+        let content = fs::read_to_string(max).expect("read cpu.max");
+        assert_eq!(content, "200000 250000");
+
+        set_fixture(&tmp, CGROUP_CPU_MAX, "200000 250000\n").expect("set fixture for cpu.max");
+        assert_eq!(Cpu::cpu_max(&tmp, None, Some(50000)), "200000 50000");
+    }
+
+    #[test]
+    fn test_convert_shares_to_cgroup2_boundaries() {
+        // the minimum and maximum of cpu.shares map to the minimum and maximum of cpu.weight
+        assert_eq!(Cpu::convert_shares_to_cgroup2(2), 1);
+        assert_eq!(Cpu::convert_shares_to_cgroup2(262144), 10000);
+        assert_eq!(Cpu::convert_shares_to_cgroup2(1024), 39);
+        assert_eq!(Cpu::convert_shares_to_cgroup2(0), 0);
+    }
+
     #[test]
     fn test_set_positive_quota() {
         // arrange
@@ -227,4 +296,31 @@ mod tests {
             "realtime period is not supported and should return an error"
         );
     }
+
+    #[test]
+    fn test_stat_cpu() {
+        // arrange
+        let tmp = create_temp_dir("test_stat_cpu").expect("create temp directory for test");
+        let stat_content = [
+            "usage_usec 3000",
+            "user_usec 2000",
+            "system_usec 1000",
+            "nr_periods 10",
+            "nr_throttled 5",
+            "throttled_usec 500",
+        ]
+        .join("\n");
+        set_fixture(&tmp, CGROUP_CPU_STAT, &stat_content).expect("set fixture for cpu stat");
+
+        // act
+        let stats = Cpu::stats(&tmp).expect("get cpu stats");
+
+        // assert
+        assert_eq!(stats.usage.usage_total, 3_000_000);
+        assert_eq!(stats.usage.usage_user, 2_000_000);
+        assert_eq!(stats.usage.usage_kernel, 1_000_000);
+        assert_eq!(stats.throttling.periods, 10);
+        assert_eq!(stats.throttling.throttled_periods, 5);
+        assert_eq!(stats.throttling.throttled_time, 500_000);
+    }
 }
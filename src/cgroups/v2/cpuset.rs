@@ -14,7 +14,7 @@ pub struct CpuSet {}
 impl Controller for CpuSet {
     fn apply(linux_resources: &LinuxResources, cgroup_path: &Path) -> Result<()> {
         if let Some(cpuset) = &linux_resources.cpu {
-            Self::apply(cgroup_path, cpuset)?;
+            Self::apply(cgroup_path, cpuset, Path::new(common::ONLINE_CPUS))?;
         }
 
         Ok(())
@@ -22,8 +22,9 @@ impl Controller for CpuSet {
 }
 
 impl CpuSet {
-    fn apply(path: &Path, cpuset: &LinuxCpu) -> Result<()> {
+    fn apply(path: &Path, cpuset: &LinuxCpu, online_cpus: &Path) -> Result<()> {
         if let Some(cpus) = &cpuset.cpus {
+            common::validate_cpus(cpus, online_cpus)?;
             common::write_cgroup_file_str(path.join(CGROUP_CPUSET_CPUS), cpus)?;
         }
 
@@ -40,7 +41,13 @@ mod tests {
     use std::fs;
 
     use super::*;
-    use crate::cgroups::test::{setup, LinuxCpuBuilder};
+    use crate::cgroups::test::{set_fixture, setup, LinuxCpuBuilder};
+    use std::path::PathBuf;
+
+    fn online_cpus(tmp: &Path) -> PathBuf {
+        set_fixture(tmp, "online", "0-7\n").expect("set fixture for online cpus");
+        tmp.join("online")
+    }
 
     #[test]
     fn test_set_cpus() {
@@ -49,7 +56,7 @@ mod tests {
         let cpuset = LinuxCpuBuilder::new().with_cpus("1-3".to_owned()).build();
 
         // act
-        CpuSet::apply(&tmp, &cpuset).expect("apply cpuset");
+        CpuSet::apply(&tmp, &cpuset, &online_cpus(&tmp)).expect("apply cpuset");
 
         // assert
         let content = fs::read_to_string(&cpus)
@@ -64,7 +71,7 @@ mod tests {
         let cpuset = LinuxCpuBuilder::new().with_mems("1-3".to_owned()).build();
 
         // act
-        CpuSet::apply(&tmp, &cpuset).expect("apply cpuset");
+        CpuSet::apply(&tmp, &cpuset, &online_cpus(&tmp)).expect("apply cpuset");
 
         // assert
         let content = fs::read_to_string(&mems)
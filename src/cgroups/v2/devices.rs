@@ -0,0 +1,544 @@
+//! Device access of cgroup v2, which has no interface files like devices.allow. Instead the
+//! rules are compiled into an eBPF program, which the kernel runs on every access to a device
+//! by a process of the cgroup, check https://www.kernel.org/doc/html/latest/admin-guide/cgroup-v2.html#device-controller
+use std::{
+    fs::File,
+    mem,
+    os::unix::io::{AsRawFd, RawFd},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use nix::{errno::Errno, unistd};
+use oci_spec::{LinuxDeviceCgroup, LinuxDeviceType, LinuxResources};
+
+use super::controller::Controller;
+use crate::{cgroups::v1::devices::Devices as V1Devices, rootfs::default_devices};
+
+// device types and access of struct bpf_cgroup_dev_ctx, see linux/bpf.h
+const BPF_DEVCG_DEV_BLOCK: u32 = 1;
+const BPF_DEVCG_DEV_CHAR: u32 = 2;
+const BPF_DEVCG_ACC_MKNOD: u32 = 1;
+const BPF_DEVCG_ACC_READ: u32 = 2;
+const BPF_DEVCG_ACC_WRITE: u32 = 4;
+const BPF_DEVCG_ACC_ALL: u32 = BPF_DEVCG_ACC_MKNOD | BPF_DEVCG_ACC_READ | BPF_DEVCG_ACC_WRITE;
+
+// instruction classes and fields of eBPF, see linux/bpf_common.h and linux/bpf.h
+const BPF_LDX: u8 = 0x01;
+const BPF_ALU: u8 = 0x04;
+const BPF_JMP: u8 = 0x05;
+const BPF_ALU64: u8 = 0x07;
+const BPF_W: u8 = 0x00;
+const BPF_MEM: u8 = 0x60;
+const BPF_K: u8 = 0x00;
+const BPF_X: u8 = 0x08;
+const BPF_AND: u8 = 0x50;
+const BPF_RSH: u8 = 0x70;
+const BPF_MOV: u8 = 0xb0;
+const BPF_JNE: u8 = 0x50;
+const BPF_EXIT: u8 = 0x90;
+
+// registers of eBPF, r0 holds the return value and r1 the context of the program
+const R0: u8 = 0;
+const R1: u8 = 1;
+const R2: u8 = 2;
+const R3: u8 = 3;
+const R4: u8 = 4;
+const R5: u8 = 5;
+
+// commands, program and attach types of the bpf syscall
+const BPF_PROG_LOAD: libc::c_int = 5;
+const BPF_PROG_ATTACH: libc::c_int = 8;
+const BPF_PROG_DETACH: libc::c_int = 9;
+const BPF_PROG_GET_FD_BY_ID: libc::c_int = 13;
+const BPF_PROG_QUERY: libc::c_int = 16;
+const BPF_PROG_TYPE_CGROUP_DEVICE: u32 = 15;
+const BPF_CGROUP_DEVICE: u32 = 6;
+const BPF_F_ALLOW_MULTI: u32 = 2;
+/// Number of programs, which are queried from the cgroup to be replaced
+const MAX_ATTACHED_PROGRAMS: usize = 64;
+
+pub struct Devices {}
+
+impl Controller for Devices {
+    fn apply(linux_resources: &LinuxResources, cgroup_path: &Path) -> Result<()> {
+        log::debug!("Apply devices cgroup config");
+
+        let rules = rule_table(&Self::device_rules(linux_resources))?;
+        let program = build_program(&rules);
+        attach_program(cgroup_path, &program)
+    }
+}
+
+impl Devices {
+    /// Returns the rules in the order of cgroup v1: access to all devices is denied first,
+    /// then the rules of the spec and the default devices are applied
+    fn device_rules(linux_resources: &LinuxResources) -> Vec<LinuxDeviceCgroup> {
+        let deny_all = LinuxDeviceCgroup {
+            allow: false,
+            typ: LinuxDeviceType::A,
+            major: None,
+            minor: None,
+            access: "rwm".to_string(),
+        };
+        let defaults: Vec<LinuxDeviceCgroup> = default_devices().iter().map(|d| d.into()).collect();
+
+        std::iter::once(deny_all)
+            .chain(linux_resources.devices.iter().cloned())
+            .chain(defaults)
+            .chain(V1Devices::default_allow_devices())
+            .collect()
+    }
+}
+
+/// Rule, which the program checks against the device that is accessed
+#[derive(Debug, PartialEq, Eq)]
+struct DeviceRule {
+    // BPF_DEVCG_DEV_BLOCK or BPF_DEVCG_DEV_CHAR, None for devices of all types
+    typ: Option<u32>,
+    // None for all devices of the type
+    major: Option<u32>,
+    minor: Option<u32>,
+    // BPF_DEVCG_ACC_* bits, which the access must be limited to
+    access: u32,
+    allow: bool,
+}
+
+impl DeviceRule {
+    fn matches_all(&self) -> bool {
+        self.typ.is_none()
+            && self.major.is_none()
+            && self.minor.is_none()
+            && self.access == BPF_DEVCG_ACC_ALL
+    }
+}
+
+/// Translates the rules into the order the program checks them. A later rule overrides the
+/// earlier ones like in cgroup v1, so that the first matching rule from the end decides.
+/// Rules, which are overridden by a rule for all devices, are dropped
+fn rule_table(devices: &[LinuxDeviceCgroup]) -> Result<Vec<DeviceRule>> {
+    let mut rules = Vec::new();
+    for device in devices.iter().rev() {
+        let typ = match device.typ {
+            LinuxDeviceType::A => None,
+            LinuxDeviceType::B => Some(BPF_DEVCG_DEV_BLOCK),
+            LinuxDeviceType::C | LinuxDeviceType::U => Some(BPF_DEVCG_DEV_CHAR),
+            LinuxDeviceType::P => bail!(
+                "device type p of {} is not supported by cgroup v2",
+                device.to_string()
+            ),
+        };
+        let mut access = 0;
+        for c in device.access.chars() {
+            access |= match c {
+                'r' => BPF_DEVCG_ACC_READ,
+                'w' => BPF_DEVCG_ACC_WRITE,
+                'm' => BPF_DEVCG_ACC_MKNOD,
+                _ => bail!(
+                    "invalid access {:?} of device {}",
+                    device.access,
+                    device.to_string()
+                ),
+            };
+        }
+        // a rule without access applies to any access like in cgroup v1
+        if access == 0 {
+            access = BPF_DEVCG_ACC_ALL;
+        }
+        let number = |n: Option<i64>| n.filter(|n| *n >= 0).map(|n| n as u32);
+
+        let rule = DeviceRule {
+            typ,
+            major: number(device.major),
+            minor: number(device.minor),
+            access,
+            allow: device.allow,
+        };
+        let matches_all = rule.matches_all();
+        rules.push(rule);
+        if matches_all {
+            break;
+        }
+    }
+    Ok(rules)
+}
+
+/// Instruction of an eBPF program, see struct bpf_insn
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Instruction {
+    code: u8,
+    // the destination register in the low and the source register in the high nibble
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+impl Instruction {
+    fn new(code: u8, dst: u8, src: u8, off: i16, imm: i32) -> Self {
+        Self {
+            code,
+            regs: dst | (src << 4),
+            off,
+            imm,
+        }
+    }
+}
+
+/// Builds the program, which returns 1 if the access is allowed and 0 otherwise. Every rule is
+/// a block, whose conditions jump to the next block if the device does not match the rule
+fn build_program(rules: &[DeviceRule]) -> Vec<Instruction> {
+    let mut program = vec![
+        // the type is in the low and the access in the high 16 bits of access_type
+        Instruction::new(BPF_LDX | BPF_MEM | BPF_W, R2, R1, 0, 0),
+        Instruction::new(BPF_ALU | BPF_AND | BPF_K, R2, 0, 0, 0xffff),
+        Instruction::new(BPF_LDX | BPF_MEM | BPF_W, R3, R1, 0, 0),
+        Instruction::new(BPF_ALU | BPF_RSH | BPF_K, R3, 0, 0, 16),
+        Instruction::new(BPF_LDX | BPF_MEM | BPF_W, R4, R1, 4, 0),
+        Instruction::new(BPF_LDX | BPF_MEM | BPF_W, R5, R1, 8, 0),
+    ];
+
+    for rule in rules {
+        let mut block = Vec::new();
+        if let Some(typ) = rule.typ {
+            block.push(Instruction::new(
+                BPF_JMP | BPF_JNE | BPF_K,
+                R2,
+                0,
+                0,
+                typ as i32,
+            ));
+        }
+        // the requested access has to be a subset of the access of the rule
+        if rule.access != BPF_DEVCG_ACC_ALL {
+            block.push(Instruction::new(BPF_ALU | BPF_MOV | BPF_X, R1, R3, 0, 0));
+            block.push(Instruction::new(
+                BPF_ALU | BPF_AND | BPF_K,
+                R1,
+                0,
+                0,
+                rule.access as i32,
+            ));
+            block.push(Instruction::new(BPF_JMP | BPF_JNE | BPF_X, R1, R3, 0, 0));
+        }
+        if let Some(major) = rule.major {
+            block.push(Instruction::new(
+                BPF_JMP | BPF_JNE | BPF_K,
+                R4,
+                0,
+                0,
+                major as i32,
+            ));
+        }
+        if let Some(minor) = rule.minor {
+            block.push(Instruction::new(
+                BPF_JMP | BPF_JNE | BPF_K,
+                R5,
+                0,
+                0,
+                minor as i32,
+            ));
+        }
+        block.push(Instruction::new(
+            BPF_ALU64 | BPF_MOV | BPF_K,
+            R0,
+            0,
+            0,
+            rule.allow as i32,
+        ));
+        block.push(Instruction::new(BPF_JMP | BPF_EXIT, 0, 0, 0, 0));
+
+        // the offset of a jump is relative to the next instruction
+        let len = block.len();
+        for (i, insn) in block.iter_mut().enumerate() {
+            if insn.code & 0x07 == BPF_JMP && insn.code != BPF_JMP | BPF_EXIT {
+                insn.off = (len - i - 1) as i16;
+            }
+        }
+        program.extend(block);
+    }
+
+    // access to a device, which no rule matches, is denied. The verifier rejects the program
+    // with the default if it is unreachable after a rule for all devices
+    if !rules.last().is_some_and(DeviceRule::matches_all) {
+        program.push(Instruction::new(BPF_ALU64 | BPF_MOV | BPF_K, R0, 0, 0, 0));
+        program.push(Instruction::new(BPF_JMP | BPF_EXIT, 0, 0, 0, 0));
+    }
+    program
+}
+
+// attributes of the commands of the bpf syscall, see union bpf_attr
+#[repr(C)]
+#[derive(Default)]
+struct ProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+    kern_version: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgAttachAttr {
+    target_fd: u32,
+    attach_bpf_fd: u32,
+    attach_type: u32,
+    attach_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgQueryAttr {
+    target_fd: u32,
+    attach_type: u32,
+    query_flags: u32,
+    attach_flags: u32,
+    prog_ids: u64,
+    prog_cnt: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgGetFdByIdAttr {
+    prog_id: u32,
+    next_id: u32,
+    open_flags: u32,
+}
+
+fn bpf<T>(cmd: libc::c_int, attr: &mut T) -> nix::Result<RawFd> {
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            cmd,
+            attr as *mut T,
+            mem::size_of::<T>() as libc::c_uint,
+        )
+    };
+    Errno::result(ret).map(|fd| fd as RawFd)
+}
+
+/// Loads the program and attaches it to the cgroup. The programs, which have been attached
+/// before, e.g. by an earlier update, are detached afterwards, as all programs of a cgroup
+/// have to allow an access
+fn attach_program(cgroup_path: &Path, program: &[Instruction]) -> Result<()> {
+    let license = b"Apache\0";
+    let mut load = ProgLoadAttr {
+        prog_type: BPF_PROG_TYPE_CGROUP_DEVICE,
+        insn_cnt: program.len() as u32,
+        insns: program.as_ptr() as u64,
+        license: license.as_ptr() as u64,
+        ..Default::default()
+    };
+    let prog_fd = bpf(BPF_PROG_LOAD, &mut load).context("failed to load the device program")?;
+
+    let result = File::open(cgroup_path)
+        .with_context(|| format!("failed to open the cgroup {:?}", cgroup_path))
+        .and_then(|cgroup| {
+            let cgroup_fd = cgroup.as_raw_fd();
+            let attached = query_programs(cgroup_fd)?;
+
+            let mut attach = ProgAttachAttr {
+                target_fd: cgroup_fd as u32,
+                attach_bpf_fd: prog_fd as u32,
+                attach_type: BPF_CGROUP_DEVICE,
+                attach_flags: BPF_F_ALLOW_MULTI,
+            };
+            bpf(BPF_PROG_ATTACH, &mut attach).with_context(|| {
+                format!("failed to attach the device program to {:?}", cgroup_path)
+            })?;
+
+            for id in attached {
+                detach_program(cgroup_fd, id).with_context(|| {
+                    format!(
+                        "failed to detach the device program {} from {:?}",
+                        id, cgroup_path
+                    )
+                })?;
+            }
+            Ok(())
+        });
+    // the program stays attached to the cgroup without the fd
+    let _ = unistd::close(prog_fd);
+    result
+}
+
+fn query_programs(cgroup_fd: RawFd) -> Result<Vec<u32>> {
+    let mut ids = [0u32; MAX_ATTACHED_PROGRAMS];
+    let mut query = ProgQueryAttr {
+        target_fd: cgroup_fd as u32,
+        attach_type: BPF_CGROUP_DEVICE,
+        prog_ids: ids.as_mut_ptr() as u64,
+        prog_cnt: ids.len() as u32,
+        ..Default::default()
+    };
+    bpf(BPF_PROG_QUERY, &mut query).context("failed to query the device programs")?;
+    Ok(ids[..query.prog_cnt as usize].to_vec())
+}
+
+fn detach_program(cgroup_fd: RawFd, id: u32) -> Result<()> {
+    let mut get_fd = ProgGetFdByIdAttr {
+        prog_id: id,
+        ..Default::default()
+    };
+    let prog_fd = bpf(BPF_PROG_GET_FD_BY_ID, &mut get_fd)?;
+    let mut detach = ProgAttachAttr {
+        target_fd: cgroup_fd as u32,
+        attach_bpf_fd: prog_fd as u32,
+        attach_type: BPF_CGROUP_DEVICE,
+        ..Default::default()
+    };
+    let result = bpf(BPF_PROG_DETACH, &mut detach);
+    let _ = unistd::close(prog_fd);
+    result?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(
+        allow: bool,
+        typ: LinuxDeviceType,
+        major: Option<i64>,
+        minor: Option<i64>,
+        access: &str,
+    ) -> LinuxDeviceCgroup {
+        LinuxDeviceCgroup {
+            allow,
+            typ,
+            major,
+            minor,
+            access: access.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rule_table() {
+        let devices = vec![
+            device(false, LinuxDeviceType::A, None, None, "rwm"),
+            // /dev/null and the block devices of sda
+            device(true, LinuxDeviceType::C, Some(1), Some(3), "rw"),
+            device(true, LinuxDeviceType::B, Some(8), None, "r"),
+        ];
+
+        assert_eq!(
+            rule_table(&devices).expect("build rule table"),
+            vec![
+                DeviceRule {
+                    typ: Some(BPF_DEVCG_DEV_BLOCK),
+                    major: Some(8),
+                    minor: None,
+                    access: BPF_DEVCG_ACC_READ,
+                    allow: true,
+                },
+                DeviceRule {
+                    typ: Some(BPF_DEVCG_DEV_CHAR),
+                    major: Some(1),
+                    minor: Some(3),
+                    access: BPF_DEVCG_ACC_READ | BPF_DEVCG_ACC_WRITE,
+                    allow: true,
+                },
+                DeviceRule {
+                    typ: None,
+                    major: None,
+                    minor: None,
+                    access: BPF_DEVCG_ACC_ALL,
+                    allow: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rule_table_drops_overridden_rules() {
+        let devices = vec![
+            device(true, LinuxDeviceType::C, Some(1), Some(3), "rwm"),
+            device(false, LinuxDeviceType::A, None, None, ""),
+            device(true, LinuxDeviceType::U, Some(5), Some(1), "rwm"),
+        ];
+
+        let rules = rule_table(&devices).expect("build rule table");
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].typ, Some(BPF_DEVCG_DEV_CHAR));
+        assert!(rules[1].matches_all());
+        assert!(!rules[1].allow);
+    }
+
+    #[test]
+    fn test_rule_table_with_invalid_device() {
+        assert!(rule_table(&[device(true, LinuxDeviceType::P, None, None, "rwm")]).is_err());
+        assert!(rule_table(&[device(true, LinuxDeviceType::C, None, None, "rwx")]).is_err());
+    }
+
+    #[test]
+    fn test_build_program() {
+        let rules = vec![DeviceRule {
+            typ: Some(BPF_DEVCG_DEV_CHAR),
+            major: Some(1),
+            minor: Some(3),
+            access: BPF_DEVCG_ACC_READ,
+            allow: true,
+        }];
+
+        let program = build_program(&rules);
+
+        // the prologue, a block of 8 instructions for the rule and the default
+        assert_eq!(program.len(), 6 + 8 + 2);
+        let block = &program[6..14];
+        // every condition jumps to the instruction after the block
+        let offsets: Vec<i16> = block.iter().map(|insn| insn.off).collect();
+        assert_eq!(offsets, vec![7, 0, 0, 4, 3, 2, 0, 0]);
+        assert_eq!(block[0].imm, BPF_DEVCG_DEV_CHAR as i32);
+        assert_eq!(block[2].imm, BPF_DEVCG_ACC_READ as i32);
+        assert_eq!(block[4].imm, 1);
+        assert_eq!(block[5].imm, 3);
+        assert_eq!(
+            block[6],
+            Instruction::new(BPF_ALU64 | BPF_MOV | BPF_K, R0, 0, 0, 1)
+        );
+        assert_eq!(
+            &program[14..],
+            &[
+                Instruction::new(BPF_ALU64 | BPF_MOV | BPF_K, R0, 0, 0, 0),
+                Instruction::new(BPF_JMP | BPF_EXIT, 0, 0, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_program_with_rule_for_all_devices() {
+        let rules = vec![DeviceRule {
+            typ: None,
+            major: None,
+            minor: None,
+            access: BPF_DEVCG_ACC_ALL,
+            allow: false,
+        }];
+
+        let program = build_program(&rules);
+
+        // the rule matches every access, so that there is no default after it
+        assert_eq!(
+            &program[6..],
+            &[
+                Instruction::new(BPF_ALU64 | BPF_MOV | BPF_K, R0, 0, 0, 0),
+                Instruction::new(BPF_JMP | BPF_EXIT, 0, 0, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_device_rules() {
+        let rules = Devices::device_rules(&LinuxResources::default());
+
+        // access to all devices is denied before the default devices are allowed
+        assert!(!rules[0].allow);
+        assert!(matches!(rules[0].typ, LinuxDeviceType::A));
+        assert!(rules[1..].iter().all(|rule| rule.allow));
+    }
+}
@@ -1,12 +1,191 @@
 use anyhow::Result;
+use std::{collections::BTreeMap, path::Path};
 
 use super::controller::Controller;
-use oci_spec::LinuxResources;
+use crate::cgroups::common;
+use oci_spec::{LinuxBlockIo, LinuxResources};
+
+const CGROUP_IO_WEIGHT: &str = "io.weight";
+const CGROUP_IO_MAX: &str = "io.max";
 
 pub struct Io {}
 
 impl Controller for Io {
-    fn apply(_: &LinuxResources, _: &std::path::Path) -> Result<()> {
+    fn apply(linux_resources: &LinuxResources, cgroup_path: &Path) -> Result<()> {
+        if let Some(blkio) = &linux_resources.block_io {
+            Self::apply(cgroup_path, blkio)?;
+        }
+
         Ok(())
     }
 }
+
+/// Limits of io.max for a single device
+#[derive(Debug, Default)]
+struct IoMax {
+    rbps: Option<u64>,
+    wbps: Option<u64>,
+    riops: Option<u64>,
+    wiops: Option<u64>,
+}
+
+impl Io {
+    fn apply(cgroup_path: &Path, blkio: &LinuxBlockIo) -> Result<()> {
+        // the weights of io.weight share the format of cpu.weight,
+        // e.g. 'default 100' and '8:0 200' for a single device
+        if let Some(weight) = blkio.blkio_weight {
+            let weight = Self::convert_cfq_io_weight_to_bfq(weight);
+            if weight != 0 {
+                common::write_cgroup_file_str(
+                    cgroup_path.join(CGROUP_IO_WEIGHT),
+                    &format!("default {}", weight),
+                )?;
+            }
+        }
+
+        for wd in &blkio.blkio_weight_device {
+            if let Some(weight) = wd.weight {
+                let weight = Self::convert_cfq_io_weight_to_bfq(weight);
+                if weight != 0 {
+                    common::write_cgroup_file_str(
+                        cgroup_path.join(CGROUP_IO_WEIGHT),
+                        &format!("{}:{} {}", wd.major, wd.minor, weight),
+                    )?;
+                }
+            }
+        }
+
+        // every line of io.max only changes the limits of the device it names
+        for line in Self::io_max_lines(blkio) {
+            common::write_cgroup_file_str(cgroup_path.join(CGROUP_IO_MAX), &line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Converts a weight of the cfq scheduler of cgroup v1, which ranges from 10 to 1000,
+    /// to a weight of io.weight, which ranges from 1 to 10000
+    fn convert_cfq_io_weight_to_bfq(weight: u16) -> u64 {
+        if weight == 0 {
+            return 0;
+        }
+
+        let weight = u64::from(weight.clamp(10, 1000));
+        1 + (weight - 10) * 9999 / 990
+    }
+
+    /// Returns the lines of io.max, one for every device with all its throttle limits,
+    /// e.g. '8:0 rbps=1048576 wiops=120'
+    fn io_max_lines(blkio: &LinuxBlockIo) -> Vec<String> {
+        let mut devices: BTreeMap<(i64, i64), IoMax> = BTreeMap::new();
+        for td in &blkio.blkio_throttle_read_bps_device {
+            devices.entry((td.major, td.minor)).or_default().rbps = Some(td.rate);
+        }
+        for td in &blkio.blkio_throttle_write_bps_device {
+            devices.entry((td.major, td.minor)).or_default().wbps = Some(td.rate);
+        }
+        for td in &blkio.blkio_throttle_read_iops_device {
+            devices.entry((td.major, td.minor)).or_default().riops = Some(td.rate);
+        }
+        for td in &blkio.blkio_throttle_write_iops_device {
+            devices.entry((td.major, td.minor)).or_default().wiops = Some(td.rate);
+        }
+
+        devices
+            .iter()
+            .map(|((major, minor), max)| {
+                let mut line = format!("{}:{}", major, minor);
+                for (key, rate) in [
+                    ("rbps", max.rbps),
+                    ("wbps", max.wbps),
+                    ("riops", max.riops),
+                    ("wiops", max.wiops),
+                ]
+                .iter()
+                {
+                    if let Some(rate) = rate {
+                        line.push_str(&format!(" {}={}", key, rate));
+                    }
+                }
+                line
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgroups::test::setup;
+    use oci_spec::LinuxThrottleDevice;
+    use std::fs;
+
+    fn throttle(major: i64, minor: i64, rate: u64) -> LinuxThrottleDevice {
+        LinuxThrottleDevice { major, minor, rate }
+    }
+
+    #[test]
+    fn test_convert_cfq_io_weight_to_bfq() {
+        // the minimum and maximum of blkio.weight map to the minimum and maximum of io.weight
+        assert_eq!(Io::convert_cfq_io_weight_to_bfq(10), 1);
+        assert_eq!(Io::convert_cfq_io_weight_to_bfq(1000), 10000);
+        assert_eq!(Io::convert_cfq_io_weight_to_bfq(500), 4950);
+        assert_eq!(Io::convert_cfq_io_weight_to_bfq(0), 0);
+    }
+
+    #[test]
+    fn test_set_io_weight() {
+        // arrange
+        let (tmp, weight) = setup("test_set_io_weight", CGROUP_IO_WEIGHT);
+        let blkio = LinuxBlockIo {
+            blkio_weight: Some(100),
+            ..Default::default()
+        };
+
+        // act
+        Io::apply(&tmp, &blkio).expect("apply io");
+
+        // assert
+        let content = fs::read_to_string(weight)
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_IO_WEIGHT));
+        assert_eq!(content, "default 910");
+    }
+
+    #[test]
+    fn test_io_max_lines() {
+        let blkio = LinuxBlockIo {
+            blkio_throttle_read_bps_device: vec![throttle(8, 0, 1048576), throttle(8, 16, 2048)],
+            blkio_throttle_write_iops_device: vec![throttle(8, 0, 120)],
+            blkio_throttle_write_bps_device: vec![throttle(8, 16, 4096)],
+            blkio_throttle_read_iops_device: vec![throttle(253, 1, 300)],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Io::io_max_lines(&blkio),
+            vec![
+                "8:0 rbps=1048576 wiops=120",
+                "8:16 rbps=2048 wbps=4096",
+                "253:1 riops=300",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_io_max() {
+        // arrange
+        let (tmp, max) = setup("test_set_io_max", CGROUP_IO_MAX);
+        let blkio = LinuxBlockIo {
+            blkio_throttle_write_bps_device: vec![throttle(8, 0, 4096)],
+            ..Default::default()
+        };
+
+        // act
+        Io::apply(&tmp, &blkio).expect("apply io");
+
+        // assert
+        let content = fs::read_to_string(max)
+            .unwrap_or_else(|_| panic!("read {} file content", CGROUP_IO_MAX));
+        assert_eq!(content, "8:0 wbps=4096");
+    }
+}
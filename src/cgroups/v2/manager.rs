@@ -4,16 +4,23 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Context, Result};
 
-use nix::unistd::Pid;
+use nix::{
+    errno::Errno,
+    unistd::{self, Gid, Pid, Uid},
+};
 use oci_spec::LinuxResources;
 
-use super::{cpu::Cpu, cpuset::CpuSet, hugetlb::HugeTlb, io::Io, memory::Memory, pids::Pids};
+use super::{
+    cpu::Cpu, cpuset::CpuSet, devices::Devices, hugetlb::HugeTlb, io::Io, memory::Memory,
+    pids::Pids,
+};
 use crate::{
     cgroups::v2::controller::Controller,
     cgroups::{
-        common::{self, CgroupManager, CGROUP_PROCS},
+        common::{self, CgroupManager, FreezerState, CGROUP_PROCS},
+        stats::{Stats, StatsProvider},
         v2::controller_type::ControllerType,
     },
     utils::PathBufExt,
@@ -21,6 +28,11 @@ use crate::{
 
 const CGROUP_CONTROLLERS: &str = "cgroup.controllers";
 const CGROUP_SUBTREE_CONTROL: &str = "cgroup.subtree_control";
+const CGROUP_FREEZE: &str = "cgroup.freeze";
+const CGROUP_THREADS: &str = "cgroup.threads";
+// interface files, which the owner of a delegated cgroup has to be able to write,
+// check https://www.kernel.org/doc/html/latest/admin-guide/cgroup-v2.html#delegation
+const DELEGATED_FILES: &[&str] = &[CGROUP_PROCS, CGROUP_SUBTREE_CONTROL, CGROUP_THREADS];
 
 const CONTROLLER_TYPES: &[ControllerType] = &[
     ControllerType::Cpu,
@@ -34,6 +46,10 @@ const CONTROLLER_TYPES: &[ControllerType] = &[
 pub struct Manager {
     root_path: PathBuf,
     cgroup_path: PathBuf,
+    // only the controllers, which are delegated to the user, are used by a rootless container
+    rootless: bool,
+    // owner of the new cgroup directories of a rootless container
+    owner: Option<(u32, u32)>,
 }
 
 impl Manager {
@@ -41,19 +57,33 @@ impl Manager {
         Ok(Self {
             root_path,
             cgroup_path,
+            rootless: false,
+            owner: None,
         })
     }
 
-    fn create_unified_cgroup(&self, cgroup_path: &Path, pid: Pid) -> Result<PathBuf> {
-        let full_path = self.root_path.join_absolute_path(cgroup_path)?;
-        let controllers: Vec<String> = self
-            .get_available_controllers(&self.root_path)?
-            .into_iter()
-            .map(|c| format!("{}{}", "+", c.to_string()))
-            .collect();
+    /// Creates the manager of a cgroup of a rootless container. The root path is the subtree,
+    /// which is delegated to the user, instead of the root of the hierarchy. The new cgroups
+    /// are handed over to the owner, which the root user of the container is mapped to
+    pub fn new_rootless(
+        root_path: PathBuf,
+        cgroup_path: PathBuf,
+        owner: Option<(u32, u32)>,
+    ) -> Result<Self> {
+        Ok(Self {
+            root_path,
+            cgroup_path,
+            rootless: true,
+            owner,
+        })
+    }
 
-        Self::write_controllers(&self.root_path, &controllers)?;
+    fn create_unified_cgroup(&self, cgroup_path: &Path) -> Result<PathBuf> {
+        let full_path = self.root_path.join_absolute_path(cgroup_path)?;
 
+        // a controller can only be used in a cgroup if it is enabled in the
+        // subtree_control of all of its ancestors, starting with the root
+        Self::enable_controllers(&self.root_path, true)?;
         let mut current_path = self.root_path.clone();
         let mut components = cgroup_path.components().skip(1).peekable();
         while let Some(component) = components.next() {
@@ -61,24 +91,23 @@ impl Manager {
             if !current_path.exists() {
                 fs::create_dir(&current_path)?;
                 fs::metadata(&current_path)?.permissions().set_mode(0o755);
+                if let Some(owner) = self.owner {
+                    chown_cgroup(&current_path, owner)?;
+                }
             }
 
             // last component cannot have subtree_control enabled due to internal process constraint
             // if this were set, writing to the cgroups.procs file will fail with Erno 16 (device or resource busy)
             if components.peek().is_some() {
-                Self::write_controllers(&current_path, &controllers)?;
+                Self::enable_controllers(&current_path, false)?;
             }
         }
 
-        common::write_cgroup_file(&full_path.join(CGROUP_PROCS), pid)?;
         Ok(full_path)
     }
 
-    fn get_available_controllers<P: AsRef<Path>>(
-        &self,
-        cgroup_path: P,
-    ) -> Result<Vec<ControllerType>> {
-        let controllers_path = self.root_path.join(cgroup_path).join(CGROUP_CONTROLLERS);
+    fn get_available_controllers(cgroup_path: &Path) -> Result<Vec<ControllerType>> {
+        let controllers_path = cgroup_path.join(CGROUP_CONTROLLERS);
         if !controllers_path.exists() {
             return Err(anyhow!(
                 "cannot get available controllers. {:?} does not exist",
@@ -102,38 +131,345 @@ impl Manager {
         Ok(controllers)
     }
 
-    fn write_controllers(path: &Path, controllers: &Vec<String>) -> Result<()> {
-        for controller in controllers {
-            common::write_cgroup_file_str(path.join(CGROUP_SUBTREE_CONTROL), controller)?;
+    /// Returns the controllers, whose resources are applied to the cgroup
+    fn controllers(&self, cgroup_path: &Path) -> Result<Vec<ControllerType>> {
+        if !self.rootless {
+            return Ok(CONTROLLER_TYPES.to_vec());
         }
-
-        Ok(())
+        Ok(delegated_controllers(&Self::get_available_controllers(
+            cgroup_path,
+        )?))
     }
-}
 
-impl CgroupManager for Manager {
-    fn apply(&self, linux_resources: &LinuxResources, pid: Pid) -> Result<()> {
-        let full_cgroup_path = self.create_unified_cgroup(&self.cgroup_path, pid)?;
+    /// Applies the resources to the cgroup, the device rules only if devices is set
+    fn apply_resources(&self, linux_resources: &LinuxResources, devices: bool) -> Result<()> {
+        let full_cgroup_path = self.root_path.join_absolute_path(&self.cgroup_path)?;
 
-        for controller in CONTROLLER_TYPES {
+        for controller in &self.controllers(&full_cgroup_path)? {
             match controller {
                 ControllerType::Cpu => Cpu::apply(linux_resources, &full_cgroup_path)?,
                 ControllerType::CpuSet => CpuSet::apply(linux_resources, &full_cgroup_path)?,
-                ControllerType::HugeTlb => HugeTlb::apply(linux_resources, &&full_cgroup_path)?,
-                ControllerType::Io => Io::apply(linux_resources, &&full_cgroup_path)?,
+                ControllerType::HugeTlb => HugeTlb::apply(linux_resources, &full_cgroup_path)?,
+                ControllerType::Io => Io::apply(linux_resources, &full_cgroup_path)?,
                 ControllerType::Memory => Memory::apply(linux_resources, &full_cgroup_path)?,
-                ControllerType::Pids => Pids::apply(linux_resources, &&full_cgroup_path)?,
+                ControllerType::Pids => Pids::apply(linux_resources, &full_cgroup_path)?,
             }
         }
+        // the device controller is not listed in cgroup.controllers, as it is always available.
+        // Its programs can not be attached by an unprivileged user, though
+        if devices && !self.rootless {
+            Devices::apply(linux_resources, &full_cgroup_path)?;
+        }
 
         Ok(())
     }
 
+    /// Enables the available controllers of a cgroup for its children, which are not enabled yet.
+    /// All controllers are written at once, e.g. '+cpu +memory +pids +io'
+    fn enable_controllers(cgroup_path: &Path, is_root: bool) -> Result<()> {
+        let subtree_control = cgroup_path.join(CGROUP_SUBTREE_CONTROL);
+        let enabled = fs::read_to_string(&subtree_control)
+            .with_context(|| format!("failed to read {:?}", subtree_control))?;
+        let enabled: Vec<&str> = enabled.split_whitespace().collect();
+        let controllers: Vec<String> = Self::get_available_controllers(cgroup_path)?
+            .into_iter()
+            .map(|c| c.to_string())
+            .filter(|c| !enabled.contains(&c.as_str()))
+            .collect();
+        if controllers.is_empty() {
+            return Ok(());
+        }
+
+        // except for the root, a cgroup with processes can not enable controllers for
+        // its children, because processes are only allowed in the leaves of the tree
+        if !is_root && !common::get_procs(cgroup_path)?.is_empty() {
+            bail!(
+                "cannot enable the controllers {:?} in {:?}, because it has processes",
+                controllers,
+                cgroup_path
+            )
+        }
+
+        let controllers: Vec<String> = controllers.iter().map(|c| format!("+{}", c)).collect();
+        common::write_cgroup_file_str(&subtree_control, &controllers.join(" "))
+    }
+}
+
+impl CgroupManager for Manager {
+    fn add_task(&self, pid: Pid) -> Result<()> {
+        let full_cgroup_path = self.create_unified_cgroup(&self.cgroup_path)?;
+        common::write_cgroup_file(full_cgroup_path.join(CGROUP_PROCS), pid)?;
+
+        Ok(())
+    }
+
+    fn apply(&self, linux_resources: &LinuxResources) -> Result<()> {
+        self.apply_resources(linux_resources, true)
+    }
+
+    fn update(&self, linux_resources: &LinuxResources) -> Result<()> {
+        // the device rules are replaced as a whole by a new program, so they are
+        // only applied again if the update contains any rules
+        self.apply_resources(linux_resources, !linux_resources.devices.is_empty())
+    }
+
     fn remove(&self) -> Result<()> {
         let full_path = self.root_path.join_absolute_path(&self.cgroup_path)?;
-        log::debug!("remove cgroup {:?}", full_path);
-        fs::remove_dir_all(full_path)?;
+        common::remove_cgroup(&self.root_path, &full_path)
+    }
+
+    fn freeze(&self, state: FreezerState) -> Result<()> {
+        let full_path = self.root_path.join_absolute_path(&self.cgroup_path)?;
+        let freeze = match state {
+            FreezerState::Frozen => "1",
+            FreezerState::Thawed => "0",
+        };
+        common::write_cgroup_file_str(full_path.join(CGROUP_FREEZE), freeze)?;
 
         Ok(())
     }
+
+    fn stats(&self) -> Result<Stats> {
+        let full_path = self.root_path.join_absolute_path(&self.cgroup_path)?;
+        let mut stats = Stats::default();
+        // the interface files of a controller only exist if it is available
+        for controller in Self::get_available_controllers(&self.root_path)? {
+            match controller {
+                ControllerType::Cpu => stats.cpu = Cpu::stats(&full_path)?,
+                ControllerType::Memory => stats.memory = Memory::stats(&full_path)?,
+                ControllerType::Pids => stats.pids = Pids::stats(&full_path)?,
+                _ => continue,
+            }
+        }
+
+        Ok(stats)
+    }
+
+    fn get_all_pids(&self) -> Result<Vec<Pid>> {
+        let full_path = self.root_path.join_absolute_path(&self.cgroup_path)?;
+        common::get_procs(&full_path)
+    }
+}
+
+/// Changes the owner of a cgroup directory and of the interface files, which are needed
+/// to manage the cgroups below it. An unprivileged user can only hand a cgroup over to the
+/// ids of its own, so a cgroup, which can not be handed over, stays with the user of youki
+fn chown_cgroup(cgroup_path: &Path, (uid, gid): (u32, u32)) -> Result<()> {
+    if (uid, gid) == (unistd::geteuid().as_raw(), unistd::getegid().as_raw()) {
+        return Ok(());
+    }
+
+    let chown =
+        |path: &Path| unistd::chown(path, Some(Uid::from_raw(uid)), Some(Gid::from_raw(gid)));
+    let context = |path: &Path| {
+        format!(
+            "failed to change the owner of {:?} to {}:{}",
+            path, uid, gid
+        )
+    };
+    match chown(cgroup_path) {
+        Ok(()) => {}
+        Err(nix::Error::Sys(Errno::EPERM)) => {
+            log::warn!(
+                "cgroup {:?} can not be handed over to {}:{}, the container can not manage the cgroups below it",
+                cgroup_path,
+                uid,
+                gid
+            );
+            return Ok(());
+        }
+        Err(e) => return Err(e).with_context(|| context(cgroup_path)),
+    }
+    for file in DELEGATED_FILES {
+        let path = cgroup_path.join(file);
+        if path.exists() {
+            chown(&path).with_context(|| context(&path))?;
+        }
+    }
+    Ok(())
+}
+
+/// Filters the controllers of youki to the ones, which are available in a delegated cgroup.
+/// The resources of the other controllers can not be restricted by the user
+fn delegated_controllers(available: &[ControllerType]) -> Vec<ControllerType> {
+    CONTROLLER_TYPES
+        .iter()
+        .copied()
+        .filter(|controller| {
+            let delegated = available.contains(controller);
+            if !delegated {
+                log::debug!(
+                    "controller {} is not delegated, its resources are not restricted",
+                    controller.to_string()
+                );
+            }
+            delegated
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgroups::test::{create_temp_dir, set_fixture};
+    use std::os::unix::fs::MetadataExt;
+
+    const ALL_CONTROLLERS: &str = "cpuset cpu io memory hugetlb pids";
+
+    fn set_cgroup(path: &Path, controllers: &str, subtree_control: &str) {
+        fs::create_dir_all(path).expect("create cgroup directory");
+        set_fixture(path, CGROUP_CONTROLLERS, controllers).expect("set cgroup.controllers");
+        set_fixture(path, CGROUP_SUBTREE_CONTROL, subtree_control)
+            .expect("set cgroup.subtree_control");
+        set_fixture(path, CGROUP_PROCS, "").expect("set cgroup.procs");
+    }
+
+    fn subtree_control(path: &Path) -> String {
+        fs::read_to_string(path.join(CGROUP_SUBTREE_CONTROL)).expect("read cgroup.subtree_control")
+    }
+
+    #[test]
+    fn test_enable_controllers_of_ancestors() {
+        let tmp = create_temp_dir("test_enable_controllers_of_ancestors")
+            .expect("create temp directory for test");
+        set_cgroup(&tmp, ALL_CONTROLLERS, "");
+        set_cgroup(&tmp.join("youki"), "cpu io memory pids", "");
+        let manager = Manager::new(tmp.to_path_buf(), PathBuf::from("/youki/container"))
+            .expect("create manager");
+
+        let path = manager
+            .create_unified_cgroup(&manager.cgroup_path)
+            .expect("create cgroup");
+
+        assert_eq!(path, tmp.join("youki/container"));
+        assert_eq!(
+            subtree_control(&tmp),
+            "+cpuset +cpu +io +memory +hugetlb +pids"
+        );
+        assert_eq!(
+            subtree_control(&tmp.join("youki")),
+            "+cpu +io +memory +pids"
+        );
+        // the controllers must not be enabled in the leaf, which the process is attached to
+        assert!(!path.join(CGROUP_SUBTREE_CONTROL).exists());
+    }
+
+    #[test]
+    fn test_enable_only_disabled_controllers() {
+        let tmp = create_temp_dir("test_enable_only_disabled_controllers")
+            .expect("create temp directory for test");
+        set_cgroup(&tmp, "cpu memory pids", "cpu memory pids");
+        set_cgroup(&tmp.join("youki"), "cpu memory pids", "memory");
+        // processes in a cgroup do not matter, if all of its controllers are enabled already
+        set_cgroup(&tmp.join("youki/pod"), "cpu memory pids", "cpu memory pids");
+        set_fixture(&tmp.join("youki/pod"), CGROUP_PROCS, "1234\n").expect("set cgroup.procs");
+        let manager = Manager::new(tmp.to_path_buf(), PathBuf::from("/youki/pod/container"))
+            .expect("create manager");
+
+        manager
+            .create_unified_cgroup(&manager.cgroup_path)
+            .expect("create cgroup");
+
+        assert_eq!(subtree_control(&tmp), "cpu memory pids");
+        assert_eq!(subtree_control(&tmp.join("youki")), "+cpu +pids");
+        assert_eq!(subtree_control(&tmp.join("youki/pod")), "cpu memory pids");
+    }
+
+    #[test]
+    fn test_enable_controllers_with_internal_processes() {
+        let tmp = create_temp_dir("test_enable_controllers_with_internal_processes")
+            .expect("create temp directory for test");
+        set_cgroup(&tmp, ALL_CONTROLLERS, "");
+        set_cgroup(&tmp.join("youki"), "cpu memory", "");
+        set_fixture(&tmp.join("youki"), CGROUP_PROCS, "1234\n").expect("set cgroup.procs");
+        let manager = Manager::new(tmp.to_path_buf(), PathBuf::from("/youki/container"))
+            .expect("create manager");
+
+        assert!(manager.create_unified_cgroup(&manager.cgroup_path).is_err());
+        assert_eq!(subtree_control(&tmp.join("youki")), "");
+    }
+
+    #[test]
+    fn test_update_without_devices() {
+        let tmp =
+            create_temp_dir("test_update_without_devices").expect("create temp directory for test");
+        set_cgroup(&tmp.join("container"), ALL_CONTROLLERS, "");
+        let manager =
+            Manager::new(tmp.to_path_buf(), PathBuf::from("/container")).expect("create manager");
+
+        // the device program can not be attached to the fixture, so any attempt to replace it fails
+        manager
+            .update(&LinuxResources::default())
+            .expect("update without devices");
+        assert!(manager.apply(&LinuxResources::default()).is_err());
+        let resources = LinuxResources {
+            devices: vec![oci_spec::LinuxDeviceCgroup {
+                allow: true,
+                typ: oci_spec::LinuxDeviceType::C,
+                major: Some(10),
+                minor: Some(200),
+                access: "rwm".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(manager.update(&resources).is_err());
+    }
+
+    #[test]
+    fn test_chown_cgroup() {
+        let tmp = create_temp_dir("test_chown_cgroup").expect("create temp directory for test");
+        set_cgroup(&tmp, "memory pids", "");
+        let euid = unistd::geteuid().as_raw();
+        // the root user of a rootless container is usually mapped to a subordinate id of the user
+        let owner = (euid + 100000, unistd::getegid().as_raw() + 100000);
+
+        // cgroup.threads does not exist in the fixture and is skipped.
+        // Without the privileges to hand the cgroup over, it stays with the user
+        chown_cgroup(&tmp, owner).expect("change owner of cgroup");
+        let expected = if euid == 0 {
+            owner
+        } else {
+            (euid, unistd::getegid().as_raw())
+        };
+        for path in &[tmp.to_path_buf(), tmp.join(CGROUP_PROCS)] {
+            let metadata = fs::metadata(path).expect("read metadata");
+            assert_eq!((metadata.uid(), metadata.gid()), expected);
+        }
+
+        assert!(chown_cgroup(&tmp.join("missing"), owner).is_err());
+    }
+
+    #[test]
+    fn test_delegated_controllers() {
+        assert_eq!(
+            delegated_controllers(&[ControllerType::Pids, ControllerType::Memory]),
+            vec![ControllerType::Memory, ControllerType::Pids]
+        );
+        assert!(delegated_controllers(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_rootless_controllers() {
+        let tmp =
+            create_temp_dir("test_rootless_controllers").expect("create temp directory for test");
+        set_cgroup(&tmp.join("container"), "memory pids", "");
+
+        let rootless = Manager::new_rootless(tmp.to_path_buf(), PathBuf::from("/container"), None)
+            .expect("create manager");
+        assert_eq!(
+            rootless
+                .controllers(&tmp.join("container"))
+                .expect("get controllers"),
+            vec![ControllerType::Memory, ControllerType::Pids]
+        );
+
+        let manager =
+            Manager::new(tmp.to_path_buf(), PathBuf::from("/container")).expect("create manager");
+        assert_eq!(
+            manager
+                .controllers(&tmp.join("container"))
+                .expect("get controllers"),
+            CONTROLLER_TYPES.to_vec()
+        );
+    }
 }
@@ -1,10 +1,23 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use std::path::Path;
 
+use crate::cgroups::{
+    common,
+    stats::{self, MemoryStat, MemoryStats, StatsProvider},
+};
 use oci_spec::{LinuxMemory, LinuxResources};
 
 use super::controller::Controller;
 
+const CGROUP_MEMORY_CURRENT: &str = "memory.current";
+const CGROUP_MEMORY_PEAK: &str = "memory.peak";
+const CGROUP_MEMORY_MAX: &str = "memory.max";
+const CGROUP_MEMORY_SWAP_MAX: &str = "memory.swap.max";
+const CGROUP_MEMORY_LOW: &str = "memory.low";
+const UNLIMITED: &str = "max";
+const CGROUP_MEMORY_EVENTS: &str = "memory.events";
+const CGROUP_MEMORY_STAT: &str = "memory.stat";
+
 pub struct Memory {}
 
 impl Controller for Memory {
@@ -17,8 +30,285 @@ impl Controller for Memory {
     }
 }
 
+impl StatsProvider for Memory {
+    type Stats = MemoryStats;
+
+    fn stats(cgroup_path: &Path) -> Result<Self::Stats> {
+        // memory.peak is only available on newer kernels
+        let peak_path = cgroup_path.join(CGROUP_MEMORY_PEAK);
+        let max_usage = if peak_path.exists() {
+            stats::parse_single_value(&peak_path)?
+        } else {
+            0
+        };
+
+        // memory.events counts how often the limits of the cgroup were hit
+        let events = stats::parse_flat_keyed_data(&cgroup_path.join(CGROUP_MEMORY_EVENTS))?;
+        let event = |key: &str| events.get(key).copied().unwrap_or(0);
+
+        Ok(MemoryStats {
+            usage: stats::parse_single_value(&cgroup_path.join(CGROUP_MEMORY_CURRENT))?,
+            max_usage,
+            limit: stats::parse_max_value(&cgroup_path.join(CGROUP_MEMORY_MAX))?
+                .unwrap_or(u64::MAX),
+            // the usage hit memory.max, which is the equivalent of the fail count of cgroup v1
+            fail_count: event("max"),
+            oom: event("oom"),
+            oom_kill: event("oom_kill"),
+            stat: MemoryStat::from_v2(&stats::parse_flat_keyed_data(
+                &cgroup_path.join(CGROUP_MEMORY_STAT),
+            )?),
+        })
+    }
+}
+
 impl Memory {
-    fn apply(_: &Path, _: &LinuxMemory) -> Result<()> {
+    fn apply(path: &Path, memory: &LinuxMemory) -> Result<()> {
+        // like runc, the reservation is mapped to memory.low, below which the memory of the cgroup
+        // is protected from being reclaimed. Mapping it to memory.high would throttle the cgroup
+        // above the reservation instead, so that memory.high is left at the default of the kernel
+        if let Some(swap) = Self::convert_swap_to_cgroup2(memory.limit, memory.swap)? {
+            common::write_cgroup_file_str(path.join(CGROUP_MEMORY_SWAP_MAX), &swap)?;
+        }
+        if let Some(limit) = memory.limit.and_then(Self::convert_limit_to_cgroup2) {
+            common::write_cgroup_file_str(path.join(CGROUP_MEMORY_MAX), &limit)?;
+        }
+        if let Some(low) = memory.reservation.and_then(Self::convert_limit_to_cgroup2) {
+            common::write_cgroup_file_str(path.join(CGROUP_MEMORY_LOW), &low)?;
+        }
+
         Ok(())
     }
+
+    /// Converts a limit of the spec into the value of a v2 interface file, where -1 means unlimited.
+    /// 0 leaves the limit unchanged
+    fn convert_limit_to_cgroup2(limit: i64) -> Option<String> {
+        match limit {
+            0 => None,
+            limit if limit < 0 => Some(UNLIMITED.to_owned()),
+            limit => Some(limit.to_string()),
+        }
+    }
+
+    /// The swap of the spec limits memory and swap together like memory.memsw.limit_in_bytes
+    /// of cgroup v1, while memory.swap.max only limits the swap, so the memory limit is subtracted
+    fn convert_swap_to_cgroup2(limit: Option<i64>, swap: Option<i64>) -> Result<Option<String>> {
+        let swap = match swap {
+            // the swap is left at the default of the kernel, if only the memory is limited
+            None | Some(0) => return Ok(None),
+            Some(swap) if swap < 0 => return Ok(Some(UNLIMITED.to_owned())),
+            Some(swap) => swap,
+        };
+
+        match limit {
+            Some(limit) if limit > 0 => {
+                if swap < limit {
+                    bail!(
+                        "memory and swap limit {} must not be lower than the memory limit {}",
+                        swap,
+                        limit
+                    )
+                }
+                Ok(Some((swap - limit).to_string()))
+            }
+            _ => bail!("the swap can only be limited together with the memory"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgroups::test::{create_temp_dir, set_fixture};
+
+    #[test]
+    fn test_stat_memory() {
+        let tmp = create_temp_dir("test_stat_memory").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_MEMORY_CURRENT, "1024\n").expect("set fixture for memory usage");
+        set_fixture(&tmp, CGROUP_MEMORY_MAX, "max\n").expect("set fixture for memory limit");
+        set_fixture(
+            &tmp,
+            CGROUP_MEMORY_EVENTS,
+            "low 0\nhigh 0\nmax 7\noom 2\noom_kill 1\n",
+        )
+        .expect("set fixture for memory events");
+        // memory.stat of a cgroup v2 on linux 5.10
+        let content = [
+            "anon 5001216",
+            "file 34410496",
+            "kernel_stack 49152",
+            "sock 0",
+            "shmem 135168",
+            "file_mapped 8650752",
+            "file_dirty 270336",
+            "file_writeback 0",
+            "anon_thp 2097152",
+            "inactive_anon 2703360",
+            "active_anon 2297856",
+            "inactive_file 15994880",
+            "active_file 18296832",
+            "unevictable 0",
+            "slab 1048576",
+            "pgfault 43617",
+            "pgmajfault 165",
+            "thp_fault_alloc 1",
+        ]
+        .join("\n");
+        set_fixture(&tmp, CGROUP_MEMORY_STAT, &content).expect("set fixture for memory stat");
+
+        let stats = Memory::stats(&tmp).expect("get memory stats");
+
+        let expected = MemoryStats {
+            usage: 1024,
+            max_usage: 0,
+            limit: u64::MAX,
+            fail_count: 7,
+            oom: 2,
+            oom_kill: 1,
+            stat: MemoryStat {
+                cache: 34410496,
+                rss: 5001216,
+                rss_huge: 2097152,
+                shmem: 135168,
+                mapped_file: 8650752,
+                dirty: 270336,
+                writeback: 0,
+                pgfault: 43617,
+                pgmajfault: 165,
+                active_anon: 2297856,
+                inactive_anon: 2703360,
+                active_file: 18296832,
+                inactive_file: 15994880,
+                unevictable: 0,
+                ..Default::default()
+            },
+        };
+        assert_eq!(stats, expected);
+    }
+
+    fn set_memory_files(tmp: &Path) {
+        for file in &[CGROUP_MEMORY_MAX, CGROUP_MEMORY_SWAP_MAX, CGROUP_MEMORY_LOW] {
+            set_fixture(tmp, file, "0").expect("set fixture for memory");
+        }
+    }
+
+    fn read(tmp: &Path, file: &str) -> String {
+        std::fs::read_to_string(tmp.join(file)).expect("read memory file")
+    }
+
+    #[test]
+    fn test_set_memory() {
+        let tmp = create_temp_dir("test_set_memory_v2").expect("create temp directory for test");
+        set_memory_files(&tmp);
+        let memory = LinuxMemory {
+            limit: Some(1024 * 1024),
+            reservation: Some(512 * 1024),
+            swap: Some(3 * 1024 * 1024),
+            ..Default::default()
+        };
+
+        Memory::apply(&tmp, &memory).expect("apply memory");
+
+        assert_eq!(read(&tmp, CGROUP_MEMORY_MAX), "1048576");
+        assert_eq!(read(&tmp, CGROUP_MEMORY_LOW), "524288");
+        // the memory limit is subtracted from the limit of memory and swap
+        assert_eq!(read(&tmp, CGROUP_MEMORY_SWAP_MAX), "2097152");
+    }
+
+    #[test]
+    fn test_set_memory_reservation() {
+        let tmp = create_temp_dir("test_set_memory_reservation_v2")
+            .expect("create temp directory for test");
+        set_memory_files(&tmp);
+        let memory = LinuxMemory {
+            reservation: Some(256 * 1024),
+            ..Default::default()
+        };
+
+        Memory::apply(&tmp, &memory).expect("apply memory");
+
+        assert_eq!(read(&tmp, CGROUP_MEMORY_LOW), "262144");
+        // the limits are left unchanged
+        assert_eq!(read(&tmp, CGROUP_MEMORY_MAX), "0");
+        assert_eq!(read(&tmp, CGROUP_MEMORY_SWAP_MAX), "0");
+
+        let tmp = create_temp_dir("test_set_unlimited_memory_reservation_v2")
+            .expect("create temp directory for test");
+        set_memory_files(&tmp);
+        let memory = LinuxMemory {
+            reservation: Some(-1),
+            ..Default::default()
+        };
+
+        Memory::apply(&tmp, &memory).expect("apply memory");
+
+        assert_eq!(read(&tmp, CGROUP_MEMORY_LOW), "max");
+    }
+
+    #[test]
+    fn test_set_unlimited_memory() {
+        let tmp = create_temp_dir("test_set_unlimited_memory_v2")
+            .expect("create temp directory for test");
+        set_memory_files(&tmp);
+        let memory = LinuxMemory {
+            limit: Some(-1),
+            swap: Some(-1),
+            ..Default::default()
+        };
+
+        Memory::apply(&tmp, &memory).expect("apply memory");
+
+        assert_eq!(read(&tmp, CGROUP_MEMORY_MAX), "max");
+        assert_eq!(read(&tmp, CGROUP_MEMORY_SWAP_MAX), "max");
+    }
+
+    #[test]
+    fn test_set_memory_without_swap() {
+        let tmp = create_temp_dir("test_set_memory_without_swap_v2")
+            .expect("create temp directory for test");
+        set_memory_files(&tmp);
+        set_fixture(&tmp, CGROUP_MEMORY_SWAP_MAX, "4096").expect("set fixture for swap");
+        let memory = LinuxMemory {
+            limit: Some(1024),
+            ..Default::default()
+        };
+
+        Memory::apply(&tmp, &memory).expect("apply memory");
+
+        assert_eq!(read(&tmp, CGROUP_MEMORY_MAX), "1024");
+        assert_eq!(read(&tmp, CGROUP_MEMORY_SWAP_MAX), "4096");
+    }
+
+    #[test]
+    fn test_convert_limit_to_cgroup2() {
+        assert_eq!(Memory::convert_limit_to_cgroup2(-1), Some("max".to_owned()));
+        assert_eq!(Memory::convert_limit_to_cgroup2(0), None);
+        assert_eq!(
+            Memory::convert_limit_to_cgroup2(4096),
+            Some("4096".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_convert_swap_to_cgroup2() {
+        assert_eq!(
+            Memory::convert_swap_to_cgroup2(Some(1024), Some(3072)).expect("convert swap"),
+            Some("2048".to_owned())
+        );
+        assert_eq!(
+            Memory::convert_swap_to_cgroup2(Some(1024), Some(1024)).expect("convert swap"),
+            Some("0".to_owned())
+        );
+        assert_eq!(
+            Memory::convert_swap_to_cgroup2(Some(1024), None).expect("convert swap"),
+            None
+        );
+        assert_eq!(
+            Memory::convert_swap_to_cgroup2(Some(1024), Some(-1)).expect("convert swap"),
+            Some("max".to_owned())
+        );
+        assert!(Memory::convert_swap_to_cgroup2(Some(2048), Some(1024)).is_err());
+        assert!(Memory::convert_swap_to_cgroup2(None, Some(1024)).is_err());
+        assert!(Memory::convert_swap_to_cgroup2(Some(-1), Some(1024)).is_err());
+    }
 }
@@ -1,7 +1,8 @@
 mod controller;
 mod controller_type;
-mod cpu;
+pub(crate) mod cpu;
 mod cpuset;
+mod devices;
 mod hugetlb;
 mod io;
 pub mod manager;
@@ -1,12 +1,89 @@
 use anyhow::Result;
+use std::path::Path;
 
 use super::controller::Controller;
-use oci_spec::LinuxResources;
+use crate::cgroups::{
+    common,
+    stats::{self, PidsStats, StatsProvider},
+};
+use oci_spec::{LinuxPids, LinuxResources};
+
+const CGROUP_PIDS_CURRENT: &str = "pids.current";
+const CGROUP_PIDS_MAX: &str = "pids.max";
 
 pub struct Pids {}
 
 impl Controller for Pids {
-    fn apply(_: &LinuxResources, _: &std::path::Path) -> Result<()> {
+    fn apply(linux_resources: &LinuxResources, cgroup_path: &Path) -> Result<()> {
+        if let Some(pids) = &linux_resources.pids {
+            Self::apply(cgroup_path, pids)?;
+        }
+
         Ok(())
     }
 }
+
+impl StatsProvider for Pids {
+    type Stats = PidsStats;
+
+    fn stats(cgroup_path: &Path) -> Result<Self::Stats> {
+        Ok(PidsStats {
+            current: stats::parse_single_value(&cgroup_path.join(CGROUP_PIDS_CURRENT))?,
+            limit: stats::parse_max_value(&cgroup_path.join(CGROUP_PIDS_MAX))?,
+        })
+    }
+}
+
+impl Pids {
+    fn apply(cgroup_path: &Path, pids: &LinuxPids) -> Result<()> {
+        // a limit of 0 or below means that the number of tasks is not limited
+        let limit = if pids.limit > 0 {
+            pids.limit.to_string()
+        } else {
+            "max".to_string()
+        };
+
+        common::write_cgroup_file_str(cgroup_path.join(CGROUP_PIDS_MAX), &limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgroups::test::{create_temp_dir, set_fixture, setup};
+
+    #[test]
+    fn test_stat_pids() {
+        let tmp = create_temp_dir("test_stat_pids").expect("create temp directory for test");
+        set_fixture(&tmp, CGROUP_PIDS_CURRENT, "5\n").expect("set fixture for current pids");
+        set_fixture(&tmp, CGROUP_PIDS_MAX, "1024\n").expect("set fixture for max pids");
+
+        let stats = Pids::stats(&tmp).expect("get pids stats");
+
+        assert_eq!(
+            stats,
+            PidsStats {
+                current: 5,
+                limit: Some(1024)
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_pids() {
+        let (tmp, max) = setup("test_set_pids_v2", CGROUP_PIDS_MAX);
+
+        Pids::apply(&tmp, &LinuxPids { limit: 1000 }).expect("apply pids");
+
+        assert_eq!(std::fs::read_to_string(max).expect("read pids.max"), "1000");
+    }
+
+    #[test]
+    fn test_set_unlimited_pids() {
+        let (tmp, max) = setup("test_set_unlimited_pids_v2", CGROUP_PIDS_MAX);
+
+        Pids::apply(&tmp, &LinuxPids { limit: -1 }).expect("apply pids");
+
+        assert_eq!(std::fs::read_to_string(max).expect("read pids.max"), "max");
+    }
+}
@@ -6,6 +6,7 @@ use std::{any::Any, path::Path};
 use anyhow::Result;
 use caps::{errors::CapsError, CapSet, CapsHashSet};
 use nix::{
+    mount::MsFlags,
     sched::CloneFlags,
     unistd::{Gid, Uid},
 };
@@ -19,8 +20,18 @@ pub trait Command {
     fn pivot_rootfs(&self, path: &Path) -> Result<()>;
     fn set_ns(&self, rawfd: i32, nstype: CloneFlags) -> Result<()>;
     fn set_id(&self, uid: Uid, gid: Gid) -> Result<()>;
+    fn set_groups(&self, gids: &[Gid]) -> Result<()>;
     fn unshare(&self, flags: CloneFlags) -> Result<()>;
     fn set_capability(&self, cset: CapSet, value: &CapsHashSet) -> Result<(), CapsError>;
     fn set_hostname(&self, hostname: &str) -> Result<()>;
     fn set_rlimit(&self, rlimit: &LinuxRlimit) -> Result<()>;
+    fn set_no_new_privileges(&self) -> Result<()>;
+    fn mount(
+        &self,
+        source: Option<&Path>,
+        target: &Path,
+        fstype: Option<&str>,
+        flags: MsFlags,
+        data: Option<&str>,
+    ) -> Result<()>;
 }
@@ -13,7 +13,7 @@ use nix::{
     unistd::{Gid, Uid},
 };
 use nix::{
-    mount::{umount2, MntFlags},
+    mount::{umount2, MntFlags, MsFlags},
     unistd,
 };
 use nix::{sched::unshare, sys::stat::Mode};
@@ -22,6 +22,7 @@ use oci_spec::LinuxRlimit;
 
 use super::Command;
 use crate::capabilities;
+use crate::rlimits;
 
 /// Empty structure to implement Command trait for
 #[derive(Clone)]
@@ -86,6 +87,13 @@ impl Command for LinuxCommand {
         Ok(())
     }
 
+    /// Sets the supplementary groups of the process
+    // see https://man7.org/linux/man-pages/man2/setgroups.2.html for more information
+    fn set_groups(&self, gids: &[Gid]) -> Result<()> {
+        unistd::setgroups(gids)?;
+        Ok(())
+    }
+
     /// Disassociate parts of execution context
     // see https://man7.org/linux/man-pages/man2/unshare.2.html for more information
     fn unshare(&self, flags: CloneFlags) -> Result<()> {
@@ -95,7 +103,18 @@ impl Command for LinuxCommand {
 
     /// Set capabilities for container process
     fn set_capability(&self, cset: CapSet, value: &CapsHashSet) -> Result<(), CapsError> {
-        caps::set(None, cset, value)
+        match cset {
+            // caps::set cannot be used for the bounding set, as capabilities can
+            // only be dropped from it. Capabilities which are unknown to the
+            // running kernel can not be dropped and are skipped
+            CapSet::Bounding => {
+                for c in caps::runtime::thread_all_supported().difference(value) {
+                    caps::drop(None, CapSet::Bounding, *c)?;
+                }
+                Ok(())
+            }
+            _ => caps::set(None, cset, value),
+        }
     }
 
     /// Sets hostname for process
@@ -112,10 +131,35 @@ impl Command for LinuxCommand {
             rlim_cur: rlimit.soft,
             rlim_max: rlimit.hard,
         };
-        let res = unsafe { libc::setrlimit(rlimit.typ as u32, rlim) };
+        let res = unsafe { libc::setrlimit(rlimits::resource(rlimit.typ), rlim) };
         if let Err(e) = Errno::result(res).map(drop) {
             bail!("Failed to set {:?}. {:?}", rlimit.typ, e)
         }
         Ok(())
     }
+
+    /// Prevents the process and its children from gaining privileges through execve,
+    /// e.g. by set-user-ID binaries or file capabilities
+    // see https://www.kernel.org/doc/html/latest/userspace-api/no_new_privs.html for more information
+    fn set_no_new_privileges(&self) -> Result<()> {
+        if let Err(e) = prctl::set_no_new_privileges(true) {
+            bail!("Failed to set no new privileges. {:?}", e)
+        }
+        Ok(())
+    }
+    /// Mounts a filesystem, or changes the flags of an existing mount
+    // see https://man7.org/linux/man-pages/man2/mount.2.html for more information
+    fn mount(
+        &self,
+        source: Option<&Path>,
+        target: &Path,
+        fstype: Option<&str>,
+        flags: MsFlags,
+        data: Option<&str>,
+    ) -> Result<()> {
+        if let Err(e) = nix::mount::mount(source, target, fstype, flags, data) {
+            bail!("Failed to mount {:?} to {:?}. {:?}", source, target, e)
+        }
+        Ok(())
+    }
 }
@@ -1,24 +1,60 @@
-use std::{any::Any, cell::RefCell};
+use std::{
+    any::Any,
+    cell::RefCell,
+    path::{Path, PathBuf},
+};
 
 use caps::{errors::CapsError, CapSet, CapsHashSet};
-use nix::sched::CloneFlags;
+use nix::{
+    mount::MsFlags,
+    sched::CloneFlags,
+    unistd::{Gid, Uid},
+};
 use oci_spec::LinuxRlimit;
 
 use super::Command;
 
+/// Operation on the mounts of a process, which are recorded in the order they were performed
+#[derive(Debug, Clone, PartialEq)]
+pub enum MountOperation {
+    Mount {
+        source: Option<PathBuf>,
+        target: PathBuf,
+        fstype: Option<String>,
+        flags: MsFlags,
+        data: Option<String>,
+    },
+    PivotRoot(PathBuf),
+}
+
+/// Operation on the ids of a process, which are recorded in the order they were performed
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdOperation {
+    SetGroups(Vec<Gid>),
+    SetId(Uid, Gid),
+}
+
 #[derive(Clone)]
 pub struct TestHelperCommand {
     set_ns_args: RefCell<Vec<(i32, CloneFlags)>>,
+    id_operations: RefCell<Vec<IdOperation>>,
     unshare_args: RefCell<Vec<CloneFlags>>,
     set_capability_args: RefCell<Vec<(CapSet, CapsHashSet)>>,
+    set_rlimit_args: RefCell<Vec<LinuxRlimit>>,
+    set_no_new_privileges_calls: RefCell<usize>,
+    mount_operations: RefCell<Vec<MountOperation>>,
 }
 
 impl Default for TestHelperCommand {
     fn default() -> Self {
         TestHelperCommand {
             set_ns_args: RefCell::new(vec![]),
+            id_operations: RefCell::new(vec![]),
             unshare_args: RefCell::new(vec![]),
             set_capability_args: RefCell::new(vec![]),
+            set_rlimit_args: RefCell::new(vec![]),
+            set_no_new_privileges_calls: RefCell::new(0),
+            mount_operations: RefCell::new(vec![]),
         }
     }
 }
@@ -28,8 +64,11 @@ impl Command for TestHelperCommand {
         self
     }
 
-    fn pivot_rootfs(&self, _path: &std::path::Path) -> anyhow::Result<()> {
-        unimplemented!()
+    fn pivot_rootfs(&self, path: &Path) -> anyhow::Result<()> {
+        self.mount_operations
+            .borrow_mut()
+            .push(MountOperation::PivotRoot(path.to_path_buf()));
+        Ok(())
     }
 
     fn set_ns(&self, rawfd: i32, nstype: CloneFlags) -> anyhow::Result<()> {
@@ -38,8 +77,18 @@ impl Command for TestHelperCommand {
         Ok(())
     }
 
-    fn set_id(&self, _uid: nix::unistd::Uid, _gid: nix::unistd::Gid) -> anyhow::Result<()> {
-        unimplemented!()
+    fn set_id(&self, uid: Uid, gid: Gid) -> anyhow::Result<()> {
+        self.id_operations
+            .borrow_mut()
+            .push(IdOperation::SetId(uid, gid));
+        Ok(())
+    }
+
+    fn set_groups(&self, gids: &[Gid]) -> anyhow::Result<()> {
+        self.id_operations
+            .borrow_mut()
+            .push(IdOperation::SetGroups(gids.to_vec()));
+        Ok(())
     }
 
     fn unshare(&self, flags: CloneFlags) -> anyhow::Result<()> {
@@ -57,8 +106,34 @@ impl Command for TestHelperCommand {
         todo!()
     }
 
-    fn set_rlimit(&self, _rlimit: &LinuxRlimit) -> anyhow::Result<()> {
-        todo!()
+    fn set_rlimit(&self, rlimit: &LinuxRlimit) -> anyhow::Result<()> {
+        self.set_rlimit_args.borrow_mut().push(rlimit.clone());
+        Ok(())
+    }
+
+    fn set_no_new_privileges(&self) -> anyhow::Result<()> {
+        *self.set_no_new_privileges_calls.borrow_mut() += 1;
+        Ok(())
+    }
+
+    fn mount(
+        &self,
+        source: Option<&Path>,
+        target: &Path,
+        fstype: Option<&str>,
+        flags: MsFlags,
+        data: Option<&str>,
+    ) -> anyhow::Result<()> {
+        self.mount_operations
+            .borrow_mut()
+            .push(MountOperation::Mount {
+                source: source.map(Path::to_path_buf),
+                target: target.to_path_buf(),
+                fstype: fstype.map(str::to_string),
+                flags,
+                data: data.map(str::to_string),
+            });
+        Ok(())
     }
 }
 
@@ -67,6 +142,21 @@ impl TestHelperCommand {
         self.set_ns_args.borrow_mut().clone()
     }
 
+    pub fn get_set_id_args(&self) -> Vec<(Uid, Gid)> {
+        self.id_operations
+            .borrow()
+            .iter()
+            .filter_map(|op| match op {
+                IdOperation::SetId(uid, gid) => Some((*uid, *gid)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn get_id_operations(&self) -> Vec<IdOperation> {
+        self.id_operations.borrow().clone()
+    }
+
     pub fn get_unshare_args(&self) -> Vec<CloneFlags> {
         self.unshare_args.borrow_mut().clone()
     }
@@ -74,4 +164,16 @@ impl TestHelperCommand {
     pub fn get_set_capability_args(&self) -> Vec<(CapSet, CapsHashSet)> {
         self.set_capability_args.borrow_mut().clone()
     }
+
+    pub fn get_set_rlimit_args(&self) -> Vec<LinuxRlimit> {
+        self.set_rlimit_args.borrow().clone()
+    }
+
+    pub fn get_set_no_new_privileges_calls(&self) -> usize {
+        *self.set_no_new_privileges_calls.borrow()
+    }
+
+    pub fn get_mount_operations(&self) -> Vec<MountOperation> {
+        self.mount_operations.borrow().clone()
+    }
 }
@@ -0,0 +1,381 @@
+//! Removes a container and the resources which were created for it, similar to `runc delete`
+
+use std::{
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::Clap;
+use nix::errno::Errno;
+use nix::sys::signal::{self, Signal};
+use oci_spec::LinuxIntelRdt;
+
+use crate::cgroups;
+use crate::cgroups::common::{CgroupManager, FreezerState, ManagerOptions};
+use crate::container::{Container, ContainerStatus};
+use crate::hooks;
+use crate::rdt;
+use crate::utils;
+
+/// Time to wait for the init process to exit after it has been killed with SIGKILL
+const WAIT_FOR_EXIT: Duration = Duration::from_secs(10);
+/// Time to wait between two checks whether the init process has exited
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Clap, Debug)]
+pub struct Delete {
+    /// Kill the container if it is still running and delete it afterwards
+    #[clap(short, long)]
+    pub force: bool,
+    /// Seconds to wait for a running container to exit after SIGTERM, before it is killed with SIGKILL.
+    /// With 0 it is killed right away
+    #[clap(short, long, default_value = "10")]
+    pub timeout: u64,
+    pub container_id: String,
+}
+
+/// What has to be done to delete a container in its current status
+#[derive(Debug, PartialEq, Eq)]
+enum DeleteAction {
+    // the container has already stopped
+    Remove,
+    // the container process has to be killed before the container can be removed
+    KillAndRemove,
+}
+
+impl Delete {
+    pub fn exec(&self, root_path: PathBuf) -> Result<()> {
+        log::debug!("start deleting {}", self.container_id);
+        // state of container is stored in a directory named as container id inside
+        // root directory given in commandline options
+        let container_root = utils::get_container_dir(&root_path, &self.container_id)?;
+        if !container_root.exists() {
+            bail!("{} doesn't exist.", self.container_id)
+        }
+        // load container state from json file, and check status of the container
+        // it might be possible that delete is invoked on a running container.
+        log::debug!("load the container from {:?}", container_root);
+        let container = Container::load(container_root)?.refresh_status()?;
+        let action = delete_action(container.status(), self.force)?;
+
+        let spec = container.spec()?;
+        log::debug!("spec: {:?}", spec);
+        let cgroups_path = utils::get_cgroup_path(
+            &spec
+                .linux
+                .as_ref()
+                .context("linux section is missing")?
+                .cgroups_path,
+            container.id(),
+            container.systemd(),
+            container.cgroup_parent(),
+        )?;
+        // check https://man7.org/linux/man-pages/man7/cgroups.7.html
+        // creating and removing cgroups section for more information on cgroups
+        let cmanager = cgroups::common::create_cgroup_manager(
+            cgroups_path,
+            container.systemd(),
+            ManagerOptions::default(),
+        )?;
+
+        if action == DeleteAction::KillAndRemove {
+            // a frozen process does not handle signals, not even SIGKILL
+            if matches!(container.status(), ContainerStatus::Paused) {
+                cmanager.freeze(FreezerState::Thawed)?;
+            }
+            // the init process of a created container has not executed the program of the container yet,
+            // nothing would handle SIGTERM
+            let timeout = if matches!(container.status(), ContainerStatus::Created) {
+                Duration::from_secs(0)
+            } else {
+                Duration::from_secs(self.timeout)
+            };
+            // the resources of a container whose init process is still there are kept, together with
+            // its state, so that the container can still be deleted later on
+            stop(&container, timeout).with_context(|| {
+                format!("failed to stop {}, it has not been deleted", container.id())
+            })?;
+        }
+
+        let intel_rdt = spec.linux.as_ref().and_then(|l| l.intel_rdt.as_ref());
+        remove(&container, cmanager.as_ref(), intel_rdt)?;
+
+        // the container is already gone, so a failing poststop hook is only logged
+        if let Some(hooks) = spec.hooks {
+            if let Err(e) = hooks::run_hooks(&hooks.poststop, &container.state) {
+                log::warn!("{:?}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Removes the resources which were created for the container. The directory storing the container
+/// state is removed last, so that a failed removal can be retried with another delete
+fn remove(
+    container: &Container,
+    cmanager: &dyn CgroupManager,
+    intel_rdt: Option<&LinuxIntelRdt>,
+) -> Result<()> {
+    // remove the cgroup created for the container
+    cmanager.remove()?;
+    if let Some(intel_rdt) = intel_rdt {
+        rdt::remove(intel_rdt, container.id())?;
+    }
+    // remove the directory storing container state
+    log::debug!("remove dir {:?}", container.root);
+    fs::remove_dir_all(&container.root)?;
+    Ok(())
+}
+
+fn delete_action(status: ContainerStatus, force: bool) -> Result<DeleteAction> {
+    if status.can_delete() {
+        return Ok(DeleteAction::Remove);
+    }
+    // the init process of a container which has not been started blocks on the exec fifo
+    // until it is started, so that it is killed instead
+    if matches!(status, ContainerStatus::Created) {
+        return Ok(DeleteAction::KillAndRemove);
+    }
+    if force {
+        return Ok(DeleteAction::KillAndRemove);
+    }
+    bail!(
+        "container could not be deleted because it was {:?}, use --force to kill it first",
+        status
+    )
+}
+
+/// Step of stopping the init process, which is asked to terminate first and killed if it
+/// does not exit within the timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StopStep {
+    // SIGTERM has been sent, the process may still shut down on its own
+    Terminating,
+    // SIGKILL has been sent
+    Killing,
+    Exited,
+    // the process did not exit, not even after SIGKILL
+    TimedOut,
+}
+
+/// Returns the step after checking the process, which has been in the given step for `waited`
+fn next_stop_step(step: StopStep, exited: bool, waited: Duration, timeout: Duration) -> StopStep {
+    match step {
+        _ if exited => StopStep::Exited,
+        StopStep::Terminating if waited >= timeout => StopStep::Killing,
+        StopStep::Killing if waited >= WAIT_FOR_EXIT => StopStep::TimedOut,
+        step => step,
+    }
+}
+
+/// Stops the init process of the container with SIGTERM, escalating to SIGKILL if it does not exit
+/// within the timeout. Returns the last signal, which was sent to the process
+fn stop(container: &Container, timeout: Duration) -> Result<Option<Signal>> {
+    let pid = match container.pid() {
+        Some(pid) => pid,
+        // the init process has not been forked yet
+        None => return Ok(None),
+    };
+    let send = |signal: Signal| -> Result<bool> {
+        log::debug!("kill signal {} to {}", signal, pid);
+        match signal::kill(pid, signal) {
+            Ok(()) => Ok(true),
+            // the process has exited in the meantime
+            Err(nix::Error::Sys(Errno::ESRCH)) => Ok(false),
+            Err(e) => bail!("failed to kill {}: {}", container.id(), e),
+        }
+    };
+
+    let (mut step, signal) = if timeout == Duration::from_secs(0) {
+        (StopStep::Killing, Signal::SIGKILL)
+    } else {
+        (StopStep::Terminating, Signal::SIGTERM)
+    };
+    if !send(signal)? {
+        return Ok(Some(signal));
+    }
+    let mut last_signal = signal;
+    let mut since = Instant::now();
+
+    // the init process is not a child of youki, so it can not be waited for with waitpid
+    loop {
+        let exited = matches!(
+            container.refresh_status()?.status(),
+            ContainerStatus::Stopped
+        );
+        let next = next_stop_step(step, exited, since.elapsed(), timeout);
+        match next {
+            StopStep::Exited => return Ok(Some(last_signal)),
+            StopStep::Killing if step == StopStep::Terminating => {
+                log::warn!(
+                    "{} did not exit within {:?} after SIGTERM, kill it",
+                    container.id(),
+                    timeout
+                );
+                last_signal = Signal::SIGKILL;
+                if !send(Signal::SIGKILL)? {
+                    return Ok(Some(last_signal));
+                }
+                since = Instant::now();
+            }
+            StopStep::TimedOut => bail!(
+                "{} did not exit within {} seconds after it was killed",
+                container.id(),
+                WAIT_FOR_EXIT.as_secs()
+            ),
+            _ => {}
+        }
+        step = next;
+        thread::sleep(EXIT_POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delete_action_without_force() {
+        use ContainerStatus::*;
+
+        assert_eq!(delete_action(Stopped, false).unwrap(), DeleteAction::Remove);
+        assert_eq!(
+            delete_action(Created, false).unwrap(),
+            DeleteAction::KillAndRemove
+        );
+        for status in [Creating, Running, Paused].iter() {
+            assert!(
+                delete_action(*status, false).is_err(),
+                "{:?} container should not be deleted without force",
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn test_delete_action_with_force() {
+        use ContainerStatus::*;
+
+        assert_eq!(delete_action(Stopped, true).unwrap(), DeleteAction::Remove);
+        for status in [Creating, Created, Running, Paused].iter() {
+            assert_eq!(
+                delete_action(*status, true).unwrap(),
+                DeleteAction::KillAndRemove
+            );
+        }
+    }
+
+    #[test]
+    fn test_kill_without_init_process() {
+        let tmp = crate::utils::create_temp_dir("test_kill_without_init_process")
+            .expect("create temp directory");
+        let container = Container::new("no_init", ContainerStatus::Creating, None, "/", &tmp)
+            .expect("create container");
+
+        assert_eq!(stop(&container, Duration::from_secs(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_next_stop_step() {
+        use StopStep::*;
+        let timeout = Duration::from_secs(5);
+        let second = Duration::from_secs(1);
+
+        assert_eq!(
+            next_stop_step(Terminating, false, second, timeout),
+            Terminating
+        );
+        assert_eq!(next_stop_step(Terminating, true, second, timeout), Exited);
+        assert_eq!(
+            next_stop_step(Terminating, false, timeout, timeout),
+            Killing
+        );
+        assert_eq!(next_stop_step(Killing, false, timeout, timeout), Killing);
+        assert_eq!(next_stop_step(Killing, true, timeout, timeout), Exited);
+        assert_eq!(
+            next_stop_step(Killing, false, WAIT_FOR_EXIT, timeout),
+            TimedOut
+        );
+    }
+
+    /// Cgroup manager of a cgroup which can not be removed
+    struct BusyCgroup;
+
+    impl CgroupManager for BusyCgroup {
+        fn add_task(&self, _: nix::unistd::Pid) -> Result<()> {
+            unimplemented!()
+        }
+        fn apply(&self, _: &oci_spec::LinuxResources) -> Result<()> {
+            unimplemented!()
+        }
+        fn remove(&self) -> Result<()> {
+            bail!("failed to remove the cgroup: EBUSY")
+        }
+        fn freeze(&self, _: FreezerState) -> Result<()> {
+            unimplemented!()
+        }
+        fn stats(&self) -> Result<cgroups::stats::Stats> {
+            unimplemented!()
+        }
+        fn get_all_pids(&self) -> Result<Vec<nix::unistd::Pid>> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_remove_keeps_state_of_busy_cgroup() {
+        let tmp = crate::utils::create_temp_dir("test_remove_keeps_state_of_busy_cgroup")
+            .expect("create temp directory");
+        let container = Container::new("busy", ContainerStatus::Stopped, None, "/", &tmp)
+            .expect("create container");
+        container.save().expect("save container");
+
+        assert!(remove(&container, &BusyCgroup, None).is_err());
+        assert!(container.root.exists());
+    }
+
+    fn container_of(name: &str, child: &std::process::Child) -> Container {
+        let tmp = crate::utils::create_temp_dir(name).expect("create temp directory");
+        Container::new(
+            name,
+            ContainerStatus::Running,
+            Some(child.id() as i32),
+            "/",
+            &tmp,
+        )
+        .expect("create container")
+    }
+
+    #[test]
+    fn test_stop_exits_after_sigterm() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .expect("spawn process");
+        let container = container_of("test_stop_exits_after_sigterm", &child);
+
+        let signal = stop(&container, Duration::from_secs(5)).expect("stop process");
+        child.wait().expect("wait for process");
+        assert_eq!(signal, Some(Signal::SIGTERM));
+    }
+
+    #[test]
+    fn test_stop_kills_after_timeout() {
+        // the ignored SIGTERM is inherited by sleep
+        let mut child = std::process::Command::new("sh")
+            .args(&["-c", "trap '' TERM; exec sleep 10"])
+            .spawn()
+            .expect("spawn process");
+        // give the shell the time to ignore SIGTERM
+        thread::sleep(Duration::from_millis(100));
+        let container = container_of("test_stop_kills_after_timeout", &child);
+
+        let signal = stop(&container, Duration::from_millis(200)).expect("stop process");
+        child.wait().expect("wait for process");
+        assert_eq!(signal, Some(Signal::SIGKILL));
+    }
+}
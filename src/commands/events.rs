@@ -0,0 +1,103 @@
+//! Reports the resource usage of a container, similar to `runc events`
+
+use std::{path::PathBuf, thread, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use clap::Clap;
+use serde::Serialize;
+
+use crate::cgroups::{self, common::ManagerOptions, stats::Stats};
+use crate::container::{Container, ContainerStatus};
+use crate::utils;
+
+#[derive(Clap, Debug)]
+pub struct Events {
+    /// Sets the stats collection interval in seconds
+    #[clap(long, default_value = "5")]
+    pub interval: u32,
+    /// Display the container stats only once
+    #[clap(long)]
+    pub stats: bool,
+    pub container_id: String,
+}
+
+/// Event which is emitted for the resource usage of a container
+#[derive(Debug, Serialize)]
+struct StatsEvent<'a> {
+    #[serde(rename = "type")]
+    typ: &'a str,
+    id: &'a str,
+    data: Stats,
+}
+
+impl Events {
+    pub fn exec(&self, root_path: PathBuf) -> Result<()> {
+        let container_root = utils::get_container_dir(&root_path, &self.container_id)?;
+        if !container_root.exists() {
+            bail!("{} doesn't exist.", self.container_id)
+        }
+
+        let container = Container::load(container_root)?.refresh_status()?;
+        if !matches!(container.status(), ContainerStatus::Running) {
+            bail!(
+                "{} is not running, its status is {:?}",
+                container.id(),
+                container.status()
+            )
+        }
+
+        let spec = container.spec()?;
+        let cgroups_path = utils::get_cgroup_path(
+            &spec
+                .linux
+                .as_ref()
+                .context("linux section is missing")?
+                .cgroups_path,
+            container.id(),
+            container.systemd(),
+            container.cgroup_parent(),
+        )?;
+        let cmanager = cgroups::common::create_cgroup_manager(
+            cgroups_path,
+            container.systemd(),
+            ManagerOptions::default(),
+        )?;
+
+        loop {
+            let event = StatsEvent {
+                typ: "stats",
+                id: container.id(),
+                data: cmanager.stats()?,
+            };
+            println!("{}", serde_json::to_string(&event)?);
+
+            if self.stats {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_secs(self.interval as u64));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_stats_event_serialization() {
+        let event = StatsEvent {
+            typ: "stats",
+            id: "test_container",
+            data: Stats::default(),
+        };
+
+        let value = serde_json::to_value(&event).expect("serialize event");
+        assert_eq!(value["type"], json!("stats"));
+        assert_eq!(value["id"], json!("test_container"));
+        assert_eq!(
+            value["data"],
+            serde_json::to_value(Stats::default()).expect("serialize stats")
+        );
+    }
+}
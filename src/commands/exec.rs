@@ -0,0 +1,381 @@
+//! Runs an additional process in the namespaces and cgroup of a container, similar to `runc exec`
+
+use std::{env, fs::File, path::PathBuf, process};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{AppSettings, Clap};
+use nix::sched::CloneFlags;
+use nix::unistd::{self, Pid};
+use oci_spec::{LinuxSeccomp, Process};
+
+use crate::apparmor;
+use crate::capabilities;
+use crate::cgroups::{self, common::ManagerOptions};
+use crate::command::{linux::LinuxCommand, Command};
+use crate::container::Container;
+use crate::create;
+use crate::error::{self, YoukiError};
+use crate::fds;
+use crate::namespaces::{self, Namespaces};
+use crate::oom;
+use crate::process::channel::{self, ChannelMessage};
+use crate::process::fork::clone_child;
+use crate::rlimits;
+use crate::seccomp::{self, NotifySocket};
+use crate::selinux;
+use crate::stdio::FileDescriptor;
+use crate::tty;
+use crate::utils;
+
+#[derive(Clap, Debug, Default)]
+#[clap(setting = AppSettings::TrailingVarArg)]
+pub struct Exec {
+    /// Path to a json file with the process to execute, instead of the command line
+    #[clap(short, long)]
+    pub process: Option<PathBuf>,
+    /// Allocate a pseudoterminal for the process
+    #[clap(short, long)]
+    pub tty: bool,
+    /// Unix socket (file) path, which will receive file descriptor of the writing end of the pseudoterminal
+    #[clap(long)]
+    pub console_socket: Option<String>,
+    /// Current working directory of the process in the container
+    #[clap(long)]
+    pub cwd: Option<String>,
+    /// Additional environment variables of the process, e.g. KEY=value
+    #[clap(short, long, number_of_values = 1)]
+    pub env: Vec<String>,
+    /// File to write the pid of the process to
+    #[clap(long)]
+    pub pid_file: Option<PathBuf>,
+    /// Do not wait for the process to exit
+    #[clap(short, long)]
+    pub detach: bool,
+    /// Pass N additional file descriptors to the process, starting at fd 3
+    #[clap(long, default_value = "0")]
+    pub preserve_fds: u32,
+    /// Unix socket (file) path, which will receive the listener of the seccomp filter, if the profile notifies syscalls
+    #[clap(long)]
+    pub seccomp_notify_socket: Option<PathBuf>,
+    pub container_id: String,
+    /// Command line of the process, used if no process file is given
+    pub command: Vec<String>,
+}
+
+impl Exec {
+    pub fn exec(&self, root_path: PathBuf) -> Result<()> {
+        let container_root = utils::get_container_dir(&root_path, &self.container_id)?;
+        if !container_root.exists() {
+            bail!("{} doesn't exist.", self.container_id)
+        }
+
+        let container = Container::load(container_root)?.refresh_status()?;
+        if !container.can_exec() {
+            bail!(
+                "{} could not execute a process because it was {:?}",
+                container.id(),
+                container.status()
+            )
+        }
+        let init_pid = container
+            .pid()
+            .ok_or_else(|| anyhow!("{} has no init process", container.id()))?;
+
+        let spec = container.spec()?;
+        let process = self.build_process(spec.process.as_ref())?;
+        let linux = spec
+            .linux
+            .as_ref()
+            .ok_or_else(|| anyhow!("no linux section in the spec of {}", container.id()))?;
+        // the process joins all namespaces of the init process, none of them is created
+        let namespaces: Namespaces = namespaces::namespaces_of(init_pid, &linux.namespaces).into();
+        let cgroups_path = utils::get_cgroup_path(
+            &linux.cgroups_path,
+            container.id(),
+            container.systemd(),
+            container.cgroup_parent(),
+        )?;
+        let cmanager = cgroups::common::create_cgroup_manager(
+            cgroups_path,
+            container.systemd(),
+            ManagerOptions::default(),
+        )?;
+
+        tty::validate_console_socket(process.terminal, self.console_socket.as_deref())?;
+        // a relative pid file is resolved before the working directory is changed
+        let pid_file = match &self.pid_file {
+            Some(pid_file) => Some(env::current_dir()?.join(pid_file)),
+            None => None,
+        };
+        let seccomp_notify_socket = match &self.seccomp_notify_socket {
+            Some(socket) => Some(env::current_dir()?.join(socket)),
+            None => None,
+        };
+        // the sockets are connected through links relative to the directory of the container
+        if self.console_socket.is_some() || seccomp_notify_socket.is_some() {
+            unistd::chdir(&container.root)?;
+        }
+        let csocketfd = match &self.console_socket {
+            Some(console_socket) => {
+                Some(tty::load_console_sockets(&container.root, console_socket)?)
+            }
+            None => None,
+        };
+        let notify_socket = match &seccomp_notify_socket {
+            Some(socket) => Some(NotifySocket::connect(
+                &container.root,
+                socket,
+                &self.container_id,
+            )?),
+            None => None,
+        };
+
+        // the intermediate process exits as soon as it has forked the process,
+        // which is then reparented to youki, so that youki can wait for it
+        crate::process::set_subreaper()?;
+        let (mut parent_channel, mut child_channel) = channel::channel()?;
+        match unsafe { unistd::fork()? } {
+            unistd::ForkResult::Child => {
+                drop(parent_channel);
+                // the process must not be traced through /proc by the processes of the container
+                prctl::set_dumpable(false).map_err(|e| anyhow!("failed to set dumpable: {}", e))?;
+                let joined = cmanager
+                    .add_task(unistd::getpid())
+                    .and_then(|_| rlimits::set_rlimits(&process.rlimits, &LinuxCommand))
+                    .and_then(|_| match process.oom_score_adj {
+                        Some(adj) => oom::set_oom_score_adj(adj),
+                        None => Ok(()),
+                    })
+                    .and_then(|_| namespaces.apply_setns());
+                if let Err(e) = joined {
+                    child_channel.send(&ChannelMessage::error(&e))?;
+                    return Err(e);
+                }
+
+                // a process which joins a pid namespace stays in its namespace, only its children
+                // are created in the joined one
+                let mut csocketfd = csocketfd;
+                let child = clone_child(CloneFlags::empty(), || {
+                    match exec_process(
+                        &process,
+                        linux.seccomp.as_ref(),
+                        csocketfd.take(),
+                        notify_socket.as_ref(),
+                        self.preserve_fds,
+                        &LinuxCommand,
+                    ) {
+                        Ok(_) => 0,
+                        Err(e) => {
+                            let _ = child_channel.send(&ChannelMessage::error(&e));
+                            // the process exits like youki, which tells whether its program was not found
+                            eprintln!("Error: {:?}", e);
+                            error::exit_code(&e) as isize
+                        }
+                    }
+                })?;
+                child_channel.send(&ChannelMessage::ProcessPid(child.as_raw()))?;
+                process::exit(0);
+            }
+            unistd::ForkResult::Parent { .. } => {
+                drop(child_channel);
+                let pid = match parent_channel
+                    .recv()
+                    .context("failed to join the container")?
+                {
+                    ChannelMessage::ProcessPid(pid) => Pid::from_raw(pid),
+                    // the error is reported by the child, which joins the namespaces and the cgroup
+                    ChannelMessage::Error { msg, exit_code } => {
+                        return Err(anyhow::Error::from(YoukiError::Process { msg, exit_code })
+                            .context(YoukiError::NamespaceSetup))
+                    }
+                    msg => bail!("received unexpected message {:?}, expected a pid", msg),
+                };
+                log::debug!("pid of the executed process is {}", pid);
+                if let Err(e) = parent_channel.wait_for_close() {
+                    // the exit code of the error is sent along with it, which tells whether the program
+                    // was not found or could not be executed
+                    let exit_code = error::exit_code(&e);
+                    return Err(e.context(YoukiError::Exec {
+                        cmd: process.args[0].clone(),
+                        exit_code,
+                    }));
+                }
+
+                if let Some(pid_file) = &pid_file {
+                    utils::write_pid_file(pid_file, pid.as_raw())?;
+                }
+                if self.detach {
+                    return Ok(());
+                }
+                let code = crate::process::wait_for_exit(pid, crate::process::FORWARDED_SIGNALS)?;
+                process::exit(code);
+            }
+        }
+    }
+
+    /// Returns the process to execute, read from the process file or built from the command line
+    /// and the process of the spec. The flags take precedence over both. A container created
+    /// without a process requires the process file
+    fn build_process(&self, spec_process: Option<&Process>) -> Result<Process> {
+        let mut process = match &self.process {
+            Some(path) => {
+                let file = File::open(path)
+                    .with_context(|| format!("failed to open the process file {:?}", path))?;
+                serde_json::from_reader(file)
+                    .with_context(|| format!("failed to parse the process file {:?}", path))?
+            }
+            None => match spec_process {
+                Some(spec_process) => Process {
+                    args: self.command.clone(),
+                    // a terminal is only allocated if requested for this process
+                    terminal: false,
+                    ..spec_process.clone()
+                },
+                None => bail!(
+                    "{} has no process in its spec, so the process has to be given with --process",
+                    self.container_id
+                ),
+            },
+        };
+
+        if process.args.is_empty() {
+            bail!("no command to execute in {} was given", self.container_id)
+        }
+        process.terminal |= self.tty;
+        process.env.extend(self.env.iter().cloned());
+        if let Some(cwd) = &self.cwd {
+            process.cwd = cwd.clone();
+        }
+        Ok(process)
+    }
+}
+
+/// Sets up the process in the namespaces of the container the same way as the init process
+/// and executes its program
+fn exec_process(
+    process: &Process,
+    seccomp: Option<&LinuxSeccomp>,
+    csocketfd: Option<FileDescriptor>,
+    notify_socket: Option<&NotifySocket>,
+    preserve_fds: u32,
+    command: &impl Command,
+) -> Result<()> {
+    let mut envs = utils::prepare_env(&process.env)?;
+    if let Some(csocketfd) = csocketfd {
+        tty::ready(csocketfd)?;
+    }
+    // joining the mount namespace changes the working directory to the root of the container
+    utils::set_working_dir(&process.cwd)?;
+
+    apparmor::apply_profile(&process.apparmor_profile)?;
+    selinux::set_exec_label(&process.selinux_label)?;
+    let preserved = fds::preserve_fds(preserve_fds)?;
+    fds::set_listen_env(&mut envs, preserved, unistd::getpid());
+    create::set_user(&process.user, create::is_setgroups_allowed(), command)?;
+    capabilities::reset_effective(command)?;
+    fds::close_leaked_fds(preserved)?;
+    fds::pass_preserved_fds(preserved)?;
+    // without no_new_privileges, installing the filter requires the capabilities which are dropped
+    if !process.no_new_privileges {
+        if let Some(seccomp) = seccomp {
+            seccomp::initialize_seccomp(seccomp, notify_socket)?;
+        }
+    }
+    if let Some(caps) = &process.capabilities {
+        capabilities::drop_privileges(caps, command)?;
+    }
+    if process.no_new_privileges {
+        command.set_no_new_privileges()?;
+        if let Some(seccomp) = seccomp {
+            seccomp::initialize_seccomp(seccomp, notify_socket)?;
+        }
+    }
+
+    utils::do_exec(&process.args[0], &process.args, &envs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::spec::default_spec;
+    use crate::utils::create_temp_dir;
+    use std::fs;
+
+    #[test]
+    fn test_build_process_from_command_line() {
+        let spec = default_spec();
+        let exec = Exec {
+            container_id: "exec_test".to_string(),
+            command: vec!["ls".to_string(), "-l".to_string()],
+            env: vec!["EXEC=youki".to_string()],
+            cwd: Some("/tmp".to_string()),
+            ..Default::default()
+        };
+
+        let process = exec
+            .build_process(spec.process.as_ref())
+            .expect("build process");
+
+        assert_eq!(process.args, vec!["ls", "-l"]);
+        assert_eq!(process.cwd, "/tmp");
+        assert!(!process.terminal, "the terminal of the spec is not used");
+        assert!(process.env.contains(&"TERM=xterm".to_string()));
+        assert_eq!(process.env.last().map(String::as_str), Some("EXEC=youki"));
+        assert_eq!(process.user.uid, spec.process.unwrap().user.uid);
+    }
+
+    #[test]
+    fn test_build_process_from_file() {
+        let tmp = create_temp_dir("test_build_process_from_file").expect("create temp directory");
+        let path = tmp.join("process.json");
+        fs::write(
+            &path,
+            r#"{"user": {"uid": 1000, "gid": 1000}, "args": ["sh"], "cwd": "/home"}"#,
+        )
+        .expect("write process file");
+        let exec = Exec {
+            container_id: "exec_test".to_string(),
+            process: Some(path),
+            tty: true,
+            // the command line is ignored if a process file is given
+            command: vec!["ls".to_string()],
+            ..Default::default()
+        };
+
+        let process = exec
+            .build_process(default_spec().process.as_ref())
+            .expect("build process");
+
+        assert_eq!(process.args, vec!["sh"]);
+        assert_eq!(process.cwd, "/home");
+        assert_eq!(process.user.uid, 1000);
+        assert!(process.terminal);
+        assert!(process.env.is_empty());
+    }
+
+    #[test]
+    fn test_build_process_without_command() {
+        let exec = Exec {
+            container_id: "exec_test".to_string(),
+            ..Default::default()
+        };
+        assert!(exec.build_process(default_spec().process.as_ref()).is_err());
+
+        let exec = Exec {
+            container_id: "exec_test".to_string(),
+            process: Some(PathBuf::from("/nonexistent/process.json")),
+            ..Default::default()
+        };
+        assert!(exec.build_process(default_spec().process.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_build_process_without_spec_process() {
+        let exec = Exec {
+            container_id: "exec_test".to_string(),
+            command: vec!["ls".to_string()],
+            ..Default::default()
+        };
+        assert!(exec.build_process(None).is_err());
+    }
+}
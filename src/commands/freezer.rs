@@ -0,0 +1,50 @@
+//! Freezer of the cgroup of a container, shared by the `pause` and `resume` subcommands
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::cgroups;
+use crate::cgroups::common::{FreezerState, ManagerOptions};
+use crate::container::{Container, ContainerStatus};
+use crate::utils;
+
+/// Sets the freezer of the cgroup of the container to the given state and updates the container to
+/// the given status. The container is only changed if `check` accepts its current status
+pub fn set_freezer_state(
+    container_id: &str,
+    root_path: &Path,
+    check: fn(ContainerStatus) -> Result<()>,
+    state: FreezerState,
+    status: ContainerStatus,
+) -> Result<()> {
+    let container_root = utils::get_container_dir(root_path, container_id)?;
+    if !container_root.exists() {
+        bail!("{} doesn't exist.", container_id)
+    }
+
+    let container = Container::load(container_root)?.refresh_status()?;
+    check(container.status())?;
+
+    let spec = container.spec()?;
+    let linux = spec
+        .linux
+        .as_ref()
+        .ok_or_else(|| anyhow!("no linux section in the spec of {}", container.id()))?;
+    let cgroups_path = utils::get_cgroup_path(
+        &linux.cgroups_path,
+        container.id(),
+        container.systemd(),
+        container.cgroup_parent(),
+    )?;
+    let cmanager = cgroups::common::create_cgroup_manager(
+        cgroups_path,
+        container.systemd(),
+        ManagerOptions::default(),
+    )?;
+
+    log::debug!("set the freezer of {} to {:?}", container.id(), state);
+    cmanager.freeze(state)?;
+    container.update_status(status)?.save()?;
+    Ok(())
+}
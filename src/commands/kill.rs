@@ -0,0 +1,51 @@
+//! Sends a signal to the init process of a container, similar to `runc kill`
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+use nix::sys::signal as nix_signal;
+
+use crate::container::Container;
+use crate::signal;
+use crate::utils;
+
+#[derive(Clap, Debug)]
+pub struct Kill {
+    pub container_id: String,
+    /// Signal to send, either a name like SIGTERM or TERM, or a number like 15
+    pub signal: String,
+}
+
+impl Kill {
+    pub fn exec(&self, root_path: PathBuf) -> Result<()> {
+        // state of container is stored in a directory named as container id inside
+        // root directory given in commandline options
+        let container_root = utils::get_container_dir(&root_path, &self.container_id)?;
+        if !container_root.exists() {
+            bail!("{} doesn't exist.", self.container_id)
+        }
+
+        // load container state from json file, and check status of the container
+        // it might be possible that kill is invoked on a already stopped container etc.
+        let container = Container::load(container_root)?.refresh_status()?;
+        if !container.can_kill() {
+            bail!(
+                "{} could not be killed because it was {:?}",
+                container.id(),
+                container.status()
+            )
+        }
+
+        let sig = signal::from_str(&self.signal)?;
+        let pid = match container.pid() {
+            Some(pid) => pid,
+            None => bail!("{} has no init process to kill", container.id()),
+        };
+        log::debug!("kill signal {} to {}", sig, pid);
+        nix_signal::kill(pid, sig)?;
+        // not every signal terminates the container, so the status is taken from its init process
+        container.refresh_status()?.save()?;
+        Ok(())
+    }
+}
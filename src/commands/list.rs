@@ -0,0 +1,226 @@
+//! Lists the containers which have been created by youki, similar to `runc list`
+
+use std::{fs, path::PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::Clap;
+use nix::errno::Errno;
+use nix::sys::signal;
+use nix::unistd::Pid;
+
+use crate::container::{Container, ContainerStatus};
+
+const HEADER: [&str; 5] = ["ID", "PID", "STATUS", "BUNDLE", "CREATED"];
+
+#[derive(Clap, Debug)]
+pub struct List {}
+
+/// Row of the table of containers
+#[derive(Debug)]
+struct ContainerRow {
+    id: String,
+    pid: i32,
+    status: ContainerStatus,
+    bundle: String,
+    created: Option<DateTime<Utc>>,
+}
+
+impl List {
+    pub fn exec(&self, root_path: PathBuf) -> Result<()> {
+        let mut rows = Vec::new();
+        for entry in fs::read_dir(&root_path)? {
+            let container_root = entry?.path();
+            if !container_root.is_dir() {
+                continue;
+            }
+            // a single broken container should not prevent listing the others
+            match load_row(container_root.clone()) {
+                Ok(row) => rows.push(row),
+                Err(e) => log::warn!("failed to load container {:?}: {:?}", container_root, e),
+            }
+        }
+        sort_rows(&mut rows);
+
+        print!("{}", format_table(&rows));
+        Ok(())
+    }
+}
+
+fn load_row(container_root: PathBuf) -> Result<ContainerRow> {
+    let container = Container::load(container_root)?.refresh_status()?;
+    let status = status_of(container.status(), is_alive(container.pid()));
+
+    Ok(ContainerRow {
+        id: container.id().to_string(),
+        pid: container.pid().map(Pid::as_raw).unwrap_or(0),
+        status,
+        bundle: container.state.bundle.clone(),
+        created: container.created(),
+    })
+}
+
+/// Probes whether the process exists by sending the null signal to it
+fn is_alive(pid: Option<Pid>) -> bool {
+    match pid {
+        Some(pid) => match signal::kill(pid, None) {
+            Ok(()) => true,
+            // the process exists, but belongs to another user
+            Err(nix::Error::Sys(Errno::EPERM)) => true,
+            Err(_) => false,
+        },
+        None => false,
+    }
+}
+
+// a container which is being created has no process yet, all others are stopped once their process is gone
+fn status_of(status: ContainerStatus, alive: bool) -> ContainerStatus {
+    match status {
+        ContainerStatus::Creating => status,
+        _ if !alive => ContainerStatus::Stopped,
+        _ => status,
+    }
+}
+
+// containers are listed in the order they were created, those without a creation time first
+fn sort_rows(rows: &mut [ContainerRow]) {
+    rows.sort_by(|a, b| a.created.cmp(&b.created).then_with(|| a.id.cmp(&b.id)));
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    time.map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+fn format_table(rows: &[ContainerRow]) -> String {
+    let cells: Vec<[String; 5]> = rows
+        .iter()
+        .map(|row| {
+            [
+                row.id.clone(),
+                row.pid.to_string(),
+                format!("{:?}", row.status).to_lowercase(),
+                row.bundle.clone(),
+                format_time(row.created),
+            ]
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = HEADER.iter().map(|h| h.len()).collect();
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let header: Vec<String> = HEADER.iter().map(|h| h.to_string()).collect();
+    std::iter::once(header.as_slice())
+        .chain(cells.iter().map(|row| row.as_ref()))
+        .map(|row| {
+            let line: Vec<String> = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect();
+            format!("{}\n", line.join("   ").trim_end())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(rfc3339: &str) -> Option<DateTime<Utc>> {
+        Some(
+            DateTime::parse_from_rfc3339(rfc3339)
+                .expect("parse timestamp")
+                .into(),
+        )
+    }
+
+    fn row(id: &str, created: Option<DateTime<Utc>>) -> ContainerRow {
+        ContainerRow {
+            id: id.to_string(),
+            pid: 0,
+            status: ContainerStatus::Stopped,
+            bundle: "/bundle".to_string(),
+            created,
+        }
+    }
+
+    #[test]
+    fn test_is_alive() {
+        assert!(is_alive(Some(nix::unistd::getpid())));
+        assert!(!is_alive(None));
+        // pids are limited to 2^22, so this process can not exist
+        assert!(!is_alive(Some(Pid::from_raw(i32::MAX))));
+    }
+
+    #[test]
+    fn test_status_of() {
+        use ContainerStatus::*;
+
+        assert!(matches!(status_of(Running, true), Running));
+        assert!(matches!(status_of(Running, false), Stopped));
+        assert!(matches!(status_of(Created, false), Stopped));
+        assert!(matches!(status_of(Paused, true), Paused));
+        assert!(matches!(status_of(Creating, false), Creating));
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(
+            format_time(time("2020-09-13T12:26:40.5Z")),
+            "2020-09-13T12:26:40Z"
+        );
+        assert_eq!(format_time(None), "");
+    }
+
+    #[test]
+    fn test_sort_rows() {
+        let mut rows = vec![
+            row("late", time("2021-06-01T10:00:00Z")),
+            row("early", time("2021-05-01T10:00:00Z")),
+            row("unknown", None),
+            row("also_late", time("2021-06-01T10:00:00Z")),
+        ];
+
+        sort_rows(&mut rows);
+
+        let ids: Vec<&str> = rows.iter().map(|row| row.id.as_str()).collect();
+        assert_eq!(ids, vec!["unknown", "early", "also_late", "late"]);
+    }
+
+    #[test]
+    fn test_format_table() {
+        let rows = vec![
+            ContainerRow {
+                id: "web".to_string(),
+                pid: 4242,
+                status: ContainerStatus::Running,
+                bundle: "/bundles/web".to_string(),
+                created: time("2020-09-13T12:26:40Z"),
+            },
+            ContainerRow {
+                id: "database".to_string(),
+                pid: 0,
+                status: ContainerStatus::Stopped,
+                bundle: "/db".to_string(),
+                created: time("2020-09-13T12:30:00Z"),
+            },
+        ];
+
+        assert_eq!(
+            format_table(&rows),
+            "ID         PID    STATUS    BUNDLE         CREATED\n\
+             web        4242   running   /bundles/web   2020-09-13T12:26:40Z\n\
+             database   0      stopped   /db            2020-09-13T12:30:00Z\n"
+        );
+    }
+
+    #[test]
+    fn test_format_empty_table() {
+        assert_eq!(format_table(&[]), "ID   PID   STATUS   BUNDLE   CREATED\n");
+    }
+}
@@ -0,0 +1,15 @@
+//! Subcommands of youki which operate on containers that have already been created
+
+pub mod delete;
+pub mod events;
+pub mod exec;
+mod freezer;
+pub mod kill;
+pub mod list;
+pub mod pause;
+pub mod ps;
+pub mod resume;
+pub mod run;
+pub mod spec;
+pub mod state;
+pub mod update;
@@ -0,0 +1,56 @@
+//! Suspends all processes of a container with the freezer of its cgroup, similar to `runc pause`
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+
+use super::freezer;
+use crate::cgroups::common::FreezerState;
+use crate::container::ContainerStatus;
+
+#[derive(Clap, Debug)]
+pub struct Pause {
+    pub container_id: String,
+}
+
+impl Pause {
+    pub fn exec(&self, root_path: PathBuf) -> Result<()> {
+        freezer::set_freezer_state(
+            &self.container_id,
+            &root_path,
+            check_can_pause,
+            FreezerState::Frozen,
+            ContainerStatus::Paused,
+        )
+    }
+}
+
+fn check_can_pause(status: ContainerStatus) -> Result<()> {
+    if !status.can_pause() {
+        bail!(
+            "container could not be paused because it was {:?}, only a running container can be paused",
+            status
+        )
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_can_pause() {
+        use ContainerStatus::*;
+
+        assert!(check_can_pause(Running).is_ok());
+        for status in [Creating, Created, Paused, Stopped].iter() {
+            assert!(
+                check_can_pause(*status).is_err(),
+                "{:?} container should not be paused",
+                status
+            );
+        }
+    }
+}
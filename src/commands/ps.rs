@@ -0,0 +1,79 @@
+//! Lists the processes which are running in a container
+
+use std::{path::PathBuf, process::Command};
+
+use anyhow::{bail, Context, Result};
+use clap::Clap;
+
+use crate::cgroups::{self, common::ManagerOptions};
+use crate::container::{Container, ContainerStatus};
+use crate::utils;
+
+#[derive(Clap, Debug)]
+pub struct Ps {
+    /// Format of the output, either table or json
+    #[clap(short, long, default_value = "table", possible_values = &["table", "json"])]
+    pub format: String,
+    pub container_id: String,
+    /// Options which are passed to ps for the table format
+    #[clap(last = true)]
+    pub ps_options: Vec<String>,
+}
+
+impl Ps {
+    pub fn exec(&self, root_path: PathBuf) -> Result<()> {
+        let container_root = utils::get_container_dir(&root_path, &self.container_id)?;
+        if !container_root.exists() {
+            bail!("{} doesn't exist.", self.container_id)
+        }
+
+        // a stopped container has no processes left to list
+        let container = Container::load(container_root)?.refresh_status()?;
+        if matches!(container.status(), ContainerStatus::Stopped) {
+            return Ok(());
+        }
+
+        let spec = container.spec()?;
+        let cgroups_path = utils::get_cgroup_path(
+            &spec
+                .linux
+                .as_ref()
+                .context("linux section is missing")?
+                .cgroups_path,
+            container.id(),
+            container.systemd(),
+            container.cgroup_parent(),
+        )?;
+        let cmanager = cgroups::common::create_cgroup_manager(
+            cgroups_path,
+            container.systemd(),
+            ManagerOptions::default(),
+        )?;
+        let pids: Vec<i32> = cmanager
+            .get_all_pids()?
+            .into_iter()
+            .map(|pid| pid.as_raw())
+            .collect();
+
+        match self.format.as_str() {
+            "json" => println!("{}", serde_json::to_string(&pids)?),
+            _ => {
+                if pids.is_empty() {
+                    return Ok(());
+                }
+
+                let pid_list: Vec<String> = pids.iter().map(|pid| pid.to_string()).collect();
+                let status = Command::new("ps")
+                    .args(&self.ps_options)
+                    .arg("-p")
+                    .arg(pid_list.join(","))
+                    .status()?;
+                if !status.success() {
+                    bail!("ps exited with {}", status)
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
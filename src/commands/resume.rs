@@ -0,0 +1,56 @@
+//! Continues the processes of a paused container, similar to `runc resume`
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+
+use super::freezer;
+use crate::cgroups::common::FreezerState;
+use crate::container::ContainerStatus;
+
+#[derive(Clap, Debug)]
+pub struct Resume {
+    pub container_id: String,
+}
+
+impl Resume {
+    pub fn exec(&self, root_path: PathBuf) -> Result<()> {
+        freezer::set_freezer_state(
+            &self.container_id,
+            &root_path,
+            check_can_resume,
+            FreezerState::Thawed,
+            ContainerStatus::Running,
+        )
+    }
+}
+
+fn check_can_resume(status: ContainerStatus) -> Result<()> {
+    if !status.can_resume() {
+        bail!(
+            "container could not be resumed because it was {:?}, only a paused container can be resumed",
+            status
+        )
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_can_resume() {
+        use ContainerStatus::*;
+
+        assert!(check_can_resume(Paused).is_ok());
+        for status in [Creating, Created, Running, Stopped].iter() {
+            assert!(
+                check_can_resume(*status).is_err(),
+                "{:?} container should not be resumed",
+                status
+            );
+        }
+    }
+}
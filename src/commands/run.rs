@@ -0,0 +1,119 @@
+//! Creates and starts a container in one step, similar to `runc run`
+
+use std::path::PathBuf;
+use std::process;
+
+use anyhow::{anyhow, Result};
+use clap::Clap;
+
+use crate::command::Command;
+use crate::commands::delete::Delete;
+use crate::container::Container;
+use crate::create::Create;
+use crate::process::Process;
+use crate::start::Start;
+use crate::utils;
+
+#[derive(Clap, Debug)]
+pub struct Run {
+    /// Return once the container has been started, instead of waiting for it to exit
+    #[clap(short, long)]
+    pub detach: bool,
+    #[clap(flatten)]
+    pub create: Create,
+}
+
+/// What is done after the container has been started
+#[derive(Debug, PartialEq, Eq)]
+enum RunAction {
+    // the container keeps running without youki, like after create and start
+    Return,
+    // youki waits for the init process, deletes the container and exits with its exit code
+    WaitAndDelete,
+}
+
+impl Run {
+    pub fn exec(
+        &self,
+        root_path: PathBuf,
+        systemd_cgroup: bool,
+        strict_cgroups: bool,
+        cgroup_parent: Option<PathBuf>,
+        command: impl Command,
+    ) -> Result<()> {
+        let action = run_action(self.detach);
+        // the init process is reparented to youki once the intermediate process has exited,
+        // so that youki can wait for it
+        if action == RunAction::WaitAndDelete {
+            crate::process::set_subreaper()?;
+        }
+
+        // unlike create, run starts the container, so that the spec has to contain its process
+        match self.create.create(
+            root_path.clone(),
+            systemd_cgroup,
+            strict_cgroups,
+            cgroup_parent,
+            true,
+            self.detach,
+            command,
+        )? {
+            Some(Process::Parent(_)) => (),
+            // a dry run or a child process after the fork
+            _ => return Ok(()),
+        }
+        let container_id = self.create.container_id.clone();
+        Start {
+            container_id: container_id.clone(),
+        }
+        .exec(root_path.clone())?;
+
+        if action == RunAction::Return {
+            process::exit(0);
+        }
+        let container = Container::load(utils::get_container_dir(&root_path, &container_id)?)?;
+        let pid = container
+            .pid()
+            .ok_or_else(|| anyhow!("{} has no init process", container_id))?;
+        let code = crate::process::wait_for_exit(pid, crate::process::FORWARDED_SIGNALS)?;
+        log::debug!("init process of {} exited with {}", container_id, code);
+
+        Delete {
+            force: false,
+            timeout: 0,
+            container_id,
+        }
+        .exec(root_path)?;
+        process::exit(code);
+    }
+}
+
+fn run_action(detach: bool) -> RunAction {
+    if detach {
+        RunAction::Return
+    } else {
+        RunAction::WaitAndDelete
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_action() {
+        assert_eq!(run_action(true), RunAction::Return);
+        assert_eq!(run_action(false), RunAction::WaitAndDelete);
+    }
+
+    #[test]
+    fn test_parse_run() {
+        let run = Run::try_parse_from(&["run", "--detach", "--bundle", "/bundle", "abc"])
+            .expect("parse run");
+        assert!(run.detach);
+        assert_eq!(run.create.container_id, "abc");
+
+        let run = Run::try_parse_from(&["run", "abc"]).expect("parse run");
+        assert!(!run.detach);
+    }
+}
@@ -0,0 +1,401 @@
+//! Generates a default config.json for a new bundle, similar to `runc spec`
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use caps::Capability;
+use clap::Clap;
+use nix::unistd;
+use oci_spec::{
+    Box, Linux, LinuxCapabilities, LinuxCapabilityType, LinuxDeviceCgroup, LinuxDeviceType,
+    LinuxIdMapping, LinuxNamespace, LinuxNamespaceType, LinuxResources, LinuxRlimit,
+    LinuxRlimitType, Mount, Process, Root, Spec, User,
+};
+
+use crate::rootless::{self, SubIdRange};
+
+const CONFIG_FILE: &str = "config.json";
+
+#[derive(Clap, Debug)]
+pub struct SpecArgs {
+    /// Generate a configuration for a container which is run by an unprivileged user
+    #[clap(long)]
+    pub rootless: bool,
+    /// Overwrite an existing config.json
+    #[clap(long)]
+    pub force: bool,
+}
+
+impl SpecArgs {
+    pub fn exec(&self) -> Result<()> {
+        let spec = if self.rootless {
+            let uid = unistd::geteuid();
+            let gid = unistd::getegid();
+            // without a name, the user can only be looked up by its id in /etc/subuid and /etc/subgid
+            let user_name = unistd::User::from_uid(uid)?
+                .map(|user| user.name)
+                .unwrap_or_default();
+            let subuids = rootless::read_subuids(&user_name, uid.as_raw())?;
+            let subgids = rootless::read_subgids(&user_name, gid.as_raw())?;
+            rootless_spec(uid.as_raw(), gid.as_raw(), &subuids, &subgids)
+        } else {
+            default_spec()
+        };
+
+        write_spec(Path::new(CONFIG_FILE), &spec, self.force)
+    }
+}
+
+fn write_spec(path: &Path, spec: &Spec, force: bool) -> Result<()> {
+    if path.exists() && !force {
+        bail!("{:?} already exists, use --force to overwrite it", path)
+    }
+
+    fs::write(path, serde_json::to_string_pretty(spec)?)?;
+    Ok(())
+}
+
+/// Returns the configuration of a container, which runs sh in the rootfs directory of the bundle
+pub fn default_spec() -> Spec {
+    let caps: Vec<LinuxCapabilityType> = [
+        Capability::CAP_AUDIT_WRITE,
+        Capability::CAP_KILL,
+        Capability::CAP_NET_BIND_SERVICE,
+    ]
+    .iter()
+    .map(|cap| LinuxCapabilityType { cap: *cap })
+    .collect();
+
+    Spec {
+        version: "1.0.2-dev".to_string(),
+        platform: None,
+        process: Some(Process {
+            terminal: true,
+            console_size: Box::default(),
+            user: User {
+                uid: 0,
+                gid: 0,
+                additional_gids: vec![],
+                username: String::new(),
+            },
+            args: vec!["sh".to_string()],
+            env: vec![
+                "PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin".to_string(),
+                "TERM=xterm".to_string(),
+            ],
+            cwd: "/".to_string(),
+            no_new_privileges: true,
+            apparmor_profile: String::new(),
+            selinux_label: String::new(),
+            capabilities: Some(LinuxCapabilities {
+                bounding: caps.clone(),
+                effective: caps.clone(),
+                inheritable: caps.clone(),
+                permitted: caps.clone(),
+                ambient: caps,
+            }),
+            rlimits: vec![LinuxRlimit {
+                typ: LinuxRlimitType::RlimitNofile,
+                hard: 1024,
+                soft: 1024,
+            }],
+            oom_score_adj: None,
+        }),
+        root: Root {
+            path: PathBuf::from("rootfs"),
+            readonly: true,
+        },
+        hostname: "youki".to_string(),
+        mounts: vec![
+            mount("/proc", "proc", "proc", &[]),
+            mount(
+                "/dev",
+                "tmpfs",
+                "tmpfs",
+                &["nosuid", "strictatime", "mode=755", "size=65536k"],
+            ),
+            mount(
+                "/dev/pts",
+                "devpts",
+                "devpts",
+                &[
+                    "nosuid",
+                    "noexec",
+                    "newinstance",
+                    "ptmxmode=0666",
+                    "mode=0620",
+                    "gid=5",
+                ],
+            ),
+            mount(
+                "/dev/shm",
+                "tmpfs",
+                "shm",
+                &["nosuid", "noexec", "nodev", "mode=1777", "size=65536k"],
+            ),
+            mount(
+                "/dev/mqueue",
+                "mqueue",
+                "mqueue",
+                &["nosuid", "noexec", "nodev"],
+            ),
+            mount(
+                "/sys",
+                "sysfs",
+                "sysfs",
+                &["nosuid", "noexec", "nodev", "ro"],
+            ),
+            mount(
+                "/sys/fs/cgroup",
+                "cgroup",
+                "cgroup",
+                &["nosuid", "noexec", "nodev", "relatime", "ro"],
+            ),
+        ],
+        annotations: HashMap::new(),
+        linux: Some(Linux {
+            uid_mappings: vec![],
+            gid_mappings: vec![],
+            sysctl: HashMap::new(),
+            resources: Some(LinuxResources {
+                // deny access to all devices, except the default devices
+                devices: vec![LinuxDeviceCgroup {
+                    allow: false,
+                    typ: LinuxDeviceType::A,
+                    major: None,
+                    minor: None,
+                    access: "rwm".to_string(),
+                }],
+                ..Default::default()
+            }),
+            cgroups_path: None,
+            namespaces: [
+                LinuxNamespaceType::Pid,
+                LinuxNamespaceType::Network,
+                LinuxNamespaceType::Ipc,
+                LinuxNamespaceType::Uts,
+                LinuxNamespaceType::Mount,
+            ]
+            .iter()
+            .map(|typ| LinuxNamespace {
+                typ: *typ,
+                path: None,
+            })
+            .collect(),
+            devices: vec![],
+            rootfs_propagation: String::new(),
+            masked_paths: [
+                "/proc/acpi",
+                "/proc/asound",
+                "/proc/kcore",
+                "/proc/keys",
+                "/proc/latency_stats",
+                "/proc/timer_list",
+                "/proc/timer_stats",
+                "/proc/sched_debug",
+                "/sys/firmware",
+                "/proc/scsi",
+            ]
+            .iter()
+            .map(|path| path.to_string())
+            .collect(),
+            readonly_paths: [
+                "/proc/bus",
+                "/proc/fs",
+                "/proc/irq",
+                "/proc/sys",
+                "/proc/sysrq-trigger",
+            ]
+            .iter()
+            .map(|path| path.to_string())
+            .collect(),
+            mount_label: String::new(),
+            seccomp: None,
+            intel_rdt: None,
+        }),
+        hooks: None,
+        unknown_names: Vec::new(),
+    }
+}
+
+/// Returns the default configuration adjusted for a container which is run by the given user.
+/// The user becomes root inside of a new user namespace, the subordinate ids of the user
+/// are mapped to the following ids of the container
+pub fn rootless_spec(uid: u32, gid: u32, subuids: &[SubIdRange], subgids: &[SubIdRange]) -> Spec {
+    let mut spec = default_spec();
+
+    // an unprivileged user can neither mount sysfs nor cgroups, so the ones of the host are bind mounted
+    spec.mounts
+        .retain(|m| m.typ != "cgroup" && m.destination != Path::new("/sys"));
+    spec.mounts.push(mount(
+        "/sys",
+        "none",
+        "/sys",
+        &["rbind", "nosuid", "noexec", "nodev", "ro"],
+    ));
+    // the tty group of the host is not mapped into the user namespace
+    for mount in &mut spec.mounts {
+        mount.options.retain(|o| !o.starts_with("gid="));
+    }
+
+    if let Some(linux) = &mut spec.linux {
+        linux.namespaces.push(LinuxNamespace {
+            typ: LinuxNamespaceType::User,
+            path: None,
+        });
+        linux.uid_mappings = id_mappings(uid, subuids);
+        linux.gid_mappings = id_mappings(gid, subgids);
+        // the device cgroup can not be changed by an unprivileged user
+        linux.resources = None;
+    }
+
+    spec
+}
+
+/// Maps root of the container to the id of the user and the subordinate ids to the ids after it.
+/// Without subordinate ids, an unprivileged user may still map its own id with a single entry
+/// without the help of newuidmap
+fn id_mappings(id: u32, subids: &[SubIdRange]) -> Vec<LinuxIdMapping> {
+    let mut mappings = vec![LinuxIdMapping {
+        host_id: id,
+        container_id: 0,
+        size: 1,
+    }];
+    let mut container_id = 1u32;
+    for range in subids {
+        mappings.push(LinuxIdMapping {
+            host_id: range.start,
+            container_id,
+            size: range.count,
+        });
+        container_id = container_id.saturating_add(range.count);
+    }
+    mappings
+}
+
+fn mount(destination: &str, typ: &str, source: &str, options: &[&str]) -> Mount {
+    Mount {
+        destination: PathBuf::from(destination),
+        typ: typ.to_string(),
+        source: PathBuf::from(source),
+        options: options.iter().map(|o| o.to_string()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_temp_dir;
+
+    fn has_user_namespace(spec: &Spec) -> bool {
+        spec.linux
+            .as_ref()
+            .unwrap()
+            .namespaces
+            .iter()
+            .any(|ns| matches!(ns.typ, LinuxNamespaceType::User))
+    }
+
+    #[test]
+    fn test_default_spec() {
+        let spec = default_spec();
+
+        assert!(!has_user_namespace(&spec));
+        let linux = spec.linux.as_ref().unwrap();
+        assert!(linux.uid_mappings.is_empty());
+        assert!(linux.resources.is_some());
+        assert!(spec.mounts.iter().any(|m| m.typ == "cgroup"));
+    }
+
+    #[test]
+    fn test_rootless_spec() {
+        let spec = rootless_spec(1000, 1001, &[], &[]);
+
+        assert!(has_user_namespace(&spec));
+        let linux = spec.linux.as_ref().unwrap();
+        assert_eq!(linux.uid_mappings.len(), 1);
+        assert_eq!(linux.uid_mappings[0].container_id, 0);
+        assert_eq!(linux.uid_mappings[0].host_id, 1000);
+        assert_eq!(linux.uid_mappings[0].size, 1);
+        assert_eq!(linux.gid_mappings.len(), 1);
+        assert_eq!(linux.gid_mappings[0].host_id, 1001);
+        assert_eq!(linux.gid_mappings[0].size, 1);
+        assert!(linux.resources.is_none());
+        assert!(!spec.mounts.iter().any(|m| m.typ == "cgroup"));
+        assert!(!spec
+            .mounts
+            .iter()
+            .any(|m| m.options.iter().any(|o| o.starts_with("gid="))));
+    }
+
+    #[test]
+    fn test_rootless_spec_with_subordinate_ids() {
+        let subuids = vec![
+            SubIdRange {
+                start: 100000,
+                count: 65536,
+            },
+            SubIdRange {
+                start: 300000,
+                count: 1000,
+            },
+        ];
+        let subgids = vec![SubIdRange {
+            start: 200000,
+            count: 65536,
+        }];
+        let spec = rootless_spec(1000, 1001, &subuids, &subgids);
+
+        let linux = spec.linux.as_ref().unwrap();
+        let uid_mappings: Vec<(u32, u32, u32)> = linux
+            .uid_mappings
+            .iter()
+            .map(|m| (m.container_id, m.host_id, m.size))
+            .collect();
+        assert_eq!(
+            uid_mappings,
+            vec![(0, 1000, 1), (1, 100000, 65536), (65537, 300000, 1000)]
+        );
+        let gid_mappings: Vec<(u32, u32, u32)> = linux
+            .gid_mappings
+            .iter()
+            .map(|m| (m.container_id, m.host_id, m.size))
+            .collect();
+        assert_eq!(gid_mappings, vec![(0, 1001, 1), (1, 200000, 65536)]);
+    }
+
+    #[test]
+    fn test_spec_round_trip() {
+        let spec = rootless_spec(1000, 1000, &[], &[]);
+
+        let json = serde_json::to_string(&spec).expect("serialize spec");
+        let parsed: Spec = serde_json::from_str(&json).expect("deserialize spec");
+
+        let linux = parsed.linux.unwrap();
+        assert_eq!(linux.uid_mappings.len(), 1);
+        assert_eq!(linux.gid_mappings.len(), 1);
+        assert_eq!(
+            parsed.process.unwrap().capabilities.unwrap().bounding.len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_write_spec_refuses_to_overwrite() {
+        let tmp = create_temp_dir("test_write_spec_refuses_to_overwrite")
+            .expect("create temp directory for test");
+        let path = tmp.join(CONFIG_FILE);
+        fs::write(&path, "{}").expect("write existing config");
+
+        assert!(write_spec(&path, &default_spec(), false).is_err());
+        assert_eq!(fs::read_to_string(&path).expect("read config"), "{}");
+
+        write_spec(&path, &default_spec(), true).expect("overwrite config");
+        let content = fs::read_to_string(&path).expect("read config");
+        assert!(content.contains("ociVersion"));
+    }
+}
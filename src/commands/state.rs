@@ -0,0 +1,28 @@
+//! Prints the state of a container as json, as defined by the runtime specification
+//! check https://github.com/opencontainers/runtime-spec/blob/master/runtime.md#state for more information
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+
+use crate::container::Container;
+use crate::utils;
+
+#[derive(Clap, Debug)]
+pub struct State {
+    pub container_id: String,
+}
+
+impl State {
+    pub fn exec(&self, root_path: PathBuf) -> Result<()> {
+        let container_root = utils::get_container_dir(&root_path, &self.container_id)?;
+        if !container_root.exists() {
+            bail!("{} doesn't exist.", self.container_id)
+        }
+
+        let container = Container::load(container_root)?.refresh_status()?;
+        println!("{}", serde_json::to_string_pretty(&container.state)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+//! Changes the resource restrictions of a container which has already been created, similar to `runc update`
+
+use std::{fs::File, io, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Clap;
+use nix::errno::Errno;
+use oci_spec::{LinuxBlockIo, LinuxCpu, LinuxMemory, LinuxPids, LinuxResources};
+
+use crate::cgroups::{self, common::ManagerOptions};
+use crate::container::{Container, ContainerStatus};
+use crate::utils;
+
+#[derive(Clap, Debug, Default)]
+pub struct Update {
+    /// Path to a json file with the resources to update, - reads the resources from stdin
+    #[clap(short, long)]
+    pub resources: Option<PathBuf>,
+    /// Relative cpu weight of the container
+    #[clap(long)]
+    pub cpu_shares: Option<u64>,
+    /// Length of the cpu cfs period in microseconds
+    #[clap(long)]
+    pub cpu_period: Option<u64>,
+    /// Cpu time in microseconds the container may use in a cfs period
+    #[clap(long)]
+    pub cpu_quota: Option<i64>,
+    /// Length of the cpu realtime period in microseconds
+    #[clap(long)]
+    pub cpu_rt_period: Option<u64>,
+    /// Cpu time in microseconds realtime tasks may use in a realtime period
+    #[clap(long)]
+    pub cpu_rt_runtime: Option<i64>,
+    /// Cpus the container may use, e.g. 0-3,6
+    #[clap(long)]
+    pub cpuset_cpus: Option<String>,
+    /// Memory nodes the container may use, e.g. 0,1
+    #[clap(long)]
+    pub cpuset_mems: Option<String>,
+    /// Memory limit in bytes
+    #[clap(long)]
+    pub memory: Option<i64>,
+    /// Soft memory limit in bytes
+    #[clap(long)]
+    pub memory_reservation: Option<i64>,
+    /// Limit of memory and swap in bytes, -1 for unlimited swap
+    #[clap(long)]
+    pub memory_swap: Option<i64>,
+    /// Maximum number of tasks, -1 for no limit
+    #[clap(long)]
+    pub pids_limit: Option<i64>,
+    /// Relative block io weight of the container, between 10 and 1000
+    #[clap(long)]
+    pub blkio_weight: Option<u16>,
+    pub container_id: String,
+}
+
+impl Update {
+    pub fn exec(&self, root_path: PathBuf, strict_cgroups: bool) -> Result<()> {
+        let container_root = utils::get_container_dir(&root_path, &self.container_id)?;
+        if !container_root.exists() {
+            bail!("{} doesn't exist.", self.container_id)
+        }
+
+        let container = Container::load(container_root)?.refresh_status()?;
+        if matches!(
+            container.status(),
+            ContainerStatus::Creating | ContainerStatus::Stopped
+        ) {
+            bail!(
+                "{} could not be updated because it was {:?}",
+                container.id(),
+                container.status()
+            )
+        }
+
+        let resources = match &self.resources {
+            Some(path) if path.as_os_str() == "-" => serde_json::from_reader(io::stdin())?,
+            Some(path) => serde_json::from_reader(File::open(path)?)?,
+            None => LinuxResources::default(),
+        };
+        let resources = self.merge_into(resources);
+        log::debug!("update resources of {} to {:?}", container.id(), resources);
+
+        let spec = container.spec()?;
+        let cgroups_path = utils::get_cgroup_path(
+            &spec
+                .linux
+                .as_ref()
+                .context("linux section is missing")?
+                .cgroups_path,
+            container.id(),
+            container.systemd(),
+            container.cgroup_parent(),
+        )?;
+        let options = ManagerOptions {
+            strict: strict_cgroups,
+            ..Default::default()
+        };
+        let cmanager =
+            cgroups::common::create_cgroup_manager(cgroups_path, container.systemd(), options)?;
+        if let Err(e) = cmanager.update(&resources) {
+            if is_busy(&e) && resources.memory.is_some() {
+                bail!(
+                    "failed to update the memory limit of {}, it is lower than the current memory usage: {:?}",
+                    container.id(),
+                    e
+                )
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Sets the resources given as flags, which take precedence over the resources read from json
+    fn merge_into(&self, mut resources: LinuxResources) -> LinuxResources {
+        if self.cpu_shares.is_some()
+            || self.cpu_period.is_some()
+            || self.cpu_quota.is_some()
+            || self.cpu_rt_period.is_some()
+            || self.cpu_rt_runtime.is_some()
+            || self.cpuset_cpus.is_some()
+            || self.cpuset_mems.is_some()
+        {
+            let cpu = resources.cpu.get_or_insert_with(LinuxCpu::default);
+            set_if_some(&mut cpu.shares, self.cpu_shares);
+            set_if_some(&mut cpu.period, self.cpu_period);
+            set_if_some(&mut cpu.quota, self.cpu_quota);
+            set_if_some(&mut cpu.realtime_period, self.cpu_rt_period);
+            set_if_some(&mut cpu.realtime_runtime, self.cpu_rt_runtime);
+            set_if_some(&mut cpu.cpus, self.cpuset_cpus.clone());
+            set_if_some(&mut cpu.mems, self.cpuset_mems.clone());
+        }
+
+        if self.memory.is_some() || self.memory_reservation.is_some() || self.memory_swap.is_some()
+        {
+            let memory = resources.memory.get_or_insert_with(LinuxMemory::default);
+            set_if_some(&mut memory.limit, self.memory);
+            set_if_some(&mut memory.reservation, self.memory_reservation);
+            set_if_some(&mut memory.swap, self.memory_swap);
+        }
+
+        if let Some(limit) = self.pids_limit {
+            resources.pids = Some(LinuxPids { limit });
+        }
+
+        if let Some(weight) = self.blkio_weight {
+            let block_io = resources.block_io.get_or_insert_with(LinuxBlockIo::default);
+            block_io.blkio_weight = Some(weight);
+        }
+
+        resources
+    }
+}
+
+fn set_if_some<T>(target: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *target = value;
+    }
+}
+
+// the kernel refuses to lower the memory limit below the current usage with EBUSY
+fn is_busy(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .and_then(|e| e.raw_os_error())
+            == Some(Errno::EBUSY as i32)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_flags_without_resources() {
+        let update = Update {
+            cpu_shares: Some(512),
+            memory: Some(1024 * 1024),
+            pids_limit: Some(100),
+            ..Default::default()
+        };
+
+        let resources = update.merge_into(LinuxResources::default());
+
+        let cpu = resources.cpu.expect("cpu resources are set");
+        assert_eq!(cpu.shares, Some(512));
+        assert_eq!(cpu.quota, None);
+        let memory = resources.memory.expect("memory resources are set");
+        assert_eq!(memory.limit, Some(1024 * 1024));
+        assert_eq!(memory.swap, None);
+        assert_eq!(resources.pids.expect("pids resources are set").limit, 100);
+        assert!(resources.block_io.is_none());
+        assert!(resources.devices.is_empty());
+    }
+
+    #[test]
+    fn test_merge_flags_override_resources() {
+        let resources: LinuxResources = serde_json::from_str(
+            r#"{
+                "cpu": {"shares": 1024, "quota": 50000},
+                "blockIO": {"blkioWeight": 100}
+            }"#,
+        )
+        .expect("parse resources");
+        let update = Update {
+            cpu_shares: Some(256),
+            blkio_weight: Some(500),
+            ..Default::default()
+        };
+
+        let resources = update.merge_into(resources);
+
+        let cpu = resources.cpu.expect("cpu resources are set");
+        assert_eq!(cpu.shares, Some(256));
+        assert_eq!(cpu.quota, Some(50000), "values without flags are kept");
+        assert_eq!(
+            resources.block_io.expect("block io is set").blkio_weight,
+            Some(500)
+        );
+        assert!(resources.memory.is_none());
+    }
+
+    #[test]
+    fn test_no_flags_keep_resources() {
+        let update = Update::default();
+
+        let resources = update.merge_into(LinuxResources::default());
+
+        assert!(resources.cpu.is_none());
+        assert!(resources.memory.is_none());
+        assert!(resources.pids.is_none());
+        assert!(resources.block_io.is_none());
+    }
+
+    #[test]
+    fn test_is_busy() {
+        let busy = anyhow::Error::new(io::Error::from_raw_os_error(libc::EBUSY))
+            .context("failed to write 1024 to memory.limit_in_bytes");
+        assert!(is_busy(&busy));
+
+        let invalid = anyhow::Error::new(io::Error::from_raw_os_error(libc::EINVAL));
+        assert!(!is_busy(&invalid));
+    }
+}
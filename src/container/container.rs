@@ -1,11 +1,15 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use nix::unistd::Pid;
+use oci_spec::Spec;
 use procfs::process::Process;
 
 use crate::container::{ContainerStatus, State};
+use crate::error::YoukiError;
 
 /// Structure representing the container data
 #[derive(Debug)]
@@ -39,6 +43,10 @@ impl Container {
     pub fn status(&self) -> ContainerStatus {
         self.state.status
     }
+
+    pub fn created(&self) -> Option<DateTime<Utc>> {
+        self.state.created
+    }
     pub fn refresh_status(&self) -> Result<Self> {
         let new_status = match self.pid() {
             Some(pid) => {
@@ -50,7 +58,9 @@ impl Container {
                     match proc.stat.state().unwrap() {
                         ProcState::Zombie | ProcState::Dead => ContainerStatus::Stopped,
                         _ => match self.status() {
-                            ContainerStatus::Creating | ContainerStatus::Created => self.status(),
+                            ContainerStatus::Creating
+                            | ContainerStatus::Created
+                            | ContainerStatus::Paused => self.status(),
                             _ => ContainerStatus::Running,
                         },
                     }
@@ -65,7 +75,11 @@ impl Container {
 
     pub fn save(&self) -> Result<()> {
         log::debug!("Sava container status: {:?} in {:?}", self, self.root);
-        self.state.save(&self.root)
+        self.state
+            .save(&self.root)
+            .with_context(|| YoukiError::StatePersist {
+                id: self.id().to_string(),
+            })
     }
 
     pub fn can_start(&self) -> bool {
@@ -80,29 +94,83 @@ impl Container {
         self.state.status.can_delete()
     }
 
+    pub fn can_pause(&self) -> bool {
+        self.state.status.can_pause()
+    }
+
+    pub fn can_resume(&self) -> bool {
+        self.state.status.can_resume()
+    }
+
+    pub fn can_exec(&self) -> bool {
+        self.state.status.can_exec()
+    }
+
     pub fn pid(&self) -> Option<Pid> {
         self.state.pid.map(Pid::from_raw)
     }
 
     pub fn set_pid(&self, pid: i32) -> Self {
-        Self::new(
-            self.state.id.as_str(),
-            self.state.status,
-            Some(pid),
-            self.state.bundle.as_str(),
-            &self.root,
-        )
-        .expect("unexpected error")
+        let mut new_state = self.state.clone();
+        new_state.pid = Some(pid);
+        Self {
+            state: new_state,
+            root: self.root.clone(),
+        }
     }
 
     pub fn update_status(&self, status: ContainerStatus) -> Result<Self> {
-        Self::new(
-            self.state.id.as_str(),
-            status,
-            self.state.pid,
-            self.state.bundle.as_str(),
-            &self.root,
-        )
+        let mut new_state = self.state.clone();
+        new_state.status = status;
+        Ok(Self {
+            state: new_state,
+            root: self.root.clone(),
+        })
+    }
+
+    /// Whether the cgroup of the container is managed by systemd
+    pub fn systemd(&self) -> bool {
+        self.state.use_systemd.unwrap_or(false)
+    }
+
+    pub fn set_systemd(&self, should_use: bool) -> Self {
+        let mut new_state = self.state.clone();
+        new_state.use_systemd = Some(should_use);
+        Self {
+            state: new_state,
+            root: self.root.clone(),
+        }
+    }
+
+    /// Parent of the cgroup of the container, which was given when it was created
+    pub fn cgroup_parent(&self) -> Option<&Path> {
+        self.state.cgroup_parent.as_deref()
+    }
+
+    pub fn set_cgroup_parent(&self, cgroup_parent: Option<PathBuf>) -> Self {
+        let mut new_state = self.state.clone();
+        new_state.cgroup_parent = cgroup_parent;
+        Self {
+            state: new_state,
+            root: self.root.clone(),
+        }
+    }
+
+    /// Sets the annotations of the spec, which are passed to the hooks along with the rest of the state
+    pub fn set_annotations(&self, annotations: HashMap<String, String>) -> Self {
+        let mut new_state = self.state.clone();
+        new_state.annotations = annotations;
+        Self {
+            state: new_state,
+            root: self.root.clone(),
+        }
+    }
+
+    /// Loads the specification of the container from its bundle
+    pub fn spec(&self) -> Result<Spec> {
+        let config_path = Path::new(&self.state.bundle).join("config.json");
+        Spec::load(&config_path.to_string_lossy())
+            .with_context(|| YoukiError::SpecParse { path: config_path })
     }
 
     pub fn load(container_root: PathBuf) -> Result<Self> {
@@ -113,3 +181,74 @@ impl Container {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::spec::default_spec;
+    use crate::utils::create_temp_dir;
+
+    #[test]
+    fn test_annotations_are_saved() {
+        let tmp = create_temp_dir("test_annotations_are_saved").expect("create temp directory");
+        let mut spec = default_spec();
+        spec.annotations.insert(
+            "io.kubernetes.cri.sandbox-id".to_string(),
+            "abc".to_string(),
+        );
+
+        Container::new(
+            "annotations",
+            ContainerStatus::Creating,
+            None,
+            "/bundle",
+            &tmp,
+        )
+        .expect("create container")
+        .set_annotations(spec.annotations.clone())
+        .save()
+        .expect("save container");
+
+        let container = Container::load(tmp.to_path_buf()).expect("load container");
+        assert_eq!(container.state.annotations, spec.annotations);
+        // the annotations are kept when the state changes
+        let container = container
+            .update_status(ContainerStatus::Created)
+            .unwrap()
+            .set_pid(1);
+        assert_eq!(container.state.annotations, spec.annotations);
+
+        let content = fs::read_to_string(tmp.join("state.json")).expect("read state file");
+        let json: serde_json::Value = serde_json::from_str(&content).expect("parse state file");
+        assert_eq!(json["annotations"]["io.kubernetes.cri.sandbox-id"], "abc");
+    }
+
+    #[test]
+    fn test_spec_of_bundle() {
+        let tmp = create_temp_dir("test_spec_of_bundle").expect("create temp directory");
+        let bundle = tmp.join("bundle");
+        fs::create_dir_all(bundle.join("rootfs")).expect("create root filesystem");
+        let mut spec = default_spec();
+        spec.root.path = PathBuf::from("rootfs");
+        fs::write(
+            bundle.join("config.json"),
+            serde_json::to_string(&spec).expect("serialize spec"),
+        )
+        .expect("write config.json");
+
+        let container = Container::new(
+            "spec",
+            ContainerStatus::Created,
+            None,
+            bundle.to_str().unwrap(),
+            &tmp,
+        )
+        .expect("create container");
+        // the root filesystem is resolved relative to the bundle, wherever youki runs
+        let spec = container.spec().expect("load spec");
+        assert_eq!(
+            spec.root.path,
+            fs::canonicalize(bundle.join("rootfs")).unwrap()
+        );
+    }
+}
@@ -3,5 +3,7 @@
 #[allow(clippy::module_inception)]
 mod container;
 mod state;
+mod validate;
 pub use container::Container;
 pub use state::{ContainerStatus, State};
+pub use validate::validate_spec;
@@ -1,9 +1,13 @@
 //! Information about status and state of the container
 use std::collections::HashMap;
 use std::fs;
-use std::{fs::File, path::Path};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 const STATE_FILE_PATH: &str = "state.json";
@@ -18,6 +22,8 @@ pub enum ContainerStatus {
     Created,
     // The container process has executed the user-specified program but has not exited
     Running,
+    // The processes of the container are frozen and can be resumed
+    Paused,
     // The container process has exited
     Stopped,
 }
@@ -31,13 +37,26 @@ impl ContainerStatus {
         use ContainerStatus::*;
         match self {
             Creating | Stopped => false,
-            Created | Running => true,
+            // a signal sent to a paused container is delivered once it is resumed
+            Created | Running | Paused => true,
         }
     }
 
     pub fn can_delete(&self) -> bool {
         matches!(self, ContainerStatus::Stopped)
     }
+
+    pub fn can_pause(&self) -> bool {
+        matches!(self, ContainerStatus::Running)
+    }
+
+    pub fn can_resume(&self) -> bool {
+        matches!(self, ContainerStatus::Paused)
+    }
+
+    pub fn can_exec(&self) -> bool {
+        matches!(self, ContainerStatus::Created | ContainerStatus::Running)
+    }
 }
 
 /// Stores the state information of the container
@@ -57,6 +76,16 @@ pub struct State {
     pub bundle: String,
     // Annotations are key values associated with the container.
     pub annotations: HashMap<String, String>,
+    // Whether the cgroup of the container is managed by systemd
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_systemd: Option<bool>,
+    // Parent of the cgroup of the container, if it was given when the container was created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup_parent: Option<PathBuf>,
+    // Time the container was created at, as an RFC 3339 timestamp. Missing in the
+    // state of containers created by earlier versions of youki
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
 }
 
 impl State {
@@ -73,6 +102,9 @@ impl State {
             pid,
             bundle: bundle.to_string(),
             annotations: HashMap::default(),
+            use_systemd: None,
+            cgroup_parent: None,
+            created: Some(Utc::now()),
         }
     }
 
@@ -84,16 +116,165 @@ impl State {
             .append(false)
             .create(true)
             .truncate(true)
-            .open(state_file_path)
-            .expect("Unable to open");
-        serde_json::to_writer(&file, self)?;
+            .open(&state_file_path)
+            .with_context(|| format!("failed to open the state file {:?}", state_file_path))?;
+        serde_json::to_writer(&file, self)
+            .with_context(|| format!("failed to write the state file {:?}", state_file_path))?;
         Ok(())
     }
 
     pub fn load(container_root: &Path) -> Result<Self> {
         let state_file_path = container_root.join(STATE_FILE_PATH);
-        let file = File::open(state_file_path)?;
-        let state: Self = serde_json::from_reader(&file)?;
+        let file = File::open(&state_file_path).with_context(|| {
+            format!(
+                "failed to open the state file {:?}, the container may not exist",
+                state_file_path
+            )
+        })?;
+        let state: Self = serde_json::from_reader(&file).with_context(|| {
+            format!(
+                "failed to parse the state file {:?}, it may be corrupted",
+                state_file_path
+            )
+        })?;
         Ok(state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_temp_dir;
+
+    #[test]
+    fn test_state_round_trip() {
+        let tmp = create_temp_dir("test_state_round_trip").expect("create temp directory");
+        let mut state = State::new("round_trip", ContainerStatus::Running, Some(42), "/bundle");
+        state
+            .annotations
+            .insert("org.youki.test".to_string(), "value".to_string());
+
+        state.save(&tmp).expect("save state");
+        let loaded = State::load(&tmp).expect("load state");
+
+        assert_eq!(loaded.oci_version, state.oci_version);
+        assert_eq!(loaded.id, "round_trip");
+        assert!(matches!(loaded.status, ContainerStatus::Running));
+        assert_eq!(loaded.pid, Some(42));
+        assert_eq!(loaded.bundle, "/bundle");
+        assert_eq!(loaded.annotations, state.annotations);
+        assert_eq!(loaded.created, state.created);
+    }
+
+    #[test]
+    fn test_state_created() {
+        let mut state = State::new("created", ContainerStatus::Created, None, "/bundle");
+        state.created = Some(
+            DateTime::parse_from_rfc3339("2021-06-01T10:20:30.123456789Z")
+                .expect("parse timestamp")
+                .into(),
+        );
+
+        let json = serde_json::to_string(&state).expect("serialize state");
+        assert!(json.contains("\"created\":\"2021-06-01T10:20:30.123456789Z\""));
+        let loaded: State = serde_json::from_str(&json).expect("deserialize state");
+        assert_eq!(loaded.created, state.created);
+
+        // the state of a container created by an earlier version has no timestamp
+        let loaded: State = serde_json::from_str(
+            r#"{"ociVersion": "v1.0.2", "id": "old", "status": "running", "bundle": "/bundle", "annotations": {}}"#,
+        )
+        .expect("deserialize state without timestamp");
+        assert_eq!(loaded.created, None);
+    }
+
+    #[test]
+    fn test_state_json() {
+        let state = State::new("json", ContainerStatus::Created, None, "/bundle");
+
+        let json: serde_json::Value = serde_json::to_value(&state).expect("serialize state");
+
+        assert_eq!(json["ociVersion"], "v1.0.2");
+        assert_eq!(json["id"], "json");
+        assert_eq!(json["status"], "created");
+        assert_eq!(json["bundle"], "/bundle");
+        assert!(
+            json.get("pid").is_none(),
+            "pid is omitted without a process"
+        );
+    }
+
+    #[test]
+    fn test_load_missing_state() {
+        let tmp = create_temp_dir("test_load_missing_state").expect("create temp directory");
+
+        let err = State::load(&tmp).unwrap_err();
+
+        assert!(format!("{:?}", err).contains("failed to open the state file"));
+    }
+
+    #[test]
+    fn test_load_corrupt_state() {
+        let tmp = create_temp_dir("test_load_corrupt_state").expect("create temp directory");
+        fs::write(tmp.join(STATE_FILE_PATH), "{\"id\": ").expect("write corrupt state");
+
+        let err = State::load(&tmp).unwrap_err();
+
+        assert!(format!("{:?}", err).contains("failed to parse the state file"));
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        use ContainerStatus::*;
+        let statuses = [Creating, Created, Running, Paused, Stopped];
+
+        let allowed = |guard: fn(&ContainerStatus) -> bool| -> Vec<bool> {
+            statuses.iter().map(guard).collect()
+        };
+
+        assert_eq!(
+            allowed(ContainerStatus::can_start),
+            vec![false, true, false, false, false]
+        );
+        assert_eq!(
+            allowed(ContainerStatus::can_kill),
+            vec![false, true, true, true, false]
+        );
+        assert_eq!(
+            allowed(ContainerStatus::can_delete),
+            vec![false, false, false, false, true]
+        );
+        assert_eq!(
+            allowed(ContainerStatus::can_pause),
+            vec![false, false, true, false, false]
+        );
+        assert_eq!(
+            allowed(ContainerStatus::can_resume),
+            vec![false, false, false, true, false]
+        );
+        assert_eq!(
+            allowed(ContainerStatus::can_exec),
+            vec![false, true, true, false, false]
+        );
+    }
+
+    #[test]
+    fn test_status_json() {
+        use ContainerStatus::*;
+        let names: Vec<String> = [Creating, Created, Running, Paused, Stopped]
+            .iter()
+            .map(|s| serde_json::to_string(s).expect("serialize status"))
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "\"creating\"",
+                "\"created\"",
+                "\"running\"",
+                "\"paused\"",
+                "\"stopped\""
+            ]
+        );
+    }
+}
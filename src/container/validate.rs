@@ -0,0 +1,285 @@
+//! Validation of the spec of a bundle, before anything is set up for the container.
+//! A malformed spec is rejected with all of its problems at once, instead of the first
+//! operation which fails because of it
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use oci_spec::{LinuxNamespaceType, Process, Spec};
+
+use crate::capabilities;
+use crate::oom;
+use crate::rlimits;
+use crate::sysctl;
+use crate::utils;
+
+/// Checks the spec, returning an error which lists every problem found.
+/// Unknown namespace types, rlimit and capability names are left out when the spec is loaded
+/// and reported here.
+/// A spec without a process is only valid if the process is not required, as for a container which
+/// is created only to execute processes in it later
+pub fn validate_spec(spec: &Spec, process_required: bool) -> Result<()> {
+    let problems = find_problems(spec, process_required);
+    if !problems.is_empty() {
+        bail!("invalid spec: {}", problems.join("; "))
+    }
+    Ok(())
+}
+
+fn find_problems(spec: &Spec, process_required: bool) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if spec.root.path.as_os_str().is_empty() {
+        problems.push("root.path is empty".to_string());
+    }
+    problems.extend(spec.unknown_names.iter().cloned());
+
+    match &spec.process {
+        Some(process) => find_process_problems(process, &mut problems),
+        None if process_required => problems.push("process is missing".to_string()),
+        None => {}
+    }
+
+    let linux = match &spec.linux {
+        Some(linux) => linux,
+        None => {
+            problems.push("linux section is missing".to_string());
+            return problems;
+        }
+    };
+    for (i, ns) in linux.namespaces.iter().enumerate() {
+        if linux.namespaces[..i]
+            .iter()
+            .any(|other| other.typ == ns.typ)
+        {
+            problems.push(format!("namespace {:?} is given more than once", ns.typ));
+        }
+        if matches!(&ns.path, Some(path) if path.is_empty()) {
+            problems.push(format!("path of namespace {:?} is empty", ns.typ));
+        }
+    }
+
+    // the paths are resolved in the root of the container, a relative path would be resolved
+    // relative to the working directory of youki instead
+    for (field, paths) in &[
+        ("linux.maskedPaths", &linux.masked_paths),
+        ("linux.readonlyPaths", &linux.readonly_paths),
+    ] {
+        for path in paths.iter() {
+            if !Path::new(path).is_absolute() {
+                problems.push(format!("{} {:?} is not an absolute path", field, path));
+            }
+        }
+    }
+
+    if let Err(e) = sysctl::validate_sysctl(&linux.sysctl, &linux.namespaces) {
+        problems.push(format!("linux.sysctl: {}", e));
+    }
+
+    let has_userns = linux
+        .namespaces
+        .iter()
+        .any(|ns| ns.typ == LinuxNamespaceType::User);
+    let has_mappings = !linux.uid_mappings.is_empty() || !linux.gid_mappings.is_empty();
+    if has_mappings && !has_userns {
+        problems.push("id mappings are given without a user namespace".to_string());
+    }
+
+    problems
+}
+
+fn find_process_problems(process: &Process, problems: &mut Vec<String>) {
+    if process.args.is_empty() {
+        problems.push("process.args must contain at least the program to execute".to_string());
+    }
+    if !process.cwd.is_empty() && !Path::new(&process.cwd).is_absolute() {
+        problems.push(format!(
+            "process.cwd {:?} is not an absolute path",
+            process.cwd
+        ));
+    }
+    for env in &process.env {
+        if let Err(e) = utils::parse_env(env) {
+            problems.push(format!("process.env: {}", e));
+        }
+    }
+    if let Err(e) = rlimits::validate_rlimits(&process.rlimits) {
+        problems.push(format!("process.rlimits: {}", e));
+    }
+    if let Some(adj) = process.oom_score_adj {
+        if let Err(e) = oom::validate_oom_score_adj(adj) {
+            problems.push(format!("process.oomScoreAdj: {}", e));
+        }
+    }
+    if let Some(caps) = &process.capabilities {
+        let invalid_ambient = capabilities::invalid_ambient_capabilities(caps);
+        if !invalid_ambient.is_empty() {
+            problems.push(format!(
+                "ambient capabilities {:?} are not in the permitted and the inheritable set",
+                invalid_ambient
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::spec::{default_spec, rootless_spec};
+    use crate::utils::create_temp_dir;
+    use oci_spec::{
+        LinuxCapabilityType, LinuxIdMapping, LinuxNamespace, LinuxRlimit, LinuxRlimitType,
+    };
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn process_mut(spec: &mut Spec) -> &mut Process {
+        spec.process.as_mut().expect("process of the default spec")
+    }
+
+    #[test]
+    fn test_valid_specs() {
+        assert!(validate_spec(&default_spec(), true).is_ok());
+        assert!(validate_spec(&rootless_spec(1000, 1000, &[], &[]), true).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_process() {
+        let mut spec = default_spec();
+        spec.root.path = PathBuf::new();
+        let process = process_mut(&mut spec);
+        process.args = vec![];
+        process.cwd = "home".to_string();
+        process.env.push("TERM".to_string());
+        process.oom_score_adj = Some(-1001);
+
+        assert_eq!(
+            find_problems(&spec, true),
+            vec![
+                "root.path is empty",
+                "process.args must contain at least the program to execute",
+                "process.cwd \"home\" is not an absolute path",
+                "process.env: environment variable \"TERM\" is not in the form KEY=VALUE",
+                "process.oomScoreAdj: oom score adjustment -1001 is not between -1000 and 1000",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_rlimits_and_capabilities() {
+        let mut spec = default_spec();
+        process_mut(&mut spec).rlimits.push(LinuxRlimit {
+            typ: LinuxRlimitType::RlimitNofile,
+            hard: 1,
+            soft: 1,
+        });
+        let caps = process_mut(&mut spec).capabilities.as_mut().unwrap();
+        caps.ambient.push(LinuxCapabilityType {
+            cap: caps::Capability::CAP_SYS_ADMIN,
+        });
+
+        assert_eq!(
+            find_problems(&spec, true),
+            vec![
+                "process.rlimits: RlimitNofile is given more than once",
+                "ambient capabilities [CAP_SYS_ADMIN] are not in the permitted and the inheritable set",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_namespaces() {
+        let mut spec = default_spec();
+        let linux = spec.linux.as_mut().unwrap();
+        linux.namespaces.push(LinuxNamespace {
+            typ: LinuxNamespaceType::Pid,
+            path: Some(String::new()),
+        });
+        linux.uid_mappings.push(LinuxIdMapping {
+            host_id: 1000,
+            container_id: 0,
+            size: 1,
+        });
+        linux
+            .sysctl
+            .insert("vm.swappiness".to_string(), "10".to_string());
+
+        assert_eq!(
+            find_problems(&spec, true),
+            vec![
+                "namespace Pid is given more than once",
+                "path of namespace Pid is empty",
+                "linux.sysctl: sysctl vm.swappiness is not namespaced, so it can not be set for a container",
+                "id mappings are given without a user namespace",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relative_masked_and_readonly_paths() {
+        let mut spec = default_spec();
+        let linux = spec.linux.as_mut().unwrap();
+        linux.masked_paths.push("proc/kcore".to_string());
+        linux.readonly_paths.push("".to_string());
+        linux.readonly_paths.push("./sys".to_string());
+
+        assert_eq!(
+            find_problems(&spec, true),
+            vec![
+                "linux.maskedPaths \"proc/kcore\" is not an absolute path",
+                "linux.readonlyPaths \"\" is not an absolute path",
+                "linux.readonlyPaths \"./sys\" is not an absolute path",
+            ]
+        );
+        let err = validate_spec(&spec, true).unwrap_err();
+        assert!(err.to_string().contains("\"proc/kcore\""));
+    }
+
+    #[test]
+    fn test_missing_linux_section() {
+        let mut spec = default_spec();
+        spec.linux = None;
+        process_mut(&mut spec).args = vec![];
+
+        let err = validate_spec(&spec, true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid spec: process.args must contain at least the program to execute; linux section is missing"
+        );
+    }
+
+    #[test]
+    fn test_missing_process() {
+        let mut spec = default_spec();
+        spec.process = None;
+
+        // a container may be created without a process, to execute processes in it later
+        assert!(validate_spec(&spec, false).is_ok());
+        // but run has to start the process of the spec
+        let err = validate_spec(&spec, true).unwrap_err();
+        assert_eq!(err.to_string(), "invalid spec: process is missing");
+    }
+
+    #[test]
+    fn test_unknown_names() {
+        let tmp = create_temp_dir("test_unknown_names").expect("create temp directory");
+        let mut spec = serde_json::to_value(default_spec()).expect("serialize spec");
+        spec["root"]["path"] = "".into();
+        spec["linux"]["namespaces"][0]["type"] = "unknown".into();
+        spec["process"]["rlimits"][0]["type"] = "RLIMIT_UNKNOWN".into();
+        spec["process"]["capabilities"]["ambient"] = vec!["CAP_UNKNOWN"].into();
+        let config = tmp.join("config.json");
+        fs::write(&config, spec.to_string()).expect("write config.json");
+
+        // the spec is loaded, so that all of its problems are reported at once
+        let spec = Spec::load(config.to_str().unwrap()).expect("load spec");
+        assert_eq!(
+            find_problems(&spec, true),
+            vec![
+                "root.path is empty",
+                "linux.namespaces: unknown namespace type \"unknown\"",
+                "process.rlimits: unknown rlimit \"RLIMIT_UNKNOWN\"",
+                "process.capabilities.ambient: unknown capability \"CAP_UNKNOWN\"",
+            ]
+        );
+    }
+}
@@ -1,24 +1,38 @@
 //! Handles the creation of a new container
+use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use clap::Clap;
 use nix::sched;
 use nix::unistd;
 use nix::unistd::{Gid, Uid};
 
-use crate::cgroups;
-use crate::container::{Container, ContainerStatus};
+use crate::cgroups::{self, common::ManagerOptions};
+use crate::commands::delete::Delete;
+use crate::container::{validate_spec, Container, ContainerStatus};
+use crate::error::YoukiError;
+use crate::exec_fifo::ExecFifo;
+use crate::fds;
+use crate::hooks;
 use crate::namespaces::Namespaces;
-use crate::notify_socket::NotifyListener;
+use crate::oom;
 use crate::process::{fork, Process};
+use crate::rlimits;
 use crate::rootfs;
-use crate::stdio::FileDescriptor;
+use crate::rootless;
+use crate::seccomp::{self, NotifySocket};
+use crate::selinux;
+use crate::stdio::{FileDescriptor, OutputFiles};
+use crate::sysctl;
 use crate::tty;
 use crate::utils;
-use crate::{capabilities, command::Command};
+use crate::{apparmor, capabilities, command::Command};
+
+/// Name of the spec of the container in its bundle
+const CONFIG_FILE: &str = "config.json";
 
 /// This is the main structure which stores various commandline options given by
 /// high-level container runtime
@@ -34,6 +48,24 @@ pub struct Create {
     /// Unix socket (file) path , which will receive file descriptor of the writing end of the pseudoterminal
     #[clap(short, long)]
     console_socket: Option<String>,
+    /// Validate the bundle and print what would be created, without creating the container
+    #[clap(long)]
+    dry_run: bool,
+    /// Pass N additional file descriptors to the container, starting at fd 3
+    #[clap(long, default_value = "0")]
+    pub preserve_fds: u32,
+    /// Replace the state of an earlier container with the same id, whose process has exited
+    #[clap(long)]
+    force: bool,
+    /// Unix socket (file) path, which will receive the listener of the seccomp filter, if the profile notifies syscalls
+    #[clap(long)]
+    seccomp_notify_socket: Option<PathBuf>,
+    /// File to append the stdout of the container process to, instead of the stdout of youki
+    #[clap(long)]
+    out: Option<PathBuf>,
+    /// File to append the stderr of the container process to, instead of the stderr of youki
+    #[clap(long)]
+    err: Option<PathBuf>,
     /// name of the container instance to be started
     pub container_id: String,
 }
@@ -45,26 +77,136 @@ pub struct Create {
 // associated with it like any other process.
 impl Create {
     /// Starts a new container process
-    pub fn exec(&self, root_path: PathBuf, command: impl Command) -> Result<()> {
-        // create a directory for the container to store state etc.
-        // if already present, return error
-        let bundle_canonicalized = fs::canonicalize(&self.bundle)
-            .unwrap_or_else(|_| panic!("failed to canonicalied {:?}", &self.bundle));
-        let container_dir = root_path.join(&self.container_id);
-        log::debug!("container directory will be {:?}", container_dir);
+    pub fn exec(
+        &self,
+        root_path: PathBuf,
+        systemd_cgroup: bool,
+        strict_cgroups: bool,
+        cgroup_parent: Option<PathBuf>,
+        command: impl Command,
+    ) -> Result<()> {
+        // the container is forked, so not after return if in
+        // parent process, exit ;  as the work of creating the container is done
+        // a container, whose processes are only executed later, is created without a process
+        if let Some(Process::Parent(_)) = self.create(
+            root_path,
+            systemd_cgroup,
+            strict_cgroups,
+            cgroup_parent,
+            false,
+            false,
+            command,
+        )? {
+            process::exit(0);
+        }
+        // if in the child process after fork, or in a dry run, then just return
+        Ok(())
+    }
 
-        if !container_dir.exists() {
-            fs::create_dir(&container_dir).unwrap();
+    /// Creates the container, returning the process after the fork, which is the parent process
+    /// in youki itself. Nothing is returned for a dry run. If the process is not required, the
+    /// spec may leave it out, and the container just holds its namespaces and its cgroup.
+    /// The output of a detached container goes to files, as nobody reads the stdio of youki
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(
+        &self,
+        root_path: PathBuf,
+        systemd_cgroup: bool,
+        strict_cgroups: bool,
+        cgroup_parent: Option<PathBuf>,
+        process_required: bool,
+        detach: bool,
+        command: impl Command,
+    ) -> Result<Option<Process>> {
+        let bundle_canonicalized = resolve_bundle(&self.bundle)?;
+        let container_dir = utils::get_container_dir(&root_path, &self.container_id)?;
+        log::debug!("container directory will be {:?}", container_dir);
+        // the directory of the container stores its state, an earlier container with the same id
+        // is only replaced if its process has exited
+        let existing = if container_dir.exists() {
+            Some(is_alive(&container_dir))
         } else {
-            bail!("{} already exists", self.container_id)
+            None
+        };
+        let action = existing_action(&self.container_id, existing, self.force)?;
+        if action == ExistingAction::CleanUpAndCreate && !self.dry_run {
+            log::warn!(
+                "remove the state of the stopped container {}",
+                self.container_id
+            );
+            let delete = Delete {
+                force: false,
+                timeout: 0,
+                container_id: self.container_id.clone(),
+            };
+            if let Err(e) = delete.exec(root_path.clone()) {
+                // the state of a container, whose creation failed early, may not even be readable
+                log::debug!("failed to delete {}: {:?}", self.container_id, e);
+                fs::remove_dir_all(&container_dir)?;
+            }
         }
 
+        // a relative pid file is resolved before the working directory is changed
+        let pid_file = match &self.pid_file {
+            Some(pid_file) => Some(env::current_dir()?.join(pid_file)),
+            None => None,
+        };
+        let seccomp_notify_socket = match &self.seccomp_notify_socket {
+            Some(socket) => Some(env::current_dir()?.join(socket)),
+            None => None,
+        };
+        let out = match &self.out {
+            Some(out) => Some(env::current_dir()?.join(out)),
+            None => None,
+        };
+        let err = match &self.err {
+            Some(err) => Some(env::current_dir()?.join(err)),
+            None => None,
+        };
+
         // change directory to the bundle directory, and load configuration,
         // copy that to the container's directory
-        unistd::chdir(&self.bundle)?;
+        unistd::chdir(&bundle_canonicalized)?;
 
-        let spec = oci_spec::Spec::load("config.json")?;
-        fs::copy("config.json", container_dir.join("config.json"))?;
+        let spec = oci_spec::Spec::load(CONFIG_FILE).with_context(|| YoukiError::SpecParse {
+            path: bundle_canonicalized.join(CONFIG_FILE),
+        })?;
+        validate_spec(&spec, process_required)?;
+        // a terminal for the container process is only allocated if requested by the spec,
+        // its master end is sent to the socket given in the commandline options
+        let terminal = spec
+            .process
+            .as_ref()
+            .is_some_and(|process| process.terminal);
+        tty::validate_console_socket(terminal, self.console_socket.as_deref())?;
+        let output = OutputFiles::resolve(
+            out.as_deref(),
+            err.as_deref(),
+            &container_dir,
+            detach,
+            terminal,
+        )?;
+        // the cgroups path is resolved before anything is written, as it may conflict with the cgroup parent
+        let cgroups_path = utils::get_cgroup_path(
+            &spec
+                .linux
+                .as_ref()
+                .and_then(|linux| linux.cgroups_path.clone()),
+            &self.container_id,
+            systemd_cgroup,
+            cgroup_parent.as_deref(),
+        )?;
+        // a dry run stops before anything is written, so that a bundle can be checked without side effects
+        if self.dry_run {
+            print!(
+                "{}",
+                plan(&self.container_id, &container_dir, &spec, &cgroups_path)
+            );
+            return Ok(None);
+        }
+
+        fs::create_dir(&container_dir)?;
+        fs::copy(CONFIG_FILE, container_dir.join(CONFIG_FILE))?;
         log::debug!("spec: {:?}", spec);
 
         // convert path to absolute path, as relative path will be evaluated
@@ -80,50 +222,142 @@ impl Create {
             None,
             bundle_canonicalized.to_str().unwrap(),
             &container_dir,
-        )?;
+        )?
+        .set_systemd(systemd_cgroup)
+        .set_cgroup_parent(cgroup_parent)
+        .set_annotations(spec.annotations.clone());
         container.save()?;
 
-        let mut notify_socket: NotifyListener = NotifyListener::new(&container_dir)?;
+        // the init process blocks on the fifo after it has been set up, until the container is started
+        let exec_fifo = ExecFifo::create(&container_dir)?;
         // convert path of root file system of the container to absolute path
         let rootfs = fs::canonicalize(&spec.root.path)?;
-        // if socket file path is given in commandline options,
-        // get file descriptors of console and console socket
-        let (csocketfd, _consolefd) = {
-            if let Some(console_socket) = &self.console_socket {
-                let (csocketfd, consolefd) =
-                    tty::load_console_sockets(&container_dir, console_socket)?;
-                (Some(csocketfd), Some(consolefd))
-            } else {
-                (None, None)
+        let csocketfd = match &self.console_socket {
+            Some(console_socket) => {
+                Some(tty::load_console_sockets(&container_dir, console_socket)?)
             }
+            None => None,
+        };
+        let notify_socket = match &seccomp_notify_socket {
+            Some(socket) => Some(NotifySocket::connect(
+                &container_dir,
+                socket,
+                &self.container_id,
+            )?),
+            None => None,
         };
 
         let process = run_container(
-            self.pid_file.as_ref(),
-            &mut notify_socket,
+            pid_file,
+            cgroups_path,
+            exec_fifo,
+            self.preserve_fds,
             rootfs,
             spec,
             csocketfd,
+            notify_socket,
+            output,
             container,
+            strict_cgroups,
             command,
         )?;
-        // the run_container forks the process, so not after return if in
-        // parent process, exit ;  as the work of creating the container is done
-        if let Process::Parent(_) = process {
-            process::exit(0);
+        Ok(Some(process))
+    }
+}
+/// What has to be done about an earlier container with the same id before a container is created
+#[derive(Debug, PartialEq, Eq)]
+enum ExistingAction {
+    // there is no container with the id
+    Create,
+    // the process of the earlier container has exited, so that its state is removed first
+    CleanUpAndCreate,
+}
+
+/// Returns the absolute path of the bundle, which has to contain the spec of the container
+fn resolve_bundle(bundle: &Path) -> Result<PathBuf> {
+    let bundle = fs::canonicalize(bundle)
+        .with_context(|| format!("failed to find the bundle {:?}", bundle))?;
+    if !bundle.join(CONFIG_FILE).is_file() {
+        bail!("bundle {:?} does not contain {}", bundle, CONFIG_FILE)
+    }
+    Ok(bundle)
+}
+
+/// Decides whether a container can be created, given whether a container with the same id
+/// exists and whether its process is alive
+fn existing_action(
+    container_id: &str,
+    existing: Option<bool>,
+    force: bool,
+) -> Result<ExistingAction> {
+    match existing {
+        None => Ok(ExistingAction::Create),
+        Some(true) => bail!("container {} already exists", container_id),
+        Some(false) if force => Ok(ExistingAction::CleanUpAndCreate),
+        Some(false) => bail!(
+            "container {} already exists, but its process has exited. Delete it or use --force to replace it",
+            container_id
+        ),
+    }
+}
+
+// a container without a readable state or without a process is considered stopped
+fn is_alive(container_dir: &Path) -> bool {
+    Container::load(container_dir.to_path_buf())
+        .and_then(|container| container.refresh_status())
+        .map(|container| !matches!(container.status(), ContainerStatus::Stopped))
+        .unwrap_or(false)
+}
+
+/// Describes the container, which would be created from the spec
+fn plan(
+    container_id: &str,
+    container_dir: &Path,
+    spec: &oci_spec::Spec,
+    cgroups_path: &Path,
+) -> String {
+    let mut plan = format!("container: {}\n", container_id);
+    plan.push_str(&format!("state directory: {:?}\n", container_dir));
+    plan.push_str(&format!("root filesystem: {:?}\n", spec.root.path));
+    match &spec.process {
+        Some(process) => {
+            plan.push_str(&format!("process: {:?}\n", process.args));
+            plan.push_str(&format!(
+                "user: uid={} gid={}\n",
+                process.user.uid, process.user.gid
+            ));
         }
-        // if in the child process after fork, then just return
-        Ok(())
+        None => plan.push_str("process: none, processes are executed later\n"),
     }
+    plan.push_str(&format!("cgroup: {:?}\n", cgroups_path));
+    if let Some(linux) = &spec.linux {
+        for namespace in &linux.namespaces {
+            match &namespace.path {
+                Some(path) => plan.push_str(&format!(
+                    "namespace: {:?} joined from {:?}\n",
+                    namespace.typ, path
+                )),
+                None => plan.push_str(&format!("namespace: {:?} created\n", namespace.typ)),
+            }
+        }
+    }
+    plan
 }
+
 /// Fork the process and actually start the container process
+#[allow(clippy::too_many_arguments)]
 fn run_container<P: AsRef<Path>>(
     pid_file: Option<P>,
-    notify_socket: &mut NotifyListener,
+    cgroups_path: PathBuf,
+    exec_fifo: ExecFifo,
+    preserve_fds: u32,
     rootfs: PathBuf,
     spec: oci_spec::Spec,
     csocketfd: Option<FileDescriptor>,
+    notify_socket: Option<NotifySocket>,
+    output: OutputFiles,
     container: Container,
+    strict_cgroups: bool,
     command: impl Command,
 ) -> Result<Process> {
     // disable core dump for the process, check https://man7.org/linux/man-pages/man2/prctl.2.html for more information
@@ -134,8 +368,17 @@ fn run_container<P: AsRef<Path>>(
     let linux = spec.linux.as_ref().unwrap();
     let namespaces: Namespaces = linux.namespaces.clone().into();
 
-    let cgroups_path = utils::get_cgroup_path(&linux.cgroups_path, container.id());
-    let cmanager = cgroups::common::create_cgroup_manager(&cgroups_path)?;
+    // the cgroups of a rootless container belong to its root user, who manages the cgroups below
+    let options = ManagerOptions {
+        strict: strict_cgroups,
+        owner: if rootless::is_rootless() {
+            rootless::mapped_root_ids(&linux.uid_mappings, &linux.gid_mappings)
+        } else {
+            None
+        },
+    };
+    let cmanager =
+        cgroups::common::create_cgroup_manager(&cgroups_path, container.systemd(), options)?;
 
     // first fork, which creates process, which will later create actual container process
     match fork::fork_first(
@@ -144,6 +387,7 @@ fn run_container<P: AsRef<Path>>(
             .clone_flags
             .contains(sched::CloneFlags::CLONE_NEWUSER),
         linux,
+        spec.hooks.as_ref(),
         &container,
         cmanager,
     )? {
@@ -152,8 +396,8 @@ fn run_container<P: AsRef<Path>>(
         // in child process
         Process::Child(child) => {
             // set limits and namespaces to the process
-            for rlimit in spec.process.rlimits.iter() {
-                command.set_rlimit(rlimit)?
+            if let Some(process) = &spec.process {
+                rlimits::set_rlimits(&process.rlimits, &command)?;
             }
             command.set_id(Uid::from_raw(0), Gid::from_raw(0))?;
 
@@ -164,6 +408,8 @@ fn run_container<P: AsRef<Path>>(
             if let Some(csocketfd) = csocketfd {
                 tty::ready(csocketfd)?;
             }
+            // the files are opened in the namespaces of youki, so that their paths are the ones of the host
+            output.redirect()?;
 
             // set namespaces
             namespaces.apply_setns()?;
@@ -173,15 +419,67 @@ fn run_container<P: AsRef<Path>>(
                 Process::Child(_child) => unreachable!(),
                 // This is actually the child process after fork
                 Process::Init(mut init) => {
-                    // setup args and env vars as in the spec
-                    let spec_args: &Vec<String> = &spec.process.args.clone();
-                    let envs: &Vec<String> = &spec.process.env.clone();
-                    // prepare process
-                    init_process(spec, command, rootfs, namespaces)?;
+                    // report to the child process right away, so that the parent process learns the pid
+                    // of the init process and can wait for the result of the setup
                     init.ready()?;
-                    notify_socket.wait_for_container_start()?;
+                    // setup args and env vars as in the spec
+                    let process = spec.process.clone();
+                    let seccomp = linux.seccomp.clone();
+                    let hooks = spec.hooks.clone().unwrap_or_default();
+                    let setup = || -> Result<Vec<String>> {
+                        let envs = match &process {
+                            Some(process) => utils::prepare_env(&process.env)?,
+                            None => Vec::new(),
+                        };
+                        // create container hooks are run in the namespaces of the container, but before
+                        // the root is changed, so that their path is resolved in the runtime namespace
+                        hooks::run_hooks(&hooks.create_container, &container.state)?;
+                        // prepare process
+                        init_process(spec, command, rootfs, namespaces, notify_socket.as_ref())?;
+                        // the program is executed after start, when the parent process has already exited,
+                        // so that it has to be checked here whether it can be found
+                        if let Some(process) = &process {
+                            utils::find_executable(&process.args[0], &envs)?;
+                        }
+                        Ok(envs)
+                    };
+                    let mut envs = match setup() {
+                        Ok(envs) => envs,
+                        Err(e) => {
+                            init.send_error(&e)?;
+                            return Err(e);
+                        }
+                    };
+                    init.complete()?;
+                    let process = match process {
+                        Some(process) => process,
+                        // without a process the container is never started, the init process only
+                        // keeps the namespaces alive for the processes executed in them, until it is killed
+                        None => loop {
+                            unistd::pause();
+                        },
+                    };
+                    exec_fifo.wait()?;
+                    let preserved = fds::preserve_fds(preserve_fds)?;
+                    fds::set_listen_env(&mut envs, preserved, unistd::getpid());
+                    // the directory of the container is not reachable anymore after the root has been changed
+                    let mut state = container.state.clone();
+                    state.status = ContainerStatus::Created;
+                    hooks::run_hooks(&hooks.start_container, &state)?;
+                    fds::close_leaked_fds(preserved)?;
+                    fds::pass_preserved_fds(preserved)?;
+                    // without no_new_privileges the filter has already been installed by init_process,
+                    // as the capabilities required for it have been dropped. Otherwise it is installed
+                    // as late as possible so that youki itself is not restricted by the filter
+                    if process.no_new_privileges {
+                        if let Some(seccomp) = &seccomp {
+                            seccomp::initialize_seccomp(seccomp, notify_socket.as_ref())?;
+                        }
+                    }
+                    // the listener has been sent, the socket is not passed on to the container process
+                    drop(notify_socket);
                     // actually run the command / program to be run in container
-                    utils::do_exec(&spec_args[0], spec_args, envs)?;
+                    utils::do_exec(&process.args[0], &process.args, &envs)?;
                     // the command / program is done executing
                     container.update_status(ContainerStatus::Stopped)?.save()?;
 
@@ -200,13 +498,12 @@ fn init_process(
     command: impl Command,
     rootfs: PathBuf,
     namespaces: Namespaces,
+    notify_socket: Option<&NotifySocket>,
 ) -> Result<()> {
-    let proc = spec.process.clone();
-
-    command.set_hostname(&spec.hostname.as_str())?;
-    if spec.process.no_new_privileges {
-        let _ = prctl::set_no_new_privileges(true);
+    if let Some(adj) = spec.process.as_ref().and_then(|proc| proc.oom_score_adj) {
+        oom::set_oom_score_adj(adj)?;
     }
+    command.set_hostname(&spec.hostname.as_str())?;
 
     rootfs::prepare_rootfs(
         &spec,
@@ -214,15 +511,303 @@ fn init_process(
         namespaces
             .clone_flags
             .contains(sched::CloneFlags::CLONE_NEWUSER),
+        &command,
     )?;
 
     // change the root of filesystem of the process to the rootfs
-    command.pivot_rootfs(&rootfs)?;
+    rootfs::pivot_rootfs(&rootfs, &command)?;
+    if let Some(linux) = &spec.linux {
+        // /proc/sys is usually one of the readonly paths
+        sysctl::apply_sysctl(&linux.sysctl, &linux.namespaces)?;
+    }
+    rootfs::finish_rootfs(&spec, &command)?;
 
-    command.set_id(Uid::from_raw(proc.user.uid), Gid::from_raw(proc.user.gid))?;
+    // the rest only prepares the process for the exec of its program
+    let proc = match spec.process.clone() {
+        Some(proc) => proc,
+        None => return Ok(()),
+    };
+    // the profile and the label are changed on exec, without privileges
+    apparmor::apply_profile(&proc.apparmor_profile)?;
+    selinux::set_exec_label(&proc.selinux_label)?;
+    set_user(&proc.user, is_setgroups_allowed(), &command)?;
+    // the directory is changed as the user of the process, whose permissions apply
+    utils::set_working_dir(&proc.cwd)?;
     capabilities::reset_effective(&command)?;
+    // installing a seccomp filter requires CAP_SYS_ADMIN unless no_new_privileges is set,
+    // so the filter has to be installed before the capabilities are dropped
+    if !proc.no_new_privileges {
+        if let Some(seccomp) = spec.linux.as_ref().and_then(|linux| linux.seccomp.as_ref()) {
+            seccomp::initialize_seccomp(seccomp, notify_socket)?;
+        }
+    }
     if let Some(caps) = &proc.capabilities {
         capabilities::drop_privileges(&caps, &command)?;
     }
+    // must be set after the capabilities, as no_new_privileges does not
+    // prevent the process from changing its capabilities, but before exec
+    set_no_new_privileges(proc.no_new_privileges, &command)?;
     Ok(())
 }
+
+/// Changes the ids of the process to the user of the spec. The supplementary groups are set first,
+/// as setting them requires privileges which are lost once the uid has been changed
+pub fn set_user(
+    user: &oci_spec::User,
+    setgroups_allowed: bool,
+    command: &impl Command,
+) -> Result<()> {
+    let gids: Vec<Gid> = user
+        .additional_gids
+        .iter()
+        .map(|gid| Gid::from_raw(*gid))
+        .collect();
+    if setgroups_allowed {
+        // the groups of youki are not passed on to the container process
+        command.set_groups(&gids)?;
+    } else if !gids.is_empty() {
+        bail!("additional gids can not be set, as setgroups is denied in the user namespace")
+    }
+
+    command.set_id(Uid::from_raw(user.uid), Gid::from_raw(user.gid))
+}
+
+/// Returns false if setgroups has been denied for the user namespace of the process,
+/// which is required for an unprivileged user to map its own gid
+pub fn is_setgroups_allowed() -> bool {
+    // the file does not exist on kernels which do not allow to deny setgroups
+    fs::read_to_string("/proc/self/setgroups")
+        .map(|content| content.trim() != "deny")
+        .unwrap_or(true)
+}
+
+/// Prevents the container process from gaining privileges it did not have when it was
+/// started, if requested by the spec
+fn set_no_new_privileges(no_new_privileges: bool, command: &impl Command) -> Result<()> {
+    if no_new_privileges {
+        command.set_no_new_privileges()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::linux::LinuxCommand;
+    use crate::command::test::{IdOperation, TestHelperCommand};
+    use crate::commands::spec::default_spec;
+    use crate::process::fork::clone_child;
+    use crate::utils::create_temp_dir;
+    use nix::sched::CloneFlags;
+    use nix::sys::wait::{waitpid, WaitStatus};
+
+    fn user(uid: u32, gid: u32, additional_gids: Vec<u32>) -> oci_spec::User {
+        oci_spec::User {
+            uid,
+            gid,
+            additional_gids,
+            username: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_set_user() {
+        let test_command = TestHelperCommand::default();
+        set_user(&user(1000, 1001, vec![10, 20]), true, &test_command).expect("set user");
+
+        assert_eq!(
+            test_command.get_id_operations(),
+            vec![
+                IdOperation::SetGroups(vec![Gid::from_raw(10), Gid::from_raw(20)]),
+                IdOperation::SetId(Uid::from_raw(1000), Gid::from_raw(1001)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_user_clears_groups() {
+        let test_command = TestHelperCommand::default();
+        set_user(&user(0, 0, vec![]), true, &test_command).expect("set user");
+
+        assert_eq!(
+            test_command.get_id_operations(),
+            vec![
+                IdOperation::SetGroups(vec![]),
+                IdOperation::SetId(Uid::from_raw(0), Gid::from_raw(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_user_with_setgroups_denied() {
+        let test_command = TestHelperCommand::default();
+        set_user(&user(0, 0, vec![]), false, &test_command).expect("set user");
+        assert_eq!(
+            test_command.get_id_operations(),
+            vec![IdOperation::SetId(Uid::from_raw(0), Gid::from_raw(0))]
+        );
+
+        let test_command = TestHelperCommand::default();
+        assert!(set_user(&user(0, 0, vec![10]), false, &test_command).is_err());
+        assert!(test_command.get_id_operations().is_empty());
+    }
+
+    #[test]
+    fn test_set_no_new_privileges() {
+        let test_command = TestHelperCommand::default();
+        assert!(set_no_new_privileges(true, &test_command).is_ok());
+        assert_eq!(test_command.get_set_no_new_privileges_calls(), 1);
+    }
+
+    #[test]
+    fn test_skip_no_new_privileges() {
+        let test_command = TestHelperCommand::default();
+        assert!(set_no_new_privileges(false, &test_command).is_ok());
+        assert_eq!(test_command.get_set_no_new_privileges_calls(), 0);
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let tmp = create_temp_dir("test_create_dry_run").expect("create temp directory");
+        let bundle = tmp.join("bundle");
+        let root_path = tmp.join("root");
+        fs::create_dir_all(bundle.join("rootfs")).expect("create root filesystem");
+        fs::create_dir_all(&root_path).expect("create root directory");
+        fs::write(
+            bundle.join("config.json"),
+            serde_json::to_string(&default_spec()).expect("serialize spec"),
+        )
+        .expect("write config.json");
+        let container_id = "youki_test_create_dry_run";
+        let create = Create {
+            pid_file: None,
+            bundle: bundle.clone(),
+            console_socket: Some("/tmp/console.sock".to_string()),
+            dry_run: true,
+            preserve_fds: 0,
+            force: false,
+            seccomp_notify_socket: None,
+            out: None,
+            err: None,
+            container_id: container_id.to_string(),
+        };
+
+        // create changes the working directory to the bundle, which is shared by all tests of the process
+        let child = clone_child(CloneFlags::empty(), || {
+            match create.exec(root_path.clone(), false, false, None, LinuxCommand) {
+                Ok(_) => 0,
+                Err(_) => 1,
+            }
+        })
+        .expect("clone child");
+        assert_eq!(
+            waitpid(child, None).expect("wait for child"),
+            WaitStatus::Exited(child, 0),
+            "dry run of create"
+        );
+
+        assert!(
+            !root_path.join(container_id).exists(),
+            "no state is written"
+        );
+        let cgroup = utils::get_cgroup_path(&None, container_id, false, None).unwrap();
+        assert!(!Path::new(cgroups::common::DEFAULT_CGROUP_ROOT)
+            .join(cgroup.strip_prefix("/").unwrap())
+            .exists());
+        assert!(!bundle.join(container_id).exists());
+    }
+
+    #[test]
+    fn test_dry_run_plan() {
+        let spec = default_spec();
+
+        let plan = plan(
+            "plan",
+            Path::new("/run/youki/plan"),
+            &spec,
+            Path::new("/youki/plan"),
+        );
+
+        assert!(plan.contains("state directory: \"/run/youki/plan\""));
+        assert!(plan.contains("cgroup: \"/youki/plan\""));
+        assert!(plan.contains("namespace: Pid created"));
+        assert!(plan.contains("process: [\"sh\"]"));
+    }
+
+    #[test]
+    fn test_resolve_bundle() {
+        let tmp = create_temp_dir("test_resolve_bundle").expect("create temp directory");
+        let bundle = tmp.join("bundle");
+        fs::create_dir(&bundle).expect("create bundle");
+
+        let err = resolve_bundle(&bundle).unwrap_err();
+        assert!(err.to_string().ends_with("does not contain config.json"));
+        assert!(resolve_bundle(&tmp.join("missing")).is_err());
+
+        fs::write(bundle.join(CONFIG_FILE), "{}").expect("write config.json");
+        assert_eq!(
+            resolve_bundle(&bundle.join("../bundle")).expect("resolve bundle"),
+            fs::canonicalize(&bundle).expect("canonicalize bundle")
+        );
+    }
+
+    #[test]
+    fn test_existing_action() {
+        assert_eq!(
+            existing_action("new", None, false).unwrap(),
+            ExistingAction::Create
+        );
+
+        let err = existing_action("alive", Some(true), true).unwrap_err();
+        assert_eq!(err.to_string(), "container alive already exists");
+
+        assert!(existing_action("dead", Some(false), false).is_err());
+        assert_eq!(
+            existing_action("dead", Some(false), true).unwrap(),
+            ExistingAction::CleanUpAndCreate
+        );
+    }
+
+    #[test]
+    fn test_existing_container_is_alive() {
+        let tmp =
+            create_temp_dir("test_existing_container_is_alive").expect("create temp directory");
+        let alive = tmp.join("alive");
+        fs::create_dir(&alive).expect("create container directory");
+        Container::new(
+            "alive",
+            ContainerStatus::Running,
+            Some(unistd::getpid().as_raw()),
+            "/",
+            &alive,
+        )
+        .expect("create container")
+        .save()
+        .expect("save container");
+
+        let mut child = std::process::Command::new("true")
+            .spawn()
+            .expect("spawn process");
+        child.wait().expect("wait for process");
+        let dead = tmp.join("dead");
+        fs::create_dir(&dead).expect("create container directory");
+        Container::new(
+            "dead",
+            ContainerStatus::Running,
+            Some(child.id() as i32),
+            "/",
+            &dead,
+        )
+        .expect("create container")
+        .save()
+        .expect("save container");
+
+        // the state of a container may be missing, if its creation failed early
+        let broken = tmp.join("broken");
+        fs::create_dir(&broken).expect("create container directory");
+
+        assert!(is_alive(&alive));
+        assert!(!is_alive(&dead));
+        assert!(!is_alive(&broken));
+    }
+}
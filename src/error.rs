@@ -0,0 +1,160 @@
+//! Errors of youki, which callers can tell apart by the exit code of youki. Internally errors
+//! are still anyhow errors, these errors are attached to them as context where a failure of
+//! one of the categories happens, so that the command layer can find them in the chain.
+//! The exit codes are the ones of runc and of shells for a command, which can not be executed
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Exit code of any error, which does not belong to one of the categories
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code if youki is invoked wrongly, e.g. with unknown options or an invalid bundle
+pub const EXIT_USAGE: i32 = 125;
+/// Exit code if the program of a process is found, but can not be executed
+pub const EXIT_CANNOT_INVOKE: i32 = 126;
+/// Exit code if the program of a process is not found
+pub const EXIT_NOT_FOUND: i32 = 127;
+
+#[derive(Debug, Error)]
+pub enum YoukiError {
+    #[error("failed to load the spec {path:?}")]
+    SpecParse { path: PathBuf },
+    #[error("failed to write {data} to {path:?}")]
+    CgroupWrite { path: PathBuf, data: String },
+    #[error("failed to set up the namespaces of the container")]
+    NamespaceSetup,
+    /// The process failed before its program was executed and exited with the exit code
+    #[error("failed to execute {cmd}")]
+    Exec { cmd: String, exit_code: i32 },
+    #[error("exec: {cmd:?}: {reason}")]
+    CommandNotFound { cmd: String, reason: String },
+    #[error("exec: {cmd:?}: {reason}")]
+    CommandNotInvokable { cmd: String, reason: String },
+    #[error("failed to persist the state of container {id}")]
+    StatePersist { id: String },
+    #[error("hook did not exit within {timeout} seconds")]
+    HookTimeout { timeout: i64 },
+    /// An error of a process forked by youki, which it has reported with the exit code for it
+    #[error("{msg}")]
+    Process { msg: String, exit_code: i32 },
+}
+
+impl YoukiError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::SpecParse { .. } => EXIT_USAGE,
+            Self::CgroupWrite { .. }
+            | Self::NamespaceSetup
+            | Self::StatePersist { .. }
+            | Self::HookTimeout { .. } => EXIT_FAILURE,
+            // the process reports whether its program could be executed by its exit code
+            Self::Exec { exit_code, .. } => match *exit_code {
+                EXIT_CANNOT_INVOKE | EXIT_NOT_FOUND => *exit_code,
+                _ => EXIT_FAILURE,
+            },
+            Self::CommandNotFound { .. } => EXIT_NOT_FOUND,
+            Self::CommandNotInvokable { .. } => EXIT_CANNOT_INVOKE,
+            Self::Process { exit_code, .. } => *exit_code,
+        }
+    }
+}
+
+/// Returns the exit code of youki for an error. The outermost error of youki in the chain
+/// is the most specific one
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.downcast_ref::<YoukiError>()
+        .map(YoukiError::exit_code)
+        .unwrap_or(EXIT_FAILURE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn test_exit_codes() {
+        let errors = vec![
+            (
+                YoukiError::SpecParse {
+                    path: PathBuf::from("config.json"),
+                },
+                EXIT_USAGE,
+            ),
+            (
+                YoukiError::CgroupWrite {
+                    path: PathBuf::from("/sys/fs/cgroup/pids.max"),
+                    data: "10".to_string(),
+                },
+                EXIT_FAILURE,
+            ),
+            (YoukiError::NamespaceSetup, EXIT_FAILURE),
+            (
+                YoukiError::StatePersist {
+                    id: "youki".to_string(),
+                },
+                EXIT_FAILURE,
+            ),
+            (YoukiError::HookTimeout { timeout: 1 }, EXIT_FAILURE),
+            (
+                YoukiError::Process {
+                    msg: "exec: \"missing\": executable file not found in $PATH".to_string(),
+                    exit_code: EXIT_NOT_FOUND,
+                },
+                EXIT_NOT_FOUND,
+            ),
+            (
+                YoukiError::CommandNotFound {
+                    cmd: "missing".to_string(),
+                    reason: "executable file not found in $PATH".to_string(),
+                },
+                EXIT_NOT_FOUND,
+            ),
+            (
+                YoukiError::CommandNotInvokable {
+                    cmd: "/etc/passwd".to_string(),
+                    reason: "permission denied".to_string(),
+                },
+                EXIT_CANNOT_INVOKE,
+            ),
+        ];
+
+        for (err, code) in errors {
+            assert_eq!(err.exit_code(), code, "exit code of {:?}", err);
+            let err: anyhow::Error = err.into();
+            assert_eq!(exit_code(&err), code);
+        }
+    }
+
+    #[test]
+    fn test_exit_code_of_context() {
+        let err = Err::<(), _>(anyhow!("permission denied"))
+            .context(YoukiError::NamespaceSetup)
+            .context("failed to create the container")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), EXIT_FAILURE);
+        let err = Err::<(), _>(anyhow!("permission denied"))
+            .context(YoukiError::CommandNotInvokable {
+                cmd: "sh".to_string(),
+                reason: "permission denied".to_string(),
+            })
+            .context("failed to execute the process")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), EXIT_CANNOT_INVOKE);
+
+        assert_eq!(exit_code(&anyhow!("unknown failure")), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn test_exit_code_of_exec() {
+        let exec = |exit_code| YoukiError::Exec {
+            cmd: "sh".to_string(),
+            exit_code,
+        };
+        assert_eq!(exec(EXIT_NOT_FOUND).exit_code(), EXIT_NOT_FOUND);
+        assert_eq!(exec(EXIT_CANNOT_INVOKE).exit_code(), EXIT_CANNOT_INVOKE);
+        // any other exit code of the process is a failure of youki
+        assert_eq!(exec(EXIT_USAGE).exit_code(), EXIT_FAILURE);
+        assert_eq!(exec(0).exit_code(), EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,131 @@
+//! FIFO in the directory of a container, which the init process blocks on after it has been set
+//! up, until start opens it to let the init process execute the program of the container
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use nix::{
+    errno::Errno,
+    fcntl::{self, OFlag},
+    sys::{signal, stat::Mode},
+    unistd::{self, Pid},
+};
+
+pub const EXEC_FIFO: &str = "exec.fifo";
+/// Time to wait between two attempts of start to open the FIFO, while the init process
+/// has not opened it yet
+const OPEN_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// FIFO, which is created along with the container and waited on by its init process
+pub struct ExecFifo {
+    // opened with O_PATH, which does not block like opening a FIFO for reading or writing
+    fd: i32,
+}
+
+impl ExecFifo {
+    /// Creates the FIFO in the directory of the container. It is opened before the root of the
+    /// container is changed, so that the init process can still reach it afterwards
+    pub fn create(container_dir: &Path) -> Result<Self> {
+        let path = fifo_path(container_dir);
+        unistd::mkfifo(&path, Mode::from_bits_truncate(0o622))
+            .with_context(|| format!("failed to create the exec fifo {:?}", path))?;
+        let fd = fcntl::open(&path, OFlag::O_PATH | OFlag::O_CLOEXEC, Mode::empty())
+            .with_context(|| format!("failed to open the exec fifo {:?}", path))?;
+        Ok(Self { fd })
+    }
+
+    /// Blocks until start opens the FIFO for writing. The FIFO is reopened for reading through
+    /// the fd in /proc, as its path is not reachable from the root of the container
+    pub fn wait(self) -> Result<()> {
+        let path = format!("/proc/self/fd/{}", self.fd);
+        let result = fcntl::open(
+            path.as_str(),
+            OFlag::O_RDONLY | OFlag::O_CLOEXEC,
+            Mode::empty(),
+        )
+        .context("failed to open the exec fifo for reading")
+        .and_then(|fd| {
+            let mut buf = [0u8; 1];
+            let read = unistd::read(fd, &mut buf).context("failed to read the exec fifo");
+            let _ = unistd::close(fd);
+            read.map(|_| ())
+        });
+        let _ = unistd::close(self.fd);
+        result
+    }
+}
+
+/// Unblocks the init process, which waits on the FIFO of the container, and removes the FIFO,
+/// as a container is only started once. Fails if the init process exits before it opens the FIFO
+pub fn notify(container_dir: &Path, init_pid: Pid) -> Result<()> {
+    let path = fifo_path(container_dir);
+    // opening a FIFO for writing without blocking fails with ENXIO, as long as it is not opened for reading
+    let fd = loop {
+        match fcntl::open(
+            &path,
+            OFlag::O_WRONLY | OFlag::O_NONBLOCK | OFlag::O_CLOEXEC,
+            Mode::empty(),
+        ) {
+            Ok(fd) => break fd,
+            Err(nix::Error::Sys(Errno::ENXIO)) => {
+                if let Err(nix::Error::Sys(Errno::ESRCH)) = signal::kill(init_pid, None) {
+                    bail!(
+                        "init process {} exited before the container was started",
+                        init_pid
+                    )
+                }
+                thread::sleep(OPEN_RETRY_INTERVAL);
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to open the exec fifo {:?}", path))
+            }
+        }
+    };
+
+    let written = unistd::write(fd, b"0").context("failed to write to the exec fifo");
+    let _ = unistd::close(fd);
+    written?;
+    fs::remove_file(&path).with_context(|| format!("failed to remove the exec fifo {:?}", path))
+}
+
+fn fifo_path(container_dir: &Path) -> PathBuf {
+    container_dir.join(EXEC_FIFO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_temp_dir;
+
+    #[test]
+    fn test_wait_until_notified() {
+        let tmp = create_temp_dir("test_wait_until_notified").expect("create temp directory");
+        let fifo = ExecFifo::create(&tmp).expect("create exec fifo");
+        assert!(tmp.join(EXEC_FIFO).exists());
+
+        let waiting = thread::spawn(move || fifo.wait());
+        notify(&tmp, unistd::getpid()).expect("notify exec fifo");
+
+        waiting
+            .join()
+            .expect("join waiting thread")
+            .expect("wait for exec fifo");
+        assert!(!tmp.join(EXEC_FIFO).exists());
+    }
+
+    #[test]
+    fn test_notify_exited_process() {
+        let tmp = create_temp_dir("test_notify_exited_process").expect("create temp directory");
+        let _fifo = ExecFifo::create(&tmp).expect("create exec fifo");
+        let mut child = std::process::Command::new("true")
+            .spawn()
+            .expect("spawn process");
+        child.wait().expect("wait for process");
+
+        assert!(notify(&tmp, Pid::from_raw(child.id() as i32)).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! File descriptors which are passed from the caller of youki into the container, e.g. the
+//! sockets of socket activation, check https://www.freedesktop.org/software/systemd/man/sd_listen_fds.html
+use std::{fs, os::unix::io::RawFd};
+
+use anyhow::{bail, Context, Result};
+use nix::{
+    errno::Errno,
+    fcntl::{self, FcntlArg, FdFlag},
+    unistd::{self, Pid},
+};
+
+/// The preserved fds follow stdin, stdout and stderr
+pub const FIRST_PRESERVED_FD: RawFd = 3;
+
+/// How the preserved fds are rearranged before the program of the container is executed
+#[derive(Debug, PartialEq)]
+struct FdPlan {
+    // preserved fds, which are moved down to close the gaps between them
+    moves: Vec<(RawFd, RawFd)>,
+    // number of fds, which are passed to the program starting at FIRST_PRESERVED_FD
+    preserved: u32,
+}
+
+/// Keeps the open fds in the range of `preserve_fds` fds after stderr for the program of the container,
+/// numbered without gaps from fd 3. Returns the number of preserved fds.
+/// The fds which youki opened itself are close on exec and not preserved. As they can not be
+/// moved, youki moves the preserved fds together before it opens any fd, so that its own fds
+/// are placed after them. The preserved fds stay close on exec, so that the hooks do not inherit
+/// them, until [pass_preserved_fds] is called right before the exec
+pub fn preserve_fds(preserve_fds: u32) -> Result<u32> {
+    let mut open_fds = open_fds()?;
+    let mut internal = Vec::new();
+    open_fds.retain(|fd| match fcntl::fcntl(*fd, FcntlArg::F_GETFD) {
+        Ok(flags) => {
+            if FdFlag::from_bits_truncate(flags).contains(FdFlag::FD_CLOEXEC) {
+                internal.push(*fd);
+            }
+            true
+        }
+        // the fd of the directory, which the fds were listed from, is already closed
+        Err(_) => false,
+    });
+    let plan = plan_fds(&open_fds, preserve_fds, &internal)?;
+    log::debug!("rearrange the fds of the container process {:?}", plan);
+
+    for (from, to) in &plan.moves {
+        unistd::dup2(*from, *to)
+            .with_context(|| format!("failed to move fd {} to {}", from, to))?;
+        unistd::close(*from)?;
+    }
+    // the duplicated fds do not inherit the close on exec flag, and the others never had it
+    set_preserved_fds_flags(plan.preserved, FdFlag::FD_CLOEXEC)?;
+
+    Ok(plan.preserved)
+}
+
+/// Passes the preserved fds on to the program of the container, which is executed next
+pub fn pass_preserved_fds(preserved: u32) -> Result<()> {
+    set_preserved_fds_flags(preserved, FdFlag::empty())
+}
+
+fn set_preserved_fds_flags(preserved: u32, flags: FdFlag) -> Result<()> {
+    for fd in FIRST_PRESERVED_FD..FIRST_PRESERVED_FD + preserved as RawFd {
+        fcntl::fcntl(fd, FcntlArg::F_SETFD(flags))
+            .with_context(|| format!("failed to preserve fd {}", fd))?;
+    }
+    Ok(())
+}
+
+/// Marks all fds except stdio and the preserved fds close on exec, so that no fd of the host,
+/// which youki has inherited or opened without the flag, leaks into the container
+pub fn close_leaked_fds(preserved: u32) -> Result<()> {
+    let keep: Vec<RawFd> = (FIRST_PRESERVED_FD..FIRST_PRESERVED_FD + preserved as RawFd).collect();
+    for fd in fds_to_close(&open_fds()?, &keep) {
+        match fcntl::fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)) {
+            // the fd of the directory, which the fds were listed from, is already closed
+            Ok(_) | Err(nix::Error::Sys(Errno::EBADF)) => {}
+            Err(e) => return Err(e).with_context(|| format!("failed to close fd {} on exec", fd)),
+        }
+    }
+    Ok(())
+}
+
+/// Tells the program how many fds it received, replacing the variables of the spec if any.
+/// The pid is the one of the process which executes the program
+pub fn set_listen_env(envs: &mut Vec<String>, preserved: u32, pid: Pid) {
+    if preserved == 0 {
+        return;
+    }
+    envs.retain(|env| !env.starts_with("LISTEN_FDS=") && !env.starts_with("LISTEN_PID="));
+    envs.push(format!("LISTEN_FDS={}", preserved));
+    envs.push(format!("LISTEN_PID={}", pid));
+}
+
+fn open_fds() -> Result<Vec<RawFd>> {
+    let mut fds = Vec::new();
+    for entry in fs::read_dir("/proc/self/fd").context("failed to list the open fds")? {
+        if let Some(fd) = entry?.file_name().to_str().and_then(|s| s.parse().ok()) {
+            fds.push(fd);
+        }
+    }
+    fds.sort_unstable();
+    Ok(fds)
+}
+
+fn plan_fds(open_fds: &[RawFd], preserve_fds: u32, internal: &[RawFd]) -> Result<FdPlan> {
+    let end = FIRST_PRESERVED_FD.saturating_add(preserve_fds as RawFd);
+    let mut plan = FdPlan {
+        moves: Vec::new(),
+        preserved: 0,
+    };
+
+    // the fds are sorted, so that an fd is only moved to a lower fd, which has already been freed
+    for &fd in open_fds
+        .iter()
+        .filter(|fd| (FIRST_PRESERVED_FD..end).contains(*fd) && !internal.contains(fd))
+    {
+        let to = FIRST_PRESERVED_FD + plan.preserved as RawFd;
+        // moving a preserved fd onto an fd of youki would close it
+        if let Some(fd) = internal
+            .iter()
+            .find(|internal| (to..fd).contains(*internal))
+        {
+            bail!(
+                "fd {} of youki is between the preserved fds, so that they can not be moved together",
+                fd
+            )
+        }
+        if fd != to {
+            plan.moves.push((fd, to));
+        }
+        plan.preserved += 1;
+    }
+    Ok(plan)
+}
+
+// stdin, stdout and stderr are the only fds, which are always kept open
+fn fds_to_close(open_fds: &[RawFd], keep: &[RawFd]) -> Vec<RawFd> {
+    open_fds
+        .iter()
+        .copied()
+        .filter(|fd| *fd >= FIRST_PRESERVED_FD && !keep.contains(fd))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_fds() {
+        let plan = plan_fds(&[0, 1, 2, 3, 5, 6, 7, 9], 4, &[]).unwrap();
+
+        assert_eq!(
+            plan,
+            FdPlan {
+                moves: vec![(5, 4), (6, 5)],
+                preserved: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_plan_fds_without_preserved_fds() {
+        let plan = plan_fds(&[0, 1, 2, 3, 4], 0, &[]).unwrap();
+
+        assert!(plan.moves.is_empty());
+        assert_eq!(plan.preserved, 0);
+    }
+
+    #[test]
+    fn test_plan_contiguous_fds() {
+        let plan = plan_fds(&[0, 1, 2, 3, 4, 5], 2, &[]).unwrap();
+
+        assert!(plan.moves.is_empty());
+        assert_eq!(plan.preserved, 2);
+    }
+
+    #[test]
+    fn test_plan_fds_with_internal_fds() {
+        // the fds of youki after the preserved fds are not passed to the container
+        let plan = plan_fds(&[0, 1, 2, 3, 4, 6, 7], 5, &[6, 7]).unwrap();
+        assert_eq!(
+            plan,
+            FdPlan {
+                moves: vec![],
+                preserved: 2,
+            }
+        );
+
+        // but an fd of youki in a gap between them would be replaced by a moved fd
+        let err = plan_fds(&[0, 1, 2, 3, 4, 5, 7], 5, &[4]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "fd 4 of youki is between the preserved fds, so that they can not be moved together"
+        );
+    }
+
+    #[test]
+    fn test_fds_to_close() {
+        assert_eq!(fds_to_close(&[0, 1, 2, 3, 4, 7, 9], &[3, 4]), vec![7, 9]);
+        assert_eq!(fds_to_close(&[0, 1, 2, 5], &[]), vec![5]);
+        // stdio is kept open even if it is not preserved explicitly
+        assert!(fds_to_close(&[0, 1, 2], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_set_listen_env() {
+        let mut envs = vec!["PATH=/bin".to_string(), "LISTEN_FDS=9".to_string()];
+
+        set_listen_env(&mut envs, 2, Pid::from_raw(1));
+        assert_eq!(envs, vec!["PATH=/bin", "LISTEN_FDS=2", "LISTEN_PID=1"]);
+
+        let mut envs = vec!["PATH=/bin".to_string()];
+        set_listen_env(&mut envs, 0, Pid::from_raw(1));
+        assert_eq!(envs, vec!["PATH=/bin"]);
+    }
+}
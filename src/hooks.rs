@@ -0,0 +1,222 @@
+//! Runs the hooks of the specification at the points of the container lifecycle they are registered for.
+//! The state of the container is passed to every hook on its stdin
+//! check https://github.com/opencontainers/runtime-spec/blob/master/config.md#posix-platform-hooks for more information
+use std::{
+    io::{ErrorKind, Write},
+    os::unix::process::CommandExt,
+    process::{self, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use nix::{
+    sys::signal::{self, Signal},
+    unistd::Pid,
+};
+use oci_spec::Hook;
+
+use crate::container::State;
+use crate::error::YoukiError;
+
+/// Time to wait between two checks whether a hook with a timeout has exited
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runs the hooks one after another, stopping at the first hook which fails
+pub fn run_hooks(hooks: &[Hook], state: &State) -> Result<()> {
+    if hooks.is_empty() {
+        return Ok(());
+    }
+
+    let state = serde_json::to_vec(state)?;
+    for hook in hooks {
+        log::debug!("run hook {:?}", hook.path);
+        run_hook(hook, &state).with_context(|| format!("failed to run hook {:?}", hook.path))?;
+    }
+    Ok(())
+}
+
+fn run_hook(hook: &Hook, state: &[u8]) -> Result<()> {
+    let mut command = process::Command::new(&hook.path);
+    // args of a hook have the same semantics as execv, so the first one is the name of the program
+    if let Some((arg0, args)) = hook.args.split_first() {
+        command.arg0(arg0).args(args);
+    }
+    command.env_clear();
+    for env in &hook.env {
+        match env.split_once('=') {
+            Some((key, value)) => command.env(key, value),
+            None => bail!("invalid environment variable {:?}", env),
+        };
+    }
+
+    // the hook is the leader of its own process group, so that the processes it has
+    // forked are killed along with it
+    let mut child = command.stdin(Stdio::piped()).process_group(0).spawn()?;
+    let kill = |child: &mut process::Child| {
+        let _ = signal::killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL);
+        let _ = child.wait();
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // a hook is not required to read its stdin
+        if let Err(e) = stdin.write_all(state) {
+            if e.kind() != ErrorKind::BrokenPipe {
+                bail!("failed to pass the state to the hook: {}", e)
+            }
+        }
+    }
+
+    let status = match hook.timeout {
+        Some(timeout) if timeout <= 0 => {
+            kill(&mut child);
+            bail!("timeout of hook must be greater than zero, got {}", timeout)
+        }
+        Some(timeout) => {
+            // Instant is monotonic, so that the deadline is not moved by changes of the system clock
+            let deadline = Instant::now() + Duration::from_secs(timeout as u64);
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+                if Instant::now() >= deadline {
+                    kill(&mut child);
+                    return Err(YoukiError::HookTimeout { timeout }.into());
+                }
+                thread::sleep(HOOK_POLL_INTERVAL);
+            }
+        }
+        None => child.wait()?,
+    };
+
+    if !status.success() {
+        bail!("hook exited with {}", status)
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::ContainerStatus;
+    use crate::utils::create_temp_dir;
+    use std::fs;
+
+    // the hooks are run as shell scripts, so that no executable has to be written by the tests
+    fn shell_hook(script: &str, timeout: Option<i64>) -> Hook {
+        Hook {
+            path: "/bin/sh".into(),
+            args: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            env: vec![],
+            timeout,
+        }
+    }
+
+    #[test]
+    fn test_run_hooks_passes_state() {
+        let tmp = create_temp_dir("test_run_hooks_passes_state").expect("create temp directory");
+        let output = tmp.join("state.json");
+        let hooks = vec![shell_hook(&format!("cat > {:?}", output), None)];
+        let mut state = State::new("hook_test", ContainerStatus::Running, Some(42), "/bundle");
+        state
+            .annotations
+            .insert("org.youki.hook".to_string(), "value".to_string());
+
+        run_hooks(&hooks, &state).expect("run hooks");
+
+        let content = fs::read_to_string(&output).expect("read state written by hook");
+        let received: serde_json::Value = serde_json::from_str(&content).expect("parse state");
+        assert_eq!(received["id"], "hook_test");
+        assert_eq!(received["status"], "running");
+        assert_eq!(received["pid"], 42);
+        assert_eq!(received["bundle"], "/bundle");
+        assert_eq!(received["annotations"]["org.youki.hook"], "value");
+    }
+
+    #[test]
+    fn test_run_hooks_env() {
+        let tmp = create_temp_dir("test_run_hooks_env").expect("create temp directory");
+        let output = tmp.join("env");
+        let mut hook = shell_hook(&format!("echo \"$HOOK_VAR\" > {:?}", output), None);
+        hook.env = vec!["HOOK_VAR=youki".to_string()];
+        let state = State::new("hook_test", ContainerStatus::Created, None, "/bundle");
+
+        run_hooks(&[hook], &state).expect("run hooks");
+
+        let content = fs::read_to_string(&output).expect("read env written by hook");
+        assert_eq!(content.trim(), "youki");
+    }
+
+    #[test]
+    fn test_run_hooks_fails() {
+        let tmp = create_temp_dir("test_run_hooks_fails").expect("create temp directory");
+        let output = tmp.join("second");
+        let hooks = vec![
+            shell_hook("exit 1", None),
+            shell_hook(&format!("touch {:?}", output), None),
+        ];
+        let state = State::new("hook_test", ContainerStatus::Created, None, "/bundle");
+
+        assert!(run_hooks(&hooks, &state).is_err());
+        assert!(
+            !output.exists(),
+            "hooks after a failing hook should not be run"
+        );
+    }
+
+    #[test]
+    fn test_run_hooks_timeout() {
+        let hooks = vec![shell_hook("exec sleep 10", Some(1))];
+        let state = State::new("hook_test", ContainerStatus::Created, None, "/bundle");
+
+        let start = Instant::now();
+        let result = run_hooks(&hooks, &state);
+
+        let err = result.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<YoukiError>(),
+            Some(YoukiError::HookTimeout { timeout: 1 })
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_run_hooks_timeout_kills_process_group() {
+        let tmp = create_temp_dir("test_run_hooks_timeout_kills_process_group")
+            .expect("create temp directory");
+        let pid_file = tmp.join("pid");
+        let hooks = vec![shell_hook(
+            &format!("sleep 10 & echo $! > {:?}; wait", pid_file),
+            Some(1),
+        )];
+        let state = State::new("hook_test", ContainerStatus::Created, None, "/bundle");
+
+        assert!(run_hooks(&hooks, &state).is_err());
+
+        let pid: i32 = fs::read_to_string(&pid_file)
+            .expect("read pid of the forked process")
+            .trim()
+            .parse()
+            .expect("parse pid");
+        // the killed process may not have been reaped by its new parent yet
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let terminated = loop {
+            let terminated = match procfs::process::Process::new(pid) {
+                Ok(process) => process.stat.state == 'Z',
+                Err(_) => true,
+            };
+            if terminated || Instant::now() >= deadline {
+                break terminated;
+            }
+            thread::sleep(HOOK_POLL_INTERVAL);
+        };
+        assert!(terminated, "process {} of the hook is still running", pid);
+    }
+
+    #[test]
+    fn test_run_hooks_exit_is_not_timeout() {
+        let state = State::new("hook_test", ContainerStatus::Created, None, "/bundle");
+
+        let err = run_hooks(&[shell_hook("exit 1", Some(5))], &state).unwrap_err();
+        assert!(err.downcast_ref::<YoukiError>().is_none());
+    }
+}
@@ -2,19 +2,31 @@
 #[macro_use]
 extern crate quickcheck;
 
+pub mod apparmor;
 pub mod capabilities;
 pub mod cgroups;
 pub mod command;
+pub mod commands;
 pub mod cond;
 pub mod container;
 pub mod create;
+pub mod error;
+pub mod exec_fifo;
+pub mod fds;
+pub mod hooks;
 pub mod logger;
 pub mod namespaces;
-pub mod notify_socket;
+pub mod oom;
 pub mod process;
+pub mod rdt;
+pub mod rlimits;
 pub mod rootfs;
+pub mod rootless;
+pub mod seccomp;
+pub mod selinux;
 pub mod signal;
 pub mod start;
 pub mod stdio;
+pub mod sysctl;
 pub mod tty;
 pub mod utils;
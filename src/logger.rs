@@ -1,50 +1,107 @@
 //! Default Youki Logger
 
 use std::env;
+use std::fs::{self, File, OpenOptions};
 use std::io::{stderr, Write};
-use std::path::PathBuf;
-use std::{
-    fs::{File, OpenOptions},
-    str::FromStr,
-};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use log::{LevelFilter, Log, Metadata, Record};
 use once_cell::sync::OnceCell;
 
 pub static YOUKI_LOGGER: OnceCell<YoukiLogger> = OnceCell::new();
-pub static LOG_FILE: OnceCell<Option<File>> = OnceCell::new();
+/// Id of the container the command operates on, which is added to every log record
+pub static CONTAINER_ID: OnceCell<String> = OnceCell::new();
+/// Environment variables, which override the log level of the commandline. YOUKI_LOG_LEVEL
+/// is still accepted, YOUKI_LOG takes precedence if both are set
+const LOG_LEVEL_ENVS: &[&str] = &["YOUKI_LOG", "YOUKI_LOG_LEVEL"];
+const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Warn;
 
-pub fn init(log_file: Option<PathBuf>) -> Result<()> {
-    let _log_file = LOG_FILE.get_or_init(|| -> Option<File> {
-        let level_filter = if let Ok(log_level_str) = env::var("YOUKI_LOG_LEVEL") {
-            LevelFilter::from_str(&log_level_str).unwrap_or(LevelFilter::Warn)
-        } else {
-            LevelFilter::Warn
-        };
-
-        let logger = YOUKI_LOGGER.get_or_init(|| YoukiLogger::new(level_filter.to_level()));
-        log::set_logger(logger)
-            .map(|()| log::set_max_level(level_filter))
-            .expect("set logger failed");
-        log_file.as_ref().map(|log_file_path| {
-            OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(false)
-                .open(log_file_path)
-                .expect("failed opening log file ")
-        })
-    });
+/// Format of the log records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    // a line for humans with the level, the location and the time in front of the message
+    Text,
+    // a json object per line, for supervisors which ingest structured logs
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => bail!("unknown log format {:?}, expected text or json", s),
+        }
+    }
+}
+
+/// Sets the id of the container, which is included in the json log records
+pub fn set_container_id(container_id: &str) {
+    let _ = CONTAINER_ID.set(container_id.to_string());
+}
+
+/// Initializes the logger, which writes to the log file or to stderr if no log file is given.
+/// The log file is truncated, unless the records are appended to it
+pub fn init(
+    log_file: Option<PathBuf>,
+    log_level: Option<&str>,
+    log_format: LogFormat,
+    log_append: bool,
+) -> Result<()> {
+    let env_level = LOG_LEVEL_ENVS.iter().find_map(|env| env::var(env).ok());
+    let level_filter = log_level_filter(env_level.as_deref().or(log_level));
+    let log_file = match log_file {
+        Some(path) => Some(open_log_file(&path, log_append)?),
+        None => None,
+    };
+
+    let logger = YOUKI_LOGGER
+        .get_or_init(|| YoukiLogger::new(level_filter.to_level(), log_format, log_file));
+    log::set_logger(logger)
+        .map(|()| log::set_max_level(level_filter))
+        .context("set logger failed")?;
     Ok(())
 }
+
+fn log_level_filter(log_level: Option<&str>) -> LevelFilter {
+    log_level
+        .and_then(|level| LevelFilter::from_str(level).ok())
+        .unwrap_or(DEFAULT_LOG_LEVEL)
+}
+
+fn open_log_file(path: &Path, append: bool) -> Result<File> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| {
+            format!("failed to create the directory of the log file {:?}", path)
+        })?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .with_context(|| format!("failed to open the log file {:?}", path))
+}
+
 pub struct YoukiLogger {
     level: Option<log::Level>,
+    format: LogFormat,
+    log_file: Option<File>,
 }
 
 impl YoukiLogger {
-    pub fn new(level: Option<log::Level>) -> Self {
-        Self { level }
+    pub fn new(level: Option<log::Level>, format: LogFormat, log_file: Option<File>) -> Self {
+        Self {
+            level,
+            format,
+            log_file,
+        }
     }
 }
 
@@ -59,23 +116,9 @@ impl Log for YoukiLogger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            let log_msg = match (record.file(), record.line()) {
-                (Some(file), Some(line)) => format!(
-                    "[{} {}:{}] {} {}\r",
-                    record.level(),
-                    file,
-                    line,
-                    chrono::Local::now().to_rfc3339(),
-                    record.args()
-                ),
-                (_, _) => format!(
-                    "[{}] {} {}\r",
-                    record.level(),
-                    chrono::Local::now().to_rfc3339(),
-                    record.args()
-                ),
-            };
-            if let Some(mut log_file) = LOG_FILE.get().unwrap().as_ref() {
+            let log_msg =
+                format_record(self.format, record, CONTAINER_ID.get().map(String::as_str));
+            if let Some(mut log_file) = self.log_file.as_ref() {
                 let _ = writeln!(log_file, "{}", log_msg);
             } else {
                 let _ = writeln!(stderr(), "{}", log_msg);
@@ -84,10 +127,165 @@ impl Log for YoukiLogger {
     }
 
     fn flush(&self) {
-        if let Some(mut log_file) = LOG_FILE.get().unwrap().as_ref() {
+        if let Some(mut log_file) = self.log_file.as_ref() {
             log_file.flush().expect("Failed to flush");
         } else {
             stderr().flush().expect("Failed to flush");
         }
     }
 }
+
+fn format_record(format: LogFormat, record: &Record, container_id: Option<&str>) -> String {
+    match format {
+        LogFormat::Text => match (record.file(), record.line()) {
+            (Some(file), Some(line)) => format!(
+                "[{} {}:{}] {} {}\r",
+                record.level(),
+                file,
+                line,
+                chrono::Local::now().to_rfc3339(),
+                record.args()
+            ),
+            (_, _) => format!(
+                "[{}] {} {}\r",
+                record.level(),
+                chrono::Local::now().to_rfc3339(),
+                record.args()
+            ),
+        },
+        LogFormat::Json => {
+            let mut fields = serde_json::Map::new();
+            fields.insert(
+                "level".into(),
+                record.level().as_str().to_lowercase().into(),
+            );
+            fields.insert("timestamp".into(), chrono::Local::now().to_rfc3339().into());
+            fields.insert("message".into(), record.args().to_string().into());
+            if let Some(container_id) = container_id {
+                fields.insert("container_id".into(), container_id.into());
+            }
+            fields.insert("target".into(), record.target().into());
+            if let Some(file) = record.file() {
+                fields.insert("file".into(), file.into());
+            }
+            if let Some(line) = record.line() {
+                fields.insert("line".into(), line.into());
+            }
+            serde_json::Value::Object(fields).to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_temp_dir;
+    use log::Level;
+
+    #[test]
+    fn test_parse_log_format() {
+        assert_eq!("text".parse::<LogFormat>().unwrap(), LogFormat::Text);
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn test_log_level_filter() {
+        assert_eq!(log_level_filter(Some("debug")), LevelFilter::Debug);
+        assert_eq!(log_level_filter(Some("ERROR")), LevelFilter::Error);
+        assert_eq!(log_level_filter(Some("verbose")), DEFAULT_LOG_LEVEL);
+        assert_eq!(log_level_filter(None), DEFAULT_LOG_LEVEL);
+    }
+
+    #[test]
+    fn test_log_to_file() {
+        let tmp = create_temp_dir("test_log_to_file").expect("create temp directory");
+        // the directory of the log file is created if it does not exist
+        let path = tmp.join("logs").join("youki.log");
+        let logger = YoukiLogger::new(
+            Some(Level::Info),
+            LogFormat::Text,
+            Some(open_log_file(&path, false).expect("open log file")),
+        );
+
+        logger.log(
+            &Record::builder()
+                .args(format_args!("written to the log file"))
+                .level(Level::Info)
+                .build(),
+        );
+        logger.log(
+            &Record::builder()
+                .args(format_args!("below the log level"))
+                .level(Level::Debug)
+                .build(),
+        );
+        logger.flush();
+
+        let content = fs::read_to_string(&path).expect("read log file");
+        assert!(content.contains("written to the log file"));
+        assert!(!content.contains("below the log level"));
+    }
+
+    #[test]
+    fn test_open_log_file_append() {
+        let tmp = create_temp_dir("test_open_log_file_append").expect("create temp directory");
+        let path = tmp.join("youki.log");
+        fs::write(&path, "earlier record\n").expect("write log file");
+
+        open_log_file(&path, true)
+            .and_then(|mut f| Ok(f.write_all(b"appended record\n")?))
+            .expect("append to log file");
+        assert_eq!(
+            fs::read_to_string(&path).expect("read log file"),
+            "earlier record\nappended record\n"
+        );
+
+        open_log_file(&path, false).expect("truncate log file");
+        assert_eq!(fs::read_to_string(&path).expect("read log file"), "");
+    }
+
+    #[test]
+    fn test_format_json_record() {
+        let line = format_record(
+            LogFormat::Json,
+            &Record::builder()
+                .args(format_args!("container {} is created", "test"))
+                .level(Level::Info)
+                .target("youki::create")
+                .file(Some("src/create.rs"))
+                .line(Some(42))
+                .build(),
+            Some("test"),
+        );
+
+        assert!(!line.contains('\n'), "a record has to fit on a single line");
+        let record: serde_json::Value = serde_json::from_str(&line).expect("parse log record");
+        assert_eq!(record["level"], "info");
+        assert_eq!(record["message"], "container test is created");
+        assert_eq!(record["container_id"], "test");
+        assert_eq!(record["target"], "youki::create");
+        assert_eq!(record["file"], "src/create.rs");
+        assert_eq!(record["line"], 42);
+        assert!(
+            chrono::DateTime::parse_from_rfc3339(record["timestamp"].as_str().unwrap()).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_format_json_record_without_container() {
+        let line = format_record(
+            LogFormat::Json,
+            &Record::builder()
+                .args(format_args!("list containers"))
+                .level(Level::Debug)
+                .build(),
+            None,
+        );
+
+        let record: serde_json::Value = serde_json::from_str(&line).expect("parse log record");
+        assert_eq!(record["level"], "debug");
+        assert!(record.get("container_id").is_none());
+        assert!(record.get("file").is_none());
+    }
+}
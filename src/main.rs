@@ -3,55 +3,58 @@
 //! This crate provides a container runtime which can be used by a high-level container runtime to run containers.
 
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+use std::process;
 
-use anyhow::{bail, Result};
+use anyhow::{Context, Result};
 use clap::Clap;
-use nix::sys::signal as nix_signal;
 
 use youki::command::linux::LinuxCommand;
-use youki::container::{Container, ContainerStatus};
+use youki::commands::{
+    delete, events, exec, kill, list, pause, ps, resume, run, spec, state, update,
+};
 use youki::create;
-use youki::signal;
+use youki::error;
+use youki::fds;
+use youki::logger::{self, LogFormat};
 use youki::start;
 
-use youki::cgroups;
-use youki::utils;
-
 /// High-level commandline option definition
 /// This takes global options as well as individual commands as specified in [OCI runtime-spec](https://github.com/opencontainers/runtime-spec/blob/master/runtime.md)
 /// Also check [runc commandline documentation](https://github.com/opencontainers/runc/blob/master/man/runc.8.md) for more explanation
 #[derive(Clap, Debug)]
 #[clap(version = "1.0", author = "utam0k <k0ma@utam0k.jp>")]
 struct Opts {
-    /// root directory to store container state
+    /// root directory to store container state, each container has a directory named after its id inside of it
     #[clap(short, long, default_value = "/run/youki")]
     root: PathBuf,
+    /// file to write the logs to instead of stderr, its directory is created if it does not exist
     #[clap(short, long)]
     log: Option<PathBuf>,
+    /// level of the logs, e.g. error, warn, info or debug. YOUKI_LOG takes precedence over it
+    #[clap(long)]
+    log_level: Option<String>,
+    /// append to the log file instead of truncating it
+    #[clap(long)]
+    log_append: bool,
+    /// format of the log records, text or json
+    #[clap(long, default_value = "text")]
+    log_format: LogFormat,
+    /// enable systemd cgroup manager, the cgroups path of the container has the form slice:prefix:name
     #[clap(long)]
-    log_format: Option<String>,
+    systemd_cgroup: bool,
+    /// fail if any cgroup controller, whose resources are restricted, is not mounted, instead of only cpu and memory
+    #[clap(long)]
+    strict_cgroups: bool,
+    /// parent of the cgroups of new containers instead of /youki, which a relative cgroups path is placed below
+    /// and an absolute cgroups path has to be under. Not supported with the systemd cgroup driver
+    #[clap(long)]
+    cgroup_parent: Option<PathBuf>,
     /// command to actually manage container
     #[clap(subcommand)]
     subcmd: SubCommand,
 }
 
-#[derive(Clap, Debug)]
-pub struct Kill {
-    container_id: String,
-    signal: String,
-}
-
-#[derive(Clap, Debug)]
-pub struct Delete {
-    container_id: String,
-}
-
-#[derive(Clap, Debug)]
-pub struct StateArgs {
-    pub container_id: String,
-}
-
 /// Subcommands accepted by Youki, confirming with [OCI runtime-spec](https://github.com/opencontainers/runtime-spec/blob/master/runtime.md)
 /// Also for a short information, check [runc commandline documentation](https://github.com/opencontainers/runc/blob/master/man/runc.8.md)
 #[derive(Clap, Debug)]
@@ -60,107 +63,132 @@ enum SubCommand {
     Create(create::Create),
     #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
     Start(start::Start),
+    /// Create and start a container, and wait for it to exit unless it is detached
+    #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
+    Run(run::Run),
+    #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
+    Kill(kill::Kill),
+    #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
+    Delete(delete::Delete),
+    #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
+    State(state::State),
+    #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
+    List(list::List),
+    #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
+    Events(events::Events),
+    #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
+    Exec(exec::Exec),
     #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
-    Kill(Kill),
+    Ps(ps::Ps),
     #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
-    Delete(Delete),
+    Pause(pause::Pause),
     #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
-    State(StateArgs),
+    Resume(resume::Resume),
+    #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
+    Update(update::Update),
+    #[clap(version = "0.0.1", author = "utam0k <k0ma@utam0k.jp>")]
+    Spec(spec::SpecArgs),
+}
+
+impl SubCommand {
+    /// Returns the id of the container the subcommand operates on
+    fn container_id(&self) -> Option<&str> {
+        match self {
+            SubCommand::Create(create) => Some(&create.container_id),
+            SubCommand::Start(start) => Some(&start.container_id),
+            SubCommand::Run(run) => Some(&run.create.container_id),
+            SubCommand::Kill(kill) => Some(&kill.container_id),
+            SubCommand::Delete(delete) => Some(&delete.container_id),
+            SubCommand::State(state) => Some(&state.container_id),
+            SubCommand::Events(events) => Some(&events.container_id),
+            SubCommand::Exec(exec) => Some(&exec.container_id),
+            SubCommand::Ps(ps) => Some(&ps.container_id),
+            SubCommand::Pause(pause) => Some(&pause.container_id),
+            SubCommand::Resume(resume) => Some(&resume.container_id),
+            SubCommand::Update(update) => Some(&update.container_id),
+            SubCommand::List(_) | SubCommand::Spec(_) => None,
+        }
+    }
+
+    /// Returns the number of fds to pass to the process of the container, if the subcommand starts one
+    fn preserve_fds_mut(&mut self) -> Option<&mut u32> {
+        match self {
+            SubCommand::Create(create) => Some(&mut create.preserve_fds),
+            SubCommand::Run(run) => Some(&mut run.create.preserve_fds),
+            SubCommand::Exec(exec) => Some(&mut exec.preserve_fds),
+            _ => None,
+        }
+    }
 }
 
 /// This is the entry point in the container runtime. The binary is run by a high-level container runtime,
 /// with various flags passed. This parses the flags, creates and manages appropriate resources.
-fn main() -> Result<()> {
-    let opts = Opts::parse();
+fn main() {
+    // like runc, youki exits with 125 if it is invoked wrongly
+    let opts = Opts::try_parse().unwrap_or_else(|e| {
+        if !e.use_stderr() {
+            e.exit()
+        }
+        eprintln!("{}", e);
+        process::exit(error::EXIT_USAGE)
+    });
+    // the error is printed like an error returned from main, but youki exits with the code of the error
+    if let Err(e) = run(opts) {
+        eprintln!("Error: {:?}", e);
+        process::exit(error::exit_code(&e));
+    }
+}
 
-    if let Err(e) = youki::logger::init(opts.log) {
+fn run(mut opts: Opts) -> Result<()> {
+    // the preserved fds are moved together before youki opens any fd itself, e.g. the log file,
+    // which could take one of the gaps between them otherwise. Only these fds are passed on
+    if let Some(preserve_fds) = opts.subcmd.preserve_fds_mut() {
+        *preserve_fds = fds::preserve_fds(*preserve_fds)?;
+    }
+    if let Err(e) = logger::init(
+        opts.log,
+        opts.log_level.as_deref(),
+        opts.log_format,
+        opts.log_append,
+    ) {
         eprintln!("log init failed: {:?}", e);
     }
+    if let Some(container_id) = opts.subcmd.container_id() {
+        logger::set_container_id(container_id);
+    }
 
-    let root_path = PathBuf::from(&opts.root);
-    fs::create_dir_all(&root_path)?;
+    let root_path = opts.root;
+    fs::create_dir_all(&root_path)
+        .with_context(|| format!("failed to create the root directory {:?}", root_path))?;
+    // create changes into the bundle directory, so a relative root has to be resolved beforehand
+    let root_path = fs::canonicalize(root_path)?;
 
     match opts.subcmd {
-        SubCommand::Create(create) => create.exec(root_path, LinuxCommand),
+        SubCommand::Create(create) => create.exec(
+            root_path,
+            opts.systemd_cgroup,
+            opts.strict_cgroups,
+            opts.cgroup_parent,
+            LinuxCommand,
+        ),
         SubCommand::Start(start) => start.exec(root_path),
-        SubCommand::Kill(kill) => {
-            // resolves relative paths, symbolic links etc. and get complete path
-            let root_path = fs::canonicalize(root_path)?;
-            // state of container is stored in a directory named as container id inside
-            // root directory given in commandline options
-            let container_root = root_path.join(&kill.container_id);
-            if !container_root.exists() {
-                bail!("{} doesn't exist.", kill.container_id)
-            }
-
-            // load container state from json file, and check status of the container
-            // it might be possible that kill is invoked on a already stopped container etc.
-            let container = Container::load(container_root)?.refresh_status()?;
-            if container.can_kill() {
-                let sig = signal::from_str(kill.signal.as_str())?;
-                log::debug!("kill signal {} to {}", sig, container.pid().unwrap());
-                nix_signal::kill(container.pid().unwrap(), sig)?;
-                container.update_status(ContainerStatus::Stopped)?.save()?;
-                std::process::exit(0)
-            } else {
-                bail!(
-                    "{} could not be killed because it was {:?}",
-                    container.id(),
-                    container.status()
-                )
-            }
-        }
-        SubCommand::Delete(delete) => {
-            log::debug!("start deleting {}", delete.container_id);
-            // state of container is stored in a directory named as container id inside
-            // root directory given in commandline options
-            let container_root = root_path.join(&delete.container_id);
-            if !container_root.exists() {
-                bail!("{} doesn't exist.", delete.container_id)
-            }
-            // load container state from json file, and check status of the container
-            // it might be possible that delete is invoked on a running container.
-            log::debug!("load the container from {:?}", container_root);
-            let container = Container::load(container_root)?.refresh_status()?;
-            if container.can_delete() {
-                if container.root.exists() {
-                    nix::unistd::chdir(&PathBuf::from(&container.state.bundle))?;
-                    let config_absolute_path = &PathBuf::from(&container.state.bundle)
-                        .join(Path::new("config.json"))
-                        .to_string_lossy()
-                        .to_string();
-                    log::debug!("load spec from {:?}", config_absolute_path);
-                    let spec = oci_spec::Spec::load(config_absolute_path)?;
-                    log::debug!("spec: {:?}", spec);
-
-                    // remove the directory storing container state
-                    log::debug!("remove dir {:?}", container.root);
-                    fs::remove_dir_all(&container.root)?;
-
-                    let cgroups_path =
-                        utils::get_cgroup_path(&spec.linux.unwrap().cgroups_path, container.id());
-
-                    // remove the cgroup created for the container
-                    // check https://man7.org/linux/man-pages/man7/cgroups.7.html
-                    // creating and removing cgroups section for more information on cgroups
-                    let cmanager = cgroups::common::create_cgroup_manager(cgroups_path)?;
-                    cmanager.remove()?;
-                }
-                std::process::exit(0)
-            } else {
-                bail!(
-                    "{} could not be deleted because it was {:?}",
-                    container.id(),
-                    container.status()
-                )
-            }
-        }
-        SubCommand::State(state_args) => {
-            let root_path = fs::canonicalize(root_path)?;
-            let container_root = root_path.join(state_args.container_id);
-            let container = Container::load(container_root)?.refresh_status()?;
-            println!("{}", serde_json::to_string_pretty(&container.state)?);
-            std::process::exit(0);
-        }
+        SubCommand::Run(run) => run.exec(
+            root_path,
+            opts.systemd_cgroup,
+            opts.strict_cgroups,
+            opts.cgroup_parent,
+            LinuxCommand,
+        ),
+        SubCommand::Kill(kill) => kill.exec(root_path),
+        SubCommand::Delete(delete) => delete.exec(root_path),
+        SubCommand::List(list) => list.exec(root_path),
+        SubCommand::Events(events) => events.exec(root_path),
+        SubCommand::Exec(exec) => exec.exec(root_path),
+        SubCommand::Ps(ps) => ps.exec(root_path),
+        SubCommand::Pause(pause) => pause.exec(root_path),
+        SubCommand::Resume(resume) => resume.exec(root_path),
+        SubCommand::Update(update) => update.exec(root_path, opts.strict_cgroups),
+        SubCommand::Spec(spec) => spec.exec(),
+        SubCommand::State(state) => state.exec(root_path),
     }
 }
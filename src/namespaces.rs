@@ -3,16 +3,16 @@
 //! Process (processes in a namespace have two PIDs, one for the global PID,
 //! which is used by the main system and the second one is for the child within the process tree),
 //! Interprocess Communication (Control or communication between processes),
-//! Network (which network devices can be seen by the processes in the namespace), User (User configs), 
+//! Network (which network devices can be seen by the processes in the namespace), User (User configs),
 //! UTS (hostname and domain information, processes will think they're running on servers with different names),
 //! Cgroup (Resource limits, execution priority etc.)
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use nix::{
     fcntl,
     sched::{self, CloneFlags},
     sys::stat,
-    unistd::{self, Gid, Uid},
+    unistd::{self, Gid, Pid, Uid},
 };
 
 use crate::command::{linux::LinuxCommand, test::TestHelperCommand, Command};
@@ -49,21 +49,15 @@ impl From<Vec<LinuxNamespace>> for Namespaces {
 
 impl Namespaces {
     pub fn apply_setns(&self) -> Result<()> {
-        let to_enter: Vec<(CloneFlags, i32)> = self
-            .spaces
-            .iter()
-            .filter(|ns| ns.path.is_some())
-            .map(|ns| {
-                let space = CloneFlags::from_bits_truncate(ns.typ as i32);
-                let fd = fcntl::open(
-                    &*ns.path.as_ref().unwrap().clone(),
-                    fcntl::OFlag::empty(),
-                    stat::Mode::empty(),
-                )
-                .unwrap();
-                (space, fd)
-            })
-            .collect();
+        let mut to_enter = Vec::new();
+        for ns in setns_order(&self.spaces) {
+            let path = ns.path.as_ref().unwrap();
+            let space = CloneFlags::from_bits_truncate(ns.typ as i32);
+            let fd = fcntl::open(path.as_str(), fcntl::OFlag::empty(), stat::Mode::empty())
+                .with_context(|| format!("failed to open namespace {:?}", path))?;
+            to_enter.push((space, fd));
+        }
+
         for &(space, fd) in &to_enter {
             self.command.set_ns(fd, space)?;
             unistd::close(fd)?;
@@ -80,10 +74,49 @@ impl Namespaces {
     }
 }
 
+/// Returns the namespaces of the given process for each of the given types, so that another
+/// process can join the namespaces of a running container, e.g. for exec
+pub fn namespaces_of(pid: Pid, namespaces: &[LinuxNamespace]) -> Vec<LinuxNamespace> {
+    namespaces
+        .iter()
+        .map(|ns| LinuxNamespace {
+            typ: ns.typ,
+            path: Some(format!("/proc/{}/ns/{}", pid, proc_ns_name(ns.typ))),
+        })
+        .collect()
+}
+
+/// Name of the file of the namespace in /proc/<pid>/ns
+fn proc_ns_name(typ: LinuxNamespaceType) -> &'static str {
+    match typ {
+        LinuxNamespaceType::Mount => "mnt",
+        LinuxNamespaceType::Cgroup => "cgroup",
+        LinuxNamespaceType::Uts => "uts",
+        LinuxNamespaceType::Ipc => "ipc",
+        LinuxNamespaceType::User => "user",
+        LinuxNamespaceType::Pid => "pid",
+        LinuxNamespaceType::Network => "net",
+    }
+}
+
+/// Returns the namespaces which are joined by their path: the user namespace first, as joining
+/// the others requires CAP_SYS_ADMIN in it, then the mount namespace and the rest in spec order
+fn setns_order(namespaces: &[LinuxNamespace]) -> Vec<&LinuxNamespace> {
+    let mut to_enter: Vec<&LinuxNamespace> =
+        namespaces.iter().filter(|ns| ns.path.is_some()).collect();
+    // sort_by_key is stable, so the remaining namespaces are entered in the order of the spec
+    to_enter.sort_by_key(|ns| match ns.typ {
+        LinuxNamespaceType::User => 0,
+        LinuxNamespaceType::Mount => 1,
+        _ => 2,
+    });
+    to_enter
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
 
-    #[allow(dead_code)]
     fn gen_sample_linux_namespaces() -> Vec<LinuxNamespace> {
         vec![
             LinuxNamespace {
@@ -140,4 +173,121 @@ mod tests {
         expect.sort();
         assert_eq!(unshare_args, expect)
     }
+
+    fn namespace(typ: LinuxNamespaceType, path: Option<&str>) -> LinuxNamespace {
+        LinuxNamespace {
+            typ,
+            path: path.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_namespaces_clone_flags() {
+        let namespaces: Namespaces = gen_sample_linux_namespaces().into();
+
+        // namespaces with a path are joined instead of created
+        assert_eq!(
+            namespaces.clone_flags,
+            CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWIPC
+        );
+
+        let namespaces: Namespaces = vec![].into();
+        assert_eq!(namespaces.clone_flags, CloneFlags::empty());
+    }
+
+    #[test]
+    fn test_namespaces_set_ns_order() {
+        let namespaces: Namespaces = vec![
+            namespace(LinuxNamespaceType::Network, Some("/dev/null")),
+            namespace(LinuxNamespaceType::Pid, None),
+            namespace(LinuxNamespaceType::Ipc, Some("/dev/null")),
+            namespace(LinuxNamespaceType::Mount, Some("/dev/null")),
+            namespace(LinuxNamespaceType::User, Some("/dev/null")),
+        ]
+        .into();
+        let test_command: &TestHelperCommand = namespaces.command.as_any().downcast_ref().unwrap();
+        assert!(namespaces.apply_setns().is_ok());
+
+        let setns_args: Vec<_> = test_command
+            .get_setns_args()
+            .into_iter()
+            .map(|(_fd, cf)| cf)
+            .collect();
+        assert_eq!(
+            setns_args,
+            vec![
+                CloneFlags::CLONE_NEWUSER,
+                CloneFlags::CLONE_NEWNS,
+                CloneFlags::CLONE_NEWNET,
+                CloneFlags::CLONE_NEWIPC,
+            ]
+        );
+        // the process becomes root in the joined user namespace
+        assert_eq!(
+            test_command.get_set_id_args(),
+            vec![(Uid::from_raw(0), Gid::from_raw(0))]
+        );
+    }
+
+    #[test]
+    fn test_namespaces_set_ns_missing_path() {
+        let namespaces: Namespaces = vec![namespace(
+            LinuxNamespaceType::Network,
+            Some("/proc/missing/ns/net"),
+        )]
+        .into();
+
+        assert!(namespaces.apply_setns().is_err());
+    }
+
+    #[test]
+    fn test_namespaces_of() {
+        let namespaces = namespaces_of(
+            Pid::from_raw(4242),
+            &[
+                namespace(LinuxNamespaceType::Pid, None),
+                namespace(LinuxNamespaceType::Network, Some("/var/run/netns/youki")),
+                namespace(LinuxNamespaceType::Mount, None),
+                namespace(LinuxNamespaceType::User, None),
+            ],
+        );
+
+        let paths: Vec<_> = namespaces
+            .iter()
+            .map(|ns| {
+                (
+                    CloneFlags::from_bits_truncate(ns.typ as i32),
+                    ns.path.as_deref(),
+                )
+            })
+            .collect();
+        // a namespace joined by the container is joined through the container process as well
+        assert_eq!(
+            paths,
+            vec![
+                (CloneFlags::CLONE_NEWPID, Some("/proc/4242/ns/pid")),
+                (CloneFlags::CLONE_NEWNET, Some("/proc/4242/ns/net")),
+                (CloneFlags::CLONE_NEWNS, Some("/proc/4242/ns/mnt")),
+                (CloneFlags::CLONE_NEWUSER, Some("/proc/4242/ns/user")),
+            ]
+        );
+
+        // every namespace is joined, so that none is created
+        let namespaces: Namespaces = namespaces.into();
+        assert_eq!(namespaces.clone_flags, CloneFlags::empty());
+    }
+
+    #[test]
+    fn test_proc_ns_name() {
+        use LinuxNamespaceType::*;
+        for typ in [Mount, Cgroup, Uts, Ipc, User, Pid, Network].iter() {
+            let name = proc_ns_name(*typ);
+            // the files are named like the namespaces in /proc/self/ns of the test process
+            assert!(
+                std::path::Path::new("/proc/self/ns").join(name).exists(),
+                "{:?} is not a namespace file",
+                name
+            );
+        }
+    }
 }
@@ -0,0 +1,92 @@
+//! Adjustment of the score, by which the kernel picks the process to kill when the memory runs out
+//! check https://man7.org/linux/man-pages/man5/proc.5.html for more information
+use std::{fs, io, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+const OOM_SCORE_ADJ: &str = "/proc/self/oom_score_adj";
+const OOM_SCORE_ADJ_MIN: i32 = -1000;
+const OOM_SCORE_ADJ_MAX: i32 = 1000;
+
+/// Sets the oom score adjustment of the current process, which is inherited by its children
+pub fn set_oom_score_adj(adj: i32) -> Result<()> {
+    write_oom_score_adj(Path::new(OOM_SCORE_ADJ), adj)
+}
+
+/// Checks that the adjustment is in the range accepted by the kernel
+pub fn validate_oom_score_adj(adj: i32) -> Result<()> {
+    if !(OOM_SCORE_ADJ_MIN..=OOM_SCORE_ADJ_MAX).contains(&adj) {
+        bail!(
+            "oom score adjustment {} is not between {} and {}",
+            adj,
+            OOM_SCORE_ADJ_MIN,
+            OOM_SCORE_ADJ_MAX
+        )
+    }
+    Ok(())
+}
+
+fn write_oom_score_adj(path: &Path, adj: i32) -> Result<()> {
+    validate_oom_score_adj(adj)?;
+    match fs::write(path, adj.to_string()) {
+        // the kernel only allows to lower the adjustment below its lowest value so far with CAP_SYS_RESOURCE
+        // in the initial user namespace, which an unprivileged user or a rootless container does not have
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            let current = fs::read_to_string(path).unwrap_or_default();
+            bail!(
+                "failed to lower the oom score adjustment to {} from {}: this requires CAP_SYS_RESOURCE, \
+                 which an unprivileged or rootless container does not have. Use a value of at least {} \
+                 or run the container as root",
+                adj,
+                current.trim(),
+                current.trim()
+            )
+        }
+        result => {
+            result.with_context(|| format!("failed to set the oom score adjustment to {}", adj))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_temp_dir;
+
+    #[test]
+    fn test_validate_oom_score_adj() {
+        for adj in &[-1000, -1, 0, 500, 1000] {
+            assert!(
+                validate_oom_score_adj(*adj).is_ok(),
+                "{} should be accepted",
+                adj
+            );
+        }
+        for adj in &[-1001, 1001, i32::MIN, i32::MAX] {
+            assert!(
+                validate_oom_score_adj(*adj).is_err(),
+                "{} should be rejected",
+                adj
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_oom_score_adj() {
+        let tmp = create_temp_dir("test_write_oom_score_adj").expect("create temp directory");
+        let path = tmp.join("oom_score_adj");
+
+        write_oom_score_adj(&path, 300).expect("write oom score adjustment");
+        assert_eq!(
+            fs::read_to_string(&path).expect("read oom score adjustment"),
+            "300"
+        );
+
+        // an invalid adjustment is not written at all
+        assert!(write_oom_score_adj(&path, 1001).is_err());
+        assert_eq!(
+            fs::read_to_string(&path).expect("read oom score adjustment"),
+            "300"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Synchronization channel between the youki process and the processes it forks to create a container.
+//! The messages are sent over a unix socket pair, so that either side notices when the other one has exited
+
+use std::{
+    io::{Cursor, ErrorKind, Read, Write},
+    os::unix::net::UnixStream,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::error::{self, YoukiError};
+
+const CHILD_READY: u8 = 0x00;
+const MAPPING_WRITTEN: u8 = 0x01;
+const INIT_COMPLETE: u8 = 0x02;
+const PROCESS_PID: u8 = 0x03;
+const ERROR: u8 = 0xff;
+
+/// Messages which are exchanged while the container is set up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelMessage {
+    // the child has created its user namespace and waits for the id mappings
+    ChildReady,
+    // the parent has written the id mappings of the user namespace of the child
+    MappingWritten,
+    // the init process has finished the setup of the container
+    InitComplete,
+    // pid of a process which has been forked into the pid namespace of the container
+    ProcessPid(i32),
+    // the setup failed on the sending side, with the exit code of youki for the error
+    Error { msg: String, exit_code: i32 },
+}
+
+impl ChannelMessage {
+    /// Reports an error to the other side, which returns it with the same exit code
+    pub fn error(err: &anyhow::Error) -> Self {
+        Self::Error {
+            msg: format!("{:?}", err),
+            exit_code: error::exit_code(err),
+        }
+    }
+
+    /// Encodes the message as a tag byte, followed by a pid or the exit code, the length and the bytes
+    /// of the text of an error
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::ChildReady => vec![CHILD_READY],
+            Self::MappingWritten => vec![MAPPING_WRITTEN],
+            Self::InitComplete => vec![INIT_COMPLETE],
+            Self::ProcessPid(pid) => {
+                let mut buf = vec![PROCESS_PID];
+                buf.extend_from_slice(&pid.to_be_bytes());
+                buf
+            }
+            Self::Error { msg, exit_code } => {
+                let mut buf = vec![ERROR];
+                buf.extend_from_slice(&exit_code.to_be_bytes());
+                buf.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+                buf.extend_from_slice(msg.as_bytes());
+                buf
+            }
+        }
+    }
+
+    fn decode(reader: &mut impl Read) -> Result<Self> {
+        let mut tag = [0u8; 1];
+        match reader.read_exact(&mut tag) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                bail!("the other side of the channel has been closed")
+            }
+            result => result.context("failed to receive a message")?,
+        }
+
+        Ok(match tag[0] {
+            CHILD_READY => Self::ChildReady,
+            MAPPING_WRITTEN => Self::MappingWritten,
+            INIT_COMPLETE => Self::InitComplete,
+            PROCESS_PID => {
+                let mut pid = [0u8; 4];
+                reader
+                    .read_exact(&mut pid)
+                    .context("failed to receive a pid")?;
+                Self::ProcessPid(i32::from_be_bytes(pid))
+            }
+            ERROR => {
+                let mut exit_code = [0u8; 4];
+                reader
+                    .read_exact(&mut exit_code)
+                    .context("failed to receive the exit code of an error")?;
+                let mut len = [0u8; 4];
+                reader
+                    .read_exact(&mut len)
+                    .context("failed to receive the length of an error")?;
+                let mut msg = vec![0u8; u32::from_be_bytes(len) as usize];
+                reader
+                    .read_exact(&mut msg)
+                    .context("failed to receive an error")?;
+                Self::Error {
+                    msg: String::from_utf8_lossy(&msg).to_string(),
+                    exit_code: i32::from_be_bytes(exit_code),
+                }
+            }
+            tag => bail!("received unknown message {:#04x}", tag),
+        })
+    }
+}
+
+/// One end of the channel
+pub struct Channel {
+    stream: UnixStream,
+}
+
+/// Creates both ends of a channel. After fork, each process has to drop the end it does not use,
+/// otherwise it does not notice when the other process exits
+pub fn channel() -> Result<(Channel, Channel)> {
+    // the sockets are created with close-on-exec, so that the container process does not inherit them
+    let (first, second) = UnixStream::pair().context("failed to create the socket pair")?;
+    Ok((Channel { stream: first }, Channel { stream: second }))
+}
+
+impl Channel {
+    pub fn send(&mut self, msg: &ChannelMessage) -> Result<()> {
+        log::debug!("send {:?}", msg);
+        self.stream
+            .write_all(&msg.encode())
+            .with_context(|| format!("failed to send {:?}", msg))
+    }
+
+    pub fn recv(&mut self) -> Result<ChannelMessage> {
+        ChannelMessage::decode(&mut self.stream)
+    }
+
+    /// Waits for the expected message. An error sent by the other side is returned as an error
+    pub fn expect(&mut self, expected: ChannelMessage) -> Result<()> {
+        match self.recv()? {
+            msg if msg == expected => Ok(()),
+            ChannelMessage::Error { msg, exit_code } => {
+                Err(YoukiError::Process { msg, exit_code }.into())
+            }
+            msg => bail!(
+                "received unexpected message {:?}, expected {:?}",
+                msg,
+                expected
+            ),
+        }
+    }
+
+    /// Waits until the other side closes the channel. As the sockets are close-on-exec, this is
+    /// how a process reports that it has executed its program. An error sent before is returned as an error
+    pub fn wait_for_close(&mut self) -> Result<()> {
+        let mut buf = Vec::new();
+        self.stream
+            .read_to_end(&mut buf)
+            .context("failed to wait for the channel to be closed")?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        match ChannelMessage::decode(&mut Cursor::new(buf))? {
+            ChannelMessage::Error { msg, exit_code } => {
+                Err(YoukiError::Process { msg, exit_code }.into())
+            }
+            msg => bail!(
+                "received unexpected message {:?}, expected the channel to be closed",
+                msg
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fork::clone_child;
+    use nix::sched::CloneFlags;
+    use nix::sys::wait::waitpid;
+
+    #[test]
+    fn test_message_round_trip() {
+        let messages = vec![
+            ChannelMessage::ChildReady,
+            ChannelMessage::MappingWritten,
+            ChannelMessage::InitComplete,
+            ChannelMessage::ProcessPid(4242),
+            ChannelMessage::Error {
+                msg: "failed to mount /proc: EPERM".to_string(),
+                exit_code: 1,
+            },
+            ChannelMessage::Error {
+                msg: String::new(),
+                exit_code: -1,
+            },
+        ];
+
+        let encoded: Vec<u8> = messages.iter().flat_map(|msg| msg.encode()).collect();
+        let mut reader = Cursor::new(encoded);
+        for msg in messages {
+            assert_eq!(
+                ChannelMessage::decode(&mut reader).expect("decode message"),
+                msg
+            );
+        }
+        assert!(ChannelMessage::decode(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_decode_unknown_message() {
+        assert!(ChannelMessage::decode(&mut Cursor::new(vec![0x42])).is_err());
+        // an error whose text has been cut off
+        assert!(ChannelMessage::decode(&mut Cursor::new(vec![
+            ERROR, 0, 0, 0, 1, 0, 0, 0, 8, b'a'
+        ]))
+        .is_err());
+        assert!(ChannelMessage::decode(&mut Cursor::new(vec![PROCESS_PID, 0, 1])).is_err());
+    }
+
+    #[test]
+    fn test_channel() {
+        let (mut parent, mut child) = channel().expect("create channel");
+
+        child
+            .send(&ChannelMessage::ChildReady)
+            .expect("send message");
+        parent
+            .expect(ChannelMessage::ChildReady)
+            .expect("receive message");
+        parent
+            .send(&ChannelMessage::error(&anyhow::anyhow!("no mapping")))
+            .expect("send error");
+
+        let err = child.expect(ChannelMessage::MappingWritten).unwrap_err();
+        assert_eq!(err.to_string(), "no mapping");
+    }
+
+    #[test]
+    fn test_error_of_child_process() {
+        let (mut parent, mut child) = channel().expect("create channel");
+
+        let pid = clone_child(CloneFlags::empty(), || {
+            let err = anyhow::anyhow!("executable \"missing\" not found in /bin")
+                .context("failed to set up the container process");
+            let _ = child.send(&ChannelMessage::error(&err));
+            1
+        })
+        .expect("clone child");
+
+        let result = parent.expect(ChannelMessage::InitComplete);
+        let _ = waitpid(pid, None);
+        let err = result.expect_err("error of the child is surfaced");
+        assert!(err
+            .to_string()
+            .contains("failed to set up the container process"));
+        assert!(err.to_string().contains("not found in /bin"));
+    }
+
+    #[test]
+    fn test_exit_code_of_child_error() {
+        // the init process of create does not find the program of the container
+        let (mut parent, mut child) = channel().expect("create channel");
+        let err = anyhow::Error::from(YoukiError::CommandNotFound {
+            cmd: "missing".to_string(),
+            reason: "executable file not found in $PATH".to_string(),
+        })
+        .context("failed to prepare the container process");
+        child
+            .send(&ChannelMessage::error(&err))
+            .expect("send error");
+
+        let err = parent
+            .expect(ChannelMessage::InitComplete)
+            .context("failed to set up the container process")
+            .unwrap_err();
+        assert_eq!(error::exit_code(&err), error::EXIT_NOT_FOUND);
+        assert!(format!("{:?}", err).contains("executable file not found in $PATH"));
+    }
+
+    #[test]
+    fn test_channel_closed() {
+        let (parent, mut child) = channel().expect("create channel");
+        drop(parent);
+
+        assert!(child.recv().is_err());
+    }
+
+    #[test]
+    fn test_wait_for_close() {
+        let (mut parent, child) = channel().expect("create channel");
+        drop(child);
+        assert!(parent.wait_for_close().is_ok());
+
+        let (mut parent, mut child) = channel().expect("create channel");
+        child
+            .send(&ChannelMessage::error(&anyhow::anyhow!("exec failed")))
+            .expect("send error");
+        drop(child);
+        let err = parent.wait_for_close().unwrap_err();
+        assert_eq!(err.to_string(), "exec failed");
+
+        let (mut parent, mut child) = channel().expect("create channel");
+        child
+            .send(&ChannelMessage::InitComplete)
+            .expect("send message");
+        drop(child);
+        assert!(parent.wait_for_close().is_err());
+    }
+}
@@ -10,6 +10,7 @@ use mio::{Events, Interest, Poll, Token};
 use nix::unistd::Pid;
 
 use super::{MAX_EVENTS, WAIT_FOR_INIT};
+use crate::process::channel::Channel;
 use crate::process::message::Message;
 
 // Token is used to identify which socket generated an event
@@ -21,6 +22,8 @@ pub struct ChildProcess {
     sender_for_parent: Sender,
     receiver: Option<Receiver>,
     poll: Option<Poll>,
+    // channel to the parent process, which is handed over to the init process
+    channel: Option<Channel>,
 }
 
 // Note : The original youki process first forks into 'parent' (P) and 'child' (C1) process
@@ -29,14 +32,20 @@ pub struct ChildProcess {
 // a process point of view, init process is child of child process, which is child of original youki process.
 impl ChildProcess {
     /// create a new Child process structure
-    pub fn new(sender_for_parent: Sender) -> Result<Self> {
+    pub fn new(sender_for_parent: Sender, channel: Channel) -> Result<Self> {
         Ok(Self {
             sender_for_parent,
             receiver: None,
             poll: None,
+            channel: Some(channel),
         })
     }
 
+    /// Takes the channel to the parent process, so that it can be passed to the init process
+    pub fn take_channel(&mut self) -> Option<Channel> {
+        self.channel.take()
+    }
+
     /// sets up sockets for init process
     pub fn setup_pipe(&mut self) -> Result<Sender> {
         // create a new pipe
@@ -1,38 +1,38 @@
-use std::fs;
-use std::io::prelude::*;
 use std::path::Path;
 use std::process::exit;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 
-use anyhow::bail;
 use child::ChildProcess;
 use init::InitProcess;
-use nix::sched;
-use nix::sys::wait::{waitpid, WaitStatus};
+use nix::sched::{self, CloneFlags};
+use nix::sys::signal::Signal;
 use nix::unistd;
 use nix::unistd::Pid;
 
 use crate::cgroups::common::CgroupManager;
+use crate::container::Container;
 use crate::container::ContainerStatus;
+use crate::error::YoukiError;
+use crate::process::channel::{self, ChannelMessage};
 use crate::process::{child, init, parent, Process};
-use crate::{cond::Cond, container::Container};
+use crate::{hooks, oom, rdt, rootless, utils};
 
 /// Function to perform the first fork for in order to run the container process
 pub fn fork_first<P: AsRef<Path>>(
     pid_file: Option<P>,
     is_userns: bool,
     linux: &oci_spec::Linux,
+    hooks: Option<&oci_spec::Hooks>,
     container: &Container,
     cmanager: Box<dyn CgroupManager>,
 ) -> Result<Process> {
-    // create a new pipe
-    let ccond = Cond::new()?;
+    // the id mappings of the user namespace of the child can only be written by the parent,
+    // after the child has created it
+    let (mut parent_channel, mut child_channel) = channel::channel()?;
 
     // create new parent process structure
     let (mut parent, sender_for_parent) = parent::ParentProcess::new()?;
-    // create a new child process structure with sending end of parent process
-    let child = child::ChildProcess::new(sender_for_parent)?;
 
     unsafe {
         // fork the process
@@ -44,8 +44,7 @@ pub fn fork_first<P: AsRef<Path>>(
                 // check https://dev.to/rrampage/surviving-the-linux-oom-killer-2ki9 for some more information
                 if let Some(ref r) = linux.resources {
                     if let Some(adj) = r.oom_score_adj {
-                        let mut f = fs::File::create("/proc/self/oom_score_adj")?;
-                        f.write_all(adj.to_string().as_bytes())?;
+                        oom::set_oom_score_adj(adj)?;
                     }
                 }
 
@@ -56,26 +55,54 @@ pub fn fork_first<P: AsRef<Path>>(
                     sched::unshare(sched::CloneFlags::CLONE_NEWUSER)?;
                 }
 
-                ccond.notify()?;
+                drop(parent_channel);
+                child_channel.send(&ChannelMessage::ChildReady)?;
+                // the ids of the new user namespace can only be mapped from the outside
+                child_channel.expect(ChannelMessage::MappingWritten)?;
+                // create a new child process structure with sending end of parent process
+                let child = child::ChildProcess::new(sender_for_parent, child_channel)?;
                 Ok(Process::Child(child))
             }
             // in the parent process
             unistd::ForkResult::Parent { child } => {
-                ccond.wait()?;
+                drop(child_channel);
+                parent_channel.expect(ChannelMessage::ChildReady)?;
+                if is_userns {
+                    if let Err(e) = rootless::write_id_mappings(child, linux) {
+                        // the child must not continue without the mappings
+                        parent_channel.send(&ChannelMessage::error(&e))?;
+                        return Err(e.context(YoukiError::NamespaceSetup));
+                    }
+                }
+                parent_channel.send(&ChannelMessage::MappingWritten)?;
 
                 // wait for child to fork init process and report back its pid
                 let init_pid = parent.wait_for_child_ready()?;
                 log::debug!("init pid is {:?}", init_pid);
-                cmanager.apply(&linux.resources.as_ref().unwrap(), Pid::from_raw(init_pid))?;
+                cmanager.add_task(Pid::from_raw(init_pid))?;
+                cmanager.apply(linux.resources.as_ref().unwrap())?;
+                if let Some(intel_rdt) = &linux.intel_rdt {
+                    rdt::apply(intel_rdt, container.id(), init_pid)?;
+                }
+
+                let container = container.set_pid(init_pid);
+                if let Some(hooks) = hooks {
+                    // prestart hooks are deprecated in favor of create runtime hooks, which are run at the same time
+                    hooks::run_hooks(&hooks.prestart, &container.state)?;
+                    hooks::run_hooks(&hooks.create_runtime, &container.state)?;
+                }
+
+                // the init process reports whether it could set up the container, an error of the init
+                // process is returned from create. If it exits without a report, the channel is closed
+                parent_channel
+                    .expect(ChannelMessage::InitComplete)
+                    .context("failed to set up the container process")?;
 
                 // update status and pid of the container process
-                container
-                    .update_status(ContainerStatus::Created)?
-                    .set_pid(init_pid)
-                    .save()?;
-                // if file to write the pid to is specified, write pid of the child
+                container.update_status(ContainerStatus::Created)?.save()?;
+                // if file to write the pid to is specified, write pid of the init process, as the child exits after forking it
                 if let Some(pid_file) = pid_file {
-                    fs::write(&pid_file, format!("{}", child))?;
+                    utils::write_pid_file(pid_file.as_ref(), init_pid)?;
                 }
                 Ok(Process::Parent(parent))
             }
@@ -87,34 +114,188 @@ pub fn fork_first<P: AsRef<Path>>(
 pub fn fork_init(mut child_process: ChildProcess) -> Result<Process> {
     // setup sockets for init process
     let sender_for_child = child_process.setup_pipe()?;
+    let channel = child_process
+        .take_channel()
+        .expect("channel to the parent process has been taken");
     unsafe {
         // for the process into current process (C1) (which is child of first_fork) and init process
         match unistd::fork()? {
             // if it is child process, create new InitProcess structure and return
-            unistd::ForkResult::Child => Ok(Process::Init(InitProcess::new(sender_for_child))),
+            unistd::ForkResult::Child => {
+                Ok(Process::Init(InitProcess::new(sender_for_child, channel)))
+            }
             // in the forking process C1
             unistd::ForkResult::Parent { child } => {
+                // only the init process reports to the parent process through the channel
+                drop(channel);
                 // wait for init process to be ready
                 child_process.wait_for_init_ready()?;
                 // notify the parent process (original youki process) that init process is forked and ready
                 child_process.notify_parent(child)?;
 
-                // wait for the init process, which is container process, to change state
-                // check https://man7.org/linux/man-pages/man3/wait.3p.html for more information
-                match waitpid(child, None)? {
-                    // if normally exited
-                    WaitStatus::Exited(pid, status) => {
-                        log::debug!("exited pid: {:?}, status: {:?}", pid, status);
-                        exit(status);
-                    }
-                    // if terminated by a signal
-                    WaitStatus::Signaled(pid, status, _) => {
-                        log::debug!("signaled pid: {:?}, status: {:?}", pid, status);
-                        exit(0);
-                    }
-                    _ => bail!("abnormal exited!"),
+                // the init process is reparented to the original youki process, which is a subreaper
+                // and waits for the init process when the container is run in the foreground
+                exit(0);
+            }
+        }
+    }
+}
+
+/// Size of the stack of a process created by clone_child
+const CHILD_STACK_SIZE: usize = 1024 * 1024;
+
+/// Flags of the namespaces which can be created by clone
+const NAMESPACE_FLAGS: CloneFlags = CloneFlags::from_bits_truncate(
+    CloneFlags::CLONE_NEWNS.bits()
+        | CloneFlags::CLONE_NEWUTS.bits()
+        | CloneFlags::CLONE_NEWIPC.bits()
+        | CloneFlags::CLONE_NEWUSER.bits()
+        | CloneFlags::CLONE_NEWPID.bits()
+        | CloneFlags::CLONE_NEWNET.bits()
+        | CloneFlags::CLONE_NEWCGROUP.bits(),
+);
+
+/// Creates a child process with clone, which runs the callback and exits with the value it returns.
+/// A new user namespace can not be created by clone if the child shares the filesystem information
+/// with the parent, so the child creates the user namespace and the namespaces owned by it afterwards
+pub fn clone_child<F: FnMut() -> isize>(flags: CloneFlags, mut cb: F) -> Result<Pid> {
+    validate_clone_flags(flags)?;
+    let (clone_flags, unshare_flags) = split_clone_flags(flags);
+
+    // the memory of the parent is copied into the child as CLONE_VM is not allowed,
+    // so the stack can be freed by the parent as soon as clone returns
+    let mut stack = vec![0u8; CHILD_STACK_SIZE];
+    let child = sched::clone(
+        Box::new(|| {
+            if !unshare_flags.is_empty() {
+                // unshare creates the user namespace before the other namespaces
+                if let Err(e) = sched::unshare(unshare_flags) {
+                    log::error!("failed to unshare {:?}: {}", unshare_flags, e);
+                    return -1;
                 }
             }
+            cb()
+        }),
+        &mut stack,
+        clone_flags,
+        // the parent is notified with SIGCHLD like for a process created by fork
+        Some(Signal::SIGCHLD as i32),
+    )?;
+    Ok(child)
+}
+
+/// Rejects combinations of flags which can not be used to create a child process
+fn validate_clone_flags(flags: CloneFlags) -> Result<()> {
+    if flags.contains(CloneFlags::CLONE_VM) {
+        bail!("CLONE_VM is not supported, as the stack of the child is freed by the parent");
+    }
+    if flags
+        .intersects(CloneFlags::CLONE_THREAD | CloneFlags::CLONE_SIGHAND | CloneFlags::CLONE_PARENT)
+        && flags.intersects(CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWPID)
+    {
+        bail!("a new user or pid namespace can only be created for a new process, not a thread or sibling");
+    }
+    // the pid namespace has to be created by clone, but the user namespace could only be created
+    // afterwards, so the pid namespace would not be owned by the user namespace of the container
+    if flags.contains(CloneFlags::CLONE_FS | CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWPID) {
+        bail!("CLONE_FS can not be combined with CLONE_NEWUSER and CLONE_NEWPID");
+    }
+    Ok(())
+}
+
+/// Splits the flags into the flags for clone and the namespaces the child has to unshare afterwards.
+/// Clone fails with EINVAL if CLONE_FS is combined with CLONE_NEWUSER or CLONE_NEWNS, but the child
+/// is allowed to stop sharing the filesystem information by unsharing these namespaces itself
+fn split_clone_flags(flags: CloneFlags) -> (CloneFlags, CloneFlags) {
+    if !flags.contains(CloneFlags::CLONE_FS)
+        || !flags.intersects(CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNS)
+    {
+        return (flags, CloneFlags::empty());
+    }
+
+    let unshare_flags = if flags.contains(CloneFlags::CLONE_NEWUSER) {
+        // the other namespaces are unshared along with the user namespace, so that they are owned by it
+        flags & NAMESPACE_FLAGS & !CloneFlags::CLONE_NEWPID
+    } else {
+        CloneFlags::CLONE_NEWNS
+    };
+    (flags & !unshare_flags, unshare_flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::sys::wait::{waitpid, WaitStatus};
+
+    #[test]
+    fn test_validate_clone_flags() {
+        assert!(validate_clone_flags(CloneFlags::empty()).is_ok());
+        assert!(validate_clone_flags(
+            CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWNS
+        )
+        .is_ok());
+        assert!(validate_clone_flags(
+            CloneFlags::CLONE_FS | CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNET
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_validate_invalid_clone_flags() {
+        let invalid = [
+            CloneFlags::CLONE_VM,
+            CloneFlags::CLONE_THREAD | CloneFlags::CLONE_NEWPID,
+            CloneFlags::CLONE_SIGHAND | CloneFlags::CLONE_NEWUSER,
+            CloneFlags::CLONE_PARENT | CloneFlags::CLONE_NEWPID,
+            CloneFlags::CLONE_FS | CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWPID,
+        ];
+        for flags in invalid.iter() {
+            assert!(
+                validate_clone_flags(*flags).is_err(),
+                "{:?} should be rejected",
+                flags
+            );
         }
     }
+
+    #[test]
+    fn test_split_clone_flags() {
+        // the namespaces are created by clone, the kernel creates the user namespace first
+        let flags = CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWNS;
+        assert_eq!(split_clone_flags(flags), (flags, CloneFlags::empty()));
+        let flags = CloneFlags::CLONE_FS | CloneFlags::CLONE_NEWNET;
+        assert_eq!(split_clone_flags(flags), (flags, CloneFlags::empty()));
+    }
+
+    #[test]
+    fn test_split_clone_flags_with_shared_fs() {
+        let (clone_flags, unshare_flags) = split_clone_flags(
+            CloneFlags::CLONE_FS
+                | CloneFlags::CLONE_FILES
+                | CloneFlags::CLONE_NEWUSER
+                | CloneFlags::CLONE_NEWNS
+                | CloneFlags::CLONE_NEWNET,
+        );
+        assert_eq!(clone_flags, CloneFlags::CLONE_FS | CloneFlags::CLONE_FILES);
+        assert_eq!(
+            unshare_flags,
+            CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNS | CloneFlags::CLONE_NEWNET
+        );
+
+        let (clone_flags, unshare_flags) = split_clone_flags(
+            CloneFlags::CLONE_FS | CloneFlags::CLONE_NEWNS | CloneFlags::CLONE_NEWPID,
+        );
+        assert_eq!(clone_flags, CloneFlags::CLONE_FS | CloneFlags::CLONE_NEWPID);
+        assert_eq!(unshare_flags, CloneFlags::CLONE_NEWNS);
+    }
+
+    #[test]
+    fn test_clone_child() {
+        let child = clone_child(CloneFlags::empty(), || 3).expect("clone child");
+
+        assert_eq!(
+            waitpid(child, None).expect("wait for child"),
+            WaitStatus::Exited(child, 3)
+        );
+    }
 }
@@ -3,17 +3,33 @@ use std::io::Write;
 use anyhow::Result;
 use mio::unix::pipe::Sender;
 
+use crate::process::channel::{Channel, ChannelMessage};
 use crate::process::message::Message;
 
 /// Contains sending end for pipe for the child process
+/// and the channel to report the result of the setup to the parent process
 pub struct InitProcess {
     sender_for_child: Sender,
+    channel: Channel,
 }
 
 impl InitProcess {
     /// create a new Init process structure
-    pub fn new(sender_for_child: Sender) -> Self {
-        Self { sender_for_child }
+    pub fn new(sender_for_child: Sender, channel: Channel) -> Self {
+        Self {
+            sender_for_child,
+            channel,
+        }
+    }
+
+    /// Reports to the parent process that the container has been set up
+    pub fn complete(&mut self) -> Result<()> {
+        self.channel.send(&ChannelMessage::InitComplete)
+    }
+
+    /// Reports an error of the setup to the parent process, which returns it from create
+    pub fn send_error(&mut self, err: &anyhow::Error) -> Result<()> {
+        self.channel.send(&ChannelMessage::error(err))
     }
 
     /// Notify that this process is ready
@@ -3,6 +3,7 @@
 
 use std::time::Duration;
 
+pub mod channel;
 pub mod fork;
 pub mod message;
 
@@ -11,6 +12,7 @@ mod init;
 mod parent;
 
 pub use init::InitProcess;
+pub use parent::{set_subreaper, wait_for_exit, FORWARDED_SIGNALS};
 
 /// Used to describe type of process after fork.
 /// Parent and child processes mean the same thing as in a normal fork call
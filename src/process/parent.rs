@@ -7,6 +7,10 @@ use anyhow::{bail, Result};
 use mio::unix::pipe;
 use mio::unix::pipe::{Receiver, Sender};
 use mio::{Events, Interest, Poll, Token};
+use nix::errno::Errno;
+use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::Pid;
 
 // Token is used to identify which socket generated an event
 const PARENT: Token = Token(0);
@@ -24,6 +28,9 @@ impl ParentProcess {
     pub fn new() -> Result<(Self, Sender)> {
         // create a new pipe
         let (sender, mut receiver) = pipe::new()?;
+        // the intermediate child process exits as soon as it has forked the init process,
+        // which is then reparented to youki instead of the init of the system
+        set_subreaper()?;
         // create a new poll, and register the receiving end of pipe to it
         // This will poll for the read events, so when data is written to sending end of the pipe,
         // the receiving end will be readable and poll wil notify
@@ -87,3 +94,158 @@ impl ParentProcess {
         );
     }
 }
+
+/// Makes youki the subreaper of its descendants, so that a process whose parent exits
+/// is reparented to youki instead of the init of the system
+pub fn set_subreaper() -> Result<()> {
+    if unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) } != 0 {
+        bail!("failed to become the subreaper of the container process")
+    }
+    Ok(())
+}
+
+/// Signals, which are relayed to the process youki waits for in the foreground, e.g. when the
+/// user presses ctrl-c or a supervisor stops youki
+pub const FORWARDED_SIGNALS: &[Signal] = &[
+    Signal::SIGHUP,
+    Signal::SIGINT,
+    Signal::SIGQUIT,
+    Signal::SIGTERM,
+    Signal::SIGUSR1,
+    Signal::SIGUSR2,
+];
+
+/// How youki handles a signal it receives while it waits for a process
+#[derive(Debug, PartialEq, Eq)]
+enum SignalAction {
+    // a child of youki has exited and has to be reaped
+    Reap,
+    // the signal is relayed to the process
+    Forward,
+}
+
+fn signal_action(signal: Signal, forwarded: &[Signal]) -> Option<SignalAction> {
+    match signal {
+        // SIGCHLD is about the children of youki, the process does not expect it
+        Signal::SIGCHLD => Some(SignalAction::Reap),
+        signal if forwarded.contains(&signal) => Some(SignalAction::Forward),
+        _ => None,
+    }
+}
+
+/// Waits for the init process, or a process executed in the container, to exit and returns its
+/// exit code, which youki uses as its own exit code when the process is run in the foreground.
+/// Other children of youki, such as the intermediate child process, are reaped on the way.
+/// The forwarded signals are relayed to the process instead of terminating youki
+pub fn wait_for_exit(pid: Pid, forwarded: &[Signal]) -> Result<i32> {
+    // if SIGCHLD is ignored, the kernel reaps the children on its own and their status is lost
+    let handler = SigAction::new(
+        SigHandler::Handler(handle_sigchld),
+        SaFlags::SA_NOCLDSTOP | SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    unsafe { signal::sigaction(Signal::SIGCHLD, &handler)? };
+    // the signals are blocked, so that they are received with sigwait and SIGCHLD is not missed
+    // between reaping and waiting for the next one
+    let mut signals = SigSet::empty();
+    signals.add(Signal::SIGCHLD);
+    for signal in forwarded {
+        signals.add(*signal);
+    }
+    signals.thread_block()?;
+    let result = reap_until_exit(pid, forwarded, &signals);
+    // the signal mask is inherited by the processes youki runs afterwards, e.g. poststop hooks
+    signals.thread_unblock()?;
+    result
+}
+
+fn reap_until_exit(pid: Pid, forwarded: &[Signal], signals: &SigSet) -> Result<i32> {
+    loop {
+        // several children may have exited for a single SIGCHLD, so all of them are reaped
+        loop {
+            match waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
+                Ok(WaitStatus::StillAlive) => break,
+                Ok(status) if status.pid() == Some(pid) => {
+                    if let Some(code) = exit_code(status) {
+                        log::debug!("process {} exited with {:?}", pid, status);
+                        return Ok(code);
+                    }
+                }
+                Ok(status) => log::debug!("reaped child process: {:?}", status),
+                Err(nix::Error::Sys(Errno::ECHILD)) => {
+                    bail!("process {} is not a child of youki", pid)
+                }
+                Err(e) => bail!("failed to wait for process {}: {}", pid, e),
+            }
+        }
+
+        loop {
+            let received = signals.wait()?;
+            match signal_action(received, forwarded) {
+                Some(SignalAction::Reap) => break,
+                Some(SignalAction::Forward) => {
+                    log::debug!("forward {} to {}", received, pid);
+                    match signal::kill(pid, received) {
+                        // the process has exited and is reaped with the next SIGCHLD
+                        Ok(()) | Err(nix::Error::Sys(Errno::ESRCH)) => {}
+                        Err(e) => bail!("failed to forward {} to {}: {}", received, pid, e),
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+extern "C" fn handle_sigchld(_: libc::c_int) {}
+
+/// Translates the status of an exited process into an exit code the way shells do,
+/// the exit status of a process which exited normally or 128 + the number of the terminating signal
+pub fn exit_code(status: WaitStatus) -> Option<i32> {
+    match status {
+        WaitStatus::Exited(_, code) => Some(code),
+        WaitStatus::Signaled(_, signal, _) => Some(128 + signal as i32),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let pid = Pid::from_raw(42);
+
+        assert_eq!(exit_code(WaitStatus::Exited(pid, 0)), Some(0));
+        assert_eq!(exit_code(WaitStatus::Exited(pid, 3)), Some(3));
+        assert_eq!(
+            exit_code(WaitStatus::Signaled(pid, Signal::SIGKILL, false)),
+            Some(137)
+        );
+        assert_eq!(
+            exit_code(WaitStatus::Signaled(pid, Signal::SIGTERM, true)),
+            Some(143)
+        );
+        assert_eq!(exit_code(WaitStatus::Stopped(pid, Signal::SIGSTOP)), None);
+        assert_eq!(exit_code(WaitStatus::StillAlive), None);
+    }
+
+    #[test]
+    fn test_signal_action() {
+        assert_eq!(
+            signal_action(Signal::SIGTERM, FORWARDED_SIGNALS),
+            Some(SignalAction::Forward)
+        );
+        assert_eq!(
+            signal_action(Signal::SIGCHLD, FORWARDED_SIGNALS),
+            Some(SignalAction::Reap)
+        );
+        // SIGCHLD is handled by youki, even if it is requested to be forwarded
+        assert_eq!(
+            signal_action(Signal::SIGCHLD, &[Signal::SIGCHLD]),
+            Some(SignalAction::Reap)
+        );
+        assert_eq!(signal_action(Signal::SIGTERM, &[]), None);
+    }
+}
@@ -0,0 +1,190 @@
+//! Allocation of the L3 cache and the memory bandwidth of a container with Intel RDT,
+//! which is controlled through groups of the resctrl filesystem
+//! check https://www.kernel.org/doc/html/latest/x86/resctrl.html for more information
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use oci_spec::LinuxIntelRdt;
+
+const RESCTRL_ROOT: &str = "/sys/fs/resctrl";
+const RESCTRL_SCHEMATA: &str = "schemata";
+const RESCTRL_TASKS: &str = "tasks";
+
+/// Creates the resctrl group of the container, writes its schemata and moves the process into it
+pub fn apply(intel_rdt: &LinuxIntelRdt, container_id: &str, pid: i32) -> Result<()> {
+    apply_group(Path::new(RESCTRL_ROOT), intel_rdt, container_id, pid)
+}
+
+/// Removes the resctrl group of the container. A group named by closID may be shared
+/// with other containers, so that only the group named after the container is removed
+pub fn remove(intel_rdt: &LinuxIntelRdt, container_id: &str) -> Result<()> {
+    remove_group(Path::new(RESCTRL_ROOT), intel_rdt, container_id)
+}
+
+/// Returns the content of the schemata file, one line for every resource, e.g.
+/// 'L3:0=ffff0;1=fff00' for the L3 cache and 'MB:0=20;1=70' for the memory bandwidth
+fn schemata(intel_rdt: &LinuxIntelRdt) -> String {
+    [&intel_rdt.l3_cache_schema, &intel_rdt.mem_bw_schema]
+        .iter()
+        .filter_map(|schema| schema.as_deref())
+        .flat_map(|schema| schema.lines())
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// Returns the path of the resctrl group, which is named by the closID or after the container.
+/// The closID has to be the name of a group directly below the root of resctrl
+fn group_path(
+    resctrl_root: &Path,
+    intel_rdt: &LinuxIntelRdt,
+    container_id: &str,
+) -> Result<PathBuf> {
+    if let Some(clos_id) = &intel_rdt.clos_id {
+        if clos_id.is_empty() || clos_id == "." || clos_id == ".." || clos_id.contains('/') {
+            bail!("invalid linux.intelRdt.closID {:?}", clos_id)
+        }
+    }
+    Ok(resctrl_root.join(intel_rdt.clos_id.as_deref().unwrap_or(container_id)))
+}
+
+fn apply_group(
+    resctrl_root: &Path,
+    intel_rdt: &LinuxIntelRdt,
+    container_id: &str,
+    pid: i32,
+) -> Result<()> {
+    // the root group of a mounted resctrl filesystem always has a schemata file
+    if !resctrl_root.join(RESCTRL_SCHEMATA).exists() {
+        bail!(
+            "linux.intelRdt is set, but resctrl is not mounted at {:?}",
+            resctrl_root
+        )
+    }
+
+    let group = group_path(resctrl_root, intel_rdt, container_id)?;
+    if !group.exists() {
+        fs::create_dir(&group)
+            .with_context(|| format!("failed to create the resctrl group {:?}", group))?;
+    }
+    let schemata = schemata(intel_rdt);
+    if !schemata.is_empty() {
+        fs::write(group.join(RESCTRL_SCHEMATA), &schemata).with_context(|| {
+            format!(
+                "failed to write the schemata {:?} of the resctrl group {:?}",
+                schemata, group
+            )
+        })?;
+    }
+    fs::write(group.join(RESCTRL_TASKS), pid.to_string())
+        .with_context(|| format!("failed to move {} into the resctrl group {:?}", pid, group))?;
+
+    Ok(())
+}
+
+fn remove_group(resctrl_root: &Path, intel_rdt: &LinuxIntelRdt, container_id: &str) -> Result<()> {
+    if intel_rdt.clos_id.is_some() {
+        return Ok(());
+    }
+
+    let group = group_path(resctrl_root, intel_rdt, container_id)?;
+    if group.exists() {
+        // the files of a resctrl group are removed by the kernel along with the directory
+        fs::remove_dir(&group)
+            .with_context(|| format!("failed to remove the resctrl group {:?}", group))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_temp_dir;
+
+    #[test]
+    fn test_schemata() {
+        let intel_rdt = LinuxIntelRdt {
+            l3_cache_schema: Some("L3:0=ffff0;1=fff00".to_string()),
+            mem_bw_schema: Some("MB:0=20;1=70".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(schemata(&intel_rdt), "L3:0=ffff0;1=fff00\nMB:0=20;1=70\n");
+    }
+
+    #[test]
+    fn test_schemata_with_single_resource() {
+        let intel_rdt = LinuxIntelRdt {
+            mem_bw_schema: Some("MB:0=20\n".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(schemata(&intel_rdt), "MB:0=20\n");
+        assert_eq!(schemata(&LinuxIntelRdt::default()), "");
+    }
+
+    #[test]
+    fn test_apply_and_remove_group() {
+        let tmp = create_temp_dir("test_apply_and_remove_group").expect("create temp directory");
+        fs::write(tmp.join(RESCTRL_SCHEMATA), "").expect("write root schemata");
+        let intel_rdt = LinuxIntelRdt {
+            l3_cache_schema: Some("L3:0=ffff0".to_string()),
+            ..Default::default()
+        };
+
+        apply_group(&tmp, &intel_rdt, "rdt_test", 1234).expect("apply resctrl group");
+
+        let group = tmp.join("rdt_test");
+        assert_eq!(
+            fs::read_to_string(group.join(RESCTRL_SCHEMATA)).expect("read schemata"),
+            "L3:0=ffff0\n"
+        );
+        assert_eq!(
+            fs::read_to_string(group.join(RESCTRL_TASKS)).expect("read tasks"),
+            "1234"
+        );
+
+        // unlike on resctrl, the files of the group have to be removed first
+        fs::remove_file(group.join(RESCTRL_SCHEMATA)).expect("remove schemata");
+        fs::remove_file(group.join(RESCTRL_TASKS)).expect("remove tasks");
+        remove_group(&tmp, &intel_rdt, "rdt_test").expect("remove resctrl group");
+        assert!(!group.exists());
+    }
+
+    #[test]
+    fn test_apply_group_without_resctrl() {
+        let tmp =
+            create_temp_dir("test_apply_group_without_resctrl").expect("create temp directory");
+        assert!(apply_group(&tmp, &LinuxIntelRdt::default(), "rdt_test", 1234).is_err());
+    }
+
+    #[test]
+    fn test_group_named_by_clos_id() {
+        let intel_rdt = LinuxIntelRdt {
+            clos_id: Some("shared".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            group_path(Path::new(RESCTRL_ROOT), &intel_rdt, "rdt_test").expect("get group path"),
+            PathBuf::from("/sys/fs/resctrl/shared")
+        );
+    }
+
+    #[test]
+    fn test_invalid_clos_id() {
+        for clos_id in ["", ".", "..", "../shared", "shared/group"].iter() {
+            let intel_rdt = LinuxIntelRdt {
+                clos_id: Some(clos_id.to_string()),
+                ..Default::default()
+            };
+            assert!(
+                group_path(Path::new(RESCTRL_ROOT), &intel_rdt, "rdt_test").is_err(),
+                "closID {:?} should be rejected",
+                clos_id
+            );
+        }
+    }
+}
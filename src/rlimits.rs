@@ -0,0 +1,126 @@
+//! Resource limits of the container process, which are set before the program of the container is executed
+//! check https://man7.org/linux/man-pages/man2/setrlimit.2.html for more information
+use anyhow::{bail, Context, Result};
+use oci_spec::{LinuxRlimit, LinuxRlimitType};
+
+use crate::command::Command;
+
+/// Returns the resource of the kernel, which is limited by the rlimit
+pub fn resource(typ: LinuxRlimitType) -> libc::__rlimit_resource_t {
+    match typ {
+        LinuxRlimitType::RlimitCpu => libc::RLIMIT_CPU,
+        LinuxRlimitType::RlimitFsize => libc::RLIMIT_FSIZE,
+        LinuxRlimitType::RlimitData => libc::RLIMIT_DATA,
+        LinuxRlimitType::RlimitStack => libc::RLIMIT_STACK,
+        LinuxRlimitType::RlimitCore => libc::RLIMIT_CORE,
+        LinuxRlimitType::RlimitRss => libc::RLIMIT_RSS,
+        LinuxRlimitType::RlimitNproc => libc::RLIMIT_NPROC,
+        LinuxRlimitType::RlimitNofile => libc::RLIMIT_NOFILE,
+        LinuxRlimitType::RlimitMemlock => libc::RLIMIT_MEMLOCK,
+        LinuxRlimitType::RlimitAs => libc::RLIMIT_AS,
+        LinuxRlimitType::RlimitLocks => libc::RLIMIT_LOCKS,
+        LinuxRlimitType::RlimitSigpending => libc::RLIMIT_SIGPENDING,
+        LinuxRlimitType::RlimitMsgqueue => libc::RLIMIT_MSGQUEUE,
+        LinuxRlimitType::RlimitNice => libc::RLIMIT_NICE,
+        LinuxRlimitType::RlimitRtprio => libc::RLIMIT_RTPRIO,
+        LinuxRlimitType::RlimitRttime => libc::RLIMIT_RTTIME,
+    }
+}
+
+/// Sets the rlimits of the spec for the current process. The limits are set before the process
+/// joins the user namespace of the container, as raising a hard limit requires CAP_SYS_RESOURCE
+pub fn set_rlimits(rlimits: &[LinuxRlimit], command: &impl Command) -> Result<()> {
+    validate_rlimits(rlimits)?;
+    for rlimit in rlimits {
+        command
+            .set_rlimit(rlimit)
+            .with_context(|| format!("failed to set {:?}", rlimit.typ))?;
+    }
+    Ok(())
+}
+
+/// Checks that no soft limit exceeds its hard limit and that no type is given more than once
+pub fn validate_rlimits(rlimits: &[LinuxRlimit]) -> Result<()> {
+    for (i, rlimit) in rlimits.iter().enumerate() {
+        if rlimit.soft > rlimit.hard {
+            bail!(
+                "soft limit {} of {:?} is greater than its hard limit {}",
+                rlimit.soft,
+                rlimit.typ,
+                rlimit.hard
+            )
+        }
+        // the spec requires an error for duplicated types, instead of using either of the limits
+        if rlimits[..i].iter().any(|other| other.typ == rlimit.typ) {
+            bail!("{:?} is given more than once", rlimit.typ)
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::test::TestHelperCommand;
+
+    fn rlimit(typ: LinuxRlimitType, soft: u64, hard: u64) -> LinuxRlimit {
+        LinuxRlimit { typ, hard, soft }
+    }
+
+    #[test]
+    fn test_resource() {
+        let resources: Vec<_> = LinuxRlimitType::ALL
+            .iter()
+            .map(|(_, typ)| resource(*typ))
+            .collect();
+
+        // the types are listed in the order of the resources in the kernel headers
+        let expected: Vec<libc::__rlimit_resource_t> = (0..16).collect();
+        assert_eq!(resources, expected);
+        assert_eq!(resource(LinuxRlimitType::RlimitNofile), libc::RLIMIT_NOFILE);
+        assert_eq!(resource(LinuxRlimitType::RlimitNproc), libc::RLIMIT_NPROC);
+        assert_eq!(resource(LinuxRlimitType::RlimitRttime), libc::RLIMIT_RTTIME);
+    }
+
+    #[test]
+    fn test_set_rlimits() {
+        let test_command = TestHelperCommand::default();
+        let rlimits = vec![
+            rlimit(LinuxRlimitType::RlimitNofile, 1024, 4096),
+            rlimit(LinuxRlimitType::RlimitCore, 0, 0),
+        ];
+
+        set_rlimits(&rlimits, &test_command).expect("set rlimits");
+
+        let set: Vec<_> = test_command
+            .get_set_rlimit_args()
+            .iter()
+            .map(|r| (r.typ, r.soft, r.hard))
+            .collect();
+        assert_eq!(
+            set,
+            vec![
+                (LinuxRlimitType::RlimitNofile, 1024, 4096),
+                (LinuxRlimitType::RlimitCore, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_invalid_rlimits() {
+        let test_command = TestHelperCommand::default();
+        let soft_above_hard = vec![rlimit(LinuxRlimitType::RlimitNofile, 2048, 1024)];
+        assert!(set_rlimits(&soft_above_hard, &test_command).is_err());
+
+        let duplicated = vec![
+            rlimit(LinuxRlimitType::RlimitNofile, 1024, 1024),
+            rlimit(LinuxRlimitType::RlimitCpu, 10, 10),
+            rlimit(LinuxRlimitType::RlimitNofile, 512, 512),
+        ];
+        assert!(set_rlimits(&duplicated, &test_command).is_err());
+        assert!(
+            test_command.get_set_rlimit_args().is_empty(),
+            "no limit is set for invalid rlimits"
+        );
+    }
+}
@@ -4,9 +4,9 @@
 use std::fs::OpenOptions;
 use std::fs::{canonicalize, create_dir_all, remove_file};
 use std::os::unix::fs::symlink;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use nix::errno::Errno;
 use nix::fcntl::{open, OFlag};
 use nix::mount::mount as nix_mount;
@@ -16,44 +16,32 @@ use nix::sys::stat::{mknod, umask};
 use nix::unistd::{chdir, chown, close, getcwd};
 use nix::unistd::{Gid, Uid};
 
+use crate::command::Command;
+use crate::selinux;
 use crate::utils::PathBufExt;
 use oci_spec::{LinuxDevice, LinuxDeviceType, Mount, Spec};
 
-pub fn prepare_rootfs(spec: &Spec, rootfs: &Path, bind_devices: bool) -> Result<()> {
-    let mut flags = MsFlags::MS_REC;
-    match spec.linux {
-        Some(ref linux) => match linux.rootfs_propagation.as_ref() {
-            "shared" => flags |= MsFlags::MS_SHARED,
-            "private" => flags |= MsFlags::MS_PRIVATE,
-            "slave" | "" => flags |= MsFlags::MS_SLAVE,
-            _ => panic!(),
-        },
-        None => flags |= MsFlags::MS_SLAVE,
+/// Prepares the rootfs of the container, so that the root of the process can be changed to it with
+/// [pivot_rootfs] and restricted with [finish_rootfs]. The mounts of the spec are mounted and the devices
+/// are created inside of the rootfs
+pub fn prepare_rootfs(
+    spec: &Spec,
+    rootfs: &Path,
+    bind_devices: bool,
+    command: &impl Command,
+) -> Result<()> {
+    let propagation = match spec.linux {
+        Some(ref linux) => linux.rootfs_propagation.as_str(),
+        None => "",
     };
-    nix_mount(None::<&str>, "/", None::<&str>, flags, None::<&str>)?;
+    make_mount_point(rootfs, propagation, command)?;
 
-    log::debug!("mount root fs {:?}", rootfs);
-    nix_mount::<Path, Path, str, str>(
-        Some(&rootfs),
-        &rootfs,
-        None::<&str>,
-        MsFlags::MS_BIND | MsFlags::MS_REC,
-        None::<&str>,
+    mount_all(
+        &spec.mounts,
+        rootfs,
+        &spec.linux.as_ref().unwrap().mount_label,
     )?;
 
-    for m in spec.mounts.iter() {
-        let (flags, data) = parse_mount(&m);
-        let ml = &spec.linux.as_ref().unwrap().mount_label;
-        if m.typ == "cgroup" {
-            // skip
-            log::warn!("A feature of cgroup is unimplemented.");
-        } else if m.destination == PathBuf::from("/dev") {
-            mount_to_container(&m, rootfs, flags & !MsFlags::MS_RDONLY, &data, &ml)?;
-        } else {
-            mount_to_container(&m, rootfs, flags, &data, &ml)?;
-        }
-    }
-
     let olddir = getcwd()?;
     chdir(rootfs)?;
 
@@ -66,29 +54,202 @@ pub fn prepare_rootfs(spec: &Spec, rootfs: &Path, bind_devices: bool) -> Result<
     Ok(())
 }
 
+/// Changes the root of the process to the prepared rootfs and unmounts the old root
+pub fn pivot_rootfs(rootfs: &Path, command: &impl Command) -> Result<()> {
+    command.pivot_rootfs(rootfs)
+}
+
+/// Restricts the access to the changed root as the last step of its setup: the masked and readonly
+/// paths of the spec are applied and the root is remounted read only if requested by the spec
+pub fn finish_rootfs(spec: &Spec, command: &impl Command) -> Result<()> {
+    if let Some(linux) = &spec.linux {
+        mask_paths(&linux.masked_paths, command)?;
+        readonly_paths(&linux.readonly_paths, command)?;
+    }
+
+    // nothing can be created in the root afterwards, so it is the final operation
+    if spec.root.readonly {
+        // a bind mount can only be made read only by remounting it
+        command
+            .mount(
+                None,
+                Path::new("/"),
+                None,
+                MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY,
+                None,
+            )
+            .context("failed to remount the root read only")?;
+    }
+    Ok(())
+}
+
+/// What is mounted over a masked path to hide its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MaskOperation {
+    // a file is hidden by bind mounting /dev/null over it
+    BindDevNull,
+    // a directory is hidden by mounting an empty read only tmpfs over it
+    MountTmpfs,
+}
+
+/// Hides the masked paths of the spec from the container process, must be called after the root
+/// has been changed, as the paths are resolved in the rootfs. Masked paths which do not exist are skipped
+pub fn mask_paths(paths: &[String], command: &impl Command) -> Result<()> {
+    for path in paths.iter().map(Path::new) {
+        match mask_operation(path) {
+            Some(MaskOperation::BindDevNull) => command.mount(
+                Some(Path::new("/dev/null")),
+                path,
+                None,
+                MsFlags::MS_BIND,
+                None,
+            ),
+            Some(MaskOperation::MountTmpfs) => {
+                command.mount(None, path, Some("tmpfs"), MsFlags::MS_RDONLY, None)
+            }
+            None => continue,
+        }
+        .with_context(|| format!("failed to mask {:?}", path))?;
+    }
+    Ok(())
+}
+
+fn mask_operation(path: &Path) -> Option<MaskOperation> {
+    match path.metadata() {
+        Ok(metadata) if metadata.is_dir() => Some(MaskOperation::MountTmpfs),
+        Ok(_) => Some(MaskOperation::BindDevNull),
+        Err(_) => None,
+    }
+}
+
+/// Makes the readonly paths of the spec read only for the container process, must be called after the root
+/// has been changed. Readonly paths which do not exist are skipped
+pub fn readonly_paths(paths: &[String], command: &impl Command) -> Result<()> {
+    for path in paths.iter().map(Path::new) {
+        if !path.exists() {
+            continue;
+        }
+        // a path can only be remounted read only if it is a mount point, so it is bind mounted onto itself first
+        command
+            .mount(
+                Some(path),
+                path,
+                None,
+                MsFlags::MS_BIND | MsFlags::MS_REC,
+                None,
+            )
+            .and_then(|_| {
+                command.mount(
+                    Some(path),
+                    path,
+                    None,
+                    MsFlags::MS_BIND | MsFlags::MS_REC | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY,
+                    None,
+                )
+            })
+            .with_context(|| format!("failed to make {:?} read only", path))?;
+    }
+    Ok(())
+}
+
+// pivot_root requires the new root to be a mount point, which is not the case if the rootfs is
+// just a directory on the filesystem of the host. Bind mounting the rootfs onto itself makes it one
+fn make_mount_point(rootfs: &Path, propagation: &str, command: &impl Command) -> Result<()> {
+    // the changes of the mounts in the container must not propagate to the host,
+    // unless the spec requests it. Only the r* variants apply to the mounts below the root
+    let propagation = match propagation {
+        "" => MsFlags::MS_PRIVATE | MsFlags::MS_REC,
+        _ => propagation_flags(propagation)
+            .ok_or_else(|| anyhow!("unknown rootfs propagation {:?}", propagation))?,
+    };
+    command.mount(None, Path::new("/"), None, propagation, None)?;
+
+    log::debug!("mount root fs {:?}", rootfs);
+    command.mount(
+        Some(rootfs),
+        rootfs,
+        None,
+        MsFlags::MS_BIND | MsFlags::MS_REC,
+        None,
+    )?;
+    Ok(())
+}
+
+/// Mounts the filesystems of the spec, such as /proc, /sys, /dev and tmpfs, inside of the rootfs
+pub fn mount_all(mounts: &[Mount], rootfs: &Path, mount_label: &str) -> Result<()> {
+    let mount_label = if !mount_label.is_empty() && !selinux::is_enabled() {
+        log::warn!(
+            "selinux mount label {} is ignored, as SELinux is not enabled",
+            mount_label
+        );
+        ""
+    } else {
+        mount_label
+    };
+    for m in sort_mounts(mounts) {
+        let (flags, propagation, data) = parse_mount(m);
+        log::debug!("mount {:?} to {:?}", m.source, m.destination);
+        if m.typ == "cgroup" {
+            // skip
+            log::warn!("A feature of cgroup is unimplemented.");
+        } else if m.destination == PathBuf::from("/dev") {
+            // the devices are created in /dev afterwards, so it can only be made read only later
+            mount_to_container(
+                m,
+                rootfs,
+                flags & !MsFlags::MS_RDONLY,
+                &propagation,
+                &data,
+                mount_label,
+            )?;
+        } else {
+            mount_to_container(m, rootfs, flags, &propagation, &data, mount_label)?;
+        }
+    }
+    Ok(())
+}
+
+/// Orders the mounts by the depth of their destination, so that a mount is never hidden by a mount
+/// of one of its parent directories. The order of the spec is kept for mounts of the same depth
+fn sort_mounts(mounts: &[Mount]) -> Vec<&Mount> {
+    let mut sorted: Vec<&Mount> = mounts.iter().collect();
+    // sort_by_key is stable
+    sorted.sort_by_key(|m| {
+        m.destination
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .count()
+    });
+    sorted
+}
+
+/// Symbolic links which are expected in /dev of the container, pointing to the entries of procfs
+const DEFAULT_SYMLINKS: [(&str, &str); 4] = [
+    ("/proc/self/fd", "dev/fd"),
+    ("/proc/self/fd/0", "dev/stdin"),
+    ("/proc/self/fd/1", "dev/stdout"),
+    ("/proc/self/fd/2", "dev/stderr"),
+];
+
 fn setup_ptmx(rootfs: &Path) -> Result<()> {
     if let Err(e) = remove_file(rootfs.join("dev/ptmx")) {
         if e.kind() != ::std::io::ErrorKind::NotFound {
             bail!("could not delete /dev/ptmx")
         }
     }
+    // the devpts of the container is mounted with newinstance, so the ptmx of its instance has to be used
     symlink("pts/ptmx", rootfs.join("dev/ptmx"))?;
     Ok(())
 }
 
 fn setup_default_symlinks(rootfs: &Path) -> Result<()> {
     if Path::new("/proc/kcore").exists() {
-        symlink("/proc/kcore", "dev/kcore")?;
+        symlink("/proc/kcore", rootfs.join("dev/kcore"))?;
     }
 
-    let defaults = [
-        ("/proc/self/fd", "dev/fd"),
-        ("/proc/self/fd/0", "dev/stdin"),
-        ("/proc/self/fd/1", "dev/stdout"),
-        ("/proc/self/fd/2", "dev/stderr"),
-    ];
-    for &(src, dst) in defaults.iter() {
-        symlink(src, rootfs.join(dst))?;
+    for &(src, dst) in DEFAULT_SYMLINKS.iter() {
+        symlink(src, rootfs.join(dst))
+            .with_context(|| format!("failed to link {} to {}", dst, src))?;
     }
     Ok(())
 }
@@ -152,33 +313,34 @@ pub fn default_devices() -> Vec<LinuxDevice> {
     ]
 }
 
+// the devices are created relative to the current directory, which is the rootfs
 fn create_devices(devices: &[LinuxDevice], bind: bool) -> Result<()> {
     let old_mode = umask(Mode::from_bits_truncate(0o000));
+    let result = default_devices()
+        .iter()
+        .chain(devices)
+        .try_for_each(|dev| create_device(dev, bind));
+    umask(old_mode);
+    result
+}
+
+fn create_device(dev: &LinuxDevice, bind: bool) -> Result<()> {
+    if !dev.path.starts_with("/dev") {
+        bail!("{} is not a valid device path", dev.path.display());
+    }
     if bind {
-        let _ = default_devices()
-            .iter()
-            .chain(devices)
-            .map(|dev| {
-                if !dev.path.starts_with("/dev") {
-                    panic!("{} is not a valid device path", dev.path.display());
-                }
-                bind_dev(dev)
-            })
-            .collect::<Result<Vec<_>>>()?;
-    } else {
-        default_devices()
-            .iter()
-            .chain(devices)
-            .map(|dev| {
-                if !dev.path.starts_with("/dev") {
-                    panic!("{} is not a valid device path", dev.path.display());
-                }
-                mknod_dev(dev)
-            })
-            .collect::<Result<Vec<_>>>()?;
+        return bind_dev(dev);
+    }
+
+    match mknod_dev(dev) {
+        // creating device nodes requires CAP_MKNOD in the initial user namespace,
+        // without it the device of the host is used instead
+        Err(e) if e.downcast_ref::<nix::Error>() == Some(&nix::Error::Sys(Errno::EPERM)) => {
+            log::debug!("mknod of {:?} is not permitted, bind mount it", dev.path);
+            bind_dev(dev)
+        }
+        result => result,
     }
-    umask(old_mode);
-    Ok(())
 }
 
 fn bind_dev(dev: &LinuxDevice) -> Result<()> {
@@ -189,20 +351,22 @@ fn bind_dev(dev: &LinuxDevice) -> Result<()> {
     )?;
     close(fd)?;
     nix_mount(
-        Some(&*dev.path.as_in_container()?),
-        &dev.path,
+        Some(&dev.path),
+        &*dev.path.as_in_container()?,
         None::<&str>,
         MsFlags::MS_BIND,
         None::<&str>,
-    )?;
+    )
+    .with_context(|| format!("failed to bind mount {:?}", dev.path))?;
     Ok(())
 }
 
-fn mknod_dev(dev: &LinuxDevice) -> Result<()> {
-    fn makedev(major: u64, minor: u64) -> u64 {
-        (minor & 0xff) | ((major & 0xfff) << 8) | ((minor & !0xff) << 12) | ((major & !0xfff) << 32)
-    }
+/// Combines the major and minor number into a device number the same way as makedev of glibc
+fn makedev(major: u64, minor: u64) -> u64 {
+    (minor & 0xff) | ((major & 0xfff) << 8) | ((minor & !0xff) << 12) | ((major & !0xfff) << 32)
+}
 
+fn mknod_dev(dev: &LinuxDevice) -> Result<()> {
     mknod(
         &dev.path.as_in_container()?,
         dev.typ.to_sflag()?,
@@ -221,15 +385,13 @@ fn mount_to_container(
     m: &Mount,
     rootfs: &Path,
     flags: MsFlags,
+    propagation: &[MsFlags],
     data: &str,
     label: &str,
 ) -> Result<()> {
-    let d = if !label.is_empty() && m.typ != "proc" && m.typ != "sysfs" {
-        if data.is_empty() {
-            format!("context=\"{}\"", label)
-        } else {
-            format!("{},context=\"{}\"", data, label)
-        }
+    // the files of other filesystems either have labels of their own or are labeled by the kernel
+    let d = if m.typ == "tmpfs" || m.typ == "overlay" {
+        selinux::format_mount_label(data, label)
     } else {
         data.to_string()
     };
@@ -241,24 +403,17 @@ fn mount_to_container(
     );
     let dest = Path::new(&dest_for_host);
 
-    let src = if m.typ == "bind" {
-        let src = canonicalize(&m.source)?;
-        let dir = if src.is_file() {
-            Path::new(&dest).parent().unwrap()
-        } else {
-            Path::new(&dest)
-        };
-        create_dir_all(&dir).unwrap();
-        if src.is_file() {
-            OpenOptions::new()
-                .create(true)
-                .write(true)
-                .open(&dest)
-                .unwrap();
-        }
+    let src = if m.typ == "bind" || flags.contains(MsFlags::MS_BIND) {
+        let src = canonicalize(&m.source).with_context(|| {
+            format!(
+                "failed to find the source {:?} of the bind mount to {:?}",
+                m.source, m.destination
+            )
+        })?;
+        create_bind_target(&src, dest)?;
         src
     } else {
-        create_dir_all(&dest).unwrap();
+        create_dir_all(&dest).with_context(|| format!("failed to create directory {:?}", dest))?;
         PathBuf::from(&m.source)
     };
 
@@ -266,7 +421,7 @@ fn mount_to_container(
         nix_mount(Some(&*src), dest, Some(&*m.typ), flags, Some(&*d))
     {
         if errno != Errno::EINVAL {
-            bail!("mount of {} failed", m.destination.display());
+            bail!("mount of {} failed: {}", m.destination.display(), errno);
         }
         nix_mount(Some(&*src), dest, Some(&*m.typ), flags, Some(data))?;
     }
@@ -288,13 +443,65 @@ fn mount_to_container(
             None::<&str>,
         )?;
     }
+    // the propagation of a mount can only be changed after it has been mounted
+    for flags in propagation {
+        nix_mount(None::<&str>, dest, None::<&str>, *flags, None::<&str>).with_context(|| {
+            format!(
+                "failed to change the propagation of {}",
+                m.destination.display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Creates the target of a bind mount, which has to be of the same kind as its source.
+/// A file is bind mounted onto a file, anything else onto a directory
+fn create_bind_target(src: &Path, dest: &Path) -> Result<()> {
+    if src.is_file() {
+        if let Some(dir) = dest.parent() {
+            create_dir_all(dir).with_context(|| format!("failed to create directory {:?}", dir))?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(dest)
+            .with_context(|| format!("failed to create file {:?}", dest))?;
+    } else {
+        create_dir_all(dest).with_context(|| format!("failed to create directory {:?}", dest))?;
+    }
     Ok(())
 }
 
-fn parse_mount(m: &Mount) -> (MsFlags, String) {
+/// Returns the flags of a propagation option, e.g. MS_SHARED | MS_REC for rshared
+fn propagation_flags(option: &str) -> Option<MsFlags> {
+    match option {
+        "private" => Some(MsFlags::MS_PRIVATE),
+        "rprivate" => Some(MsFlags::MS_PRIVATE | MsFlags::MS_REC),
+        "shared" => Some(MsFlags::MS_SHARED),
+        "rshared" => Some(MsFlags::MS_SHARED | MsFlags::MS_REC),
+        "slave" => Some(MsFlags::MS_SLAVE),
+        "rslave" => Some(MsFlags::MS_SLAVE | MsFlags::MS_REC),
+        "unbindable" => Some(MsFlags::MS_UNBINDABLE),
+        "runbindable" => Some(MsFlags::MS_UNBINDABLE | MsFlags::MS_REC),
+        _ => None,
+    }
+}
+
+/// Splits the options of a mount into the flags of the mount syscall, the propagation flags and
+/// the data which is passed to the filesystem, e.g. mode=755 or size=65536k for a tmpfs.
+/// The propagation is changed by separate calls, as the kernel ignores all other flags along with it
+fn parse_mount(m: &Mount) -> (MsFlags, Vec<MsFlags>, String) {
     let mut flags = MsFlags::empty();
+    let mut propagation = Vec::new();
     let mut data = Vec::new();
     for s in &m.options {
+        if let Some(propagation_flags) = propagation_flags(s) {
+            propagation.push(propagation_flags);
+            continue;
+        }
+
         if let Some((is_clear, flag)) = match s.as_str() {
             "defaults" => Some((false, MsFlags::empty())),
             "ro" => Some((false, MsFlags::MS_RDONLY)),
@@ -317,14 +524,6 @@ fn parse_mount(m: &Mount) -> (MsFlags, String) {
             "nodiratime" => Some((false, MsFlags::MS_NODIRATIME)),
             "bind" => Some((false, MsFlags::MS_BIND)),
             "rbind" => Some((false, MsFlags::MS_BIND | MsFlags::MS_REC)),
-            "unbindable" => Some((false, MsFlags::MS_UNBINDABLE)),
-            "runbindable" => Some((false, MsFlags::MS_UNBINDABLE | MsFlags::MS_REC)),
-            "private" => Some((false, MsFlags::MS_PRIVATE)),
-            "rprivate" => Some((false, MsFlags::MS_PRIVATE | MsFlags::MS_REC)),
-            "shared" => Some((false, MsFlags::MS_SHARED)),
-            "rshared" => Some((false, MsFlags::MS_SHARED | MsFlags::MS_REC)),
-            "slave" => Some((false, MsFlags::MS_SLAVE)),
-            "rslave" => Some((false, MsFlags::MS_SLAVE | MsFlags::MS_REC)),
             "relatime" => Some((false, MsFlags::MS_RELATIME)),
             "norelatime" => Some((true, MsFlags::MS_RELATIME)),
             "strictatime" => Some((false, MsFlags::MS_STRICTATIME)),
@@ -340,5 +539,423 @@ fn parse_mount(m: &Mount) -> (MsFlags, String) {
             data.push(s.as_str());
         };
     }
-    (flags, data.join(","))
+    (flags, propagation, data.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::test::{MountOperation, TestHelperCommand};
+    use crate::commands::spec::default_spec;
+    use crate::process::fork::clone_child;
+    use crate::utils::create_temp_dir;
+    use nix::sched::CloneFlags;
+    use nix::sys::stat::SFlag;
+    use nix::sys::wait::{waitpid, WaitStatus};
+    use std::fs;
+
+    fn mount_operation(source: Option<&str>, target: &str, flags: MsFlags) -> MountOperation {
+        MountOperation::Mount {
+            source: source.map(PathBuf::from),
+            target: PathBuf::from(target),
+            fstype: None,
+            flags,
+            data: None,
+        }
+    }
+
+    fn mount_with_options(options: &[&str]) -> Mount {
+        Mount {
+            destination: PathBuf::from("/dev"),
+            typ: "tmpfs".to_string(),
+            source: PathBuf::from("tmpfs"),
+            options: options.iter().map(|o| o.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_mount() {
+        let (flags, _, data) = parse_mount(&mount_with_options(&[
+            "nosuid",
+            "strictatime",
+            "mode=755",
+            "size=65536k",
+        ]));
+
+        assert_eq!(flags, MsFlags::MS_NOSUID | MsFlags::MS_STRICTATIME);
+        assert_eq!(data, "mode=755,size=65536k");
+    }
+
+    #[test]
+    fn test_parse_mount_flags() {
+        let (flags, _, data) = parse_mount(&mount_with_options(&[
+            "ro", "nosuid", "nodev", "noexec", "relatime", "rbind",
+        ]));
+
+        assert_eq!(
+            flags,
+            MsFlags::MS_RDONLY
+                | MsFlags::MS_NOSUID
+                | MsFlags::MS_NODEV
+                | MsFlags::MS_NOEXEC
+                | MsFlags::MS_RELATIME
+                | MsFlags::MS_BIND
+                | MsFlags::MS_REC
+        );
+        assert_eq!(data, "");
+    }
+
+    #[test]
+    fn test_parse_mount_clears_flags() {
+        // the later option wins, as the options are applied in order
+        let (flags, _, _) = parse_mount(&mount_with_options(&["ro", "nosuid", "rw", "suid"]));
+        assert_eq!(flags, MsFlags::empty());
+
+        let (flags, _, data) = parse_mount(&mount_with_options(&["defaults", "newinstance"]));
+        assert_eq!(flags, MsFlags::empty());
+        assert_eq!(data, "newinstance");
+    }
+
+    #[test]
+    fn test_sort_mounts() {
+        let mount = |destination: &str| Mount {
+            destination: PathBuf::from(destination),
+            typ: "tmpfs".to_string(),
+            source: PathBuf::from("tmpfs"),
+            options: vec![],
+        };
+        let mounts = vec![
+            mount("/var/lib/docker"),
+            mount("/proc"),
+            mount("/var/lib"),
+            mount("/dev/pts"),
+            mount("/dev"),
+            mount("/dev/shm"),
+            mount("/sys"),
+        ];
+
+        let destinations: Vec<&Path> = sort_mounts(&mounts)
+            .iter()
+            .map(|m| m.destination.as_path())
+            .collect();
+        assert_eq!(
+            destinations,
+            vec![
+                Path::new("/proc"),
+                Path::new("/dev"),
+                Path::new("/sys"),
+                Path::new("/var/lib"),
+                Path::new("/dev/pts"),
+                Path::new("/dev/shm"),
+                Path::new("/var/lib/docker"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mount_on_mount_point() {
+        let tmp = create_temp_dir("test_mount_on_mount_point").expect("create temp directory");
+        let rootfs = fs::canonicalize(&*tmp).expect("resolve temp directory");
+        let mount = Mount {
+            destination: PathBuf::from("/data"),
+            typ: "tmpfs".to_string(),
+            source: PathBuf::from("tmpfs"),
+            options: vec!["size=64k".to_string()],
+        };
+        let mount_point = format!(" {} ", rootfs.join("data").display());
+
+        // the mounts are made in a mount namespace of their own, which is gone with the child
+        let child = clone_child(CloneFlags::CLONE_NEWNS, || {
+            let mount_twice = || -> Result<usize> {
+                nix_mount(
+                    None::<&str>,
+                    "/",
+                    None::<&str>,
+                    MsFlags::MS_REC | MsFlags::MS_PRIVATE,
+                    None::<&str>,
+                )?;
+                mount_all(&[mount.clone()], &rootfs, "")?;
+                // a second mount of the spec on the same mount point is stacked on the first one
+                mount_all(&[mount.clone()], &rootfs, "")?;
+                let mountinfo = fs::read_to_string("/proc/self/mountinfo")?;
+                Ok(mountinfo
+                    .lines()
+                    .filter(|line| line.contains(&mount_point))
+                    .count())
+            };
+            match mount_twice() {
+                Ok(mounts) => mounts as isize,
+                Err(_) => -1,
+            }
+        })
+        .expect("clone child");
+
+        assert_eq!(
+            waitpid(child, None).expect("wait for child"),
+            WaitStatus::Exited(child, 2)
+        );
+    }
+
+    #[test]
+    fn test_create_bind_target_for_file() {
+        let tmp =
+            create_temp_dir("test_create_bind_target_for_file").expect("create temp directory");
+        let src = tmp.join("resolv.conf");
+        fs::write(&src, "nameserver 127.0.0.1").expect("write source");
+        let dest = tmp.join("rootfs/etc/resolv.conf");
+
+        create_bind_target(&src, &dest).expect("create bind target");
+
+        assert!(dest.is_file());
+        // an existing target is kept as it is
+        create_bind_target(&src, &dest).expect("create existing bind target");
+        assert!(dest.is_file());
+    }
+
+    #[test]
+    fn test_create_bind_target_for_directory() {
+        let tmp = create_temp_dir("test_create_bind_target_for_directory")
+            .expect("create temp directory");
+        let src = tmp.join("data");
+        fs::create_dir(&src).expect("create source");
+        let dest = tmp.join("rootfs/mnt/data");
+
+        create_bind_target(&src, &dest).expect("create bind target");
+
+        assert!(dest.is_dir());
+    }
+
+    #[test]
+    fn test_bind_mount_missing_source() {
+        let tmp = create_temp_dir("test_bind_mount_missing_source").expect("create temp directory");
+        let mount = Mount {
+            destination: PathBuf::from("/data"),
+            typ: "none".to_string(),
+            source: tmp.join("missing"),
+            options: vec!["rbind".to_string()],
+        };
+
+        let err = mount_all(&[mount], &tmp.join("rootfs"), "").expect_err("mount missing source");
+
+        assert!(format!("{:?}", err).contains(&*tmp.join("missing").to_string_lossy()));
+        assert!(!tmp.join("rootfs/data").exists());
+    }
+
+    #[test]
+    fn test_propagation_flags() {
+        for (option, expected) in &[
+            ("private", MsFlags::MS_PRIVATE),
+            ("rprivate", MsFlags::MS_PRIVATE | MsFlags::MS_REC),
+            ("shared", MsFlags::MS_SHARED),
+            ("rshared", MsFlags::MS_SHARED | MsFlags::MS_REC),
+            ("slave", MsFlags::MS_SLAVE),
+            ("rslave", MsFlags::MS_SLAVE | MsFlags::MS_REC),
+            ("unbindable", MsFlags::MS_UNBINDABLE),
+            ("runbindable", MsFlags::MS_UNBINDABLE | MsFlags::MS_REC),
+        ] {
+            assert_eq!(propagation_flags(option), Some(*expected), "{}", option);
+        }
+        assert_eq!(propagation_flags("rbind"), None);
+        assert_eq!(propagation_flags(""), None);
+    }
+
+    #[test]
+    fn test_parse_mount_propagation() {
+        let (flags, propagation, data) =
+            parse_mount(&mount_with_options(&["rbind", "rslave", "ro", "mode=755"]));
+
+        // the propagation is not passed along with the flags of the mount
+        assert_eq!(
+            flags,
+            MsFlags::MS_BIND | MsFlags::MS_REC | MsFlags::MS_RDONLY
+        );
+        assert_eq!(propagation, vec![MsFlags::MS_SLAVE | MsFlags::MS_REC]);
+        assert_eq!(data, "mode=755");
+    }
+
+    #[test]
+    fn test_makedev() {
+        assert_eq!(makedev(1, 3), 0x103);
+        assert_eq!(makedev(136, 300), 0x10882c);
+        // major numbers above 12 bits and minor numbers above 8 bits use the high bits
+        assert_eq!(makedev(0x1234, 0x56789), 0x1000_5672_3489);
+    }
+
+    #[test]
+    fn test_device_type_to_sflag() {
+        assert_eq!(LinuxDeviceType::C.to_sflag().unwrap(), SFlag::S_IFCHR);
+        assert_eq!(LinuxDeviceType::U.to_sflag().unwrap(), SFlag::S_IFCHR);
+        assert_eq!(LinuxDeviceType::B.to_sflag().unwrap(), SFlag::S_IFBLK);
+        assert_eq!(LinuxDeviceType::P.to_sflag().unwrap(), SFlag::S_IFIFO);
+        assert!(LinuxDeviceType::A.to_sflag().is_err());
+    }
+
+    #[test]
+    fn test_invalid_device_path() {
+        let mut dev = default_devices().remove(0);
+        dev.path = PathBuf::from("/tmp/null");
+
+        assert!(create_device(&dev, false).is_err());
+    }
+
+    #[test]
+    fn test_default_symlinks() {
+        let tmp = create_temp_dir("test_default_symlinks").expect("create temp directory");
+        create_dir_all(tmp.join("dev")).expect("create dev directory");
+
+        setup_default_symlinks(&tmp).expect("create symlinks");
+        setup_ptmx(&tmp).expect("create ptmx");
+
+        for &(src, dst) in DEFAULT_SYMLINKS.iter() {
+            assert_eq!(
+                fs::read_link(tmp.join(dst)).expect("read symlink"),
+                PathBuf::from(src)
+            );
+        }
+        assert_eq!(
+            fs::read_link(tmp.join("dev/ptmx")).expect("read ptmx"),
+            PathBuf::from("pts/ptmx")
+        );
+    }
+
+    #[test]
+    fn test_mask_operation() {
+        let tmp = create_temp_dir("test_mask_operation").expect("create temp directory");
+        let file = tmp.join("file");
+        fs::write(&file, "secret").expect("create file");
+
+        assert_eq!(mask_operation(&file), Some(MaskOperation::BindDevNull));
+        assert_eq!(mask_operation(&tmp), Some(MaskOperation::MountTmpfs));
+        assert_eq!(mask_operation(&tmp.join("missing")), None);
+    }
+
+    #[test]
+    fn test_mask_paths() {
+        let tmp = create_temp_dir("test_mask_paths").expect("create temp directory");
+        let file = tmp.join("file");
+        fs::write(&file, "secret").expect("create file");
+        let paths: Vec<String> = [file.clone(), tmp.to_path_buf(), tmp.join("missing")]
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let command = TestHelperCommand::default();
+
+        mask_paths(&paths, &command).expect("mask paths");
+
+        assert_eq!(
+            command.get_mount_operations(),
+            vec![
+                mount_operation(Some("/dev/null"), &file.to_string_lossy(), MsFlags::MS_BIND),
+                MountOperation::Mount {
+                    source: None,
+                    target: tmp.to_path_buf(),
+                    fstype: Some("tmpfs".to_string()),
+                    flags: MsFlags::MS_RDONLY,
+                    data: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_readonly_paths() {
+        let tmp = create_temp_dir("test_readonly_paths").expect("create temp directory");
+        let path = tmp.to_string_lossy().to_string();
+        let missing = tmp.join("missing").to_string_lossy().to_string();
+        let command = TestHelperCommand::default();
+
+        readonly_paths(&[path.clone(), missing], &command).expect("make paths read only");
+
+        assert_eq!(
+            command.get_mount_operations(),
+            vec![
+                mount_operation(Some(&path), &path, MsFlags::MS_BIND | MsFlags::MS_REC),
+                mount_operation(
+                    Some(&path),
+                    &path,
+                    MsFlags::MS_BIND | MsFlags::MS_REC | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rootfs_mount_order() {
+        let tmp = create_temp_dir("test_rootfs_mount_order").expect("create temp directory");
+        let path = tmp.to_string_lossy().to_string();
+        let mut spec = default_spec();
+        spec.root.readonly = true;
+        let linux = spec.linux.as_mut().unwrap();
+        linux.masked_paths = vec![path.clone()];
+        linux.readonly_paths = vec![path.clone()];
+        let command = TestHelperCommand::default();
+        let rootfs = Path::new("/bundle/rootfs");
+
+        make_mount_point(rootfs, "", &command).expect("make mount point");
+        pivot_rootfs(rootfs, &command).expect("pivot rootfs");
+        finish_rootfs(&spec, &command).expect("finish rootfs");
+
+        let operations = command.get_mount_operations();
+        assert_eq!(
+            &operations[..3],
+            &[
+                // the root is rprivate, unless the spec sets another propagation
+                mount_operation(None, "/", MsFlags::MS_REC | MsFlags::MS_PRIVATE),
+                // the rootfs has to be a mount point before the root can be changed to it
+                mount_operation(
+                    Some("/bundle/rootfs"),
+                    "/bundle/rootfs",
+                    MsFlags::MS_BIND | MsFlags::MS_REC
+                ),
+                MountOperation::PivotRoot(PathBuf::from("/bundle/rootfs")),
+            ]
+        );
+        // the masked path and the readonly path are mounted before the root is remounted
+        assert_eq!(operations.len(), 7);
+        assert_eq!(
+            operations.last(),
+            Some(&mount_operation(
+                None,
+                "/",
+                MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY
+            ))
+        );
+    }
+
+    #[test]
+    fn test_finish_rootfs_without_readonly() {
+        let mut spec = default_spec();
+        spec.root.readonly = false;
+        spec.linux.as_mut().unwrap().masked_paths = vec![];
+        spec.linux.as_mut().unwrap().readonly_paths = vec![];
+        let command = TestHelperCommand::default();
+
+        finish_rootfs(&spec, &command).expect("finish rootfs");
+
+        assert!(command.get_mount_operations().is_empty());
+    }
+
+    #[test]
+    fn test_rootfs_propagation() {
+        let command = TestHelperCommand::default();
+        make_mount_point(Path::new("/rootfs"), "private", &command).expect("make mount point");
+        assert_eq!(
+            command.get_mount_operations()[0],
+            mount_operation(None, "/", MsFlags::MS_PRIVATE)
+        );
+
+        let command = TestHelperCommand::default();
+        make_mount_point(Path::new("/rootfs"), "rshared", &command).expect("make mount point");
+        assert_eq!(
+            command.get_mount_operations()[0],
+            mount_operation(None, "/", MsFlags::MS_REC | MsFlags::MS_SHARED)
+        );
+
+        assert!(make_mount_point(
+            Path::new("/rootfs"),
+            "unknown",
+            &TestHelperCommand::default()
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,343 @@
+//! Writes the uid and gid mappings of the user namespace of the container.
+//! An unprivileged process may only map its own id with a single entry, so rootless
+//! containers rely on the setuid binaries newuidmap and newgidmap, which allow to map
+//! the subordinate ids assigned to the user in /etc/subuid and /etc/subgid
+//! check https://man7.org/linux/man-pages/man1/newuidmap.1.html for more information
+use std::{fs, path::Path, process::Command};
+
+use anyhow::{anyhow, bail, Context, Result};
+use nix::unistd::{self, Pid, User};
+use oci_spec::{Linux, LinuxIdMapping};
+
+const SUBUID_PATH: &str = "/etc/subuid";
+const SUBGID_PATH: &str = "/etc/subgid";
+const NEWUIDMAP: &str = "newuidmap";
+const NEWGIDMAP: &str = "newgidmap";
+
+/// Range of subordinate ids which is assigned to a user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubIdRange {
+    pub start: u32,
+    pub count: u32,
+}
+
+impl SubIdRange {
+    fn contains(&self, mapping: &LinuxIdMapping) -> bool {
+        let start = mapping.host_id as u64;
+        let end = start + mapping.size as u64;
+        start >= self.start as u64 && end <= self.start as u64 + self.count as u64
+    }
+}
+
+/// Returns true if youki is run by an unprivileged user
+pub fn is_rootless() -> bool {
+    !unistd::geteuid().is_root()
+}
+
+/// Writes the uid and gid mappings of the spec for the user namespace of the given process
+pub fn write_id_mappings(pid: Pid, linux: &Linux) -> Result<()> {
+    if linux.uid_mappings.is_empty() && linux.gid_mappings.is_empty() {
+        return Ok(());
+    }
+
+    if !is_rootless() {
+        write_mapping_file(&format!("/proc/{}/uid_map", pid), &linux.uid_mappings)?;
+        return write_mapping_file(&format!("/proc/{}/gid_map", pid), &linux.gid_mappings);
+    }
+
+    let uid = unistd::geteuid();
+    let gid = unistd::getegid();
+    let user_name = User::from_uid(uid)?
+        .map(|user| user.name)
+        .ok_or_else(|| anyhow!("failed to find the name of user {}", uid))?;
+
+    // an unprivileged user may map its own ids without newuidmap and newgidmap
+    let own_uid =
+        linux.uid_mappings.is_empty() || is_own_mapping(&linux.uid_mappings, uid.as_raw());
+    let own_gid =
+        linux.gid_mappings.is_empty() || is_own_mapping(&linux.gid_mappings, gid.as_raw());
+    if own_uid && own_gid {
+        write_mapping_file(&format!("/proc/{}/uid_map", pid), &linux.uid_mappings)?;
+        if !linux.gid_mappings.is_empty() {
+            // the kernel only allows an unprivileged process to map a gid once setgroups is denied,
+            // otherwise it could drop groups which are used to deny it access
+            let setgroups = format!("/proc/{}/setgroups", pid);
+            fs::write(&setgroups, "deny")
+                .with_context(|| format!("failed to deny setgroups in {}", setgroups))?;
+        }
+        return write_mapping_file(&format!("/proc/{}/gid_map", pid), &linux.gid_mappings);
+    }
+
+    let subuids = read_subid_file(Path::new(SUBUID_PATH), &user_name, uid.as_raw())?;
+    validate_mappings(&linux.uid_mappings, &subuids, uid.as_raw())
+        .context("invalid uid mappings")?;
+    let subgids = read_subid_file(Path::new(SUBGID_PATH), &user_name, gid.as_raw())?;
+    validate_mappings(&linux.gid_mappings, &subgids, gid.as_raw())
+        .context("invalid gid mappings")?;
+
+    run_id_mapper(NEWUIDMAP, pid, &linux.uid_mappings)?;
+    run_id_mapper(NEWGIDMAP, pid, &linux.gid_mappings)
+}
+
+/// Returns the ids on the host, which the root user of the container is mapped to.
+/// Both the uid and the gid of root have to be mapped
+pub fn mapped_root_ids(
+    uid_mappings: &[LinuxIdMapping],
+    gid_mappings: &[LinuxIdMapping],
+) -> Option<(u32, u32)> {
+    let host_id = |mappings: &[LinuxIdMapping]| {
+        mappings
+            .iter()
+            .find(|m| m.container_id == 0 && m.size > 0)
+            .map(|m| m.host_id)
+    };
+    Some((host_id(uid_mappings)?, host_id(gid_mappings)?))
+}
+
+/// Returns true if the mappings consist of a single entry for the given id on the host
+fn is_own_mapping(mappings: &[LinuxIdMapping], id: u32) -> bool {
+    matches!(mappings, [mapping] if mapping.host_id == id && mapping.size == 1)
+}
+
+fn write_mapping_file(path: &str, mappings: &[LinuxIdMapping]) -> Result<()> {
+    if mappings.is_empty() {
+        return Ok(());
+    }
+
+    let content: String = mappings
+        .iter()
+        .map(|m| format!("{} {} {}\n", m.container_id, m.host_id, m.size))
+        .collect();
+    fs::write(path, content).with_context(|| format!("failed to write mappings to {}", path))
+}
+
+fn run_id_mapper(mapper: &str, pid: Pid, mappings: &[LinuxIdMapping]) -> Result<()> {
+    if mappings.is_empty() {
+        return Ok(());
+    }
+
+    let args = get_mapping_args(pid, mappings);
+    log::debug!("run {} {}", mapper, args.join(" "));
+    let output = Command::new(mapper)
+        .args(&args)
+        .output()
+        .with_context(|| format!("failed to execute {}", mapper))?;
+    if !output.status.success() {
+        bail!(
+            "{} failed with {}: {}",
+            mapper,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    Ok(())
+}
+
+/// Builds the arguments for newuidmap and newgidmap, which expect the pid followed by
+/// a triple of the id in the namespace, the id on the host and the size for each mapping
+fn get_mapping_args(pid: Pid, mappings: &[LinuxIdMapping]) -> Vec<String> {
+    let mut args = vec![pid.to_string()];
+    for mapping in mappings {
+        args.push(mapping.container_id.to_string());
+        args.push(mapping.host_id.to_string());
+        args.push(mapping.size.to_string());
+    }
+    args
+}
+
+/// Reads the ranges of subordinate uids, which are assigned to the user in /etc/subuid
+pub fn read_subuids(user_name: &str, uid: u32) -> Result<Vec<SubIdRange>> {
+    read_subid_file(Path::new(SUBUID_PATH), user_name, uid)
+}
+
+/// Reads the ranges of subordinate gids, which are assigned to the user in /etc/subgid
+pub fn read_subgids(user_name: &str, gid: u32) -> Result<Vec<SubIdRange>> {
+    read_subid_file(Path::new(SUBGID_PATH), user_name, gid)
+}
+
+fn read_subid_file(path: &Path, user_name: &str, id: u32) -> Result<Vec<SubIdRange>> {
+    // subordinate ids are optional, the user can still map its own id
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path).with_context(|| format!("failed to read {:?}", path))?;
+    parse_subid_file(&content, user_name, id).with_context(|| format!("failed to parse {:?}", path))
+}
+
+/// Parses the ranges of a user from /etc/subuid or /etc/subgid. Every line consists of
+/// the name or id of the user, the first subordinate id and the number of ids
+fn parse_subid_file(content: &str, user_name: &str, id: u32) -> Result<Vec<SubIdRange>> {
+    let id = id.to_string();
+    let mut ranges = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() != 3 {
+            bail!("invalid entry {:?}", line);
+        }
+        if fields[0] != user_name && fields[0] != id {
+            continue;
+        }
+
+        let parse = |field: &str| -> Result<u32> {
+            field
+                .parse()
+                .map_err(|_| anyhow!("invalid id {:?} in entry {:?}", field, line))
+        };
+        ranges.push(SubIdRange {
+            start: parse(fields[1])?,
+            count: parse(fields[2])?,
+        });
+    }
+
+    Ok(ranges)
+}
+
+/// Checks that every mapping either maps the own id of the user
+/// or is within the subordinate ids of the user
+fn validate_mappings(mappings: &[LinuxIdMapping], ranges: &[SubIdRange], id: u32) -> Result<()> {
+    for mapping in mappings {
+        let own_id = mapping.host_id == id && mapping.size == 1;
+        if !own_id && !ranges.iter().any(|range| range.contains(mapping)) {
+            bail!(
+                "host ids {}-{} are not assigned to the user",
+                mapping.host_id,
+                mapping.host_id as u64 + mapping.size as u64 - 1
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_temp_dir;
+
+    fn mapping(container_id: u32, host_id: u32, size: u32) -> LinuxIdMapping {
+        LinuxIdMapping {
+            host_id,
+            container_id,
+            size,
+        }
+    }
+
+    #[test]
+    fn test_get_mapping_args() {
+        let mappings = vec![mapping(0, 1000, 1), mapping(1, 100000, 65536)];
+
+        let args = get_mapping_args(Pid::from_raw(4242), &mappings);
+
+        assert_eq!(args, vec!["4242", "0", "1000", "1", "1", "100000", "65536"]);
+    }
+
+    #[test]
+    fn test_get_mapping_args_without_mappings() {
+        let args = get_mapping_args(Pid::from_raw(1), &[]);
+        assert_eq!(args, vec!["1"]);
+    }
+
+    #[test]
+    fn test_parse_subid_file() {
+        let content =
+            "# subordinate ids\nroot:10000:5000\nyouki:100000:65536\n\n1000:200000:1000\n";
+
+        let ranges = parse_subid_file(content, "youki", 1000).expect("parse subid file");
+
+        assert_eq!(
+            ranges,
+            vec![
+                SubIdRange {
+                    start: 100000,
+                    count: 65536
+                },
+                SubIdRange {
+                    start: 200000,
+                    count: 1000
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_subid_file() {
+        let tmp = create_temp_dir("test_read_subid_file").expect("create temp directory for test");
+        let path = tmp.join("subuid");
+        fs::write(&path, "root:10000:5000\nyouki:100000:65536\n").expect("write subuid file");
+
+        let ranges = read_subid_file(&path, "youki", 1000).expect("read subid file");
+        assert_eq!(
+            ranges,
+            vec![SubIdRange {
+                start: 100000,
+                count: 65536
+            }]
+        );
+        assert!(read_subid_file(&path, "other", 1001)
+            .expect("read subid file")
+            .is_empty());
+        // a missing file assigns no subordinate ids
+        assert!(read_subid_file(&tmp.join("subgid"), "youki", 1000)
+            .expect("read missing subid file")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_subid_file_invalid_entry() {
+        assert!(parse_subid_file("youki:100000\n", "youki", 1000).is_err());
+        assert!(parse_subid_file("youki:start:65536\n", "youki", 1000).is_err());
+    }
+
+    #[test]
+    fn test_validate_mappings() {
+        let ranges = vec![SubIdRange {
+            start: 100000,
+            count: 65536,
+        }];
+
+        assert!(validate_mappings(&[mapping(0, 1000, 1)], &ranges, 1000).is_ok());
+        assert!(validate_mappings(&[mapping(1, 100000, 65536)], &ranges, 1000).is_ok());
+        assert!(validate_mappings(&[mapping(0, 1001, 1)], &ranges, 1000).is_err());
+        assert!(validate_mappings(&[mapping(1, 100000, 65537)], &ranges, 1000).is_err());
+        assert!(validate_mappings(&[mapping(0, 1000, 2)], &[], 1000).is_err());
+    }
+
+    #[test]
+    fn test_is_own_mapping() {
+        assert!(is_own_mapping(&[mapping(0, 1000, 1)], 1000));
+        assert!(!is_own_mapping(&[mapping(0, 1001, 1)], 1000));
+        assert!(!is_own_mapping(&[mapping(0, 1000, 2)], 1000));
+        assert!(!is_own_mapping(
+            &[mapping(0, 1000, 1), mapping(1, 100000, 65536)],
+            1000
+        ));
+        assert!(!is_own_mapping(&[], 1000));
+    }
+
+    #[test]
+    fn test_mapped_root_ids() {
+        assert_eq!(
+            mapped_root_ids(&[mapping(0, 100000, 65536)], &[mapping(0, 200000, 65536)]),
+            Some((100000, 200000))
+        );
+        // root may be mapped by any of the mappings
+        assert_eq!(
+            mapped_root_ids(
+                &[mapping(1, 100000, 65535), mapping(0, 1000, 1)],
+                &[mapping(0, 1000, 1)]
+            ),
+            Some((1000, 1000))
+        );
+        assert_eq!(
+            mapped_root_ids(&[mapping(1000, 1000, 1)], &[mapping(0, 1000, 1)]),
+            None
+        );
+        assert_eq!(mapped_root_ids(&[mapping(0, 1000, 1)], &[]), None);
+    }
+}
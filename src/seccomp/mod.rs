@@ -0,0 +1,983 @@
+//! Seccomp restricts the syscalls a process is allowed to make.
+//! The seccomp profile of the specification is translated into a classic BPF program,
+//! which is evaluated by the kernel for every syscall of the container process.
+//! check https://www.kernel.org/doc/html/latest/userspace-api/seccomp_filter.html for more information
+
+mod notify;
+mod syscall;
+
+use std::os::unix::io::RawFd;
+
+use anyhow::{bail, Result};
+use nix::errno::Errno;
+use oci_spec::{Arch, LinuxSeccomp, LinuxSeccompAction, LinuxSeccompArg, LinuxSeccompOperator};
+
+pub use notify::NotifySocket;
+
+// instruction classes and fields of classic BPF, see linux/filter.h
+const BPF_LD: u16 = 0x00;
+const BPF_ALU: u16 = 0x04;
+const BPF_JMP: u16 = 0x05;
+const BPF_JA: u16 = 0x00;
+const BPF_RET: u16 = 0x06;
+const BPF_W: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_AND: u16 = 0x50;
+const BPF_JEQ: u16 = 0x10;
+const BPF_JGT: u16 = 0x20;
+const BPF_JGE: u16 = 0x30;
+const BPF_K: u16 = 0x00;
+// maximum number of instructions the kernel accepts for a filter
+const BPF_MAXINSNS: usize = 4096;
+
+const SECCOMP_RET_KILL: u32 = 0x0000_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_TRACE: u32 = 0x7ff0_0000;
+const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc0_0000;
+const SECCOMP_RET_DATA: u32 = 0x0000_ffff;
+
+// operation and flag of the seccomp syscall, which returns the fd of a listener for the notifications
+const SECCOMP_SET_MODE_FILTER: libc::c_ulong = 1;
+const SECCOMP_FILTER_FLAG_NEW_LISTENER: libc::c_ulong = 1 << 3;
+// syscalls which are made by youki after the filter is installed, until the listener has been sent.
+// They would block forever if they were notified, as nobody receives the notifications yet
+const UNNOTIFIABLE_SYSCALLS: [&str; 1] = ["sendmsg"];
+
+// offsets of the fields in struct seccomp_data
+const SECCOMP_DATA_NR: u32 = 0;
+const SECCOMP_DATA_ARCH: u32 = 4;
+const SECCOMP_DATA_ARGS: u32 = 16;
+// number of arguments a syscall can have
+const SYSCALL_ARGS: usize = 6;
+
+#[cfg(target_arch = "x86_64")]
+const NATIVE_ARCH: Arch = Arch::ScmpArchX86_64;
+#[cfg(target_arch = "aarch64")]
+const NATIVE_ARCH: Arch = Arch::ScmpArchAarch64;
+
+// syscalls of the x32 abi share the architecture with x86_64 and are marked with this bit
+#[cfg(target_arch = "x86_64")]
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+/// A single instruction of a classic BPF program, see struct sock_filter
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SockFilter {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+#[repr(C)]
+struct SockFprog {
+    len: u16,
+    filter: *const SockFilter,
+}
+
+/// Target of a conditional jump inside of the instructions of a syscall rule
+#[derive(Debug, Clone, Copy)]
+enum Jump {
+    // skips the given number of instructions
+    Skip(u8),
+    // continues with the next rule, as the syscall does not match this rule
+    NextRule,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Instruction {
+    code: u16,
+    jt: Jump,
+    jf: Jump,
+    k: u32,
+}
+
+impl Instruction {
+    fn stmt(code: u16, k: u32) -> Self {
+        Self {
+            code,
+            jt: Jump::Skip(0),
+            jf: Jump::Skip(0),
+            k,
+        }
+    }
+
+    fn jump(code: u16, k: u32, jt: Jump, jf: Jump) -> Self {
+        Self { code, jt, jf, k }
+    }
+}
+
+fn stmt(code: u16, k: u32) -> SockFilter {
+    SockFilter {
+        code,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+fn jump(code: u16, k: u32, jt: u8, jf: u8) -> SockFilter {
+    SockFilter { code, jt, jf, k }
+}
+
+/// Builds the seccomp filter from the specification and installs it for the current process.
+/// The filter is inherited by all children and cannot be removed afterwards. If syscalls are
+/// notified, the listener for the notifications is sent to the notify socket
+pub fn initialize_seccomp(
+    seccomp: &LinuxSeccomp,
+    notify_socket: Option<&NotifySocket>,
+) -> Result<()> {
+    let filter = build_filter(seccomp)?;
+    log::debug!("install seccomp filter with {} instructions", filter.len());
+
+    let prog = SockFprog {
+        len: filter.len() as u16,
+        filter: filter.as_ptr(),
+    };
+    if !uses_notify(seccomp) {
+        let ret = unsafe {
+            libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                &prog as *const SockFprog,
+            )
+        };
+        if ret != 0 {
+            bail!("failed to install seccomp filter: {}", Errno::last())
+        }
+        return Ok(());
+    }
+
+    let notify_socket = match notify_socket {
+        Some(notify_socket) => notify_socket,
+        None => {
+            bail!("seccomp profile notifies syscalls, but no --seccomp-notify-socket was given")
+        }
+    };
+    let listener = unsafe {
+        libc::syscall(
+            libc::SYS_seccomp,
+            SECCOMP_SET_MODE_FILTER,
+            SECCOMP_FILTER_FLAG_NEW_LISTENER,
+            &prog as *const SockFprog,
+        )
+    };
+    if listener < 0 {
+        bail!(
+            "failed to install seccomp filter with a listener: {}",
+            Errno::last()
+        )
+    }
+    notify_socket.send(listener as RawFd)
+}
+
+/// Whether any syscall of the profile is handled by a supervisor, which receives the notifications
+pub fn uses_notify(seccomp: &LinuxSeccomp) -> bool {
+    seccomp
+        .syscalls
+        .iter()
+        .any(|syscall| matches!(syscall.action, LinuxSeccompAction::ScmpActNotify))
+}
+
+// A notified syscall blocks until the supervisor responds, so that syscalls which youki needs
+// to make are never notified. A syscall, which was already killed by an earlier rule, would
+// never reach the supervisor
+fn validate_notify(seccomp: &LinuxSeccomp) -> Result<()> {
+    if matches!(seccomp.default_action, LinuxSeccompAction::ScmpActNotify) {
+        bail!("SCMP_ACT_NOTIFY can not be the default action of a seccomp profile")
+    }
+    for (i, syscall) in seccomp.syscalls.iter().enumerate() {
+        if !matches!(syscall.action, LinuxSeccompAction::ScmpActNotify) {
+            continue;
+        }
+        for name in &syscall.names {
+            if UNNOTIFIABLE_SYSCALLS.contains(&name.as_str()) {
+                bail!(
+                    "syscall {} can not be notified, as youki needs it to send the listener",
+                    name
+                )
+            }
+            let killed = seccomp.syscalls[..i].iter().any(|earlier| {
+                matches!(earlier.action, LinuxSeccompAction::ScmpActKill)
+                    && earlier.args.is_empty()
+                    && earlier.names.contains(name)
+            });
+            if killed {
+                bail!(
+                    "syscall {} is notified, but it is killed by an earlier rule of the seccomp profile",
+                    name
+                )
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Translates the seccomp profile into a BPF program. The rules are checked in the
+/// order of the specification and the action of the first matching rule is returned.
+/// Syscall numbers depend on the architecture, so the rules are repeated for every
+/// architecture of the profile with its own numbers
+pub fn build_filter(seccomp: &LinuxSeccomp) -> Result<Vec<SockFilter>> {
+    let native_arch = NATIVE_ARCH as u32;
+    let extra = extra_architectures(&seccomp.architectures)?;
+    validate_notify(seccomp)?;
+
+    // the rules of the architectures, which the kernel of the host executes besides the native one
+    let mut foreign: Vec<(u32, Vec<SockFilter>)> = Vec::new();
+    #[cfg(target_arch = "x86_64")]
+    let mut x32 = None;
+    for arch in extra {
+        match arch {
+            #[cfg(target_arch = "x86_64")]
+            Arch::ScmpArchX86 => {
+                let mut rules = vec![stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_NR)];
+                rules.extend(syscall_rules(seccomp, arch, syscall::from_name_x86)?);
+                foreign.push((arch as u32, rules));
+            }
+            // syscalls of x32 are made with the architecture of x86_64 and the x32 bit
+            #[cfg(target_arch = "x86_64")]
+            Arch::ScmpArchX32 => {
+                x32 = Some(syscall_rules(seccomp, arch, |name| {
+                    syscall::from_name_x32(name).map(|nr| nr | X32_SYSCALL_BIT as i64)
+                })?)
+            }
+            arch => log::warn!(
+                "syscalls of the architecture {:?} in the seccomp profile are killed, as they can not be made on this host",
+                arch
+            ),
+        }
+    }
+
+    // syscalls of any other architecture are not allowed. The offsets of the jumps
+    // to the rules of the foreign architectures are set once the rules are placed
+    let mut filter = vec![
+        stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_ARCH),
+        jump(
+            BPF_JMP | BPF_JEQ | BPF_K,
+            native_arch,
+            (2 * foreign.len() + 1) as u8,
+            0,
+        ),
+    ];
+    let mut blocks = Vec::new();
+    let mut jumps = Vec::new();
+    for (arch, rules) in foreign {
+        filter.push(jump(BPF_JMP | BPF_JEQ | BPF_K, arch, 0, 1));
+        jumps.push((filter.len(), blocks.len()));
+        filter.push(stmt(BPF_JMP | BPF_JA, 0));
+        blocks.push(rules);
+    }
+    filter.extend_from_slice(&[
+        stmt(BPF_RET | BPF_K, SECCOMP_RET_KILL),
+        stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_NR),
+    ]);
+    #[cfg(target_arch = "x86_64")]
+    {
+        filter.push(jump(BPF_JMP | BPF_JGE | BPF_K, X32_SYSCALL_BIT, 0, 1));
+        match x32 {
+            Some(rules) => {
+                jumps.push((filter.len(), blocks.len()));
+                filter.push(stmt(BPF_JMP | BPF_JA, 0));
+                blocks.push(rules);
+            }
+            None => filter.push(stmt(BPF_RET | BPF_K, SECCOMP_RET_KILL)),
+        }
+    }
+    filter.extend(syscall_rules(seccomp, NATIVE_ARCH, syscall::from_name)?);
+
+    let mut starts = Vec::new();
+    for rules in blocks {
+        starts.push(filter.len());
+        filter.extend(rules);
+    }
+    for (index, block) in jumps {
+        filter[index].k = (starts[block] - index - 1) as u32;
+    }
+
+    if filter.len() > BPF_MAXINSNS {
+        bail!(
+            "seccomp filter has {} instructions, but at most {} are supported",
+            filter.len(),
+            BPF_MAXINSNS
+        )
+    }
+
+    Ok(filter)
+}
+
+/// Translates the rules of the profile with the syscall numbers of an architecture, followed by
+/// the default action. The accumulator has to hold the syscall number at the start of the rules
+fn syscall_rules(
+    seccomp: &LinuxSeccomp,
+    arch: Arch,
+    syscall_nr: impl Fn(&str) -> Option<i64>,
+) -> Result<Vec<SockFilter>> {
+    let mut filter = Vec::new();
+    // the accumulator holds the syscall number until arguments are compared
+    let mut holds_nr = true;
+    for syscall in &seccomp.syscalls {
+        let action = action_value(syscall.action, syscall.errno_ret);
+        for name in &syscall.names {
+            let nr = match syscall_nr(name) {
+                Some(nr) => nr as u32,
+                None => {
+                    log::warn!(
+                        "unknown syscall {} of the architecture {:?} in seccomp profile is ignored",
+                        name,
+                        arch
+                    );
+                    continue;
+                }
+            };
+
+            let mut rule = Vec::new();
+            if !holds_nr {
+                rule.push(Instruction::stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_NR));
+            }
+            rule.push(Instruction::jump(
+                BPF_JMP | BPF_JEQ | BPF_K,
+                nr,
+                Jump::Skip(0),
+                Jump::NextRule,
+            ));
+            for arg in &syscall.args {
+                rule.extend(compare_arg(arg)?);
+            }
+            rule.push(Instruction::stmt(BPF_RET | BPF_K, action));
+
+            holds_nr = syscall.args.is_empty();
+            filter.extend(resolve_jumps(&rule)?);
+        }
+    }
+
+    filter.push(stmt(
+        BPF_RET | BPF_K,
+        action_value(seccomp.default_action, None),
+    ));
+    Ok(filter)
+}
+
+/// Returns the architectures of the profile besides the native one. The rules of the profile
+/// would not apply to any syscall, if the architectures were listed without the native one.
+/// No architectures at all stand for the native architecture
+fn extra_architectures(architectures: &[Arch]) -> Result<Vec<Arch>> {
+    let is_native =
+        |arch: &Arch| matches!(arch, Arch::ScmpArchNative) || *arch as u32 == NATIVE_ARCH as u32;
+    if !architectures.is_empty() && !architectures.iter().any(is_native) {
+        bail!(
+            "seccomp profile does not support the native architecture {:?}, but only {:?}",
+            NATIVE_ARCH,
+            architectures
+        )
+    }
+    Ok(architectures
+        .iter()
+        .filter(|arch| !is_native(arch))
+        .copied()
+        .collect())
+}
+
+fn action_value(action: LinuxSeccompAction, errno_ret: Option<u32>) -> u32 {
+    let data = errno_ret.unwrap_or(libc::EPERM as u32) & SECCOMP_RET_DATA;
+    match action {
+        LinuxSeccompAction::ScmpActErrno => SECCOMP_RET_ERRNO | data,
+        LinuxSeccompAction::ScmpActTrace => SECCOMP_RET_TRACE | data,
+        LinuxSeccompAction::ScmpActNotify => SECCOMP_RET_USER_NOTIF,
+        action => action as u32,
+    }
+}
+
+// Compares a 64 bit argument of the syscall. BPF can only handle 32 bit values, so the
+// upper and the lower half of the argument are compared separately. Execution falls
+// through to the next instruction if the argument matches, otherwise the rule is skipped
+fn compare_arg(arg: &LinuxSeccompArg) -> Result<Vec<Instruction>> {
+    use Jump::{NextRule, Skip};
+    use LinuxSeccompOperator::*;
+
+    if arg.index >= SYSCALL_ARGS {
+        bail!(
+            "invalid seccomp argument index {}, a syscall has at most {} arguments",
+            arg.index,
+            SYSCALL_ARGS
+        )
+    }
+
+    // the architectures supported by youki are little endian
+    let low_offset = SECCOMP_DATA_ARGS + 8 * arg.index as u32;
+    let high_offset = low_offset + 4;
+    let load_low = Instruction::stmt(BPF_LD | BPF_W | BPF_ABS, low_offset);
+    let load_high = Instruction::stmt(BPF_LD | BPF_W | BPF_ABS, high_offset);
+    let (high, low) = split(arg.value);
+
+    let instructions = match arg.op {
+        ScmpCmpEq => vec![
+            load_high,
+            Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, high, Skip(0), NextRule),
+            load_low,
+            Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, low, Skip(0), NextRule),
+        ],
+        ScmpCmpNe => vec![
+            load_high,
+            Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, high, Skip(0), Skip(2)),
+            load_low,
+            Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, low, NextRule, Skip(0)),
+        ],
+        // value is the mask and value_two the expected value after the mask has been applied
+        ScmpCmpMaskedEq => {
+            let (mask_high, mask_low) = (high, low);
+            let (high, low) = split(arg.value_two);
+            vec![
+                load_high,
+                Instruction::stmt(BPF_ALU | BPF_AND | BPF_K, mask_high),
+                Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, high, Skip(0), NextRule),
+                load_low,
+                Instruction::stmt(BPF_ALU | BPF_AND | BPF_K, mask_low),
+                Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, low, Skip(0), NextRule),
+            ]
+        }
+        // a greater upper half decides the comparison, otherwise
+        // the upper halves have to be equal and the lower halves decide
+        ScmpCmpGt | ScmpCmpGe => vec![
+            load_high,
+            Instruction::jump(BPF_JMP | BPF_JGT | BPF_K, high, Skip(3), Skip(0)),
+            Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, high, Skip(0), NextRule),
+            load_low,
+            Instruction::jump(
+                BPF_JMP
+                    | if matches!(arg.op, ScmpCmpGt) {
+                        BPF_JGT
+                    } else {
+                        BPF_JGE
+                    }
+                    | BPF_K,
+                low,
+                Skip(0),
+                NextRule,
+            ),
+        ],
+        ScmpCmpLt | ScmpCmpLe => vec![
+            load_high,
+            Instruction::jump(BPF_JMP | BPF_JGE | BPF_K, high, Skip(0), Skip(3)),
+            Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, high, Skip(0), NextRule),
+            load_low,
+            Instruction::jump(
+                BPF_JMP
+                    | if matches!(arg.op, ScmpCmpLt) {
+                        BPF_JGE
+                    } else {
+                        BPF_JGT
+                    }
+                    | BPF_K,
+                low,
+                NextRule,
+                Skip(0),
+            ),
+        ],
+    };
+
+    Ok(instructions)
+}
+
+fn split(value: u64) -> (u32, u32) {
+    ((value >> 32) as u32, value as u32)
+}
+
+// replaces the jumps to the next rule with the offset to the end of the rule
+fn resolve_jumps(rule: &[Instruction]) -> Result<Vec<SockFilter>> {
+    let resolve = |jump: Jump, index: usize| -> Result<u8> {
+        match jump {
+            Jump::Skip(offset) => Ok(offset),
+            Jump::NextRule => {
+                let offset = rule.len() - index - 1;
+                if offset > u8::MAX as usize {
+                    bail!("seccomp rule is too long")
+                }
+                Ok(offset as u8)
+            }
+        }
+    };
+
+    rule.iter()
+        .enumerate()
+        .map(|(i, instruction)| {
+            Ok(SockFilter {
+                code: instruction.code,
+                jt: resolve(instruction.jt, i)?,
+                jf: resolve(instruction.jf, i)?,
+                k: instruction.k,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oci_spec::LinuxSyscall;
+
+    const BPF_MISC: u16 = 0x07;
+
+    // evaluates the filter for a syscall like the kernel does
+    fn run_filter(filter: &[SockFilter], arch: u32, nr: u32, args: [u64; 6]) -> u32 {
+        let mut data = vec![0u8; 64];
+        data[0..4].copy_from_slice(&nr.to_le_bytes());
+        data[4..8].copy_from_slice(&arch.to_le_bytes());
+        for (i, arg) in args.iter().enumerate() {
+            let offset = SECCOMP_DATA_ARGS as usize + 8 * i;
+            data[offset..offset + 8].copy_from_slice(&arg.to_le_bytes());
+        }
+
+        let mut accumulator = 0u32;
+        let mut pc = 0;
+        loop {
+            let instruction = filter[pc];
+            pc += 1;
+            match instruction.code & 0x07 {
+                BPF_LD => {
+                    let k = instruction.k as usize;
+                    let mut word = [0u8; 4];
+                    word.copy_from_slice(&data[k..k + 4]);
+                    accumulator = u32::from_le_bytes(word);
+                }
+                BPF_ALU => accumulator &= instruction.k,
+                BPF_JMP => {
+                    let taken = match instruction.code & 0xf0 {
+                        BPF_JA => {
+                            pc += instruction.k as usize;
+                            continue;
+                        }
+                        BPF_JEQ => accumulator == instruction.k,
+                        BPF_JGT => accumulator > instruction.k,
+                        BPF_JGE => accumulator >= instruction.k,
+                        code => panic!("unexpected jump {:#x}", code),
+                    };
+                    pc += if taken {
+                        instruction.jt
+                    } else {
+                        instruction.jf
+                    } as usize;
+                }
+                BPF_RET => return instruction.k,
+                BPF_MISC => panic!("unexpected misc instruction"),
+                class => panic!("unexpected instruction class {:#x}", class),
+            }
+        }
+    }
+
+    fn nr(name: &str) -> u32 {
+        syscall::from_name(name).expect("known syscall") as u32
+    }
+
+    fn seccomp(default_action: LinuxSeccompAction, syscalls: Vec<LinuxSyscall>) -> LinuxSeccomp {
+        LinuxSeccomp {
+            default_action,
+            architectures: vec![NATIVE_ARCH],
+            syscalls,
+        }
+    }
+
+    fn syscall_rule(
+        names: &[&str],
+        action: LinuxSeccompAction,
+        args: Vec<LinuxSeccompArg>,
+    ) -> LinuxSyscall {
+        LinuxSyscall {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            action,
+            errno_ret: None,
+            args,
+        }
+    }
+
+    fn arg(index: usize, op: LinuxSeccompOperator, value: u64, value_two: u64) -> LinuxSeccompArg {
+        LinuxSeccompArg {
+            index,
+            value,
+            value_two,
+            op,
+        }
+    }
+
+    fn native_arch() -> u32 {
+        NATIVE_ARCH as u32
+    }
+
+    #[test]
+    fn test_build_filter_rule_count_and_default_action() {
+        let seccomp = seccomp(
+            LinuxSeccompAction::ScmpActErrno,
+            vec![syscall_rule(
+                &["read", "write", "exit_group"],
+                LinuxSeccompAction::ScmpActAllow,
+                vec![],
+            )],
+        );
+
+        let filter = build_filter(&seccomp).expect("build seccomp filter");
+
+        // every syscall without arguments is a comparison followed by the action
+        let rules = filter
+            .iter()
+            .filter(|i| i.code == BPF_RET | BPF_K && i.k == LinuxSeccompAction::ScmpActAllow as u32)
+            .count();
+        assert_eq!(rules, 3);
+        assert_eq!(
+            filter.last(),
+            Some(&stmt(
+                BPF_RET | BPF_K,
+                SECCOMP_RET_ERRNO | libc::EPERM as u32
+            ))
+        );
+    }
+
+    #[test]
+    fn test_filter_actions() {
+        let mut errno_rule = syscall_rule(&["mkdir"], LinuxSeccompAction::ScmpActErrno, vec![]);
+        errno_rule.errno_ret = Some(libc::ENOSYS as u32);
+        let seccomp = seccomp(
+            LinuxSeccompAction::ScmpActAllow,
+            vec![
+                syscall_rule(&["kexec_load"], LinuxSeccompAction::ScmpActKill, vec![]),
+                syscall_rule(&["reboot"], LinuxSeccompAction::ScmpActTrap, vec![]),
+                syscall_rule(&["ptrace"], LinuxSeccompAction::ScmpActTrace, vec![]),
+                syscall_rule(&["mount"], LinuxSeccompAction::ScmpActNotify, vec![]),
+                errno_rule,
+            ],
+        );
+
+        let filter = build_filter(&seccomp).expect("build seccomp filter");
+
+        let run = |name| run_filter(&filter, native_arch(), nr(name), [0; 6]);
+        assert_eq!(run("kexec_load"), SECCOMP_RET_KILL);
+        assert_eq!(run("reboot"), LinuxSeccompAction::ScmpActTrap as u32);
+        assert_eq!(run("ptrace"), SECCOMP_RET_TRACE | libc::EPERM as u32);
+        assert_eq!(run("mkdirat"), LinuxSeccompAction::ScmpActAllow as u32);
+        assert_eq!(run("mkdir"), SECCOMP_RET_ERRNO | libc::ENOSYS as u32);
+        assert_eq!(run("mount"), SECCOMP_RET_USER_NOTIF);
+    }
+
+    #[test]
+    fn test_action_value() {
+        use LinuxSeccompAction::*;
+
+        assert_eq!(action_value(ScmpActKill, None), SECCOMP_RET_KILL);
+        assert_eq!(action_value(ScmpActAllow, None), 0x7fff_0000);
+        assert_eq!(action_value(ScmpActTrap, None), 0x0003_0000);
+        assert_eq!(
+            action_value(ScmpActErrno, Some(libc::EACCES as u32)),
+            SECCOMP_RET_ERRNO | libc::EACCES as u32
+        );
+        assert_eq!(
+            action_value(ScmpActErrno, None),
+            SECCOMP_RET_ERRNO | libc::EPERM as u32
+        );
+        // the errno is only returned by errno and trace
+        assert_eq!(action_value(ScmpActNotify, Some(1)), SECCOMP_RET_USER_NOTIF);
+    }
+
+    #[test]
+    fn test_uses_notify() {
+        let notify = seccomp(
+            LinuxSeccompAction::ScmpActAllow,
+            vec![syscall_rule(
+                &["mount"],
+                LinuxSeccompAction::ScmpActNotify,
+                vec![],
+            )],
+        );
+        assert!(uses_notify(&notify));
+        assert!(!uses_notify(&seccomp(
+            LinuxSeccompAction::ScmpActAllow,
+            vec![]
+        )));
+    }
+
+    #[test]
+    fn test_invalid_notify() {
+        let default_notify = seccomp(LinuxSeccompAction::ScmpActNotify, vec![]);
+        assert!(build_filter(&default_notify).is_err());
+
+        let sendmsg = seccomp(
+            LinuxSeccompAction::ScmpActAllow,
+            vec![syscall_rule(
+                &["sendmsg"],
+                LinuxSeccompAction::ScmpActNotify,
+                vec![],
+            )],
+        );
+        assert!(build_filter(&sendmsg).is_err());
+
+        let killed = seccomp(
+            LinuxSeccompAction::ScmpActAllow,
+            vec![
+                syscall_rule(
+                    &["mount", "umount2"],
+                    LinuxSeccompAction::ScmpActKill,
+                    vec![],
+                ),
+                syscall_rule(&["mount"], LinuxSeccompAction::ScmpActNotify, vec![]),
+            ],
+        );
+        let err = build_filter(&killed).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "syscall mount is notified, but it is killed by an earlier rule of the seccomp profile"
+        );
+
+        // notifying the syscalls, which are not killed, with a kill default is fine
+        let kill_default = seccomp(
+            LinuxSeccompAction::ScmpActKill,
+            vec![syscall_rule(
+                &["mount"],
+                LinuxSeccompAction::ScmpActNotify,
+                vec![],
+            )],
+        );
+        assert!(build_filter(&kill_default).is_ok());
+    }
+
+    #[test]
+    fn test_filter_kills_foreign_architecture() {
+        let seccomp = seccomp(LinuxSeccompAction::ScmpActAllow, vec![]);
+
+        let filter = build_filter(&seccomp).expect("build seccomp filter");
+
+        assert_eq!(
+            run_filter(&filter, Arch::ScmpArchX86 as u32, 0, [0; 6]),
+            SECCOMP_RET_KILL
+        );
+        assert_eq!(
+            run_filter(&filter, native_arch(), 0, [0; 6]),
+            LinuxSeccompAction::ScmpActAllow as u32
+        );
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_filter_of_x86_and_x32() {
+        let errno = SECCOMP_RET_ERRNO | libc::EPERM as u32;
+        let allow = LinuxSeccompAction::ScmpActAllow as u32;
+        let mut seccomp = seccomp(
+            LinuxSeccompAction::ScmpActAllow,
+            vec![syscall_rule(
+                &["getpid"],
+                LinuxSeccompAction::ScmpActErrno,
+                vec![],
+            )],
+        );
+        let x86 = Arch::ScmpArchX86 as u32;
+        seccomp.architectures = vec![NATIVE_ARCH, Arch::ScmpArchX86];
+
+        let filter = build_filter(&seccomp).expect("build seccomp filter");
+
+        // getpid is 20 on x86 and 39 on x86_64 and x32
+        assert_eq!(run_filter(&filter, x86, 20, [0; 6]), errno);
+        assert_eq!(run_filter(&filter, x86, 39, [0; 6]), allow);
+        assert_eq!(run_filter(&filter, native_arch(), 39, [0; 6]), errno);
+        assert_eq!(run_filter(&filter, native_arch(), 20, [0; 6]), allow);
+        assert_eq!(
+            run_filter(&filter, native_arch(), X32_SYSCALL_BIT | 39, [0; 6]),
+            SECCOMP_RET_KILL
+        );
+
+        seccomp.architectures = vec![Arch::ScmpArchX32, NATIVE_ARCH, Arch::ScmpArchX86];
+        let filter = build_filter(&seccomp).expect("build seccomp filter");
+
+        assert_eq!(
+            run_filter(&filter, native_arch(), X32_SYSCALL_BIT | 39, [0; 6]),
+            errno
+        );
+        assert_eq!(
+            run_filter(&filter, native_arch(), X32_SYSCALL_BIT | 20, [0; 6]),
+            allow
+        );
+        assert_eq!(run_filter(&filter, x86, 20, [0; 6]), errno);
+        assert_eq!(run_filter(&filter, native_arch(), 39, [0; 6]), errno);
+        assert_eq!(
+            run_filter(&filter, Arch::ScmpArchArm as u32, 20, [0; 6]),
+            SECCOMP_RET_KILL
+        );
+    }
+
+    #[test]
+    fn test_filter_arg_comparisons() {
+        use LinuxSeccompOperator::*;
+
+        let allow = LinuxSeccompAction::ScmpActAllow as u32;
+        let deny = SECCOMP_RET_ERRNO | libc::EPERM as u32;
+        let large = 0x1_0000_0005;
+        let cases = vec![
+            (arg(0, ScmpCmpEq, large, 0), vec![(large, true), (5, false)]),
+            (arg(0, ScmpCmpNe, large, 0), vec![(large, false), (5, true)]),
+            (
+                arg(0, ScmpCmpGt, large, 0),
+                vec![
+                    (large + 1, true),
+                    (large, false),
+                    (0x2_0000_0000, true),
+                    (6, false),
+                ],
+            ),
+            (
+                arg(0, ScmpCmpGe, large, 0),
+                vec![(large, true), (large - 1, false), (0x2_0000_0000, true)],
+            ),
+            (
+                arg(0, ScmpCmpLt, large, 0),
+                vec![
+                    (large - 1, true),
+                    (large, false),
+                    (6, true),
+                    (0x2_0000_0000, false),
+                ],
+            ),
+            (
+                arg(0, ScmpCmpLe, large, 0),
+                vec![(large, true), (large + 1, false), (6, true)],
+            ),
+            // e.g. clone is only allowed without CLONE_NEWUSER
+            (
+                arg(1, ScmpCmpMaskedEq, 0x1000_0000, 0),
+                vec![(0x1000_0000, false), (0x0100_0011, true)],
+            ),
+        ];
+
+        for (arg, expectations) in cases {
+            let op = arg.op;
+            let index = arg.index;
+            let seccomp = seccomp(
+                LinuxSeccompAction::ScmpActErrno,
+                vec![syscall_rule(
+                    &["personality"],
+                    LinuxSeccompAction::ScmpActAllow,
+                    vec![arg],
+                )],
+            );
+            let filter = build_filter(&seccomp).expect("build seccomp filter");
+
+            for (value, allowed) in expectations {
+                let mut args = [0; 6];
+                args[index] = value;
+                let result = run_filter(&filter, native_arch(), nr("personality"), args);
+                assert_eq!(
+                    result,
+                    if allowed { allow } else { deny },
+                    "{:?} with argument {:#x}",
+                    op,
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_multiple_args_must_all_match() {
+        use LinuxSeccompOperator::*;
+
+        let seccomp = seccomp(
+            LinuxSeccompAction::ScmpActErrno,
+            vec![
+                syscall_rule(
+                    &["socket"],
+                    LinuxSeccompAction::ScmpActAllow,
+                    vec![
+                        arg(0, ScmpCmpEq, libc::AF_UNIX as u64, 0),
+                        arg(1, ScmpCmpEq, libc::SOCK_STREAM as u64, 0),
+                    ],
+                ),
+                syscall_rule(&["getpid"], LinuxSeccompAction::ScmpActAllow, vec![]),
+            ],
+        );
+
+        let filter = build_filter(&seccomp).expect("build seccomp filter");
+
+        let allow = LinuxSeccompAction::ScmpActAllow as u32;
+        let socket = |domain: i32, typ: i32| {
+            run_filter(
+                &filter,
+                native_arch(),
+                nr("socket"),
+                [domain as u64, typ as u64, 0, 0, 0, 0],
+            )
+        };
+        assert_eq!(socket(libc::AF_UNIX, libc::SOCK_STREAM), allow);
+        assert_ne!(socket(libc::AF_UNIX, libc::SOCK_DGRAM), allow);
+        assert_ne!(socket(libc::AF_INET, libc::SOCK_STREAM), allow);
+        // the syscall number has to be reloaded after the arguments have been compared
+        assert_eq!(
+            run_filter(&filter, native_arch(), nr("getpid"), [0; 6]),
+            allow
+        );
+    }
+
+    #[test]
+    fn test_unknown_syscalls_are_ignored() {
+        let seccomp = seccomp(
+            LinuxSeccompAction::ScmpActErrno,
+            vec![syscall_rule(
+                &["not_a_syscall"],
+                LinuxSeccompAction::ScmpActAllow,
+                vec![],
+            )],
+        );
+
+        let filter = build_filter(&seccomp).expect("build seccomp filter");
+        assert!(!filter
+            .iter()
+            .any(|i| i.code == BPF_RET | BPF_K && i.k == LinuxSeccompAction::ScmpActAllow as u32));
+    }
+
+    #[test]
+    fn test_invalid_arg_index() {
+        let seccomp = seccomp(
+            LinuxSeccompAction::ScmpActErrno,
+            vec![syscall_rule(
+                &["read"],
+                LinuxSeccompAction::ScmpActAllow,
+                vec![arg(6, LinuxSeccompOperator::ScmpCmpEq, 0, 0)],
+            )],
+        );
+
+        assert!(build_filter(&seccomp).is_err());
+    }
+
+    #[test]
+    fn test_native_arch_of_host() {
+        let expected = match std::env::consts::ARCH {
+            "x86_64" => Arch::ScmpArchX86_64,
+            "aarch64" => Arch::ScmpArchAarch64,
+            arch => panic!("unsupported architecture {}", arch),
+        };
+        assert_eq!(NATIVE_ARCH as u32, expected as u32);
+    }
+
+    #[test]
+    fn test_extra_architectures() {
+        let values = |arches: Vec<Arch>| arches.iter().map(|a| *a as u32).collect::<Vec<_>>();
+
+        assert!(extra_architectures(&[])
+            .expect("no architectures")
+            .is_empty());
+        assert!(extra_architectures(&[Arch::ScmpArchNative])
+            .expect("native architecture")
+            .is_empty());
+        assert!(extra_architectures(&[NATIVE_ARCH])
+            .expect("host architecture")
+            .is_empty());
+        let extra = extra_architectures(&[Arch::ScmpArchX86, NATIVE_ARCH, Arch::ScmpArchX32])
+            .expect("extra architectures");
+        assert_eq!(
+            values(extra),
+            vec![Arch::ScmpArchX86 as u32, Arch::ScmpArchX32 as u32]
+        );
+        assert!(extra_architectures(&[Arch::ScmpArchS390x, Arch::ScmpArchPpc64le]).is_err());
+    }
+
+    #[test]
+    fn test_native_architecture_is_required() {
+        let mut seccomp = seccomp(LinuxSeccompAction::ScmpActAllow, vec![]);
+        seccomp.architectures = vec![Arch::ScmpArchS390x];
+
+        assert!(build_filter(&seccomp).is_err());
+    }
+}
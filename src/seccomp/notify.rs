@@ -0,0 +1,130 @@
+//! Socket of a supervisor, which handles the syscalls notified by the seccomp filter of a container
+//! through the listener of the filter. The listener is sent along with the id of the container,
+//! check https://man7.org/linux/man-pages/man2/seccomp_unotify.2.html for more information
+
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::os::unix::io::RawFd;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use nix::sys::{socket, uio::IoVec};
+use nix::unistd::close;
+
+/// Name of the link to the notify socket in the directory of the container
+const SECCOMP_NOTIFY_SOCKET: &str = "seccomp-notify-socket";
+
+/// Connection to the notify socket, which is made before the root of the container is changed
+#[derive(Debug)]
+pub struct NotifySocket {
+    fd: RawFd,
+    container_id: String,
+}
+
+impl NotifySocket {
+    /// Connects to the notify socket. Like the console socket, it is connected through a link in the
+    /// directory of the container, which has to be the current working directory
+    pub fn connect(container_dir: &Path, notify_socket: &Path, container_id: &str) -> Result<Self> {
+        let link = container_dir.join(SECCOMP_NOTIFY_SOCKET);
+        // a link of an earlier process of the container is replaced
+        if fs::symlink_metadata(&link).is_ok() {
+            fs::remove_file(&link).with_context(|| {
+                format!("failed to remove the link to the notify socket {:?}", link)
+            })?;
+        }
+        symlink(notify_socket, &link)
+            .with_context(|| format!("failed to link the notify socket {:?}", notify_socket))?;
+
+        let fd = socket::socket(
+            socket::AddressFamily::Unix,
+            socket::SockType::Stream,
+            socket::SockFlag::SOCK_CLOEXEC,
+            None,
+        )?;
+        let addr = socket::SockAddr::Unix(socket::UnixAddr::new(SECCOMP_NOTIFY_SOCKET)?);
+        if let Err(e) = socket::connect(fd, &addr) {
+            let _ = close(fd);
+            bail!(
+                "failed to connect to the notify socket {:?}: {}",
+                notify_socket,
+                e
+            );
+        }
+
+        Ok(Self {
+            fd,
+            container_id: container_id.to_string(),
+        })
+    }
+
+    /// Sends the listener with SCM_RIGHTS, so that the supervisor gets its own copy of it.
+    /// The listener of youki is closed afterwards, it must not be inherited by the container process
+    pub fn send(&self, listener: RawFd) -> Result<()> {
+        let iov = [IoVec::from_slice(self.container_id.as_bytes())];
+        let fds = [listener];
+        let cmsg = socket::ControlMessage::ScmRights(&fds);
+        let sent = socket::sendmsg(self.fd, &iov, &[cmsg], socket::MsgFlags::empty(), None)
+            .context("failed to send the seccomp listener to the notify socket");
+        let _ = close(listener);
+        sent.map(drop)
+    }
+}
+
+impl Drop for NotifySocket {
+    fn drop(&mut self) {
+        let _ = close(self.fd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::cmsg_space;
+    use nix::sys::socket::{ControlMessageOwned, MsgFlags};
+    use nix::unistd::{pipe, read, write};
+
+    #[test]
+    fn test_send_listener() {
+        let (sender, receiver) = socket::socketpair(
+            socket::AddressFamily::Unix,
+            socket::SockType::Stream,
+            None,
+            socket::SockFlag::empty(),
+        )
+        .expect("create socket pair");
+        // a pipe stands in for the listener of a seccomp filter
+        let (read_end, write_end) = pipe().expect("create pipe");
+        let notify_socket = NotifySocket {
+            fd: sender,
+            container_id: "notify_test".to_string(),
+        };
+
+        notify_socket.send(write_end).expect("send listener");
+
+        let mut buf = [0u8; 64];
+        let iov = [IoVec::from_mut_slice(&mut buf)];
+        let mut cmsg_buffer = cmsg_space!([RawFd; 1]);
+        let msg = socket::recvmsg(receiver, &iov, Some(&mut cmsg_buffer), MsgFlags::empty())
+            .expect("receive message");
+        let fds: Vec<RawFd> = msg
+            .cmsgs()
+            .filter_map(|cmsg| match cmsg {
+                ControlMessageOwned::ScmRights(fds) => Some(fds),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert_eq!(&buf[..msg.bytes], b"notify_test");
+        assert_eq!(fds.len(), 1);
+        // the received copy refers to the pipe, although the listener of youki has been closed
+        write(fds[0], b"x").expect("write to the received fd");
+        let mut content = [0u8; 1];
+        read(read_end, &mut content).expect("read pipe");
+        assert_eq!(&content, b"x");
+
+        drop(notify_socket);
+        for fd in [receiver, read_end, fds[0]].iter() {
+            let _ = close(*fd);
+        }
+    }
+}
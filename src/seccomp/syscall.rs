@@ -0,0 +1,1484 @@
+//! Maps the syscall names used in the seccomp profile to the syscall numbers of
+//! the architecture youki has been built for, and of the 32 bit architectures,
+//! whose syscalls the kernel of the architecture executes as well
+
+macro_rules! syscalls {
+    ($($name:ident => $nr:ident,)*) => {
+        /// Returns the number of the syscall with the given name or None if the
+        /// syscall does not exist on this architecture
+        pub fn from_name(name: &str) -> Option<i64> {
+            match name {
+                $(stringify!($name) => Some(libc::$nr as i64),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+// the numbers of the syscalls of the 32 bit architectures are not part of libc, so they
+// are taken from the headers of the kernel, see asm/unistd_32.h and asm/unistd_x32.h
+#[cfg(target_arch = "x86_64")]
+macro_rules! numbered_syscalls {
+    ($(#[$doc:meta])* $fn:ident, $($name:literal => $nr:literal,)*) => {
+        $(#[$doc])*
+        pub fn $fn(name: &str) -> Option<i64> {
+            match name {
+                $($name => Some($nr),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+syscalls! {
+    read => SYS_read,
+    write => SYS_write,
+    open => SYS_open,
+    close => SYS_close,
+    stat => SYS_stat,
+    fstat => SYS_fstat,
+    lstat => SYS_lstat,
+    poll => SYS_poll,
+    lseek => SYS_lseek,
+    mmap => SYS_mmap,
+    mprotect => SYS_mprotect,
+    munmap => SYS_munmap,
+    brk => SYS_brk,
+    rt_sigaction => SYS_rt_sigaction,
+    rt_sigprocmask => SYS_rt_sigprocmask,
+    rt_sigreturn => SYS_rt_sigreturn,
+    ioctl => SYS_ioctl,
+    pread64 => SYS_pread64,
+    pwrite64 => SYS_pwrite64,
+    readv => SYS_readv,
+    writev => SYS_writev,
+    access => SYS_access,
+    pipe => SYS_pipe,
+    select => SYS_select,
+    sched_yield => SYS_sched_yield,
+    mremap => SYS_mremap,
+    msync => SYS_msync,
+    mincore => SYS_mincore,
+    madvise => SYS_madvise,
+    shmget => SYS_shmget,
+    shmat => SYS_shmat,
+    shmctl => SYS_shmctl,
+    dup => SYS_dup,
+    dup2 => SYS_dup2,
+    pause => SYS_pause,
+    nanosleep => SYS_nanosleep,
+    getitimer => SYS_getitimer,
+    alarm => SYS_alarm,
+    setitimer => SYS_setitimer,
+    getpid => SYS_getpid,
+    sendfile => SYS_sendfile,
+    socket => SYS_socket,
+    connect => SYS_connect,
+    accept => SYS_accept,
+    sendto => SYS_sendto,
+    recvfrom => SYS_recvfrom,
+    sendmsg => SYS_sendmsg,
+    recvmsg => SYS_recvmsg,
+    shutdown => SYS_shutdown,
+    bind => SYS_bind,
+    listen => SYS_listen,
+    getsockname => SYS_getsockname,
+    getpeername => SYS_getpeername,
+    socketpair => SYS_socketpair,
+    setsockopt => SYS_setsockopt,
+    getsockopt => SYS_getsockopt,
+    clone => SYS_clone,
+    fork => SYS_fork,
+    vfork => SYS_vfork,
+    execve => SYS_execve,
+    exit => SYS_exit,
+    wait4 => SYS_wait4,
+    kill => SYS_kill,
+    uname => SYS_uname,
+    semget => SYS_semget,
+    semop => SYS_semop,
+    semctl => SYS_semctl,
+    shmdt => SYS_shmdt,
+    msgget => SYS_msgget,
+    msgsnd => SYS_msgsnd,
+    msgrcv => SYS_msgrcv,
+    msgctl => SYS_msgctl,
+    fcntl => SYS_fcntl,
+    flock => SYS_flock,
+    fsync => SYS_fsync,
+    fdatasync => SYS_fdatasync,
+    truncate => SYS_truncate,
+    ftruncate => SYS_ftruncate,
+    getdents => SYS_getdents,
+    getcwd => SYS_getcwd,
+    chdir => SYS_chdir,
+    fchdir => SYS_fchdir,
+    rename => SYS_rename,
+    mkdir => SYS_mkdir,
+    rmdir => SYS_rmdir,
+    creat => SYS_creat,
+    link => SYS_link,
+    unlink => SYS_unlink,
+    symlink => SYS_symlink,
+    readlink => SYS_readlink,
+    chmod => SYS_chmod,
+    fchmod => SYS_fchmod,
+    chown => SYS_chown,
+    fchown => SYS_fchown,
+    lchown => SYS_lchown,
+    umask => SYS_umask,
+    gettimeofday => SYS_gettimeofday,
+    getrlimit => SYS_getrlimit,
+    getrusage => SYS_getrusage,
+    sysinfo => SYS_sysinfo,
+    times => SYS_times,
+    ptrace => SYS_ptrace,
+    getuid => SYS_getuid,
+    syslog => SYS_syslog,
+    getgid => SYS_getgid,
+    setuid => SYS_setuid,
+    setgid => SYS_setgid,
+    geteuid => SYS_geteuid,
+    getegid => SYS_getegid,
+    setpgid => SYS_setpgid,
+    getppid => SYS_getppid,
+    getpgrp => SYS_getpgrp,
+    setsid => SYS_setsid,
+    setreuid => SYS_setreuid,
+    setregid => SYS_setregid,
+    getgroups => SYS_getgroups,
+    setgroups => SYS_setgroups,
+    setresuid => SYS_setresuid,
+    getresuid => SYS_getresuid,
+    setresgid => SYS_setresgid,
+    getresgid => SYS_getresgid,
+    getpgid => SYS_getpgid,
+    setfsuid => SYS_setfsuid,
+    setfsgid => SYS_setfsgid,
+    getsid => SYS_getsid,
+    capget => SYS_capget,
+    capset => SYS_capset,
+    rt_sigpending => SYS_rt_sigpending,
+    rt_sigtimedwait => SYS_rt_sigtimedwait,
+    rt_sigqueueinfo => SYS_rt_sigqueueinfo,
+    rt_sigsuspend => SYS_rt_sigsuspend,
+    sigaltstack => SYS_sigaltstack,
+    utime => SYS_utime,
+    mknod => SYS_mknod,
+    uselib => SYS_uselib,
+    personality => SYS_personality,
+    ustat => SYS_ustat,
+    statfs => SYS_statfs,
+    fstatfs => SYS_fstatfs,
+    sysfs => SYS_sysfs,
+    getpriority => SYS_getpriority,
+    setpriority => SYS_setpriority,
+    sched_setparam => SYS_sched_setparam,
+    sched_getparam => SYS_sched_getparam,
+    sched_setscheduler => SYS_sched_setscheduler,
+    sched_getscheduler => SYS_sched_getscheduler,
+    sched_get_priority_max => SYS_sched_get_priority_max,
+    sched_get_priority_min => SYS_sched_get_priority_min,
+    sched_rr_get_interval => SYS_sched_rr_get_interval,
+    mlock => SYS_mlock,
+    munlock => SYS_munlock,
+    mlockall => SYS_mlockall,
+    munlockall => SYS_munlockall,
+    vhangup => SYS_vhangup,
+    modify_ldt => SYS_modify_ldt,
+    pivot_root => SYS_pivot_root,
+    _sysctl => SYS__sysctl,
+    prctl => SYS_prctl,
+    arch_prctl => SYS_arch_prctl,
+    adjtimex => SYS_adjtimex,
+    setrlimit => SYS_setrlimit,
+    chroot => SYS_chroot,
+    sync => SYS_sync,
+    acct => SYS_acct,
+    settimeofday => SYS_settimeofday,
+    mount => SYS_mount,
+    umount2 => SYS_umount2,
+    swapon => SYS_swapon,
+    swapoff => SYS_swapoff,
+    reboot => SYS_reboot,
+    sethostname => SYS_sethostname,
+    setdomainname => SYS_setdomainname,
+    iopl => SYS_iopl,
+    ioperm => SYS_ioperm,
+    create_module => SYS_create_module,
+    init_module => SYS_init_module,
+    delete_module => SYS_delete_module,
+    get_kernel_syms => SYS_get_kernel_syms,
+    query_module => SYS_query_module,
+    quotactl => SYS_quotactl,
+    nfsservctl => SYS_nfsservctl,
+    getpmsg => SYS_getpmsg,
+    putpmsg => SYS_putpmsg,
+    afs_syscall => SYS_afs_syscall,
+    tuxcall => SYS_tuxcall,
+    security => SYS_security,
+    gettid => SYS_gettid,
+    readahead => SYS_readahead,
+    setxattr => SYS_setxattr,
+    lsetxattr => SYS_lsetxattr,
+    fsetxattr => SYS_fsetxattr,
+    getxattr => SYS_getxattr,
+    lgetxattr => SYS_lgetxattr,
+    fgetxattr => SYS_fgetxattr,
+    listxattr => SYS_listxattr,
+    llistxattr => SYS_llistxattr,
+    flistxattr => SYS_flistxattr,
+    removexattr => SYS_removexattr,
+    lremovexattr => SYS_lremovexattr,
+    fremovexattr => SYS_fremovexattr,
+    tkill => SYS_tkill,
+    time => SYS_time,
+    futex => SYS_futex,
+    sched_setaffinity => SYS_sched_setaffinity,
+    sched_getaffinity => SYS_sched_getaffinity,
+    set_thread_area => SYS_set_thread_area,
+    io_setup => SYS_io_setup,
+    io_destroy => SYS_io_destroy,
+    io_getevents => SYS_io_getevents,
+    io_submit => SYS_io_submit,
+    io_cancel => SYS_io_cancel,
+    get_thread_area => SYS_get_thread_area,
+    lookup_dcookie => SYS_lookup_dcookie,
+    epoll_create => SYS_epoll_create,
+    epoll_ctl_old => SYS_epoll_ctl_old,
+    epoll_wait_old => SYS_epoll_wait_old,
+    remap_file_pages => SYS_remap_file_pages,
+    getdents64 => SYS_getdents64,
+    set_tid_address => SYS_set_tid_address,
+    restart_syscall => SYS_restart_syscall,
+    semtimedop => SYS_semtimedop,
+    fadvise64 => SYS_fadvise64,
+    timer_create => SYS_timer_create,
+    timer_settime => SYS_timer_settime,
+    timer_gettime => SYS_timer_gettime,
+    timer_getoverrun => SYS_timer_getoverrun,
+    timer_delete => SYS_timer_delete,
+    clock_settime => SYS_clock_settime,
+    clock_gettime => SYS_clock_gettime,
+    clock_getres => SYS_clock_getres,
+    clock_nanosleep => SYS_clock_nanosleep,
+    exit_group => SYS_exit_group,
+    epoll_wait => SYS_epoll_wait,
+    epoll_ctl => SYS_epoll_ctl,
+    tgkill => SYS_tgkill,
+    utimes => SYS_utimes,
+    vserver => SYS_vserver,
+    mbind => SYS_mbind,
+    set_mempolicy => SYS_set_mempolicy,
+    get_mempolicy => SYS_get_mempolicy,
+    mq_open => SYS_mq_open,
+    mq_unlink => SYS_mq_unlink,
+    mq_timedsend => SYS_mq_timedsend,
+    mq_timedreceive => SYS_mq_timedreceive,
+    mq_notify => SYS_mq_notify,
+    mq_getsetattr => SYS_mq_getsetattr,
+    kexec_load => SYS_kexec_load,
+    waitid => SYS_waitid,
+    add_key => SYS_add_key,
+    request_key => SYS_request_key,
+    keyctl => SYS_keyctl,
+    ioprio_set => SYS_ioprio_set,
+    ioprio_get => SYS_ioprio_get,
+    inotify_init => SYS_inotify_init,
+    inotify_add_watch => SYS_inotify_add_watch,
+    inotify_rm_watch => SYS_inotify_rm_watch,
+    migrate_pages => SYS_migrate_pages,
+    openat => SYS_openat,
+    mkdirat => SYS_mkdirat,
+    mknodat => SYS_mknodat,
+    fchownat => SYS_fchownat,
+    futimesat => SYS_futimesat,
+    newfstatat => SYS_newfstatat,
+    unlinkat => SYS_unlinkat,
+    renameat => SYS_renameat,
+    linkat => SYS_linkat,
+    symlinkat => SYS_symlinkat,
+    readlinkat => SYS_readlinkat,
+    fchmodat => SYS_fchmodat,
+    faccessat => SYS_faccessat,
+    pselect6 => SYS_pselect6,
+    ppoll => SYS_ppoll,
+    unshare => SYS_unshare,
+    set_robust_list => SYS_set_robust_list,
+    get_robust_list => SYS_get_robust_list,
+    splice => SYS_splice,
+    tee => SYS_tee,
+    sync_file_range => SYS_sync_file_range,
+    vmsplice => SYS_vmsplice,
+    move_pages => SYS_move_pages,
+    utimensat => SYS_utimensat,
+    epoll_pwait => SYS_epoll_pwait,
+    signalfd => SYS_signalfd,
+    timerfd_create => SYS_timerfd_create,
+    eventfd => SYS_eventfd,
+    fallocate => SYS_fallocate,
+    timerfd_settime => SYS_timerfd_settime,
+    timerfd_gettime => SYS_timerfd_gettime,
+    accept4 => SYS_accept4,
+    signalfd4 => SYS_signalfd4,
+    eventfd2 => SYS_eventfd2,
+    epoll_create1 => SYS_epoll_create1,
+    dup3 => SYS_dup3,
+    pipe2 => SYS_pipe2,
+    inotify_init1 => SYS_inotify_init1,
+    preadv => SYS_preadv,
+    pwritev => SYS_pwritev,
+    rt_tgsigqueueinfo => SYS_rt_tgsigqueueinfo,
+    perf_event_open => SYS_perf_event_open,
+    recvmmsg => SYS_recvmmsg,
+    fanotify_init => SYS_fanotify_init,
+    fanotify_mark => SYS_fanotify_mark,
+    prlimit64 => SYS_prlimit64,
+    name_to_handle_at => SYS_name_to_handle_at,
+    open_by_handle_at => SYS_open_by_handle_at,
+    clock_adjtime => SYS_clock_adjtime,
+    syncfs => SYS_syncfs,
+    sendmmsg => SYS_sendmmsg,
+    setns => SYS_setns,
+    getcpu => SYS_getcpu,
+    process_vm_readv => SYS_process_vm_readv,
+    process_vm_writev => SYS_process_vm_writev,
+    kcmp => SYS_kcmp,
+    finit_module => SYS_finit_module,
+    sched_setattr => SYS_sched_setattr,
+    sched_getattr => SYS_sched_getattr,
+    renameat2 => SYS_renameat2,
+    seccomp => SYS_seccomp,
+    getrandom => SYS_getrandom,
+    memfd_create => SYS_memfd_create,
+    kexec_file_load => SYS_kexec_file_load,
+    bpf => SYS_bpf,
+    execveat => SYS_execveat,
+    userfaultfd => SYS_userfaultfd,
+    membarrier => SYS_membarrier,
+    mlock2 => SYS_mlock2,
+    copy_file_range => SYS_copy_file_range,
+    preadv2 => SYS_preadv2,
+    pwritev2 => SYS_pwritev2,
+    pkey_mprotect => SYS_pkey_mprotect,
+    pkey_alloc => SYS_pkey_alloc,
+    pkey_free => SYS_pkey_free,
+    statx => SYS_statx,
+    pidfd_send_signal => SYS_pidfd_send_signal,
+    io_uring_setup => SYS_io_uring_setup,
+    io_uring_enter => SYS_io_uring_enter,
+    io_uring_register => SYS_io_uring_register,
+    open_tree => SYS_open_tree,
+    move_mount => SYS_move_mount,
+    fsopen => SYS_fsopen,
+    fsconfig => SYS_fsconfig,
+    fsmount => SYS_fsmount,
+    fspick => SYS_fspick,
+    pidfd_open => SYS_pidfd_open,
+    clone3 => SYS_clone3,
+    close_range => SYS_close_range,
+    openat2 => SYS_openat2,
+    pidfd_getfd => SYS_pidfd_getfd,
+    faccessat2 => SYS_faccessat2,
+    process_madvise => SYS_process_madvise,
+    epoll_pwait2 => SYS_epoll_pwait2,
+    mount_setattr => SYS_mount_setattr,
+}
+
+#[cfg(target_arch = "x86_64")]
+numbered_syscalls! {
+    /// Returns the number of the syscall with the given name on x86, the 32 bit architecture
+    from_name_x86,
+    "restart_syscall" => 0,
+    "exit" => 1,
+    "fork" => 2,
+    "read" => 3,
+    "write" => 4,
+    "open" => 5,
+    "close" => 6,
+    "waitpid" => 7,
+    "creat" => 8,
+    "link" => 9,
+    "unlink" => 10,
+    "execve" => 11,
+    "chdir" => 12,
+    "time" => 13,
+    "mknod" => 14,
+    "chmod" => 15,
+    "lchown" => 16,
+    "break" => 17,
+    "oldstat" => 18,
+    "lseek" => 19,
+    "getpid" => 20,
+    "mount" => 21,
+    "umount" => 22,
+    "setuid" => 23,
+    "getuid" => 24,
+    "stime" => 25,
+    "ptrace" => 26,
+    "alarm" => 27,
+    "oldfstat" => 28,
+    "pause" => 29,
+    "utime" => 30,
+    "stty" => 31,
+    "gtty" => 32,
+    "access" => 33,
+    "nice" => 34,
+    "ftime" => 35,
+    "sync" => 36,
+    "kill" => 37,
+    "rename" => 38,
+    "mkdir" => 39,
+    "rmdir" => 40,
+    "dup" => 41,
+    "pipe" => 42,
+    "times" => 43,
+    "prof" => 44,
+    "brk" => 45,
+    "setgid" => 46,
+    "getgid" => 47,
+    "signal" => 48,
+    "geteuid" => 49,
+    "getegid" => 50,
+    "acct" => 51,
+    "umount2" => 52,
+    "lock" => 53,
+    "ioctl" => 54,
+    "fcntl" => 55,
+    "mpx" => 56,
+    "setpgid" => 57,
+    "ulimit" => 58,
+    "oldolduname" => 59,
+    "umask" => 60,
+    "chroot" => 61,
+    "ustat" => 62,
+    "dup2" => 63,
+    "getppid" => 64,
+    "getpgrp" => 65,
+    "setsid" => 66,
+    "sigaction" => 67,
+    "sgetmask" => 68,
+    "ssetmask" => 69,
+    "setreuid" => 70,
+    "setregid" => 71,
+    "sigsuspend" => 72,
+    "sigpending" => 73,
+    "sethostname" => 74,
+    "setrlimit" => 75,
+    "getrlimit" => 76,
+    "getrusage" => 77,
+    "gettimeofday" => 78,
+    "settimeofday" => 79,
+    "getgroups" => 80,
+    "setgroups" => 81,
+    "select" => 82,
+    "symlink" => 83,
+    "oldlstat" => 84,
+    "readlink" => 85,
+    "uselib" => 86,
+    "swapon" => 87,
+    "reboot" => 88,
+    "readdir" => 89,
+    "mmap" => 90,
+    "munmap" => 91,
+    "truncate" => 92,
+    "ftruncate" => 93,
+    "fchmod" => 94,
+    "fchown" => 95,
+    "getpriority" => 96,
+    "setpriority" => 97,
+    "profil" => 98,
+    "statfs" => 99,
+    "fstatfs" => 100,
+    "ioperm" => 101,
+    "socketcall" => 102,
+    "syslog" => 103,
+    "setitimer" => 104,
+    "getitimer" => 105,
+    "stat" => 106,
+    "lstat" => 107,
+    "fstat" => 108,
+    "olduname" => 109,
+    "iopl" => 110,
+    "vhangup" => 111,
+    "idle" => 112,
+    "vm86old" => 113,
+    "wait4" => 114,
+    "swapoff" => 115,
+    "sysinfo" => 116,
+    "ipc" => 117,
+    "fsync" => 118,
+    "sigreturn" => 119,
+    "clone" => 120,
+    "setdomainname" => 121,
+    "uname" => 122,
+    "modify_ldt" => 123,
+    "adjtimex" => 124,
+    "mprotect" => 125,
+    "sigprocmask" => 126,
+    "create_module" => 127,
+    "init_module" => 128,
+    "delete_module" => 129,
+    "get_kernel_syms" => 130,
+    "quotactl" => 131,
+    "getpgid" => 132,
+    "fchdir" => 133,
+    "bdflush" => 134,
+    "sysfs" => 135,
+    "personality" => 136,
+    "afs_syscall" => 137,
+    "setfsuid" => 138,
+    "setfsgid" => 139,
+    "_llseek" => 140,
+    "getdents" => 141,
+    "_newselect" => 142,
+    "flock" => 143,
+    "msync" => 144,
+    "readv" => 145,
+    "writev" => 146,
+    "getsid" => 147,
+    "fdatasync" => 148,
+    "_sysctl" => 149,
+    "mlock" => 150,
+    "munlock" => 151,
+    "mlockall" => 152,
+    "munlockall" => 153,
+    "sched_setparam" => 154,
+    "sched_getparam" => 155,
+    "sched_setscheduler" => 156,
+    "sched_getscheduler" => 157,
+    "sched_yield" => 158,
+    "sched_get_priority_max" => 159,
+    "sched_get_priority_min" => 160,
+    "sched_rr_get_interval" => 161,
+    "nanosleep" => 162,
+    "mremap" => 163,
+    "setresuid" => 164,
+    "getresuid" => 165,
+    "vm86" => 166,
+    "query_module" => 167,
+    "poll" => 168,
+    "nfsservctl" => 169,
+    "setresgid" => 170,
+    "getresgid" => 171,
+    "prctl" => 172,
+    "rt_sigreturn" => 173,
+    "rt_sigaction" => 174,
+    "rt_sigprocmask" => 175,
+    "rt_sigpending" => 176,
+    "rt_sigtimedwait" => 177,
+    "rt_sigqueueinfo" => 178,
+    "rt_sigsuspend" => 179,
+    "pread64" => 180,
+    "pwrite64" => 181,
+    "chown" => 182,
+    "getcwd" => 183,
+    "capget" => 184,
+    "capset" => 185,
+    "sigaltstack" => 186,
+    "sendfile" => 187,
+    "getpmsg" => 188,
+    "putpmsg" => 189,
+    "vfork" => 190,
+    "ugetrlimit" => 191,
+    "mmap2" => 192,
+    "truncate64" => 193,
+    "ftruncate64" => 194,
+    "stat64" => 195,
+    "lstat64" => 196,
+    "fstat64" => 197,
+    "lchown32" => 198,
+    "getuid32" => 199,
+    "getgid32" => 200,
+    "geteuid32" => 201,
+    "getegid32" => 202,
+    "setreuid32" => 203,
+    "setregid32" => 204,
+    "getgroups32" => 205,
+    "setgroups32" => 206,
+    "fchown32" => 207,
+    "setresuid32" => 208,
+    "getresuid32" => 209,
+    "setresgid32" => 210,
+    "getresgid32" => 211,
+    "chown32" => 212,
+    "setuid32" => 213,
+    "setgid32" => 214,
+    "setfsuid32" => 215,
+    "setfsgid32" => 216,
+    "pivot_root" => 217,
+    "mincore" => 218,
+    "madvise" => 219,
+    "getdents64" => 220,
+    "fcntl64" => 221,
+    "gettid" => 224,
+    "readahead" => 225,
+    "setxattr" => 226,
+    "lsetxattr" => 227,
+    "fsetxattr" => 228,
+    "getxattr" => 229,
+    "lgetxattr" => 230,
+    "fgetxattr" => 231,
+    "listxattr" => 232,
+    "llistxattr" => 233,
+    "flistxattr" => 234,
+    "removexattr" => 235,
+    "lremovexattr" => 236,
+    "fremovexattr" => 237,
+    "tkill" => 238,
+    "sendfile64" => 239,
+    "futex" => 240,
+    "sched_setaffinity" => 241,
+    "sched_getaffinity" => 242,
+    "set_thread_area" => 243,
+    "get_thread_area" => 244,
+    "io_setup" => 245,
+    "io_destroy" => 246,
+    "io_getevents" => 247,
+    "io_submit" => 248,
+    "io_cancel" => 249,
+    "fadvise64" => 250,
+    "exit_group" => 252,
+    "lookup_dcookie" => 253,
+    "epoll_create" => 254,
+    "epoll_ctl" => 255,
+    "epoll_wait" => 256,
+    "remap_file_pages" => 257,
+    "set_tid_address" => 258,
+    "timer_create" => 259,
+    "timer_settime" => 260,
+    "timer_gettime" => 261,
+    "timer_getoverrun" => 262,
+    "timer_delete" => 263,
+    "clock_settime" => 264,
+    "clock_gettime" => 265,
+    "clock_getres" => 266,
+    "clock_nanosleep" => 267,
+    "statfs64" => 268,
+    "fstatfs64" => 269,
+    "tgkill" => 270,
+    "utimes" => 271,
+    "fadvise64_64" => 272,
+    "vserver" => 273,
+    "mbind" => 274,
+    "get_mempolicy" => 275,
+    "set_mempolicy" => 276,
+    "mq_open" => 277,
+    "mq_unlink" => 278,
+    "mq_timedsend" => 279,
+    "mq_timedreceive" => 280,
+    "mq_notify" => 281,
+    "mq_getsetattr" => 282,
+    "kexec_load" => 283,
+    "waitid" => 284,
+    "add_key" => 286,
+    "request_key" => 287,
+    "keyctl" => 288,
+    "ioprio_set" => 289,
+    "ioprio_get" => 290,
+    "inotify_init" => 291,
+    "inotify_add_watch" => 292,
+    "inotify_rm_watch" => 293,
+    "migrate_pages" => 294,
+    "openat" => 295,
+    "mkdirat" => 296,
+    "mknodat" => 297,
+    "fchownat" => 298,
+    "futimesat" => 299,
+    "fstatat64" => 300,
+    "unlinkat" => 301,
+    "renameat" => 302,
+    "linkat" => 303,
+    "symlinkat" => 304,
+    "readlinkat" => 305,
+    "fchmodat" => 306,
+    "faccessat" => 307,
+    "pselect6" => 308,
+    "ppoll" => 309,
+    "unshare" => 310,
+    "set_robust_list" => 311,
+    "get_robust_list" => 312,
+    "splice" => 313,
+    "sync_file_range" => 314,
+    "tee" => 315,
+    "vmsplice" => 316,
+    "move_pages" => 317,
+    "getcpu" => 318,
+    "epoll_pwait" => 319,
+    "utimensat" => 320,
+    "signalfd" => 321,
+    "timerfd_create" => 322,
+    "eventfd" => 323,
+    "fallocate" => 324,
+    "timerfd_settime" => 325,
+    "timerfd_gettime" => 326,
+    "signalfd4" => 327,
+    "eventfd2" => 328,
+    "epoll_create1" => 329,
+    "dup3" => 330,
+    "pipe2" => 331,
+    "inotify_init1" => 332,
+    "preadv" => 333,
+    "pwritev" => 334,
+    "rt_tgsigqueueinfo" => 335,
+    "perf_event_open" => 336,
+    "recvmmsg" => 337,
+    "fanotify_init" => 338,
+    "fanotify_mark" => 339,
+    "prlimit64" => 340,
+    "name_to_handle_at" => 341,
+    "open_by_handle_at" => 342,
+    "clock_adjtime" => 343,
+    "syncfs" => 344,
+    "sendmmsg" => 345,
+    "setns" => 346,
+    "process_vm_readv" => 347,
+    "process_vm_writev" => 348,
+    "kcmp" => 349,
+    "finit_module" => 350,
+    "sched_setattr" => 351,
+    "sched_getattr" => 352,
+    "renameat2" => 353,
+    "seccomp" => 354,
+    "getrandom" => 355,
+    "memfd_create" => 356,
+    "bpf" => 357,
+    "execveat" => 358,
+    "socket" => 359,
+    "socketpair" => 360,
+    "bind" => 361,
+    "connect" => 362,
+    "listen" => 363,
+    "accept4" => 364,
+    "getsockopt" => 365,
+    "setsockopt" => 366,
+    "getsockname" => 367,
+    "getpeername" => 368,
+    "sendto" => 369,
+    "sendmsg" => 370,
+    "recvfrom" => 371,
+    "recvmsg" => 372,
+    "shutdown" => 373,
+    "userfaultfd" => 374,
+    "membarrier" => 375,
+    "mlock2" => 376,
+    "copy_file_range" => 377,
+    "preadv2" => 378,
+    "pwritev2" => 379,
+    "pkey_mprotect" => 380,
+    "pkey_alloc" => 381,
+    "pkey_free" => 382,
+    "statx" => 383,
+    "arch_prctl" => 384,
+    "io_pgetevents" => 385,
+    "rseq" => 386,
+    "semget" => 393,
+    "semctl" => 394,
+    "shmget" => 395,
+    "shmctl" => 396,
+    "shmat" => 397,
+    "shmdt" => 398,
+    "msgget" => 399,
+    "msgsnd" => 400,
+    "msgrcv" => 401,
+    "msgctl" => 402,
+    "clock_gettime64" => 403,
+    "clock_settime64" => 404,
+    "clock_adjtime64" => 405,
+    "clock_getres_time64" => 406,
+    "clock_nanosleep_time64" => 407,
+    "timer_gettime64" => 408,
+    "timer_settime64" => 409,
+    "timerfd_gettime64" => 410,
+    "timerfd_settime64" => 411,
+    "utimensat_time64" => 412,
+    "pselect6_time64" => 413,
+    "ppoll_time64" => 414,
+    "io_pgetevents_time64" => 416,
+    "recvmmsg_time64" => 417,
+    "mq_timedsend_time64" => 418,
+    "mq_timedreceive_time64" => 419,
+    "semtimedop_time64" => 420,
+    "rt_sigtimedwait_time64" => 421,
+    "futex_time64" => 422,
+    "sched_rr_get_interval_time64" => 423,
+    "pidfd_send_signal" => 424,
+    "io_uring_setup" => 425,
+    "io_uring_enter" => 426,
+    "io_uring_register" => 427,
+    "open_tree" => 428,
+    "move_mount" => 429,
+    "fsopen" => 430,
+    "fsconfig" => 431,
+    "fsmount" => 432,
+    "fspick" => 433,
+    "pidfd_open" => 434,
+    "clone3" => 435,
+    "close_range" => 436,
+    "openat2" => 437,
+    "pidfd_getfd" => 438,
+    "faccessat2" => 439,
+    "process_madvise" => 440,
+    "epoll_pwait2" => 441,
+    "mount_setattr" => 442,
+    "quotactl_fd" => 443,
+    "landlock_create_ruleset" => 444,
+    "landlock_add_rule" => 445,
+    "landlock_restrict_self" => 446,
+    "memfd_secret" => 447,
+    "process_mrelease" => 448,
+    "futex_waitv" => 449,
+    "set_mempolicy_home_node" => 450,
+}
+
+#[cfg(target_arch = "x86_64")]
+numbered_syscalls! {
+    /// Returns the number of the syscall with the given name on x32 without the x32 bit, which
+    /// marks the syscalls of x32 as they share the architecture with x86_64
+    from_name_x32,
+    "read" => 0,
+    "write" => 1,
+    "open" => 2,
+    "close" => 3,
+    "stat" => 4,
+    "fstat" => 5,
+    "lstat" => 6,
+    "poll" => 7,
+    "lseek" => 8,
+    "mmap" => 9,
+    "mprotect" => 10,
+    "munmap" => 11,
+    "brk" => 12,
+    "rt_sigprocmask" => 14,
+    "pread64" => 17,
+    "pwrite64" => 18,
+    "access" => 21,
+    "pipe" => 22,
+    "select" => 23,
+    "sched_yield" => 24,
+    "mremap" => 25,
+    "msync" => 26,
+    "mincore" => 27,
+    "madvise" => 28,
+    "shmget" => 29,
+    "shmat" => 30,
+    "shmctl" => 31,
+    "dup" => 32,
+    "dup2" => 33,
+    "pause" => 34,
+    "nanosleep" => 35,
+    "getitimer" => 36,
+    "alarm" => 37,
+    "setitimer" => 38,
+    "getpid" => 39,
+    "sendfile" => 40,
+    "socket" => 41,
+    "connect" => 42,
+    "accept" => 43,
+    "sendto" => 44,
+    "shutdown" => 48,
+    "bind" => 49,
+    "listen" => 50,
+    "getsockname" => 51,
+    "getpeername" => 52,
+    "socketpair" => 53,
+    "clone" => 56,
+    "fork" => 57,
+    "vfork" => 58,
+    "exit" => 60,
+    "wait4" => 61,
+    "kill" => 62,
+    "uname" => 63,
+    "semget" => 64,
+    "semop" => 65,
+    "semctl" => 66,
+    "shmdt" => 67,
+    "msgget" => 68,
+    "msgsnd" => 69,
+    "msgrcv" => 70,
+    "msgctl" => 71,
+    "fcntl" => 72,
+    "flock" => 73,
+    "fsync" => 74,
+    "fdatasync" => 75,
+    "truncate" => 76,
+    "ftruncate" => 77,
+    "getdents" => 78,
+    "getcwd" => 79,
+    "chdir" => 80,
+    "fchdir" => 81,
+    "rename" => 82,
+    "mkdir" => 83,
+    "rmdir" => 84,
+    "creat" => 85,
+    "link" => 86,
+    "unlink" => 87,
+    "symlink" => 88,
+    "readlink" => 89,
+    "chmod" => 90,
+    "fchmod" => 91,
+    "chown" => 92,
+    "fchown" => 93,
+    "lchown" => 94,
+    "umask" => 95,
+    "gettimeofday" => 96,
+    "getrlimit" => 97,
+    "getrusage" => 98,
+    "sysinfo" => 99,
+    "times" => 100,
+    "getuid" => 102,
+    "syslog" => 103,
+    "getgid" => 104,
+    "setuid" => 105,
+    "setgid" => 106,
+    "geteuid" => 107,
+    "getegid" => 108,
+    "setpgid" => 109,
+    "getppid" => 110,
+    "getpgrp" => 111,
+    "setsid" => 112,
+    "setreuid" => 113,
+    "setregid" => 114,
+    "getgroups" => 115,
+    "setgroups" => 116,
+    "setresuid" => 117,
+    "getresuid" => 118,
+    "setresgid" => 119,
+    "getresgid" => 120,
+    "getpgid" => 121,
+    "setfsuid" => 122,
+    "setfsgid" => 123,
+    "getsid" => 124,
+    "capget" => 125,
+    "capset" => 126,
+    "rt_sigsuspend" => 130,
+    "utime" => 132,
+    "mknod" => 133,
+    "personality" => 135,
+    "ustat" => 136,
+    "statfs" => 137,
+    "fstatfs" => 138,
+    "sysfs" => 139,
+    "getpriority" => 140,
+    "setpriority" => 141,
+    "sched_setparam" => 142,
+    "sched_getparam" => 143,
+    "sched_setscheduler" => 144,
+    "sched_getscheduler" => 145,
+    "sched_get_priority_max" => 146,
+    "sched_get_priority_min" => 147,
+    "sched_rr_get_interval" => 148,
+    "mlock" => 149,
+    "munlock" => 150,
+    "mlockall" => 151,
+    "munlockall" => 152,
+    "vhangup" => 153,
+    "modify_ldt" => 154,
+    "pivot_root" => 155,
+    "prctl" => 157,
+    "arch_prctl" => 158,
+    "adjtimex" => 159,
+    "setrlimit" => 160,
+    "chroot" => 161,
+    "sync" => 162,
+    "acct" => 163,
+    "settimeofday" => 164,
+    "mount" => 165,
+    "umount2" => 166,
+    "swapon" => 167,
+    "swapoff" => 168,
+    "reboot" => 169,
+    "sethostname" => 170,
+    "setdomainname" => 171,
+    "iopl" => 172,
+    "ioperm" => 173,
+    "init_module" => 175,
+    "delete_module" => 176,
+    "quotactl" => 179,
+    "getpmsg" => 181,
+    "putpmsg" => 182,
+    "afs_syscall" => 183,
+    "tuxcall" => 184,
+    "security" => 185,
+    "gettid" => 186,
+    "readahead" => 187,
+    "setxattr" => 188,
+    "lsetxattr" => 189,
+    "fsetxattr" => 190,
+    "getxattr" => 191,
+    "lgetxattr" => 192,
+    "fgetxattr" => 193,
+    "listxattr" => 194,
+    "llistxattr" => 195,
+    "flistxattr" => 196,
+    "removexattr" => 197,
+    "lremovexattr" => 198,
+    "fremovexattr" => 199,
+    "tkill" => 200,
+    "time" => 201,
+    "futex" => 202,
+    "sched_setaffinity" => 203,
+    "sched_getaffinity" => 204,
+    "io_destroy" => 207,
+    "io_getevents" => 208,
+    "io_cancel" => 210,
+    "lookup_dcookie" => 212,
+    "epoll_create" => 213,
+    "remap_file_pages" => 216,
+    "getdents64" => 217,
+    "set_tid_address" => 218,
+    "restart_syscall" => 219,
+    "semtimedop" => 220,
+    "fadvise64" => 221,
+    "timer_settime" => 223,
+    "timer_gettime" => 224,
+    "timer_getoverrun" => 225,
+    "timer_delete" => 226,
+    "clock_settime" => 227,
+    "clock_gettime" => 228,
+    "clock_getres" => 229,
+    "clock_nanosleep" => 230,
+    "exit_group" => 231,
+    "epoll_wait" => 232,
+    "epoll_ctl" => 233,
+    "tgkill" => 234,
+    "utimes" => 235,
+    "mbind" => 237,
+    "set_mempolicy" => 238,
+    "get_mempolicy" => 239,
+    "mq_open" => 240,
+    "mq_unlink" => 241,
+    "mq_timedsend" => 242,
+    "mq_timedreceive" => 243,
+    "mq_getsetattr" => 245,
+    "add_key" => 248,
+    "request_key" => 249,
+    "keyctl" => 250,
+    "ioprio_set" => 251,
+    "ioprio_get" => 252,
+    "inotify_init" => 253,
+    "inotify_add_watch" => 254,
+    "inotify_rm_watch" => 255,
+    "migrate_pages" => 256,
+    "openat" => 257,
+    "mkdirat" => 258,
+    "mknodat" => 259,
+    "fchownat" => 260,
+    "futimesat" => 261,
+    "newfstatat" => 262,
+    "unlinkat" => 263,
+    "renameat" => 264,
+    "linkat" => 265,
+    "symlinkat" => 266,
+    "readlinkat" => 267,
+    "fchmodat" => 268,
+    "faccessat" => 269,
+    "pselect6" => 270,
+    "ppoll" => 271,
+    "unshare" => 272,
+    "splice" => 275,
+    "tee" => 276,
+    "sync_file_range" => 277,
+    "utimensat" => 280,
+    "epoll_pwait" => 281,
+    "signalfd" => 282,
+    "timerfd_create" => 283,
+    "eventfd" => 284,
+    "fallocate" => 285,
+    "timerfd_settime" => 286,
+    "timerfd_gettime" => 287,
+    "accept4" => 288,
+    "signalfd4" => 289,
+    "eventfd2" => 290,
+    "epoll_create1" => 291,
+    "dup3" => 292,
+    "pipe2" => 293,
+    "inotify_init1" => 294,
+    "perf_event_open" => 298,
+    "fanotify_init" => 300,
+    "fanotify_mark" => 301,
+    "prlimit64" => 302,
+    "name_to_handle_at" => 303,
+    "open_by_handle_at" => 304,
+    "clock_adjtime" => 305,
+    "syncfs" => 306,
+    "setns" => 308,
+    "getcpu" => 309,
+    "kcmp" => 312,
+    "finit_module" => 313,
+    "sched_setattr" => 314,
+    "sched_getattr" => 315,
+    "renameat2" => 316,
+    "seccomp" => 317,
+    "getrandom" => 318,
+    "memfd_create" => 319,
+    "kexec_file_load" => 320,
+    "bpf" => 321,
+    "userfaultfd" => 323,
+    "membarrier" => 324,
+    "mlock2" => 325,
+    "copy_file_range" => 326,
+    "pkey_mprotect" => 329,
+    "pkey_alloc" => 330,
+    "pkey_free" => 331,
+    "statx" => 332,
+    "io_pgetevents" => 333,
+    "rseq" => 334,
+    "pidfd_send_signal" => 424,
+    "io_uring_setup" => 425,
+    "io_uring_enter" => 426,
+    "io_uring_register" => 427,
+    "open_tree" => 428,
+    "move_mount" => 429,
+    "fsopen" => 430,
+    "fsconfig" => 431,
+    "fsmount" => 432,
+    "fspick" => 433,
+    "pidfd_open" => 434,
+    "clone3" => 435,
+    "close_range" => 436,
+    "openat2" => 437,
+    "pidfd_getfd" => 438,
+    "faccessat2" => 439,
+    "process_madvise" => 440,
+    "epoll_pwait2" => 441,
+    "mount_setattr" => 442,
+    "quotactl_fd" => 443,
+    "landlock_create_ruleset" => 444,
+    "landlock_add_rule" => 445,
+    "landlock_restrict_self" => 446,
+    "memfd_secret" => 447,
+    "process_mrelease" => 448,
+    "futex_waitv" => 449,
+    "set_mempolicy_home_node" => 450,
+    "rt_sigaction" => 512,
+    "rt_sigreturn" => 513,
+    "ioctl" => 514,
+    "readv" => 515,
+    "writev" => 516,
+    "recvfrom" => 517,
+    "sendmsg" => 518,
+    "recvmsg" => 519,
+    "execve" => 520,
+    "ptrace" => 521,
+    "rt_sigpending" => 522,
+    "rt_sigtimedwait" => 523,
+    "rt_sigqueueinfo" => 524,
+    "sigaltstack" => 525,
+    "timer_create" => 526,
+    "mq_notify" => 527,
+    "kexec_load" => 528,
+    "waitid" => 529,
+    "set_robust_list" => 530,
+    "get_robust_list" => 531,
+    "vmsplice" => 532,
+    "move_pages" => 533,
+    "preadv" => 534,
+    "pwritev" => 535,
+    "rt_tgsigqueueinfo" => 536,
+    "recvmmsg" => 537,
+    "sendmmsg" => 538,
+    "process_vm_readv" => 539,
+    "process_vm_writev" => 540,
+    "setsockopt" => 541,
+    "getsockopt" => 542,
+    "io_setup" => 543,
+    "io_submit" => 544,
+    "execveat" => 545,
+    "preadv2" => 546,
+    "pwritev2" => 547,
+}
+
+#[cfg(target_arch = "aarch64")]
+syscalls! {
+    io_setup => SYS_io_setup,
+    io_destroy => SYS_io_destroy,
+    io_submit => SYS_io_submit,
+    io_cancel => SYS_io_cancel,
+    io_getevents => SYS_io_getevents,
+    setxattr => SYS_setxattr,
+    lsetxattr => SYS_lsetxattr,
+    fsetxattr => SYS_fsetxattr,
+    getxattr => SYS_getxattr,
+    lgetxattr => SYS_lgetxattr,
+    fgetxattr => SYS_fgetxattr,
+    listxattr => SYS_listxattr,
+    llistxattr => SYS_llistxattr,
+    flistxattr => SYS_flistxattr,
+    removexattr => SYS_removexattr,
+    lremovexattr => SYS_lremovexattr,
+    fremovexattr => SYS_fremovexattr,
+    getcwd => SYS_getcwd,
+    lookup_dcookie => SYS_lookup_dcookie,
+    eventfd2 => SYS_eventfd2,
+    epoll_create1 => SYS_epoll_create1,
+    epoll_ctl => SYS_epoll_ctl,
+    epoll_pwait => SYS_epoll_pwait,
+    dup => SYS_dup,
+    dup3 => SYS_dup3,
+    fcntl => SYS_fcntl,
+    inotify_init1 => SYS_inotify_init1,
+    inotify_add_watch => SYS_inotify_add_watch,
+    inotify_rm_watch => SYS_inotify_rm_watch,
+    ioctl => SYS_ioctl,
+    ioprio_set => SYS_ioprio_set,
+    ioprio_get => SYS_ioprio_get,
+    flock => SYS_flock,
+    mknodat => SYS_mknodat,
+    mkdirat => SYS_mkdirat,
+    unlinkat => SYS_unlinkat,
+    symlinkat => SYS_symlinkat,
+    linkat => SYS_linkat,
+    umount2 => SYS_umount2,
+    mount => SYS_mount,
+    pivot_root => SYS_pivot_root,
+    nfsservctl => SYS_nfsservctl,
+    statfs => SYS_statfs,
+    fstatfs => SYS_fstatfs,
+    truncate => SYS_truncate,
+    ftruncate => SYS_ftruncate,
+    fallocate => SYS_fallocate,
+    faccessat => SYS_faccessat,
+    chdir => SYS_chdir,
+    fchdir => SYS_fchdir,
+    chroot => SYS_chroot,
+    fchmod => SYS_fchmod,
+    fchmodat => SYS_fchmodat,
+    fchownat => SYS_fchownat,
+    fchown => SYS_fchown,
+    openat => SYS_openat,
+    close => SYS_close,
+    vhangup => SYS_vhangup,
+    pipe2 => SYS_pipe2,
+    quotactl => SYS_quotactl,
+    getdents64 => SYS_getdents64,
+    lseek => SYS_lseek,
+    read => SYS_read,
+    write => SYS_write,
+    readv => SYS_readv,
+    writev => SYS_writev,
+    pread64 => SYS_pread64,
+    pwrite64 => SYS_pwrite64,
+    preadv => SYS_preadv,
+    pwritev => SYS_pwritev,
+    pselect6 => SYS_pselect6,
+    ppoll => SYS_ppoll,
+    signalfd4 => SYS_signalfd4,
+    vmsplice => SYS_vmsplice,
+    splice => SYS_splice,
+    tee => SYS_tee,
+    readlinkat => SYS_readlinkat,
+    newfstatat => SYS_newfstatat,
+    fstat => SYS_fstat,
+    sync => SYS_sync,
+    fsync => SYS_fsync,
+    fdatasync => SYS_fdatasync,
+    timerfd_create => SYS_timerfd_create,
+    timerfd_settime => SYS_timerfd_settime,
+    timerfd_gettime => SYS_timerfd_gettime,
+    utimensat => SYS_utimensat,
+    acct => SYS_acct,
+    capget => SYS_capget,
+    capset => SYS_capset,
+    personality => SYS_personality,
+    exit => SYS_exit,
+    exit_group => SYS_exit_group,
+    waitid => SYS_waitid,
+    set_tid_address => SYS_set_tid_address,
+    unshare => SYS_unshare,
+    futex => SYS_futex,
+    set_robust_list => SYS_set_robust_list,
+    get_robust_list => SYS_get_robust_list,
+    nanosleep => SYS_nanosleep,
+    getitimer => SYS_getitimer,
+    setitimer => SYS_setitimer,
+    kexec_load => SYS_kexec_load,
+    init_module => SYS_init_module,
+    delete_module => SYS_delete_module,
+    timer_create => SYS_timer_create,
+    timer_gettime => SYS_timer_gettime,
+    timer_getoverrun => SYS_timer_getoverrun,
+    timer_settime => SYS_timer_settime,
+    timer_delete => SYS_timer_delete,
+    clock_settime => SYS_clock_settime,
+    clock_gettime => SYS_clock_gettime,
+    clock_getres => SYS_clock_getres,
+    clock_nanosleep => SYS_clock_nanosleep,
+    syslog => SYS_syslog,
+    ptrace => SYS_ptrace,
+    sched_setparam => SYS_sched_setparam,
+    sched_setscheduler => SYS_sched_setscheduler,
+    sched_getscheduler => SYS_sched_getscheduler,
+    sched_getparam => SYS_sched_getparam,
+    sched_setaffinity => SYS_sched_setaffinity,
+    sched_getaffinity => SYS_sched_getaffinity,
+    sched_yield => SYS_sched_yield,
+    sched_get_priority_max => SYS_sched_get_priority_max,
+    sched_get_priority_min => SYS_sched_get_priority_min,
+    sched_rr_get_interval => SYS_sched_rr_get_interval,
+    restart_syscall => SYS_restart_syscall,
+    kill => SYS_kill,
+    tkill => SYS_tkill,
+    tgkill => SYS_tgkill,
+    sigaltstack => SYS_sigaltstack,
+    rt_sigsuspend => SYS_rt_sigsuspend,
+    rt_sigaction => SYS_rt_sigaction,
+    rt_sigprocmask => SYS_rt_sigprocmask,
+    rt_sigpending => SYS_rt_sigpending,
+    rt_sigtimedwait => SYS_rt_sigtimedwait,
+    rt_sigqueueinfo => SYS_rt_sigqueueinfo,
+    rt_sigreturn => SYS_rt_sigreturn,
+    setpriority => SYS_setpriority,
+    getpriority => SYS_getpriority,
+    reboot => SYS_reboot,
+    setregid => SYS_setregid,
+    setgid => SYS_setgid,
+    setreuid => SYS_setreuid,
+    setuid => SYS_setuid,
+    setresuid => SYS_setresuid,
+    getresuid => SYS_getresuid,
+    setresgid => SYS_setresgid,
+    getresgid => SYS_getresgid,
+    setfsuid => SYS_setfsuid,
+    setfsgid => SYS_setfsgid,
+    times => SYS_times,
+    setpgid => SYS_setpgid,
+    getpgid => SYS_getpgid,
+    getsid => SYS_getsid,
+    setsid => SYS_setsid,
+    getgroups => SYS_getgroups,
+    setgroups => SYS_setgroups,
+    uname => SYS_uname,
+    sethostname => SYS_sethostname,
+    setdomainname => SYS_setdomainname,
+    getrusage => SYS_getrusage,
+    umask => SYS_umask,
+    prctl => SYS_prctl,
+    getcpu => SYS_getcpu,
+    gettimeofday => SYS_gettimeofday,
+    settimeofday => SYS_settimeofday,
+    adjtimex => SYS_adjtimex,
+    getpid => SYS_getpid,
+    getppid => SYS_getppid,
+    getuid => SYS_getuid,
+    geteuid => SYS_geteuid,
+    getgid => SYS_getgid,
+    getegid => SYS_getegid,
+    gettid => SYS_gettid,
+    sysinfo => SYS_sysinfo,
+    mq_open => SYS_mq_open,
+    mq_unlink => SYS_mq_unlink,
+    mq_timedsend => SYS_mq_timedsend,
+    mq_timedreceive => SYS_mq_timedreceive,
+    mq_notify => SYS_mq_notify,
+    mq_getsetattr => SYS_mq_getsetattr,
+    msgget => SYS_msgget,
+    msgctl => SYS_msgctl,
+    msgrcv => SYS_msgrcv,
+    msgsnd => SYS_msgsnd,
+    semget => SYS_semget,
+    semctl => SYS_semctl,
+    semtimedop => SYS_semtimedop,
+    semop => SYS_semop,
+    shmget => SYS_shmget,
+    shmctl => SYS_shmctl,
+    shmat => SYS_shmat,
+    shmdt => SYS_shmdt,
+    socket => SYS_socket,
+    socketpair => SYS_socketpair,
+    bind => SYS_bind,
+    listen => SYS_listen,
+    accept => SYS_accept,
+    connect => SYS_connect,
+    getsockname => SYS_getsockname,
+    getpeername => SYS_getpeername,
+    sendto => SYS_sendto,
+    recvfrom => SYS_recvfrom,
+    setsockopt => SYS_setsockopt,
+    getsockopt => SYS_getsockopt,
+    shutdown => SYS_shutdown,
+    sendmsg => SYS_sendmsg,
+    recvmsg => SYS_recvmsg,
+    readahead => SYS_readahead,
+    brk => SYS_brk,
+    munmap => SYS_munmap,
+    mremap => SYS_mremap,
+    add_key => SYS_add_key,
+    request_key => SYS_request_key,
+    keyctl => SYS_keyctl,
+    clone => SYS_clone,
+    execve => SYS_execve,
+    mmap => SYS_mmap,
+    swapon => SYS_swapon,
+    swapoff => SYS_swapoff,
+    mprotect => SYS_mprotect,
+    msync => SYS_msync,
+    mlock => SYS_mlock,
+    munlock => SYS_munlock,
+    mlockall => SYS_mlockall,
+    munlockall => SYS_munlockall,
+    mincore => SYS_mincore,
+    madvise => SYS_madvise,
+    remap_file_pages => SYS_remap_file_pages,
+    mbind => SYS_mbind,
+    get_mempolicy => SYS_get_mempolicy,
+    set_mempolicy => SYS_set_mempolicy,
+    migrate_pages => SYS_migrate_pages,
+    move_pages => SYS_move_pages,
+    rt_tgsigqueueinfo => SYS_rt_tgsigqueueinfo,
+    perf_event_open => SYS_perf_event_open,
+    accept4 => SYS_accept4,
+    recvmmsg => SYS_recvmmsg,
+    wait4 => SYS_wait4,
+    prlimit64 => SYS_prlimit64,
+    fanotify_init => SYS_fanotify_init,
+    fanotify_mark => SYS_fanotify_mark,
+    name_to_handle_at => SYS_name_to_handle_at,
+    open_by_handle_at => SYS_open_by_handle_at,
+    clock_adjtime => SYS_clock_adjtime,
+    syncfs => SYS_syncfs,
+    setns => SYS_setns,
+    sendmmsg => SYS_sendmmsg,
+    process_vm_readv => SYS_process_vm_readv,
+    process_vm_writev => SYS_process_vm_writev,
+    kcmp => SYS_kcmp,
+    finit_module => SYS_finit_module,
+    sched_setattr => SYS_sched_setattr,
+    sched_getattr => SYS_sched_getattr,
+    renameat2 => SYS_renameat2,
+    seccomp => SYS_seccomp,
+    getrandom => SYS_getrandom,
+    memfd_create => SYS_memfd_create,
+    bpf => SYS_bpf,
+    execveat => SYS_execveat,
+    userfaultfd => SYS_userfaultfd,
+    membarrier => SYS_membarrier,
+    mlock2 => SYS_mlock2,
+    copy_file_range => SYS_copy_file_range,
+    preadv2 => SYS_preadv2,
+    pwritev2 => SYS_pwritev2,
+    pkey_mprotect => SYS_pkey_mprotect,
+    pkey_alloc => SYS_pkey_alloc,
+    pkey_free => SYS_pkey_free,
+    statx => SYS_statx,
+    pidfd_send_signal => SYS_pidfd_send_signal,
+    io_uring_setup => SYS_io_uring_setup,
+    io_uring_enter => SYS_io_uring_enter,
+    io_uring_register => SYS_io_uring_register,
+    open_tree => SYS_open_tree,
+    move_mount => SYS_move_mount,
+    fsopen => SYS_fsopen,
+    fsconfig => SYS_fsconfig,
+    fsmount => SYS_fsmount,
+    fspick => SYS_fspick,
+    pidfd_open => SYS_pidfd_open,
+    clone3 => SYS_clone3,
+    close_range => SYS_close_range,
+    openat2 => SYS_openat2,
+    pidfd_getfd => SYS_pidfd_getfd,
+    faccessat2 => SYS_faccessat2,
+    process_madvise => SYS_process_madvise,
+    epoll_pwait2 => SYS_epoll_pwait2,
+    mount_setattr => SYS_mount_setattr,
+}
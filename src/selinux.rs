@@ -0,0 +1,66 @@
+//! SELinux labels of the container process and of the filesystems mounted for it
+//! check https://github.com/SELinuxProject/selinux-notebook for more information
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+
+/// Enforcement status of SELinux, which only exists if SELinux is enabled
+const ENFORCE: &str = "/sys/fs/selinux/enforce";
+/// Label of the process after its next exec
+const EXEC_ATTR: &str = "/proc/self/attr/exec";
+
+/// Returns true if SELinux is enabled on the host
+pub fn is_enabled() -> bool {
+    Path::new(ENFORCE).exists()
+}
+
+/// Sets the label, which the current process gets once it executes its program.
+/// A label is ignored with a warning if SELinux is not enabled
+pub fn set_exec_label(label: &str) -> Result<()> {
+    if label.is_empty() {
+        return Ok(());
+    }
+    if !is_enabled() {
+        log::warn!(
+            "selinux label {} is ignored, as SELinux is not enabled",
+            label
+        );
+        return Ok(());
+    }
+
+    fs::write(EXEC_ATTR, label).with_context(|| {
+        format!(
+            "failed to set selinux label {} through {}",
+            label, EXEC_ATTR
+        )
+    })
+}
+
+/// Appends the label as the context of all files of a filesystem to its mount options
+pub fn format_mount_label(data: &str, label: &str) -> String {
+    match (data.is_empty(), label.is_empty()) {
+        (_, true) => data.to_string(),
+        (true, false) => format!("context=\"{}\"", label),
+        (false, false) => format!("{},context=\"{}\"", data, label),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_mount_label() {
+        let label = "system_u:object_r:container_file_t:s0:c1,c2";
+        assert_eq!(
+            format_mount_label("", label),
+            "context=\"system_u:object_r:container_file_t:s0:c1,c2\""
+        );
+        assert_eq!(
+            format_mount_label("mode=755,size=65536k", label),
+            "mode=755,size=65536k,context=\"system_u:object_r:container_file_t:s0:c1,c2\""
+        );
+        assert_eq!(format_mount_label("mode=755", ""), "mode=755");
+        assert_eq!(format_mount_label("", ""), "");
+    }
+}
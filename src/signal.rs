@@ -1,42 +1,81 @@
 //! Returns *nix signal enum value from passed string
 
+use std::convert::TryFrom;
+
 use anyhow::{bail, Result};
 use nix::sys::signal::Signal;
 
+/// Parses a signal given either by its name, with or without the SIG prefix, or by its number
 pub fn from_str(signal: &str) -> Result<Signal> {
     use Signal::*;
-    Ok(match signal.to_ascii_uppercase().as_str() {
-        "1" | "HUP" | "SIGHUP" => Signal::SIGHUP,
-        "2" | "INT" | "SIGINT" => Signal::SIGINT,
-        "3" | "QUIT" | "SIGQUIT" => Signal::SIGQUIT,
-        "4" | "ILL" | "SIGILL" => Signal::SIGILL,
-        "5" | "BUS" | "SIGBUS" => Signal::SIGBUS,
-        "6" | "ABRT" | "IOT" | "SIGABRT" | "SIGIOT" => Signal::SIGABRT,
-        "7" | "TRAP" | "SIGTRAP" => Signal::SIGTRAP,
-        "8" | "FPE" | "SIGFPE" => Signal::SIGFPE,
-        "9" | "KILL" | "SIGKILL" => Signal::SIGKILL,
-        "10" | "USR1" | "SIGUSR1" => Signal::SIGUSR1,
-        "11" | "SEGV" | "SIGSEGV" => SIGSEGV,
-        "12" | "USR2" | "SIGUSR2" => SIGUSR2,
-        "13" | "PIPE" | "SIGPIPE" => SIGPIPE,
-        "14" | "ALRM" | "SIGALRM" => SIGALRM,
-        "15" | "TERM" | "SIGTERM" => SIGTERM,
-        "16" | "STKFLT" | "SIGSTKFLT" => SIGSTKFLT,
-        "17" | "CHLD" | "SIGCHLD" => SIGCHLD,
-        "18" | "CONT" | "SIGCONT" => SIGCONT,
-        "19" | "STOP" | "SIGSTOP" => SIGSTOP,
-        "20" | "TSTP" | "SIGTSTP" => SIGTSTP,
-        "21" | "TTIN" | "SIGTTIN" => SIGTTIN,
-        "22" | "TTOU" | "SIGTTOU" => SIGTTOU,
-        "23" | "URG" | "SIGURG" => SIGURG,
-        "24" | "XCPU" | "SIGXCPU" => SIGXCPU,
-        "25" | "XFSZ" | "SIGXFSZ" => SIGXFSZ,
-        "26" | "VTALRM" | "SIGVTALRM" => SIGVTALRM,
-        "27" | "PROF" | "SIGPROF" => SIGPROF,
-        "28" | "WINCH" | "SIGWINCH" => SIGWINCH,
-        "29" | "IO" | "SIGIO" => SIGIO,
-        "30" | "PWR" | "SIGPWR" => SIGPWR,
-        "31" | "SYS" | "SIGSYS" => SIGSYS,
+    if let Ok(number) = signal.parse::<i32>() {
+        return match Signal::try_from(number) {
+            Ok(signal) => Ok(signal),
+            Err(_) => bail!("{} is not a valid signal number", signal),
+        };
+    }
+
+    let name = signal.to_ascii_uppercase();
+    Ok(match name.strip_prefix("SIG").unwrap_or(&name) {
+        "HUP" => SIGHUP,
+        "INT" => SIGINT,
+        "QUIT" => SIGQUIT,
+        "ILL" => SIGILL,
+        "TRAP" => SIGTRAP,
+        "ABRT" | "IOT" => SIGABRT,
+        "BUS" => SIGBUS,
+        "FPE" => SIGFPE,
+        "KILL" => SIGKILL,
+        "USR1" => SIGUSR1,
+        "SEGV" => SIGSEGV,
+        "USR2" => SIGUSR2,
+        "PIPE" => SIGPIPE,
+        "ALRM" => SIGALRM,
+        "TERM" => SIGTERM,
+        "STKFLT" => SIGSTKFLT,
+        "CHLD" => SIGCHLD,
+        "CONT" => SIGCONT,
+        "STOP" => SIGSTOP,
+        "TSTP" => SIGTSTP,
+        "TTIN" => SIGTTIN,
+        "TTOU" => SIGTTOU,
+        "URG" => SIGURG,
+        "XCPU" => SIGXCPU,
+        "XFSZ" => SIGXFSZ,
+        "VTALRM" => SIGVTALRM,
+        "PROF" => SIGPROF,
+        "WINCH" => SIGWINCH,
+        "IO" => SIGIO,
+        "PWR" => SIGPWR,
+        "SYS" => SIGSYS,
         _ => bail! {"{} is not a valid signal", signal},
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(from_str("KILL").unwrap(), Signal::SIGKILL);
+        assert_eq!(from_str("SIGKILL").unwrap(), Signal::SIGKILL);
+        assert_eq!(from_str("9").unwrap(), Signal::SIGKILL);
+        assert_eq!(from_str("sigterm").unwrap(), Signal::SIGTERM);
+        assert_eq!(from_str("TERM").unwrap(), Signal::SIGTERM);
+        assert_eq!(from_str("15").unwrap(), Signal::SIGTERM);
+        assert_eq!(from_str("5").unwrap(), Signal::SIGTRAP);
+        assert_eq!(from_str("7").unwrap(), Signal::SIGBUS);
+    }
+
+    #[test]
+    fn test_from_str_invalid() {
+        assert!(from_str("INVALID").is_err());
+        assert!(from_str("SIG").is_err());
+        assert!(from_str("SIGSIGKILL").is_err());
+        assert!(from_str("0").is_err());
+        assert!(from_str("65").is_err());
+        assert!(from_str("-9").is_err());
+        assert!(from_str("").is_err());
+    }
+}
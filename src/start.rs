@@ -2,12 +2,13 @@
 
 use std::path::PathBuf;
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use clap::Clap;
-use nix::unistd;
 
 use crate::container::{Container, ContainerStatus};
-use crate::notify_socket::NotifySocket;
+use crate::exec_fifo;
+use crate::hooks;
+use crate::utils;
 
 #[derive(Clap, Debug)]
 pub struct Start {
@@ -16,7 +17,7 @@ pub struct Start {
 
 impl Start {
     pub fn exec(&self, root_path: PathBuf) -> Result<()> {
-        let container_root = root_path.join(&self.container_id);
+        let container_root = utils::get_container_dir(&root_path, &self.container_id)?;
         if !container_root.exists() {
             bail!("{} doesn't exist.", self.container_id)
         }
@@ -31,12 +32,28 @@ impl Start {
             bail!(err_msg);
         }
 
-        unistd::chdir(container.root.as_os_str())?;
+        // the init process of a container without a process never waits to be started
+        if container.spec()?.process.is_none() {
+            bail!(
+                "{} has no process to start, its processes have to be executed with exec",
+                container.id()
+            )
+        }
+
+        let init_pid = container
+            .pid()
+            .ok_or_else(|| anyhow!("{} has no init process", container.id()))?;
+        exec_fifo::notify(&container.root, init_pid)?;
 
-        let mut notify_socket = NotifySocket::new(&container.root)?;
-        notify_socket.notify_container_start()?;
+        let container = container.update_status(ContainerStatus::Running)?;
+        container.save()?;
 
-        container.update_status(ContainerStatus::Running)?.save()?;
+        // the container has already been started, so a failing poststart hook is only logged
+        if let Some(hooks) = container.spec()?.hooks {
+            if let Err(e) = hooks::run_hooks(&hooks.poststart, &container.state) {
+                log::warn!("{:?}", e);
+            }
+        }
         Ok(())
     }
 }
@@ -1,7 +1,12 @@
 use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
-use nix::unistd::dup2;
+use anyhow::{bail, Context, Result};
+use nix::{
+    fcntl::{self, OFlag},
+    sys::stat::Mode,
+    unistd::{close, dup2},
+};
 
 #[derive(Debug)]
 pub struct FileDescriptor(RawFd);
@@ -9,6 +14,9 @@ pub struct FileDescriptor(RawFd);
 const STDIN: i32 = 0;
 const STDOUT: i32 = 1;
 const STDERR: i32 = 2;
+/// Files in the directory of a detached container, which its output is appended to by default
+const STDOUT_FILE: &str = "stdout.log";
+const STDERR_FILE: &str = "stderr.log";
 
 // impl Drop for FileDescriptor {
 //     fn drop(&mut self) {
@@ -46,3 +54,126 @@ pub fn connect_stdio(
     dup2(stderr.as_raw_fd(), STDERR)?;
     Ok(())
 }
+
+/// Files, which the stdout and the stderr of the container process are appended to instead of
+/// the stdio of youki. The files are kept across restarts of a container, they are never rotated
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OutputFiles {
+    pub stdout: Option<PathBuf>,
+    pub stderr: Option<PathBuf>,
+}
+
+impl OutputFiles {
+    /// Returns the files for the output of the container. A detached container without a terminal
+    /// has no stdio, which will be read, so its output goes to files in its directory unless
+    /// other files are given. The output of a terminal is always sent to the terminal
+    pub fn resolve(
+        stdout: Option<&Path>,
+        stderr: Option<&Path>,
+        container_dir: &Path,
+        detached: bool,
+        terminal: bool,
+    ) -> Result<Self> {
+        if terminal {
+            if stdout.is_some() || stderr.is_some() {
+                bail!("the output of a process with a terminal can not be written to files")
+            }
+            return Ok(Self::default());
+        }
+
+        let file = |path: Option<&Path>, default: &str| match path {
+            Some(path) => Some(path.to_path_buf()),
+            None if detached => Some(container_dir.join(default)),
+            None => None,
+        };
+        Ok(Self {
+            stdout: file(stdout, STDOUT_FILE),
+            stderr: file(stderr, STDERR_FILE),
+        })
+    }
+
+    /// Replaces stdout and stderr of the process with the files. They are opened in the
+    /// namespaces of youki, before the root of the container is changed
+    pub fn redirect(&self) -> Result<()> {
+        if let Some(stdout) = &self.stdout {
+            redirect_to_file(stdout, STDOUT)?;
+        }
+        if let Some(stderr) = &self.stderr {
+            redirect_to_file(stderr, STDERR)?;
+        }
+        Ok(())
+    }
+}
+
+// the duplicated fd does not inherit the close on exec flag, so that it is passed to the program
+fn redirect_to_file(path: &Path, fd: RawFd) -> Result<()> {
+    let file = fcntl::open(
+        path,
+        OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_APPEND | OFlag::O_CLOEXEC,
+        Mode::from_bits_truncate(0o640),
+    )
+    .with_context(|| format!("failed to open the output file {:?}", path))?;
+    let duplicated =
+        dup2(file, fd).with_context(|| format!("failed to redirect fd {} to {:?}", fd, path));
+    close(file)?;
+    duplicated.map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_temp_dir;
+    use std::fs;
+    use std::os::unix::io::IntoRawFd;
+
+    #[test]
+    fn test_resolve_output_files() {
+        let dir = Path::new("/run/youki/output");
+        let out = Path::new("/var/log/out.log");
+
+        let files = OutputFiles::resolve(None, None, dir, true, false).expect("resolve files");
+        assert_eq!(files.stdout, Some(dir.join(STDOUT_FILE)));
+        assert_eq!(files.stderr, Some(dir.join(STDERR_FILE)));
+
+        let files = OutputFiles::resolve(Some(out), None, dir, true, false).expect("resolve files");
+        assert_eq!(files.stdout.as_deref(), Some(out));
+        assert_eq!(files.stderr, Some(dir.join(STDERR_FILE)));
+
+        // the stdio of youki is kept in the foreground, unless files are given
+        let files = OutputFiles::resolve(None, None, dir, false, false).expect("resolve files");
+        assert_eq!(files, OutputFiles::default());
+        let files =
+            OutputFiles::resolve(None, Some(out), dir, false, false).expect("resolve files");
+        assert_eq!(files.stdout, None);
+        assert_eq!(files.stderr.as_deref(), Some(out));
+
+        let files = OutputFiles::resolve(None, None, dir, true, true).expect("resolve files");
+        assert_eq!(files, OutputFiles::default());
+        assert!(OutputFiles::resolve(Some(out), None, dir, true, true).is_err());
+    }
+
+    #[test]
+    fn test_redirect_to_file() {
+        let tmp = create_temp_dir("test_redirect_to_file").expect("create temp directory");
+        let path = tmp.join("out.log");
+        fs::write(&path, "first\n").expect("write output file");
+        // an fd of another file stands in for stdout
+        let fd = fs::File::create(tmp.join("stdout"))
+            .expect("create file")
+            .into_raw_fd();
+
+        redirect_to_file(&path, fd).expect("redirect fd");
+        nix::unistd::write(fd, b"second\n").expect("write to redirected fd");
+        close(fd).expect("close fd");
+
+        // the output is appended to the existing content
+        assert_eq!(
+            fs::read_to_string(&path).expect("read output file"),
+            "first\nsecond\n"
+        );
+        assert_eq!(
+            fs::read_to_string(tmp.join("stdout")).expect("read replaced file"),
+            ""
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Kernel parameters of the container, which are written to /proc/sys in its namespaces
+//! check https://man7.org/linux/man-pages/man5/proc.5.html for more information
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use oci_spec::{LinuxNamespace, LinuxNamespaceType};
+
+/// IPC parameters, which are namespaced by the ipc namespace
+const IPC_SYSCTLS: &[&str] = &[
+    "kernel.msgmax",
+    "kernel.msgmnb",
+    "kernel.msgmni",
+    "kernel.sem",
+    "kernel.shmall",
+    "kernel.shmmax",
+    "kernel.shmmni",
+    "kernel.shm_rmid_forced",
+];
+
+/// Returns the file of the parameter in /proc/sys, e.g. /proc/sys/net/ipv4/ip_forward for net.ipv4.ip_forward
+pub fn sysctl_path(key: &str) -> PathBuf {
+    PathBuf::from("/proc/sys").join(key.replace('.', "/"))
+}
+
+/// Returns the namespace, which the parameter belongs to. A parameter which is not namespaced
+/// would change the host, so it is rejected
+fn namespace_of(key: &str) -> Result<LinuxNamespaceType> {
+    if key.starts_with("net.") {
+        return Ok(LinuxNamespaceType::Network);
+    }
+    if IPC_SYSCTLS.contains(&key) || key.starts_with("fs.mqueue.") {
+        return Ok(LinuxNamespaceType::Ipc);
+    }
+    if key == "kernel.hostname" || key == "kernel.domainname" {
+        return Ok(LinuxNamespaceType::Uts);
+    }
+    bail!(
+        "sysctl {} is not namespaced, so it can not be set for a container",
+        key
+    )
+}
+
+/// Checks that the namespace of every parameter is created or joined by the container,
+/// otherwise the parameter would be written for the namespace of the host
+pub fn validate_sysctl(
+    sysctl: &HashMap<String, String>,
+    namespaces: &[LinuxNamespace],
+) -> Result<()> {
+    for key in sysctl.keys() {
+        let typ = namespace_of(key)?;
+        if !namespaces.iter().any(|ns| ns.typ == typ) {
+            bail!(
+                "sysctl {} requires the {:?} namespace, which the container does not unshare",
+                key,
+                typ
+            )
+        }
+    }
+    Ok(())
+}
+
+/// Writes the parameters, which must be called in the namespaces of the container,
+/// after its /proc has been mounted
+pub fn apply_sysctl(sysctl: &HashMap<String, String>, namespaces: &[LinuxNamespace]) -> Result<()> {
+    validate_sysctl(sysctl, namespaces)?;
+    // sorted, so that the parameters are always written in the same order
+    let mut keys: Vec<&String> = sysctl.keys().collect();
+    keys.sort();
+    for key in keys {
+        let path = sysctl_path(key);
+        log::debug!("set sysctl {} to {}", key, sysctl[key]);
+        fs::write(&path, &sysctl[key])
+            .with_context(|| format!("failed to set sysctl {} through {:?}", key, path))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn namespace(typ: LinuxNamespaceType) -> LinuxNamespace {
+        LinuxNamespace { typ, path: None }
+    }
+
+    fn sysctl(key: &str) -> HashMap<String, String> {
+        let mut sysctl = HashMap::new();
+        sysctl.insert(key.to_string(), "1".to_string());
+        sysctl
+    }
+
+    #[test]
+    fn test_sysctl_path() {
+        assert_eq!(
+            sysctl_path("net.ipv4.ip_forward"),
+            PathBuf::from("/proc/sys/net/ipv4/ip_forward")
+        );
+        assert_eq!(
+            sysctl_path("kernel.shm_rmid_forced"),
+            PathBuf::from("/proc/sys/kernel/shm_rmid_forced")
+        );
+        assert_eq!(
+            sysctl_path("fs.mqueue.msg_max"),
+            PathBuf::from("/proc/sys/fs/mqueue/msg_max")
+        );
+    }
+
+    #[test]
+    fn test_validate_sysctl() {
+        let namespaces = vec![
+            namespace(LinuxNamespaceType::Network),
+            namespace(LinuxNamespaceType::Ipc),
+        ];
+
+        assert!(validate_sysctl(&sysctl("net.ipv4.ip_forward"), &namespaces).is_ok());
+        assert!(validate_sysctl(&sysctl("kernel.shmmax"), &namespaces).is_ok());
+        assert!(validate_sysctl(&sysctl("fs.mqueue.msg_max"), &namespaces).is_ok());
+        // the uts namespace is not unshared
+        assert!(validate_sysctl(&sysctl("kernel.hostname"), &namespaces).is_err());
+        // not namespaced at all
+        assert!(validate_sysctl(&sysctl("kernel.pid_max"), &namespaces).is_err());
+        assert!(validate_sysctl(&sysctl("vm.swappiness"), &namespaces).is_err());
+    }
+
+    #[test]
+    fn test_validate_sysctl_without_network_namespace() {
+        let namespaces = vec![namespace(LinuxNamespaceType::Ipc)];
+        let err = validate_sysctl(&sysctl("net.ipv4.ip_forward"), &namespaces).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "sysctl net.ipv4.ip_forward requires the Network namespace, which the container does not unshare"
+        );
+    }
+}
@@ -1,24 +1,53 @@
 //! tty (teletype) for user-system interaction
 
+use std::fs;
 use std::os::unix::fs::symlink;
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::path::Path;
 
-use anyhow::{bail, Result};
-use nix::errno::Errno;
-use nix::fcntl;
+use anyhow::{bail, Context, Result};
 use nix::sys::socket;
-use nix::sys::stat;
 use nix::unistd::{close, setsid};
 
 use crate::stdio;
 use crate::stdio::FileDescriptor;
 
+/// Name of the link to the console socket in the directory of the container
+const CONSOLE_SOCKET: &str = "console-socket";
+
+/// Checks that a console socket is given if and only if the process of the container requires a terminal
+pub fn validate_console_socket(terminal: bool, console_socket: Option<&str>) -> Result<()> {
+    match (terminal, console_socket) {
+        (true, None) => bail!("process.terminal is set but no --console-socket was given"),
+        (false, Some(_)) => bail!("--console-socket is given but process.terminal is not set"),
+        _ => Ok(()),
+    }
+}
+
+/// Allocates a pseudoterminal for the container process. The master end is sent to the
+/// console socket, while the slave end becomes the controlling terminal and the stdio of the process
 pub fn ready(console_fd: FileDescriptor) -> Result<()> {
     let openpty_result = nix::pty::openpty(None, None)?;
+    send_master(&console_fd, openpty_result.master)?;
+    // the container process only uses the slave end
+    close(openpty_result.master)?;
+    close(console_fd.as_raw_fd())?;
+
+    setsid()?;
+    if unsafe { libc::ioctl(openpty_result.slave, libc::TIOCSCTTY) } < 0 {
+        log::warn!("could not TIOCSCTTY");
+    };
+    let slave = FileDescriptor::from(openpty_result.slave);
+    stdio::connect_stdio(&slave, &slave, &slave).context("could not dup tty to stdio")?;
+    Ok(())
+}
+
+// sends the fd of the pseudoterminal master with SCM_RIGHTS, so that the receiver gets its own
+// copy of the fd. The path of the multiplexer is sent as data, like runc does
+fn send_master(console_fd: &FileDescriptor, master: RawFd) -> Result<()> {
     let data: &[u8] = b"/dev/ptmx";
     let iov = [nix::sys::uio::IoVec::from_slice(data)];
-    let fds = [openpty_result.master];
+    let fds = [master];
     let cmsg = socket::ControlMessage::ScmRights(&fds);
     socket::sendmsg(
         console_fd.as_raw_fd(),
@@ -26,56 +55,92 @@ pub fn ready(console_fd: FileDescriptor) -> Result<()> {
         &[cmsg],
         socket::MsgFlags::empty(),
         None,
-    )?;
-
-    setsid()?;
-    if unsafe { libc::ioctl(openpty_result.slave, libc::TIOCSCTTY) } < 0 {
-        log::warn!("could not TIOCSCTTY");
-    };
-    let slave = FileDescriptor::from(openpty_result.slave);
-    stdio::connect_stdio(&slave, &slave, &slave).expect("could not dup tty to stderr");
-    close(console_fd.as_raw_fd())?;
+    )
+    .context("failed to send the pseudoterminal to the console socket")?;
     Ok(())
 }
 
-pub fn load_console_sockets(
-    container_dir: &Path,
-    console_socket: &str,
-) -> Result<(FileDescriptor, FileDescriptor)> {
-    let csocket = "console-stdout";
-    symlink(console_socket, container_dir.join(csocket))?;
+/// Connects to the console socket, which will receive the master end of the pseudoterminal
+pub fn load_console_sockets(container_dir: &Path, console_socket: &str) -> Result<FileDescriptor> {
+    // the path of a unix socket is limited to 108 bytes, so it is connected
+    // through a link in the directory of the container with a short relative path
+    let link = container_dir.join(CONSOLE_SOCKET);
+    // a link to the console socket of an earlier process of the container, e.g. of create, is replaced
+    if fs::symlink_metadata(&link).is_ok() {
+        fs::remove_file(&link).with_context(|| {
+            format!("failed to remove the link to the console socket {:?}", link)
+        })?;
+    }
+    symlink(console_socket, &link)
+        .with_context(|| format!("failed to link the console socket {}", console_socket))?;
 
-    let mut csocketfd = socket::socket(
+    let csocketfd = socket::socket(
         socket::AddressFamily::Unix,
         socket::SockType::Stream,
         socket::SockFlag::empty(),
         None,
     )?;
-    csocketfd = match socket::connect(
-        csocketfd,
-        &socket::SockAddr::Unix(socket::UnixAddr::new(&*csocket)?),
-    ) {
-        Err(e) => {
-            if e != ::nix::Error::Sys(Errno::ENOENT) {
-                bail!("failed to open {}", csocket);
-            }
-            -1
-        }
-        Ok(()) => csocketfd,
-    };
-    let console = "console";
-    let consolefd = match fcntl::open(
-        &*console,
-        fcntl::OFlag::O_NOCTTY | fcntl::OFlag::O_RDWR,
-        stat::Mode::empty(),
-    ) {
-        Err(e) => {
-            if e != ::nix::Error::Sys(Errno::ENOENT) {
-                bail!("failed to open {}", console);
-            }
-            -1
+    let addr = socket::SockAddr::Unix(socket::UnixAddr::new(CONSOLE_SOCKET)?);
+    if let Err(e) = socket::connect(csocketfd, &addr) {
+        let _ = close(csocketfd);
+        bail!(
+            "failed to connect to the console socket {}: {}",
+            console_socket,
+            e
+        );
+    }
+
+    Ok(csocketfd.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::cmsg_space;
+    use nix::sys::socket::{ControlMessageOwned, MsgFlags};
+    use nix::sys::uio::IoVec;
+
+    #[test]
+    fn test_validate_console_socket() {
+        assert!(validate_console_socket(true, Some("/tmp/console.sock")).is_ok());
+        assert!(validate_console_socket(false, None).is_ok());
+        assert!(validate_console_socket(true, None).is_err());
+        assert!(validate_console_socket(false, Some("/tmp/console.sock")).is_err());
+    }
+
+    #[test]
+    fn test_send_master() {
+        let (sender, receiver) = socket::socketpair(
+            socket::AddressFamily::Unix,
+            socket::SockType::Stream,
+            None,
+            socket::SockFlag::empty(),
+        )
+        .expect("create socket pair");
+        let pty = nix::pty::openpty(None, None).expect("open pseudoterminal");
+
+        send_master(&FileDescriptor::from(sender), pty.master).expect("send master");
+
+        let mut buf = [0u8; 64];
+        let iov = [IoVec::from_mut_slice(&mut buf)];
+        let mut cmsg_buffer = cmsg_space!([RawFd; 1]);
+        let msg = socket::recvmsg(receiver, &iov, Some(&mut cmsg_buffer), MsgFlags::empty())
+            .expect("receive message");
+        let fds: Vec<RawFd> = msg
+            .cmsgs()
+            .filter_map(|cmsg| match cmsg {
+                ControlMessageOwned::ScmRights(fds) => Some(fds),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert_eq!(&buf[..msg.bytes], b"/dev/ptmx");
+        assert_eq!(fds.len(), 1);
+        // the received fd refers to the same pseudoterminal as the master
+        assert!(nix::unistd::isatty(fds[0]).expect("check received fd"));
+
+        for fd in [sender, receiver, pty.master, pty.slave, fds[0]].iter() {
+            let _ = close(*fd);
         }
-        Ok(fd) => fd,
-    };
-    Ok((csocketfd.into(), consolefd.into()))
+    }
 }
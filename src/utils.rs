@@ -2,12 +2,16 @@
 
 use std::ffi::CString;
 use std::fs;
+use std::ops::Deref;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use nix::{env::clearenv, errno::Errno, unistd};
 
+use crate::cgroups;
+use crate::error::YoukiError;
+
 pub trait PathBufExt {
     fn as_in_container(&self) -> Result<PathBuf>;
     fn join_absolute_path(&self, p: &Path) -> Result<PathBuf>;
@@ -35,7 +39,8 @@ impl PathBufExt for PathBuf {
 }
 
 pub fn do_exec(path: impl AsRef<Path>, args: &[String], envs: &[String]) -> Result<()> {
-    let p = CString::new(path.as_ref().to_string_lossy().to_string())?;
+    let path = find_executable(&path.as_ref().to_string_lossy(), envs)?;
+    let p = CString::new(path.to_string_lossy().to_string())?;
     let a: Vec<CString> = args
         .iter()
         .map(|s| CString::new(s.to_string()).unwrap_or_default())
@@ -52,8 +57,107 @@ pub fn do_exec(path: impl AsRef<Path>, args: &[String], envs: &[String]) -> Resu
         putenv(&e)?
     }
 
-    unistd::execvp(&p, &a)?;
-    Ok(())
+    // execvp only returns if the program could not be executed
+    let cmd = path.to_string_lossy().to_string();
+    match unistd::execvp(&p, &a) {
+        Err(nix::Error::Sys(Errno::ENOENT)) => Err(YoukiError::CommandNotFound {
+            cmd,
+            reason: "no such file or directory".to_string(),
+        }
+        .into()),
+        Err(nix::Error::Sys(errno)) => Err(YoukiError::CommandNotInvokable {
+            cmd,
+            reason: errno.desc().to_lowercase(),
+        }
+        .into()),
+        Err(e) => Err(e.into()),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// PATH of the container process, if the spec does not set it
+const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// Returns the environment of the container process from the KEY=VALUE entries of the spec, in
+/// their order. A variable given more than once takes the last value, PATH is set to a default if missing
+pub fn prepare_env(envs: &[String]) -> Result<Vec<String>> {
+    let mut vars: Vec<(&str, &str)> = Vec::with_capacity(envs.len() + 1);
+    for env in envs {
+        let (key, value) = parse_env(env)?;
+        match vars.iter_mut().find(|(k, _)| *k == key) {
+            Some(var) => var.1 = value,
+            None => vars.push((key, value)),
+        }
+    }
+    // a program without a slash in its name could not be found otherwise
+    if !vars.iter().any(|(key, _)| *key == "PATH") {
+        vars.push(("PATH", DEFAULT_PATH));
+    }
+
+    Ok(vars
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect())
+}
+
+/// Splits an environment variable of the spec into its name and its value
+pub fn parse_env(env: &str) -> Result<(&str, &str)> {
+    match env.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key, value)),
+        _ => bail!(
+            "environment variable {:?} is not in the form KEY=VALUE",
+            env
+        ),
+    }
+}
+
+/// Search path of execvp if the environment does not contain PATH
+const DEFAULT_EXEC_PATH: &str = "/bin:/usr/bin";
+
+/// Finds the program like execvp does, in the directories of PATH of the given environment
+/// unless the name of the program contains a slash. The errors are the ones of runc, as the
+/// ENOENT of execvp does not tell what is missing
+pub fn find_executable(name: &str, envs: &[String]) -> Result<PathBuf> {
+    if name.is_empty() {
+        bail!("exec: no executable given")
+    }
+    // a name with a slash is a path, which is relative to the working directory if not absolute
+    if name.contains('/') {
+        let path = PathBuf::from(name);
+        let metadata = fs::metadata(&path).map_err(|_| YoukiError::CommandNotFound {
+            cmd: name.to_string(),
+            reason: "no such file or directory".to_string(),
+        })?;
+        if !is_executable(&metadata) {
+            return Err(YoukiError::CommandNotInvokable {
+                cmd: name.to_string(),
+                reason: "permission denied".to_string(),
+            }
+            .into());
+        }
+        return Ok(path);
+    }
+
+    let search_path = envs
+        .iter()
+        .find_map(|env| env.strip_prefix("PATH="))
+        .unwrap_or(DEFAULT_EXEC_PATH);
+    search_path
+        .split(':')
+        // an empty directory of PATH is the working directory
+        .map(|dir| Path::new(if dir.is_empty() { "." } else { dir }).join(name))
+        .find(|path| fs::metadata(path).is_ok_and(|m| is_executable(&m)))
+        .ok_or_else(|| {
+            YoukiError::CommandNotFound {
+                cmd: name.to_string(),
+                reason: "executable file not found in $PATH".to_string(),
+            }
+            .into()
+        })
+}
+
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
 }
 
 #[inline]
@@ -68,35 +172,337 @@ pub fn set_name(_name: &str) -> Result<()> {
     Ok(())
 }
 
-/// If None, it will generate a default path for cgroups.
-pub fn get_cgroup_path(cgroups_path: &Option<PathBuf>, container_id: &str) -> PathBuf {
-    match cgroups_path {
-        Some(cpath) => cpath.clone(),
-        None => PathBuf::from(format!("/youki/{}", container_id)),
+/// If None, it will generate a default path for cgroups, below the cgroup parent if given.
+/// The systemd cgroup driver expects a path in the form slice:prefix:name instead
+pub fn get_cgroup_path(
+    cgroups_path: &Option<PathBuf>,
+    container_id: &str,
+    use_systemd: bool,
+    cgroup_parent: Option<&Path>,
+) -> Result<PathBuf> {
+    if use_systemd && cgroup_parent.is_some() {
+        bail!("a cgroup parent can not be used with the systemd cgroup driver, as the slice is part of the cgroups path")
     }
+    let path = match cgroups_path {
+        Some(cpath) if use_systemd => cpath.clone(),
+        None if use_systemd => PathBuf::from(format!(":youki:{}", container_id)),
+        _ => cgroups::common::resolve_cgroup_path(
+            cgroups_path.as_deref(),
+            container_id,
+            cgroup_parent,
+        )?,
+    };
+    Ok(path)
 }
 
-pub fn delete_with_retry<P: AsRef<Path>>(path: P) -> Result<()> {
-    let mut attempts = 0;
-    let mut delay = Duration::from_millis(10);
-    let path = path.as_ref();
+/// Returns the directory, which stores the state of the container with the given id,
+/// inside of the root directory of youki
+pub fn get_container_dir(root_path: &Path, container_id: &str) -> Result<PathBuf> {
+    // the id becomes a single path component, so it must not leave the root directory
+    if container_id.is_empty()
+        || container_id == "."
+        || container_id == ".."
+        || container_id.contains('/')
+    {
+        bail!("invalid container id {:?}", container_id)
+    }
 
-    while attempts < 5 {
-        if fs::remove_dir(path).is_ok() {
-            return Ok(());
+    // collecting the components drops trailing and repeated separators
+    let root_path: PathBuf = root_path.components().collect();
+    Ok(root_path.join(container_id))
+}
+
+/// Changes the working directory of the process to the cwd of the spec. Like in runc, a missing
+/// directory is an error instead of being created inside of the container
+pub fn set_working_dir(cwd: &str) -> Result<()> {
+    if cwd.is_empty() {
+        return Ok(());
+    }
+    match unistd::chdir(cwd) {
+        Ok(()) => Ok(()),
+        Err(nix::Error::Sys(Errno::ENOENT)) => {
+            bail!(
+                "working directory {:?} does not exist in the container",
+                cwd
+            )
         }
+        Err(e) => Err(e).with_context(|| format!("failed to change the directory to {:?}", cwd)),
+    }
+}
 
-        std::thread::sleep(delay);
-        attempts += attempts;
-        delay *= attempts;
+/// Writes the pid to a file, e.g. for a supervisor of the container. The pid is written to a
+/// temporary file which is then renamed, so that a reader never sees a partially written pid
+pub fn write_pid_file(pid_file: &Path, pid: i32) -> Result<()> {
+    let file_name = pid_file
+        .file_name()
+        .ok_or_else(|| anyhow!("pid file {:?} is not a file", pid_file))?;
+    let dir = match pid_file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir).with_context(|| {
+        format!(
+            "failed to create the directory of the pid file {:?}",
+            pid_file
+        )
+    })?;
+
+    // the temporary file is in the same directory, as a file can only be renamed atomically within a filesystem
+    let tmp_file = dir.join(format!(
+        ".{}.{}",
+        file_name.to_string_lossy(),
+        unistd::getpid()
+    ));
+    fs::write(&tmp_file, pid.to_string())
+        .with_context(|| format!("failed to write the pid file {:?}", tmp_file))?;
+    if let Err(e) = fs::rename(&tmp_file, pid_file) {
+        let _ = fs::remove_file(&tmp_file);
+        bail!("failed to write the pid file {:?}: {}", pid_file, e)
     }
+    Ok(())
+}
 
-    bail!("could not delete {:?}", path)
+/// Directory which is removed together with its content when it goes out of scope
+pub struct TempDir {
+    path: Option<PathBuf>,
+}
+
+impl TempDir {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let p = path.into();
+        fs::create_dir_all(&p)?;
+        Ok(Self { path: Some(p) })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path
+            .as_ref()
+            .expect("temp dir has already been removed")
+    }
+
+    pub fn remove(&mut self) {
+        if let Some(p) = &self.path {
+            let _ = fs::remove_dir_all(p);
+            self.path = None;
+        }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Self::Target {
+        self.path()
+    }
+}
+
+/// Creates a directory with the given name in the temporary directory of the system
+pub fn create_temp_dir(test_name: &str) -> Result<TempDir> {
+    let dir = TempDir::new(std::env::temp_dir().join(test_name))?;
+    Ok(dir)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error;
+    use crate::process::fork::clone_child;
+    use nix::sched::CloneFlags;
+    use nix::sys::wait::{waitpid, WaitStatus};
+
+    #[test]
+    fn test_find_executable() {
+        let envs = vec!["PATH=/nonexistent:/bin".to_string()];
+        assert_eq!(
+            find_executable("sh", &envs).expect("find sh"),
+            PathBuf::from("/bin/sh")
+        );
+        assert_eq!(
+            find_executable("/bin/sh", &[]).expect("find /bin/sh"),
+            PathBuf::from("/bin/sh")
+        );
+        // execvp searches /bin and /usr/bin if PATH is not set
+        assert!(find_executable("sh", &[]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_env() {
+        assert_eq!(parse_env("TERM=xterm").unwrap(), ("TERM", "xterm"));
+        assert_eq!(parse_env("EMPTY=").unwrap(), ("EMPTY", ""));
+        // only the first equals sign separates the name from the value
+        assert_eq!(parse_env("OPTS=a=b").unwrap(), ("OPTS", "a=b"));
+        assert!(parse_env("TERM").is_err());
+        assert!(parse_env("=xterm").is_err());
+    }
+
+    #[test]
+    fn test_prepare_env() {
+        let envs = vec![
+            "TERM=xterm".to_string(),
+            "HOME=/root".to_string(),
+            "TERM=vt100".to_string(),
+        ];
+        assert_eq!(
+            prepare_env(&envs).expect("prepare env"),
+            vec![
+                "TERM=vt100".to_string(),
+                "HOME=/root".to_string(),
+                format!("PATH={}", DEFAULT_PATH),
+            ]
+        );
+        assert_eq!(
+            prepare_env(&[]).expect("prepare env"),
+            vec![format!("PATH={}", DEFAULT_PATH)]
+        );
+    }
+
+    #[test]
+    fn test_prepare_env_with_path() {
+        let envs = vec!["PATH=/bin".to_string(), "HOME=/root".to_string()];
+        assert_eq!(prepare_env(&envs).expect("prepare env"), envs);
+
+        let envs = vec!["HOME=/root".to_string(), "MALFORMED".to_string()];
+        assert!(prepare_env(&envs).is_err());
+    }
+
+    #[test]
+    fn test_find_missing_executable() {
+        let envs = vec!["PATH=/bin".to_string()];
+        assert!(find_executable("youki-missing-binary", &envs).is_err());
+        assert!(find_executable("/bin/youki-missing-binary", &envs).is_err());
+        // a relative path is resolved from the working directory instead of PATH
+        assert!(find_executable("./youki-missing-binary", &envs).is_err());
+        assert!(find_executable("", &envs).is_err());
+    }
+
+    #[test]
+    fn test_find_executable_in_fake_path() {
+        let tmp = create_temp_dir("test_find_executable_in_fake_path").expect("create temp dir");
+        let (bin, sbin) = (tmp.join("bin"), tmp.join("sbin"));
+        for dir in &[&bin, &sbin] {
+            fs::create_dir_all(dir).expect("create bin directory");
+        }
+        let write_program = |path: PathBuf, mode: u32| {
+            fs::write(&path, "#!/bin/sh\n").expect("write program");
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).expect("set mode");
+            path
+        };
+        let program = write_program(sbin.join("program"), 0o755);
+        // the first match of PATH is skipped, as it can not be executed
+        write_program(bin.join("program"), 0o644);
+        let data = write_program(bin.join("data"), 0o644);
+        fs::create_dir(bin.join("directory")).expect("create directory");
+        let envs = vec![format!("PATH={}:{}", bin.display(), sbin.display())];
+
+        assert_eq!(find_executable("program", &envs).unwrap(), program);
+        for name in &["data", "directory", "missing"] {
+            let err = find_executable(name, &envs).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("exec: {:?}: executable file not found in $PATH", name)
+            );
+        }
+
+        // a path is not searched for
+        let path = program.to_str().unwrap();
+        assert_eq!(find_executable(path, &envs).unwrap(), program);
+        let err = find_executable(data.to_str().unwrap(), &envs).unwrap_err();
+        assert!(err.to_string().contains("permission denied"), "{}", err);
+        assert_eq!(error::exit_code(&err), error::EXIT_CANNOT_INVOKE);
+        let err = find_executable(&format!("{}/missing", bin.display()), &[]).unwrap_err();
+        assert!(
+            err.to_string().contains("no such file or directory"),
+            "{}",
+            err
+        );
+        assert_eq!(error::exit_code(&err), error::EXIT_NOT_FOUND);
+        let err = find_executable("missing", &envs).unwrap_err();
+        assert_eq!(error::exit_code(&err), error::EXIT_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_get_container_dir() {
+        let dir = get_container_dir(Path::new("/run/youki"), "abc").expect("get container dir");
+        assert_eq!(dir.to_str(), Some("/run/youki/abc"));
+
+        let dir = get_container_dir(Path::new("/run/youki/"), "abc").expect("get container dir");
+        assert_eq!(dir.to_str(), Some("/run/youki/abc"));
+
+        let dir = get_container_dir(Path::new("/run//user/1000//youki//"), "abc")
+            .expect("get container dir");
+        assert_eq!(dir.to_str(), Some("/run/user/1000/youki/abc"));
+    }
+
+    #[test]
+    fn test_get_container_dir_invalid_id() {
+        let root_path = Path::new("/run/youki");
+        for id in &["", ".", "..", "a/b", "../abc"] {
+            assert!(get_container_dir(root_path, id).is_err(), "id {:?}", id);
+        }
+    }
+
+    #[test]
+    fn test_set_working_dir() {
+        let tmp = create_temp_dir("test_set_working_dir").expect("create temp directory");
+
+        let err = set_working_dir(tmp.join("missing").to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().ends_with("does not exist in the container"));
+        assert!(set_working_dir("").is_ok());
+
+        // the working directory is changed in a child, the other tests share the one of the test process
+        let expected = fs::canonicalize(&*tmp).expect("canonicalize temp directory");
+        let child = clone_child(CloneFlags::empty(), || {
+            let changed = set_working_dir(tmp.to_str().unwrap()).is_ok()
+                && std::env::current_dir().ok().as_ref() == Some(&expected);
+            if changed {
+                0
+            } else {
+                1
+            }
+        })
+        .expect("clone child");
+        assert_eq!(
+            waitpid(child, None).expect("wait for child"),
+            WaitStatus::Exited(child, 0)
+        );
+    }
+
+    #[test]
+    fn test_write_pid_file() {
+        let tmp = create_temp_dir("test_write_pid_file").expect("create temp directory");
+        // the directory of the pid file does not exist yet
+        let pid_file = tmp.join("run").join("container.pid");
+
+        write_pid_file(&pid_file, 1234).expect("write pid file");
+        assert_eq!(
+            fs::read_to_string(&pid_file).expect("read pid file"),
+            "1234"
+        );
+
+        // an existing pid file is replaced, without leaving the temporary file behind
+        write_pid_file(&pid_file, 5678).expect("write pid file");
+        assert_eq!(
+            fs::read_to_string(&pid_file).expect("read pid file"),
+            "5678"
+        );
+        let entries: Vec<_> = fs::read_dir(tmp.join("run"))
+            .expect("read directory of pid file")
+            .map(|entry| entry.expect("read entry").file_name())
+            .collect();
+        assert_eq!(entries, vec!["container.pid"]);
+    }
 
     #[test]
     fn test_join_absolute_path() {
@@ -122,12 +528,21 @@ mod tests {
     fn test_get_cgroup_path() {
         let cid = "sample_container_id";
         assert_eq!(
-            get_cgroup_path(&None, cid),
+            get_cgroup_path(&None, cid, false, None).unwrap(),
             PathBuf::from("/youki/sample_container_id")
         );
         assert_eq!(
-            get_cgroup_path(&Some(PathBuf::from("/youki")), cid),
+            get_cgroup_path(&Some(PathBuf::from("/youki")), cid, false, None).unwrap(),
             PathBuf::from("/youki")
         );
+        assert_eq!(
+            get_cgroup_path(&None, cid, true, None).unwrap(),
+            PathBuf::from(":youki:sample_container_id")
+        );
+        assert_eq!(
+            get_cgroup_path(&None, cid, false, Some(Path::new("kubepods"))).unwrap(),
+            PathBuf::from("/kubepods/sample_container_id")
+        );
+        assert!(get_cgroup_path(&None, cid, true, Some(Path::new("/kubepods"))).is_err());
     }
 }